@@ -1,10 +1,4 @@
-extern crate protoc_rust;
-
 fn main() {
-    protoc_rust::Codegen::new()
-        .out_dir("src/protos")
-        .inputs(&["protos/payload.proto"])
-        .include("protos")
-        .run()
+    prost_build::compile_protos(&["protos/payload.proto", "protos/transaction.proto", "protos/batch.proto"], &["protos"])
         .expect("Running protoc failed.")
-}
\ No newline at end of file
+}
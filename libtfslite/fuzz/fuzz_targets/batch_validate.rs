@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prost::Message;
+use libtfslite::client::batch::BatchExt;
+use libtfslite::protos::batch::BatchList;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(batch_list) = BatchList::decode(data) {
+        for batch in batch_list.batches.iter() {
+            let _ = batch.validate();
+        }
+    }
+});
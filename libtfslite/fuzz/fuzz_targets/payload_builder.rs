@@ -0,0 +1,132 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use prost::Message;
+use uuid::Uuid;
+use libtfslite::client::payload::{PayloadBuilder, PayloadOperation, PayloadView};
+use libtfslite::types::{FileMode, FileRights, Permission};
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    operation: u8,
+    uuid: Option<[u8; 16]>,
+    destroyable: Option<bool>,
+    block: Option<Vec<u8>>,
+    block_reference: Option<(Vec<u8>, u64)>,
+    chunk_index: Option<u64>,
+    chunk_total: Option<u64>,
+    filename: Option<String>,
+    address: Option<Vec<u8>>,
+    amount: Option<u128>,
+    permission: Option<u8>,
+    public_key: Option<Vec<u8>>,
+    timestamps: (Option<i64>, Option<i64>, Option<i64>),
+    manifest: Option<(Vec<u8>, u64, Vec<(String, String)>)>,
+    tags: Vec<(String, String)>,
+    retain_until: Option<i64>,
+    legal_hold: Option<bool>,
+    rights: Option<u32>,
+    alias: Option<String>,
+}
+
+const OPERATIONS: [fn() -> PayloadOperation; 18] = [
+    || PayloadOperation::FileCreate,
+    || PayloadOperation::FileAppend,
+    || PayloadOperation::FileSeal,
+    || PayloadOperation::FileDestroy,
+    || PayloadOperation::AccountDeposit,
+    || PayloadOperation::AccountTransfer,
+    || PayloadOperation::AccountWithdraw,
+    || PayloadOperation::PermissionSet,
+    || PayloadOperation::PermissionClear,
+    || PayloadOperation::TimestampSet,
+    || PayloadOperation::FileManifest,
+    || PayloadOperation::FileTag,
+    || PayloadOperation::FileRetentionSet,
+    || PayloadOperation::FileLegalHoldSet,
+    || PayloadOperation::FileGrant,
+    || PayloadOperation::FileRevoke,
+    || PayloadOperation::AliasSet,
+    || PayloadOperation::AliasClear,
+    || PayloadOperation::AccountMultisigSet,
+];
+
+const PERMISSIONS: [Permission; 6] = [
+    Permission::Unset,
+    Permission::SetPermission,
+    Permission::Batcher,
+    Permission::Deposit,
+    Permission::Timestamp,
+    Permission::LegalHold,
+];
+
+// Whatever the builder is given, it either builds or refuses, and what it builds parses back
+// without panicking.
+fuzz_target!(|input: Input| {
+    let operation = OPERATIONS[input.operation as usize % OPERATIONS.len()]();
+    let mut builder = PayloadBuilder::new(operation);
+
+    if let Some(uuid) = input.uuid {
+        builder = builder.with_uuid(Uuid::from_bytes(uuid));
+    }
+    if let Some(destroyable) = input.destroyable {
+        builder = builder.with_mode(if destroyable { FileMode::Destroyable } else { FileMode::Immutable });
+    }
+    if let Some(data) = input.block {
+        builder = builder.with_block(data);
+    }
+    if let Some((sha224, reference_number)) = input.block_reference {
+        builder = builder.with_block_reference(sha224, reference_number);
+    }
+    if let Some(chunk_index) = input.chunk_index {
+        builder = builder.with_chunk_index(chunk_index);
+    }
+    if let Some(chunk_total) = input.chunk_total {
+        builder = builder.with_chunk_total(chunk_total);
+    }
+    if let Some(filename) = input.filename {
+        builder = builder.with_filename(filename);
+    }
+    if let Some(address) = input.address {
+        builder = builder.with_address(address);
+    }
+    if let Some(amount) = input.amount {
+        builder = builder.with_amount(amount);
+    }
+    if let Some(permission) = input.permission {
+        builder = builder.with_permission(PERMISSIONS[permission as usize % PERMISSIONS.len()].clone());
+    }
+    if let Some(public_key) = input.public_key {
+        builder = builder.with_permission_public_key(public_key.clone()).with_grantee(public_key);
+    }
+    if let Some(timestamp) = input.timestamps.0 {
+        builder = builder.with_timestamp_create(timestamp);
+    }
+    if let Some(timestamp) = input.timestamps.1 {
+        builder = builder.with_timestamp_append(timestamp);
+    }
+    if let Some(timestamp) = input.timestamps.2 {
+        builder = builder.with_timestamp_seal(timestamp);
+    }
+    if let Some((sha512, size, metadata)) = input.manifest {
+        builder = builder.with_manifest(sha512, size, metadata);
+    }
+    builder = builder.with_tags(input.tags);
+    if let Some(retain_until) = input.retain_until {
+        builder = builder.with_retain_until(retain_until);
+    }
+    if let Some(legal_hold) = input.legal_hold {
+        builder = builder.with_legal_hold(legal_hold);
+    }
+    if let Some(rights) = input.rights {
+        builder = builder.with_rights(FileRights(rights));
+    }
+    if let Some(alias) = input.alias {
+        builder = builder.with_alias(alias);
+    }
+
+    if let Ok(payload) = builder.build() {
+        let _ = PayloadView::from_bytes(&payload.encode_to_vec());
+    }
+});
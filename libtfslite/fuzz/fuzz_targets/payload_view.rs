@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libtfslite::client::payload::PayloadView;
+
+// Any bytes must either parse or be rejected, never panic.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(view) = PayloadView::from_bytes(data) {
+        let _ = view.to_json();
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prost::Message;
+use libtfslite::client::transaction::TransactionExt;
+use libtfslite::protos::transaction::Transaction;
+
+// Transactions from imported batches are untrusted until validate() passes.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tx) = Transaction::decode(data) {
+        let _ = tx.validate();
+        let _ = tx.dependencies();
+        let _ = tx.signer_public_key();
+        if let Ok(view) = tx.view() {
+            let _ = view.to_json();
+        }
+    }
+});
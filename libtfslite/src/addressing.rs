@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use sha2::{Digest, Sha512};
+use uuid::Uuid;
+use crate::common::get_tfslite_prefix;
+use crate::types::Permission;
+
+pub const ADDRESS_LENGTH: usize = 70;
+
+const FILE_TYPE: &str = "00";
+const BLOCK_TYPE: &str = "01";
+const ACCOUNT_TYPE: &str = "02";
+const DIRECTORY_TYPE: &str = "03";
+const PERMISSION_TYPE: &str = "04";
+const CLOCK_TYPE: &str = "05";
+const ALIAS_TYPE: &str = "06";
+
+#[derive(Debug)]
+pub enum AddressParseError {
+    InvalidLength(usize),
+    InvalidPrefix(String),
+    InvalidType(String),
+    InvalidField(String),
+}
+
+impl Error for AddressParseError {}
+
+impl Display for AddressParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            AddressParseError::InvalidLength(ref n) => write!(f, "InvalidLength: {}", n),
+            AddressParseError::InvalidPrefix(ref s) => write!(f, "InvalidPrefix: {}", s),
+            AddressParseError::InvalidType(ref s) => write!(f, "InvalidType: {}", s),
+            AddressParseError::InvalidField(ref s) => write!(f, "InvalidField: {}", s),
+        }
+    }
+}
+
+// Account, directory and permission addresses hold a hash of the public key, so parsing them
+// yields the hash rather than the key itself.
+#[derive(Debug, Clone)]
+pub enum StateAddress {
+    File(Uuid),
+    Block(Uuid, u64),
+    Account(String),
+    Directory(String),
+    Permission(Permission, String),
+    Clock,
+    Alias(String),
+}
+
+fn hash_hex(data: &[u8], len: usize) -> String {
+    hex::encode(Sha512::digest(data))[..len].to_string()
+}
+
+pub fn file_address(file_id: &Uuid) -> String {
+    format!("{}{}{}{}", get_tfslite_prefix(), FILE_TYPE, file_id.simple(), "0".repeat(30))
+}
+
+pub fn block_address(file_id: &Uuid, number: u64) -> String {
+    format!("{}{}{}{:016x}{}", get_tfslite_prefix(), BLOCK_TYPE, file_id.simple(), number, "0".repeat(14))
+}
+
+// Prefix under which all blocks of a file live, for listing them in a single state query.
+pub fn block_address_prefix(file_id: &Uuid) -> String {
+    format!("{}{}{}", get_tfslite_prefix(), BLOCK_TYPE, file_id.simple())
+}
+
+pub fn account_address(public_key: &[u8]) -> String {
+    format!("{}{}{}", get_tfslite_prefix(), ACCOUNT_TYPE, hash_hex(public_key, 62))
+}
+
+pub fn directory_address(public_key: &[u8]) -> String {
+    format!("{}{}{}", get_tfslite_prefix(), DIRECTORY_TYPE, hash_hex(public_key, 62))
+}
+
+pub fn permission_address(permission: &Permission, public_key: &[u8]) -> String {
+    format!("{}{}{}{}", get_tfslite_prefix(), PERMISSION_TYPE, permission.to_hex(), hash_hex(public_key, 60))
+}
+
+pub fn alias_address(alias: &str) -> String {
+    format!("{}{}{}", get_tfslite_prefix(), ALIAS_TYPE, hash_hex(alias.as_bytes(), 62))
+}
+
+// Latest time recorded by a timestamp authority, which retention periods are measured against.
+pub fn clock_address() -> String {
+    format!("{}{}{}", get_tfslite_prefix(), CLOCK_TYPE, "0".repeat(62))
+}
+
+fn parse_uuid(s: &str) -> Result<Uuid, AddressParseError> {
+    Uuid::try_parse(s)
+        .map_err(|err| AddressParseError::InvalidField(format!("{}", err)))
+}
+
+pub fn parse_address(address: &str) -> Result<StateAddress, AddressParseError> {
+    if address.len() != ADDRESS_LENGTH || !address.is_ascii() {
+        return Err(AddressParseError::InvalidLength(address.len()));
+    }
+
+    let prefix = get_tfslite_prefix();
+    if !address.starts_with(prefix.as_str()) {
+        return Err(AddressParseError::InvalidPrefix(address[..prefix.len()].to_string()));
+    }
+
+    let body = &address[prefix.len()..];
+    let (address_type, rest) = body.split_at(2);
+
+    match address_type {
+        FILE_TYPE => Ok(StateAddress::File(parse_uuid(&rest[..32])?)),
+        BLOCK_TYPE => {
+            let number = u64::from_str_radix(&rest[32..48], 16)
+                .map_err(|err| AddressParseError::InvalidField(format!("{}", err)))?;
+            Ok(StateAddress::Block(parse_uuid(&rest[..32])?, number))
+        },
+        ACCOUNT_TYPE => Ok(StateAddress::Account(rest.to_string())),
+        DIRECTORY_TYPE => Ok(StateAddress::Directory(rest.to_string())),
+        PERMISSION_TYPE => {
+            let permission = match &rest[..2] {
+                "00" => Permission::Unset,
+                "01" => Permission::SetPermission,
+                "02" => Permission::Batcher,
+                "03" => Permission::Deposit,
+                "04" => Permission::Timestamp,
+                "05" => Permission::LegalHold,
+                other => {
+                    return Err(AddressParseError::InvalidField(format!("Unknown permission {}", other)));
+                },
+            };
+            Ok(StateAddress::Permission(permission, rest[2..].to_string()))
+        },
+        CLOCK_TYPE => Ok(StateAddress::Clock),
+        ALIAS_TYPE => Ok(StateAddress::Alias(rest.to_string())),
+        other => Err(AddressParseError::InvalidType(other.to_string())),
+    }
+}
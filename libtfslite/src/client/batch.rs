@@ -1,15 +1,19 @@
 use std::fmt::{Display, Formatter, Debug};
 use std::error::Error;
-use protobuf::{Message, RepeatedField};
-use crate::client::keys::{Signer, SigningError};
+use std::fs;
+use std::path::Path;
+use prost::Message;
+use crate::client::keys::{PublicKey, Signature, Signer, SigningError, Verifier};
+use crate::client::transaction::TransactionExt;
 use crate::protos::transaction::Transaction;
-use crate::protos::batch::{Batch, BatchHeader};
+use crate::protos::batch::{Batch, BatchHeader, BatchList};
 
 #[derive(Debug)]
 pub enum BatchBuildError {
     SerializationError(String),
     MissingField(String),
     SigningError(String),
+    IoError(String),
 }
 
 impl Error for BatchBuildError {}
@@ -20,6 +24,7 @@ impl Display for BatchBuildError {
             BatchBuildError::SerializationError(ref s) => write!(f, "SerializationError: {}", s),
             BatchBuildError::MissingField(ref s) => write!(f, "MissingField: {}", s),
             BatchBuildError::SigningError(ref s) => write!(f, "SigningError: {}", s),
+            BatchBuildError::IoError(ref s) => write!(f, "IoError: {}", s),
         }
     }
 }
@@ -46,39 +51,188 @@ impl BatchBuilder {
     }
 
     pub fn build(self, signer: &dyn Signer) -> Result<Batch, BatchBuildError> {
-        let mut batch_header = BatchHeader::new();
+        let unsigned = self.build_unsigned(&signer.public_key()?)?;
 
-        let signer_public_key = signer.public_key()?.as_hex();
-        batch_header.set_signer_public_key(signer_public_key);
+        let signature = signer
+            .sign(unsigned.header_bytes())
+            .map_err(|err| {
+                BatchBuildError::SigningError(format!("Unable to sign batch: {}", err))
+            })?;
 
+        Ok(unsigned.finish(&signature))
+    }
+
+    // Builds everything but the signature, for signers that cannot sign synchronously.
+    pub fn build_unsigned(self, signer_public_key: &PublicKey) -> Result<UnsignedBatch, BatchBuildError> {
         let transactions = self.transactions.ok_or_else(|| {
             BatchBuildError::MissingField("Field 'transactions' is required".to_string())
         })?;
 
-        let transaction_ids = transactions
+        let batch_header = BatchHeader {
+            signer_public_key: signer_public_key.as_hex(),
+            transaction_ids: transactions
+                .iter()
+                .map(|tx| tx.header_signature.clone())
+                .collect(),
+        };
+        let batch_header_bytes = batch_header.encode_to_vec();
+
+        Ok(UnsignedBatch {
+            header: batch_header_bytes,
+            transactions,
+        })
+    }
+}
+
+pub struct UnsignedBatch {
+    header: Vec<u8>,
+    transactions: Vec<Transaction>,
+}
+
+impl UnsignedBatch {
+    pub fn header_bytes(&self) -> &[u8] {
+        &self.header
+    }
+
+    pub fn finish(self, signature: &Signature) -> Batch {
+        Batch {
+            header: self.header,
+            header_signature: signature.as_hex(),
+            transactions: self.transactions,
+            trace: false,
+        }
+    }
+}
+
+pub fn build_batch_list(batches: Vec<Batch>) -> BatchList {
+    BatchList {
+        batches,
+    }
+}
+
+// A .batch file is a serialized BatchList, as read by `sawtooth batch submit -f`.
+pub fn write_batch_file(path: impl AsRef<Path>, batch_list: &BatchList) -> Result<(), BatchBuildError> {
+    fs::write(path, batch_list.encode_to_vec())
+        .map_err(|err| {
+            BatchBuildError::IoError(format!("Unable to write batch file: {}", err))
+        })
+}
+
+pub fn read_batch_file(path: impl AsRef<Path>) -> Result<BatchList, BatchBuildError> {
+    let batch_list_bytes = fs::read(path)
+        .map_err(|err| {
+            BatchBuildError::IoError(format!("Unable to read batch file: {}", err))
+        })?;
+
+    BatchList::decode(batch_list_bytes.as_slice())
+        .map_err(|err| {
+            BatchBuildError::SerializationError(format!("Unable to parse batch list: {}", err))
+        })
+}
+
+#[derive(Debug)]
+pub struct BatchValidationError(String);
+
+impl Display for BatchValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ValidateBatchError: {}", self.0)
+    }
+}
+
+impl Error for BatchValidationError {}
+
+pub trait BatchExt {
+    fn validate(&self) -> Result<(), BatchValidationError>;
+}
+
+impl BatchExt for Batch {
+    fn validate(&self) -> Result<(), BatchValidationError> {
+        let header = BatchHeader::decode(self.header.as_slice())
+            .map_err(|_err| BatchValidationError(String::from("Batch header could not be parsed")))?;
+
+        let public_key = PublicKey::load_from_hex(&header.signer_public_key)
+            .map_err(|_err| BatchValidationError(String::from("Batch signer public key could not be loaded")))?;
+
+        let signature = Signature::try_from(self.header_signature.as_str())
+            .map_err(|err| BatchValidationError(format!("Error loading Batch signature: {}", err)))?;
+
+        let verified = public_key.verify(&self.header, &signature)
+            .map_err(|err| BatchValidationError(format!("Error during signature verification: {}", err)))?;
+
+        if !verified {
+            return Err(BatchValidationError("Batch signature is invalid".to_string()));
+        }
+
+        let transaction_ids: Vec<&String> = self.transactions
             .iter()
-            .map(|tx| tx.get_header_signature().to_string())
+            .map(|tx| &tx.header_signature)
             .collect();
-        batch_header.set_transaction_ids(RepeatedField::from_vec(transaction_ids));
+        if header.transaction_ids.iter().collect::<Vec<_>>() != transaction_ids {
+            return Err(BatchValidationError("Batch transaction ids do not match its transactions".to_string()));
+        }
 
-        let batch_header_bytes = batch_header
-            .write_to_bytes()
-            .map_err(|err| {
-                BatchBuildError::SerializationError(format!("Unable to serialize batch header: {}", err))
-            })?;
+        for tx in self.transactions.iter() {
+            tx.validate()
+                .map_err(|err| BatchValidationError(format!("Transaction {} is invalid: {}", tx.header_signature, err)))?;
 
-        let signature = signer
-            .sign(&batch_header_bytes)
-            .map_err(|err| {
-                BatchBuildError::SigningError(format!("Unable to sign batch: {}", err))
-            })?;
+            // Validators reject a batch whose signer is not the batcher its transactions name.
+            let tx_header = tx.header()
+                .map_err(|err| BatchValidationError(format!("Transaction {} is invalid: {}", tx.header_signature, err)))?;
+            if tx_header.batcher_public_key != header.signer_public_key {
+                return Err(BatchValidationError(format!("Transaction {} names batcher {}, not the batch signer", tx.header_signature, tx_header.batcher_public_key)));
+            }
+        }
 
-        let mut batch = Batch::new();
+        Ok(())
+    }
+}
 
-        batch.set_header(batch_header_bytes.to_vec());
-        batch.set_header_signature(signature.as_hex());
-        batch.set_transactions(RepeatedField::from_vec(transactions));
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use crate::client::keys::{PrivateKey, Signer};
+    use crate::client::payload::{PayloadBuilder, PayloadOperation};
+    use crate::client::transaction::TransactionBuilder;
+    use crate::protos::transaction::Transaction;
+    use crate::types::FileMode;
+    use super::{BatchBuilder, BatchExt};
+
+    fn file_create(signer: &PrivateKey, batcher: &PrivateKey) -> Transaction {
+        let payload = PayloadBuilder::new(PayloadOperation::FileCreate)
+            .with_uuid(Uuid::new_v4())
+            .with_mode(FileMode::Destroyable)
+            .build()
+            .unwrap();
+
+        TransactionBuilder::new()
+            .with_batcher_public_key(batcher.public_key().unwrap().as_slice().to_vec())
+            .with_payload(payload)
+            .build(signer)
+            .unwrap()
+    }
 
-        Ok(batch)
+    #[test]
+    fn test_validate_batcher_key() {
+        let signer = PrivateKey::generate_random_key();
+        let batcher = PrivateKey::generate_random_key();
+
+        let batch = BatchBuilder::new()
+            .with_transactions(vec![file_create(&signer, &batcher)])
+            .build(&batcher)
+            .unwrap();
+        assert!(batch.validate().is_ok());
+
+        // Correctly signed, but by a key that is not the transactions' batcher.
+        let batch = BatchBuilder::new()
+            .with_transactions(vec![file_create(&signer, &batcher), file_create(&signer, &signer)])
+            .build(&batcher)
+            .unwrap();
+        assert!(batch.validate().is_err());
+
+        let batch = BatchBuilder::new()
+            .with_transactions(vec![file_create(&signer, &batcher)])
+            .build(&signer)
+            .unwrap();
+        assert!(batch.validate().is_err());
     }
 }
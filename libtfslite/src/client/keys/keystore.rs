@@ -0,0 +1,333 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use aes_gcm::aead::{Aead, Payload};
+use rand::{Rng, thread_rng};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::client::keys::{PrivateKey, PublicKey, Signer};
+
+const KEYSTORE_VERSION: u32 = 1;
+const KEY_FILE_EXTENSION: &str = "json";
+
+// scrypt parameters for new keys; existing keys record their own.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(Debug)]
+pub enum KeystoreError {
+    IoError(String),
+    InvalidName(String),
+    NotFound(String),
+    AlreadyExists(String),
+    FormatError(String),
+    EncryptionError(String),
+    DecryptionError(String),
+}
+
+impl Error for KeystoreError {}
+
+impl Display for KeystoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            KeystoreError::IoError(ref s) => write!(f, "IoError: {}", s),
+            KeystoreError::InvalidName(ref s) => write!(f, "InvalidName: {}", s),
+            KeystoreError::NotFound(ref s) => write!(f, "NotFound: {}", s),
+            KeystoreError::AlreadyExists(ref s) => write!(f, "AlreadyExists: {}", s),
+            KeystoreError::FormatError(ref s) => write!(f, "FormatError: {}", s),
+            KeystoreError::EncryptionError(ref s) => write!(f, "EncryptionError: {}", s),
+            KeystoreError::DecryptionError(ref s) => write!(f, "DecryptionError: {}", s),
+        }
+    }
+}
+
+impl From<std::io::Error> for KeystoreError {
+    fn from(value: std::io::Error) -> Self {
+        KeystoreError::IoError(format!("{}", value))
+    }
+}
+
+// The public key is stored in the clear so keys can be listed without the passphrase, and is
+// bound to the ciphertext as associated data so it cannot be swapped out.
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    public_key: String,
+    scrypt_log_n: u8,
+    scrypt_r: u32,
+    scrypt_p: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+// A directory of private keys, one file per key, each encrypted with AES-256-GCM under a key
+// derived from its passphrase with scrypt.
+pub struct Keystore {
+    path: PathBuf,
+}
+
+impl Keystore {
+    pub fn open(path: PathBuf) -> Result<Self, KeystoreError> {
+        fs::create_dir_all(&path)?;
+        Ok(Keystore { path })
+    }
+
+    fn key_path(&self, name: &str) -> Result<PathBuf, KeystoreError> {
+        let valid = !name.is_empty() && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        if !valid || name.starts_with('.') {
+            return Err(KeystoreError::InvalidName(name.to_string()));
+        }
+
+        Ok(self.path.join(format!("{}.{}", name, KEY_FILE_EXTENSION)))
+    }
+
+    fn read_key_file(&self, name: &str) -> Result<KeyFile, KeystoreError> {
+        let path = self.key_path(name)?;
+        if !path.exists() {
+            return Err(KeystoreError::NotFound(name.to_string()));
+        }
+
+        let key_file: KeyFile = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| KeystoreError::FormatError(format!("{}", err)))?;
+        if key_file.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::FormatError(format!("Unsupported keystore version {}", key_file.version)));
+        }
+
+        Ok(key_file)
+    }
+
+    pub fn list(&self) -> Result<Vec<String>, KeystoreError> {
+        let mut names: Vec<String> = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(KEY_FILE_EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn create(&self, name: &str, passphrase: &str) -> Result<PrivateKey, KeystoreError> {
+        let private_key = PrivateKey::generate_random_key();
+        self.import(name, &private_key, passphrase)?;
+        Ok(private_key)
+    }
+
+    pub fn import(&self, name: &str, private_key: &PrivateKey, passphrase: &str) -> Result<(), KeystoreError> {
+        let path = self.key_path(name)?;
+        if path.exists() {
+            return Err(KeystoreError::AlreadyExists(name.to_string()));
+        }
+
+        let public_key = private_key.public_key()
+            .map_err(|err| KeystoreError::FormatError(format!("{}", err)))?
+            .as_hex();
+
+        let mut salt = [0u8; 32];
+        let mut nonce = [0u8; 12];
+        thread_rng().fill(&mut salt[..]);
+        thread_rng().fill(&mut nonce[..]);
+
+        let cipher = cipher(passphrase, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: private_key.expose_secret(), aad: public_key.as_bytes() })
+            .map_err(|err| KeystoreError::EncryptionError(format!("{}", err)))?;
+
+        let key_file = KeyFile {
+            version: KEYSTORE_VERSION,
+            public_key,
+            scrypt_log_n: SCRYPT_LOG_N,
+            scrypt_r: SCRYPT_R,
+            scrypt_p: SCRYPT_P,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+        let key_file_bytes = serde_json::to_vec_pretty(&key_file)
+            .map_err(|err| KeystoreError::FormatError(format!("{}", err)))?;
+
+        // Write to a temporary file of our own and link it into place, so a crash never leaves a
+        // truncated key behind and a key created concurrently under the same name is never replaced.
+        let tmp_path = self.path.join(format!(".{}.{}.tmp", name, hex::encode(thread_rng().gen::<[u8; 8]>())));
+        let result = write_new_file(&tmp_path, &key_file_bytes)
+            .and_then(|_| fs::hard_link(&tmp_path, &path));
+        let _ = fs::remove_file(&tmp_path);
+        match result {
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => Err(KeystoreError::AlreadyExists(name.to_string())),
+            result => Ok(result?),
+        }
+    }
+
+    pub fn load(&self, name: &str, passphrase: &str) -> Result<PrivateKey, KeystoreError> {
+        let key_file = self.read_key_file(name)?;
+
+        let salt = decode_hex(&key_file.salt)?;
+        let nonce = decode_hex(&key_file.nonce)?;
+        let ciphertext = decode_hex(&key_file.ciphertext)?;
+        if nonce.len() != 12 {
+            return Err(KeystoreError::FormatError("Invalid nonce length".to_string()));
+        }
+
+        let cipher = cipher(passphrase, &salt, key_file.scrypt_log_n, key_file.scrypt_r, key_file.scrypt_p)?;
+        let key_bytes = cipher
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: key_file.public_key.as_bytes() })
+            .map_err(|_err| KeystoreError::DecryptionError("Wrong passphrase or corrupted key file".to_string()))?;
+
+        let key_bytes = Zeroizing::new(key_bytes);
+
+        Ok(PrivateKey::load_from_bytes(&key_bytes))
+    }
+
+    pub fn public_key(&self, name: &str) -> Result<PublicKey, KeystoreError> {
+        let key_file = self.read_key_file(name)?;
+
+        PublicKey::load_from_hex(&key_file.public_key)
+            .map_err(|err| KeystoreError::FormatError(format!("{}", err)))
+    }
+
+    pub fn remove(&self, name: &str) -> Result<(), KeystoreError> {
+        let path = self.key_path(name)?;
+        if !path.exists() {
+            return Err(KeystoreError::NotFound(name.to_string()));
+        }
+
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
+
+// Created readable by the owner only from the start, and never over an existing file.
+fn write_new_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+fn decode_hex(data: &str) -> Result<Vec<u8>, KeystoreError> {
+    hex::decode(data)
+        .map_err(|err| KeystoreError::FormatError(format!("{}", err)))
+}
+
+fn cipher(passphrase: &str, salt: &[u8], log_n: u8, r: u32, p: u32) -> Result<Aes256Gcm, KeystoreError> {
+    let params = scrypt::Params::new(log_n, r, p, 32)
+        .map_err(|err| KeystoreError::FormatError(format!("{}", err)))?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, key.as_mut_slice())
+        .map_err(|err| KeystoreError::FormatError(format!("{}", err)))?;
+
+    Aes256Gcm::new_from_slice(key.as_slice())
+        .map_err(|err| KeystoreError::FormatError(format!("{}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+    use crate::client::keys::{PrivateKey, Signer};
+    use super::{Keystore, KeystoreError};
+
+    struct TempKeystore {
+        path: PathBuf,
+        keystore: Keystore,
+    }
+
+    impl TempKeystore {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("tfslite-keystore-{}", Uuid::new_v4()));
+            let keystore = Keystore::open(path.clone()).unwrap();
+            TempKeystore { path, keystore }
+        }
+    }
+
+    impl Drop for TempKeystore {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn public_key_hex(key: &PrivateKey) -> String {
+        key.public_key().unwrap().as_hex()
+    }
+
+    #[test]
+    fn test_keystore_create_and_load() {
+        let temp = TempKeystore::new();
+
+        let created = temp.keystore.create("signer", "passphrase").unwrap();
+        let loaded = temp.keystore.load("signer", "passphrase").unwrap();
+        assert_eq!(public_key_hex(&loaded), public_key_hex(&created));
+        assert_eq!(temp.keystore.public_key("signer").unwrap().as_hex(), public_key_hex(&created));
+
+        assert!(matches!(temp.keystore.create("signer", "passphrase"), Err(KeystoreError::AlreadyExists(_))));
+        assert!(matches!(temp.keystore.create("../signer", "passphrase"), Err(KeystoreError::InvalidName(_))));
+        assert!(matches!(temp.keystore.load("missing", "passphrase"), Err(KeystoreError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_keystore_import_and_list() {
+        let temp = TempKeystore::new();
+        assert!(temp.keystore.list().unwrap().is_empty());
+
+        let key = PrivateKey::generate_random_key();
+        temp.keystore.import("imported", &key, "passphrase").unwrap();
+        temp.keystore.create("created", "passphrase").unwrap();
+        fs::write(temp.path.join("notes.txt"), b"not a key").unwrap();
+        assert_eq!(temp.keystore.list().unwrap(), vec!["created".to_string(), "imported".to_string()]);
+
+        let loaded = temp.keystore.load("imported", "passphrase").unwrap();
+        assert_eq!(public_key_hex(&loaded), public_key_hex(&key));
+
+        temp.keystore.remove("created").unwrap();
+        assert_eq!(temp.keystore.list().unwrap(), vec!["imported".to_string()]);
+    }
+
+    #[test]
+    fn test_keystore_wrong_passphrase() {
+        let temp = TempKeystore::new();
+        temp.keystore.create("signer", "passphrase").unwrap();
+
+        assert!(matches!(temp.keystore.load("signer", "wrong passphrase"), Err(KeystoreError::DecryptionError(_))));
+    }
+
+    #[test]
+    fn test_keystore_import_never_replaces() {
+        let temp = TempKeystore::new();
+        temp.keystore.create("signer", "passphrase").unwrap();
+        let key_path = temp.path.join("signer.json");
+        let original = fs::read(&key_path).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&key_path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        // Importing over an existing key fails, and leaves no temporary file behind.
+        let key = PrivateKey::generate_random_key();
+        assert!(matches!(temp.keystore.import("signer", &key, "passphrase"), Err(KeystoreError::AlreadyExists(_))));
+        assert_eq!(fs::read(&key_path).unwrap(), original);
+        let entries: Vec<_> = fs::read_dir(&temp.path).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+}
@@ -2,3 +2,7 @@ pub mod payload;
 pub mod transaction;
 pub mod batch;
 pub mod keys;
+pub mod multisig;
+pub mod offline;
+pub mod policy;
+pub mod upload;
@@ -0,0 +1,127 @@
+// Collects cosignatures for a transaction of an account that needs k of its n keys to approve
+// it. Each cosigner signs the proposal's signing_bytes() and passes back a PartialSignature;
+// once enough are added, into_payload() gives the payload to build the transaction from, which
+// the account's own key then signs as usual.
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+use crate::client::keys::{PublicKey, Signature, Signer, Verifier};
+use crate::common::cosigning_bytes;
+use crate::protos::payload::{Payload, Payload_Cosignature};
+use crate::types::AccountRecord;
+
+#[derive(Debug)]
+pub enum MultisigError {
+    InvalidKey(String),
+    InvalidSignature(String),
+    Incomplete(String),
+}
+
+impl Error for MultisigError {}
+
+impl Display for MultisigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            MultisigError::InvalidKey(ref s) => write!(f, "InvalidKey: {}", s),
+            MultisigError::InvalidSignature(ref s) => write!(f, "InvalidSignature: {}", s),
+            MultisigError::Incomplete(ref s) => write!(f, "Incomplete: {}", s),
+        }
+    }
+}
+
+// One cosigner's approval, hex encoded so it can be passed around as JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub public_key: String,
+    pub signature: String,
+}
+
+#[derive(Clone)]
+pub struct MultisigProposal {
+    account: Vec<u8>,
+    sequence: u64,
+    threshold: u32,
+    public_keys: Vec<Vec<u8>>,
+    payload: Payload,
+    cosignatures: Vec<Payload_Cosignature>,
+}
+
+impl MultisigProposal {
+    // record is the account's current record; a proposal goes stale once another cosigned
+    // transaction of the account commits.
+    pub fn new(account: &PublicKey, record: &AccountRecord, payload: Payload) -> Self {
+        MultisigProposal {
+            account: account.as_slice().to_vec(),
+            sequence: record.multisig_sequence,
+            threshold: record.multisig_threshold,
+            public_keys: record.multisig_public_keys.clone(),
+            payload,
+            cosignatures: Vec::new(),
+        }
+    }
+
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        cosigning_bytes(&self.account, self.sequence, &self.payload)
+    }
+
+    pub fn sign(&self, signer: &dyn Signer) -> Result<PartialSignature, MultisigError> {
+        let public_key = signer.public_key()
+            .map_err(|err| MultisigError::InvalidKey(format!("{:?}", err)))?;
+        let signature = signer.sign(&self.signing_bytes())
+            .map_err(|err| MultisigError::InvalidSignature(format!("{:?}", err)))?;
+
+        Ok(PartialSignature {
+            public_key: public_key.as_hex(),
+            signature: signature.as_hex(),
+        })
+    }
+
+    // Adding the same cosigner twice is a no-op.
+    pub fn add(&mut self, partial: &PartialSignature) -> Result<(), MultisigError> {
+        let public_key = PublicKey::load_from_hex(&partial.public_key)
+            .map_err(|_| MultisigError::InvalidKey(format!("Invalid public key {}", partial.public_key)))?;
+        if !self.public_keys.iter().any(|key| key.as_slice() == public_key.as_slice()) {
+            return Err(MultisigError::InvalidKey(format!("{} is not a cosigner of the account", partial.public_key)));
+        }
+        if self.cosignatures.iter().any(|cosignature| cosignature.public_key == public_key.as_slice()) {
+            return Ok(());
+        }
+
+        let signature = Signature::try_from(partial.signature.as_str())
+            .map_err(|_| MultisigError::InvalidSignature(format!("Invalid signature {}", partial.signature)))?;
+        if !public_key.verify(&self.signing_bytes(), &signature).unwrap_or(false) {
+            return Err(MultisigError::InvalidSignature(format!("Signature by {} does not verify", partial.public_key)));
+        }
+
+        self.cosignatures.push(Payload_Cosignature {
+            public_key: public_key.as_slice().to_vec(),
+            signature: hex::decode(&partial.signature).unwrap(),
+        });
+        Ok(())
+    }
+
+    // The account's own key counts when it is one of the cosigners, as the processor counts it.
+    pub fn approvals(&self) -> usize {
+        let signer_counts = self.public_keys.contains(&self.account)
+            && !self.cosignatures.iter().any(|cosignature| cosignature.public_key == self.account);
+        self.cosignatures.len() + signer_counts as usize
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.approvals() >= self.threshold as usize
+    }
+
+    pub fn into_payload(self) -> Result<Payload, MultisigError> {
+        if !self.is_complete() {
+            return Err(MultisigError::Incomplete(format!("{} of {} approvals", self.approvals(), self.threshold)));
+        }
+
+        let mut payload = self.payload;
+        payload.cosignatures = self.cosignatures;
+        Ok(payload)
+    }
+}
@@ -0,0 +1,207 @@
+// Signing on a machine that never goes online. The online side builds transactions with
+// TransactionBuilder::build_unsigned, exports their headers with OfflineSigningRequest::export,
+// and carries the file over. The offline side signs it with SigningRequestFile::sign, and the
+// SignatureFile that makes is carried back and assembled into transactions. Payloads stay on
+// the online side; the offline side only sees the payload hash each header commits to.
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use crate::client::keys::{PublicKey, Signature, Signer, Verifier};
+use crate::client::transaction::UnsignedTransaction;
+use crate::common::FAMILY_NAME;
+use crate::protos::transaction::{Transaction, TransactionHeader};
+
+#[derive(Debug)]
+pub enum OfflineSigningError {
+    DecodeError(String),
+    InvalidHeader(String),
+    SigningError(String),
+    MissingSignature(String),
+    InvalidSignature(String),
+}
+
+impl Error for OfflineSigningError {}
+
+impl Display for OfflineSigningError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            OfflineSigningError::DecodeError(ref s) => write!(f, "DecodeError: {}", s),
+            OfflineSigningError::InvalidHeader(ref s) => write!(f, "InvalidHeader: {}", s),
+            OfflineSigningError::SigningError(ref s) => write!(f, "SigningError: {}", s),
+            OfflineSigningError::MissingSignature(ref s) => write!(f, "MissingSignature: {}", s),
+            OfflineSigningError::InvalidSignature(ref s) => write!(f, "InvalidSignature: {}", s),
+        }
+    }
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, OfflineSigningError> {
+    hex::decode(value).map_err(|err| OfflineSigningError::DecodeError(format!("Field '{}' is invalid: {}", field, err)))
+}
+
+// Signatures are matched back to headers by this, so their order in the files doesn't matter.
+fn header_sha512(header: &[u8]) -> String {
+    hex::encode(Sha512::digest(header))
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeaderToSign {
+    pub header: String,
+    pub payload_sha512: String,
+}
+
+// What is carried to the offline machine.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SigningRequestFile {
+    pub headers: Vec<HeaderToSign>,
+}
+
+impl SigningRequestFile {
+    pub fn from_bytes(bytes: &[u8]) -> Result<SigningRequestFile, OfflineSigningError> {
+        serde_json::from_slice(bytes).map_err(|err| OfflineSigningError::DecodeError(format!("{}", err)))
+    }
+
+    // Only signs tfslite headers of signer's key that commit to the payload hashes listed.
+    pub fn sign(&self, signer: &dyn Signer) -> Result<SignatureFile, OfflineSigningError> {
+        let public_key = signer.public_key()
+            .map_err(|err| OfflineSigningError::SigningError(format!("{}", err)))?;
+
+        let mut signatures = Vec::with_capacity(self.headers.len());
+        for entry in self.headers.iter() {
+            let header_bytes = decode_hex("header", &entry.header)?;
+            let header = TransactionHeader::decode(header_bytes.as_slice())
+                .map_err(|err| OfflineSigningError::InvalidHeader(format!("{}", err)))?;
+            if header.family_name != FAMILY_NAME {
+                return Err(OfflineSigningError::InvalidHeader(format!("Family {} is not {}", header.family_name, FAMILY_NAME)));
+            }
+            if header.payload_sha512 != entry.payload_sha512 {
+                return Err(OfflineSigningError::InvalidHeader("Header does not commit to the payload hash given".to_string()));
+            }
+            if header.signer_public_key != public_key.as_hex() {
+                return Err(OfflineSigningError::InvalidHeader(format!("Header is for signer {}", header.signer_public_key)));
+            }
+
+            let signature = signer.sign(&header_bytes)
+                .map_err(|err| OfflineSigningError::SigningError(format!("{}", err)))?;
+            signatures.push(HeaderSignature {
+                header_sha512: header_sha512(&header_bytes),
+                signature: signature.as_hex(),
+            });
+        }
+
+        Ok(SignatureFile { signatures })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HeaderSignature {
+    pub header_sha512: String,
+    pub signature: String,
+}
+
+// What is carried back from the offline machine.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignatureFile {
+    pub signatures: Vec<HeaderSignature>,
+}
+
+impl SignatureFile {
+    pub fn from_bytes(bytes: &[u8]) -> Result<SignatureFile, OfflineSigningError> {
+        serde_json::from_slice(bytes).map_err(|err| OfflineSigningError::DecodeError(format!("{}", err)))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("SignatureFile always serializes")
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedTransaction {
+    header: String,
+    payload: String,
+}
+
+// The online side's half, kept until the signatures come back; save and load let it outlive
+// the process.
+pub struct OfflineSigningRequest {
+    transactions: Vec<UnsignedTransaction>,
+}
+
+impl OfflineSigningRequest {
+    pub fn new(transactions: Vec<UnsignedTransaction>) -> Self {
+        OfflineSigningRequest { transactions }
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    pub fn export(&self) -> Vec<u8> {
+        let headers = self.transactions
+            .iter()
+            .map(|unsigned| HeaderToSign {
+                header: hex::encode(unsigned.header_bytes()),
+                payload_sha512: hex::encode(Sha512::digest(unsigned.payload_bytes())),
+            })
+            .collect();
+        serde_json::to_vec(&SigningRequestFile { headers }).expect("SigningRequestFile always serializes")
+    }
+
+    pub fn save(&self) -> Vec<u8> {
+        let saved: Vec<SavedTransaction> = self.transactions
+            .iter()
+            .map(|unsigned| SavedTransaction {
+                header: hex::encode(unsigned.header_bytes()),
+                payload: hex::encode(unsigned.payload_bytes()),
+            })
+            .collect();
+        serde_json::to_vec(&saved).expect("OfflineSigningRequest always serializes")
+    }
+
+    pub fn load(bytes: &[u8]) -> Result<OfflineSigningRequest, OfflineSigningError> {
+        let saved: Vec<SavedTransaction> = serde_json::from_slice(bytes)
+            .map_err(|err| OfflineSigningError::DecodeError(format!("{}", err)))?;
+
+        let transactions = saved
+            .iter()
+            .map(|saved| Ok(UnsignedTransaction::from_parts(decode_hex("header", &saved.header)?, decode_hex("payload", &saved.payload)?)))
+            .collect::<Result<Vec<_>, OfflineSigningError>>()?;
+        Ok(OfflineSigningRequest { transactions })
+    }
+
+    // Every transaction needs a signature that verifies against its header's signer; extra
+    // signatures are ignored.
+    pub fn assemble(self, signatures: &SignatureFile) -> Result<Vec<Transaction>, OfflineSigningError> {
+        let signatures: HashMap<&str, &str> = signatures.signatures
+            .iter()
+            .map(|entry| (entry.header_sha512.as_str(), entry.signature.as_str()))
+            .collect();
+
+        self.transactions
+            .into_iter()
+            .map(|unsigned| {
+                let hash = header_sha512(unsigned.header_bytes());
+                let signature = signatures.get(hash.as_str())
+                    .ok_or_else(|| OfflineSigningError::MissingSignature(format!("No signature for header {}", hash)))?;
+                let signature = Signature::try_from(*signature)
+                    .map_err(|err| OfflineSigningError::InvalidSignature(format!("{}", err)))?;
+
+                let header = TransactionHeader::decode(unsigned.header_bytes())
+                    .map_err(|err| OfflineSigningError::InvalidHeader(format!("{}", err)))?;
+                let public_key = PublicKey::load_from_hex(&header.signer_public_key)
+                    .map_err(|_| OfflineSigningError::InvalidHeader("Signer public key could not be loaded".to_string()))?;
+                if !public_key.verify(unsigned.header_bytes(), &signature).unwrap_or(false) {
+                    return Err(OfflineSigningError::InvalidSignature(format!("Signature for header {} does not verify", hash)));
+                }
+
+                Ok(unsigned.finish(&signature))
+            })
+            .collect()
+    }
+}
@@ -1,14 +1,21 @@
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use serde::{Serialize, Serializer};
 use uuid::Uuid;
 use sha2::Digest;
-use crate::types::{FileMode, Permission};
-use crate::protos::payload::{Payload, Payload_DataBlock, Payload_Operation, Payload_FileMode, Payload_Permission};
+use crate::common::{is_valid_alias, is_valid_filename, MAX_FILENAME_LENGTH, MAX_MULTISIG_KEYS};
+use crate::types::{FileMode, FileRights, Permission, TokenAmount};
+use crate::protos::compat::Message;
+use crate::protos::payload::{Payload, Payload_DataBlock, Payload_FileManifest, Payload_KeyValue, Payload_Operation, Payload_FileMode, Payload_Permission};
 
 #[derive(Debug)]
 pub enum PayloadBuildError {
     SerializationError(String),
     MissingField(String),
+    InvalidUuid(String),
+    InvalidAmount(String),
+    InvalidFilename(String),
+    BlockTooLarge(String),
 }
 
 impl Error for PayloadBuildError {}
@@ -18,26 +25,93 @@ impl Display for PayloadBuildError {
         match *self {
             PayloadBuildError::SerializationError(ref s) => write!(f, "SerializationError: {}", s),
             PayloadBuildError::MissingField(ref s) => write!(f, "MissingField: {}", s),
+            PayloadBuildError::InvalidUuid(ref s) => write!(f, "InvalidUuid: {}", s),
+            PayloadBuildError::InvalidAmount(ref s) => write!(f, "InvalidAmount: {}", s),
+            PayloadBuildError::InvalidFilename(ref s) => write!(f, "InvalidFilename: {}", s),
+            PayloadBuildError::BlockTooLarge(ref s) => write!(f, "BlockTooLarge: {}", s),
         }
     }
 }
 
+#[derive(Debug)]
+pub enum PayloadParseError {
+    DecodeError(String),
+    InvalidField(String),
+}
+
+impl Error for PayloadParseError {}
+
+impl Display for PayloadParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            PayloadParseError::DecodeError(ref s) => write!(f, "DecodeError: {}", s),
+            PayloadParseError::InvalidField(ref s) => write!(f, "InvalidField: {}", s),
+        }
+    }
+}
+
+fn require_uuid(uuid: Option<Uuid>) -> Result<Vec<u8>, PayloadBuildError> {
+    let uuid = uuid.ok_or_else(|| {
+        PayloadBuildError::MissingField("Field 'uuid' is required".to_string())
+    })?;
+    if uuid.is_nil() {
+        return Err(PayloadBuildError::InvalidUuid("Field 'uuid' can't be the nil uuid".to_string()));
+    }
+    Ok(uuid.as_bytes().to_vec())
+}
+
+fn require_amount(amount: Option<TokenAmount>) -> Result<TokenAmount, PayloadBuildError> {
+    let amount = amount.ok_or_else(|| {
+        PayloadBuildError::MissingField("Field 'amount' is required".to_string())
+    })?;
+    if amount.is_zero() {
+        return Err(PayloadBuildError::InvalidAmount("Field 'amount' must be more than 0".to_string()));
+    }
+    Ok(amount)
+}
+
+fn check_filename(filename: &str) -> Result<(), PayloadBuildError> {
+    if !is_valid_filename(filename) {
+        return Err(PayloadBuildError::InvalidFilename(format!("Field 'filename' must be 1 to {} bytes without control characters", MAX_FILENAME_LENGTH)));
+    }
+    Ok(())
+}
+
+fn key_values(entries: Vec<(String, String)>) -> Vec<Payload_KeyValue> {
+    entries
+        .into_iter()
+        .map(|(key, value)| Payload_KeyValue { key, value })
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct PayloadBuilder {
     operation: Payload_Operation,
     uuid: Option<Uuid>,
     mode: Option<Payload_FileMode>,
     block: Option<Payload_DataBlock>,
+    chunk_index: Option<u64>,
+    chunk_total: Option<u64>,
     filename: Option<String>,
     address: Option<Vec<u8>>,
-    amount: Option<u64>,
+    amount: Option<TokenAmount>,
     permission: Option<Payload_Permission>,
     permission_public_key: Option<Vec<u8>>,
     timestamp_create: Option<i64>,
     timestamp_append: Option<i64>,
     timestamp_seal: Option<i64>,
+    manifest: Option<Payload_FileManifest>,
+    tags: Vec<(String, String)>,
+    retain_until: Option<i64>,
+    legal_hold: Option<bool>,
+    grantee: Option<Vec<u8>>,
+    rights: Option<FileRights>,
+    alias: Option<String>,
+    multisig: Option<(u32, Vec<Vec<u8>>)>,
+    max_block_size: Option<usize>,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum PayloadOperation {
     FileCreate,
     FileAppend,
@@ -45,9 +119,19 @@ pub enum PayloadOperation {
     FileDestroy,
     AccountDeposit,
     AccountTransfer,
+    AccountWithdraw,
     PermissionSet,
     PermissionClear,
     TimestampSet,
+    FileManifest,
+    FileTag,
+    FileRetentionSet,
+    FileLegalHoldSet,
+    FileGrant,
+    FileRevoke,
+    AliasSet,
+    AliasClear,
+    AccountMultisigSet,
 }
 
 impl From<PayloadOperation> for Payload_Operation {
@@ -59,9 +143,19 @@ impl From<PayloadOperation> for Payload_Operation {
             PayloadOperation::FileDestroy => Payload_Operation::FILE_DESTROY,
             PayloadOperation::AccountDeposit => Payload_Operation::ACCOUNT_DEPOSIT,
             PayloadOperation::AccountTransfer => Payload_Operation::ACCOUNT_TRANSFER,
+            PayloadOperation::AccountWithdraw => Payload_Operation::ACCOUNT_WITHDRAW,
             PayloadOperation::PermissionSet => Payload_Operation::PERMISSION_SET,
             PayloadOperation::PermissionClear => Payload_Operation::PERMISSION_CLEAR,
             PayloadOperation::TimestampSet => Payload_Operation::TIMESTAMP_SET,
+            PayloadOperation::FileManifest => Payload_Operation::FILE_MANIFEST,
+            PayloadOperation::FileTag => Payload_Operation::FILE_TAG,
+            PayloadOperation::FileRetentionSet => Payload_Operation::FILE_RETENTION_SET,
+            PayloadOperation::FileLegalHoldSet => Payload_Operation::FILE_LEGAL_HOLD_SET,
+            PayloadOperation::FileGrant => Payload_Operation::FILE_GRANT,
+            PayloadOperation::FileRevoke => Payload_Operation::FILE_REVOKE,
+            PayloadOperation::AliasSet => Payload_Operation::ALIAS_SET,
+            PayloadOperation::AliasClear => Payload_Operation::ALIAS_CLEAR,
+            PayloadOperation::AccountMultisigSet => Payload_Operation::ACCOUNT_MULTISIG_SET,
         }
     }
 }
@@ -73,6 +167,8 @@ impl PayloadBuilder {
             uuid: None,
             mode: None,
             block: None,
+            chunk_index: None,
+            chunk_total: None,
             filename: None,
             address: None,
             amount: None,
@@ -81,6 +177,15 @@ impl PayloadBuilder {
             timestamp_create: None,
             timestamp_append: None,
             timestamp_seal: None,
+            manifest: None,
+            tags: Vec::new(),
+            retain_until: None,
+            legal_hold: None,
+            grantee: None,
+            rights: None,
+            alias: None,
+            multisig: None,
+            max_block_size: None,
         }
     }
 
@@ -104,6 +209,41 @@ impl PayloadBuilder {
         self
     }
 
+    // Appends a copy of an earlier block of the same file instead of sending its data again.
+    pub fn with_block_reference(mut self, sha224: Vec<u8>, reference_number: u64) -> Self {
+        let mut block = Payload_DataBlock::new();
+        block.set_sha224(sha224);
+        block.set_reference(true);
+        block.set_reference_number(reference_number);
+
+        self.block = Some(block);
+        self
+    }
+
+    // Like with_block_reference, but copies a block of another file, e.g. an earlier version.
+    pub fn with_file_block_reference(mut self, sha224: Vec<u8>, reference_uuid: Uuid, reference_number: u64) -> Self {
+        self = self.with_block_reference(sha224, reference_number);
+        if let Some(block) = self.block.as_mut() {
+            let uuid_ref: &[u8] = reference_uuid.as_ref();
+            block.set_reference_uuid(uuid_ref.to_vec());
+        }
+        self
+    }
+
+    // The block is stored at chunk_index rather than after the last block, so appends need not be
+    // applied in order.
+    pub fn with_chunk_index(mut self, chunk_index: u64) -> Self {
+        self.chunk_index = Some(chunk_index);
+        self
+    }
+
+    // Lets readers tell a partial file from a complete one, and the processor reject blocks past
+    // the end.
+    pub fn with_chunk_total(mut self, chunk_total: u64) -> Self {
+        self.chunk_total = Some(chunk_total);
+        self
+    }
+
     pub fn with_filename(mut self, filename: String) -> Self {
         self.filename = Some(filename);
         self
@@ -114,8 +254,8 @@ impl PayloadBuilder {
         self
     }
 
-    pub fn with_amount(mut self, amount: u64) -> Self {
-        self.amount = Some(amount);
+    pub fn with_amount(mut self, amount: impl Into<TokenAmount>) -> Self {
+        self.amount = Some(amount.into());
         self
     }
 
@@ -144,17 +284,74 @@ impl PayloadBuilder {
         self
     }
 
+    pub fn with_manifest(mut self, sha512: Vec<u8>, size: u64, metadata: Vec<(String, String)>) -> Self {
+        let mut manifest = Payload_FileManifest::new();
+        manifest.set_sha512(sha512);
+        manifest.set_size(size);
+
+        manifest.set_metadata(key_values(metadata));
+
+        self.manifest = Some(manifest);
+        self
+    }
+
+    // For FILE_TAG, an empty value removes the tag.
+    pub fn with_tag(mut self, key: String, value: String) -> Self {
+        self.tags.push((key, value));
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<(String, String)>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+
+    // Unix time in seconds.
+    pub fn with_retain_until(mut self, retain_until: i64) -> Self {
+        self.retain_until = Some(retain_until);
+        self
+    }
+
+    pub fn with_legal_hold(mut self, legal_hold: bool) -> Self {
+        self.legal_hold = Some(legal_hold);
+        self
+    }
+
+    pub fn with_grantee(mut self, public_key: Vec<u8>) -> Self {
+        self.grantee = Some(public_key);
+        self
+    }
+
+    pub fn with_rights(mut self, rights: FileRights) -> Self {
+        self.rights = Some(rights);
+        self
+    }
+
+    pub fn with_alias(mut self, alias: String) -> Self {
+        self.alias = Some(alias);
+        self
+    }
+
+    // A threshold of 0 with no keys turns cosigning off.
+    pub fn with_multisig(mut self, threshold: u32, public_keys: Vec<Vec<u8>>) -> Self {
+        self.multisig = Some((threshold, public_keys));
+        self
+    }
+
+    // The most block data a FILE_APPEND may carry, usually the gateway's payload limit less
+    // room for the rest of the transaction. Unchecked when not set.
+    pub fn with_max_block_size(mut self, max_block_size: usize) -> Self {
+        self.max_block_size = Some(max_block_size);
+        self
+    }
+
     pub fn build(self) -> Result<Payload, PayloadBuildError> {
         let mut payload = Payload::new();
         payload.set_operation(self.operation);
 
         match self.operation {
             Payload_Operation::FILE_CREATE => {
-                let uuid = self.uuid.ok_or_else(|| {
-                    PayloadBuildError::MissingField("Field 'uuid' is required".to_string())
-                })?;
-                let uuid_ref: &[u8] = uuid.as_ref();
-                payload.set_uuid(uuid_ref.to_vec());
+                payload.set_uuid(require_uuid(self.uuid)?);
 
                 let mode = self.mode.ok_or_else(|| {
                     PayloadBuildError::MissingField("Field 'mode' is required".to_string())
@@ -162,27 +359,33 @@ impl PayloadBuilder {
                 payload.set_mode(mode);
 
                 if let Some(filename) = self.filename {
+                    check_filename(&filename)?;
                     payload.set_filename(filename);
                 }
+                payload.set_tags(key_values(self.tags));
             },
             Payload_Operation::FILE_APPEND => {
-                let uuid = self.uuid.ok_or_else(|| {
-                    PayloadBuildError::MissingField("Field 'uuid' is required".to_string())
-                })?;
-                let uuid_ref: &[u8] = uuid.as_ref();
-                payload.set_uuid(uuid_ref.to_vec());
+                payload.set_uuid(require_uuid(self.uuid)?);
 
-                let block = self.block.ok_or_else(|| {
+                let mut block = self.block.ok_or_else(|| {
                     PayloadBuildError::MissingField("Field 'block' is required".to_string())
                 })?;
+                if let Some(max_block_size) = self.max_block_size {
+                    if block.get_data().len() > max_block_size {
+                        return Err(PayloadBuildError::BlockTooLarge(format!("Block of {} bytes is over the limit of {}", block.get_data().len(), max_block_size)));
+                    }
+                }
+                if let Some(chunk_index) = self.chunk_index {
+                    block.set_number(chunk_index);
+                    block.set_indexed(true);
+                }
+                if let Some(chunk_total) = self.chunk_total {
+                    block.set_total(chunk_total);
+                }
                 payload.set_block(block);
             },
             Payload_Operation::FILE_SEAL | Payload_Operation::FILE_DESTROY => {
-                let uuid = self.uuid.ok_or_else(|| {
-                    PayloadBuildError::MissingField("Field 'uuid' is required".to_string())
-                })?;
-                let uuid_ref: &[u8] = uuid.as_ref();
-                payload.set_uuid(uuid_ref.to_vec());
+                payload.set_uuid(require_uuid(self.uuid)?);
             },
             Payload_Operation::ACCOUNT_DEPOSIT | Payload_Operation::ACCOUNT_TRANSFER => {
                 let address = self.address.ok_or_else(|| {
@@ -190,10 +393,18 @@ impl PayloadBuilder {
                 })?;
                 payload.set_address(address);
 
-                let amount = self.amount.ok_or_else(|| {
-                    PayloadBuildError::MissingField("Field 'amount' is required".to_string())
-                })?;
+                let (amount, amount_wide) = require_amount(self.amount)?.to_payload_fields();
                 payload.set_amount(amount);
+                payload.set_amount_wide(amount_wide);
+            },
+            Payload_Operation::ACCOUNT_WITHDRAW => {
+                let (amount, amount_wide) = require_amount(self.amount)?.to_payload_fields();
+                payload.set_amount(amount);
+                payload.set_amount_wide(amount_wide);
+
+                if let Some(address) = self.address {
+                    payload.set_address(address);
+                }
             },
             Payload_Operation::PERMISSION_SET => {
                 let permission = self.permission.ok_or_else(|| {
@@ -213,11 +424,7 @@ impl PayloadBuilder {
                 payload.set_permission(permission);
             },
             Payload_Operation::TIMESTAMP_SET => {
-                let uuid = self.uuid.ok_or_else(|| {
-                    PayloadBuildError::MissingField("Field 'uuid' is required".to_string())
-                })?;
-                let uuid_ref: &[u8] = uuid.as_ref();
-                payload.set_uuid(uuid_ref.to_vec());
+                payload.set_uuid(require_uuid(self.uuid)?);
 
                 if self.timestamp_create.is_none() && self.timestamp_append.is_none() && self.timestamp_seal.is_none() {
                     return Err(PayloadBuildError::MissingField("At least one of the the fields 'timestamp_create', 'timestamp_append' or 'timestamp_seal' must be set".to_string()));
@@ -234,9 +441,488 @@ impl PayloadBuilder {
                 if let Some(timestamp) = self.timestamp_seal {
                     payload.set_timestamp_seal(timestamp)
                 }
-            }
+            },
+            Payload_Operation::FILE_MANIFEST => {
+                payload.set_uuid(require_uuid(self.uuid)?);
+
+                let manifest = self.manifest.ok_or_else(|| {
+                    PayloadBuildError::MissingField("Field 'manifest' is required".to_string())
+                })?;
+                payload.set_manifest(manifest);
+
+                if let Some(filename) = self.filename {
+                    check_filename(&filename)?;
+                    payload.set_filename(filename);
+                }
+            },
+            Payload_Operation::FILE_TAG => {
+                payload.set_uuid(require_uuid(self.uuid)?);
+
+                if self.tags.is_empty() {
+                    return Err(PayloadBuildError::MissingField("Field 'tags' is required".to_string()));
+                }
+                payload.set_tags(key_values(self.tags));
+            },
+            Payload_Operation::FILE_RETENTION_SET => {
+                payload.set_uuid(require_uuid(self.uuid)?);
+
+                let retain_until = self.retain_until.ok_or_else(|| {
+                    PayloadBuildError::MissingField("Field 'retain_until' is required".to_string())
+                })?;
+                payload.set_retain_until(retain_until);
+            },
+            Payload_Operation::FILE_LEGAL_HOLD_SET => {
+                payload.set_uuid(require_uuid(self.uuid)?);
+
+                let legal_hold = self.legal_hold.ok_or_else(|| {
+                    PayloadBuildError::MissingField("Field 'legal_hold' is required".to_string())
+                })?;
+                payload.set_legal_hold(legal_hold);
+            },
+            Payload_Operation::FILE_GRANT | Payload_Operation::FILE_REVOKE => {
+                payload.set_uuid(require_uuid(self.uuid)?);
+
+                let grantee = self.grantee.ok_or_else(|| {
+                    PayloadBuildError::MissingField("Field 'grantee' is required".to_string())
+                })?;
+                payload.set_grantee(grantee);
+
+                // A revoke without rights takes away all of them.
+                let rights = match (self.operation, self.rights) {
+                    (Payload_Operation::FILE_GRANT, None) => {
+                        return Err(PayloadBuildError::MissingField("Field 'rights' is required".to_string()));
+                    },
+                    (_, rights) => rights.unwrap_or_default(),
+                };
+                payload.set_rights(rights.0);
+            },
+            Payload_Operation::ALIAS_SET | Payload_Operation::ALIAS_CLEAR => {
+                let alias = self.alias.ok_or_else(|| {
+                    PayloadBuildError::MissingField("Field 'alias' is required".to_string())
+                })?;
+                payload.set_alias(alias);
+            },
+            Payload_Operation::ACCOUNT_MULTISIG_SET => {
+                let (threshold, public_keys) = self.multisig.ok_or_else(|| {
+                    PayloadBuildError::MissingField("Field 'multisig_threshold' is required".to_string())
+                })?;
+                payload.multisig_threshold = threshold;
+                payload.multisig_public_keys = public_keys;
+            },
         }
 
         Ok(payload)
     }
 }
+
+// Byte fields are rendered as hex and enums by name. Block data is left out and only its length
+// given, so a view can be logged or shown without dumping the file contents.
+fn serialize_hex<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+fn serialize_hex_option<S: Serializer>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match bytes {
+        Some(bytes) => serialize_hex(bytes, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_hex_list<S: Serializer>(list: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(list.iter().map(hex::encode))
+}
+
+fn serialize_display<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn serialize_len<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(bytes.len() as u64)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "operation", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PayloadView {
+    FileCreate {
+        uuid: Uuid,
+        #[serde(serialize_with = "serialize_display")]
+        mode: FileMode,
+        filename: Option<String>,
+        tags: Vec<(String, String)>,
+    },
+    FileAppend {
+        uuid: Uuid,
+        #[serde(rename = "data_len", serialize_with = "serialize_len")]
+        data: Vec<u8>,
+        #[serde(serialize_with = "serialize_hex")]
+        sha224: Vec<u8>,
+        // None when the block is appended after the last one.
+        number: Option<u64>,
+        total: Option<u64>,
+        // Number of the earlier block this one copies, in which case data is empty.
+        reference: Option<u64>,
+        // File the copied block belongs to, when not this one.
+        reference_uuid: Option<Uuid>,
+    },
+    FileSeal {
+        uuid: Uuid,
+    },
+    FileDestroy {
+        uuid: Uuid,
+    },
+    AccountDeposit {
+        #[serde(serialize_with = "serialize_hex")]
+        address: Vec<u8>,
+        #[serde(serialize_with = "serialize_display")]
+        amount: TokenAmount,
+    },
+    AccountTransfer {
+        #[serde(serialize_with = "serialize_hex")]
+        address: Vec<u8>,
+        #[serde(serialize_with = "serialize_display")]
+        amount: TokenAmount,
+    },
+    AccountWithdraw {
+        #[serde(serialize_with = "serialize_display")]
+        amount: TokenAmount,
+        // Where the gateway settles the amount, if it was given one.
+        #[serde(serialize_with = "serialize_hex_option")]
+        address: Option<Vec<u8>>,
+    },
+    PermissionSet {
+        #[serde(serialize_with = "serialize_display")]
+        permission: Permission,
+        #[serde(serialize_with = "serialize_hex")]
+        public_key: Vec<u8>,
+    },
+    PermissionClear {
+        #[serde(serialize_with = "serialize_display")]
+        permission: Permission,
+        #[serde(serialize_with = "serialize_hex_option")]
+        public_key: Option<Vec<u8>>,
+    },
+    TimestampSet {
+        uuid: Uuid,
+        timestamp_create: Option<i64>,
+        timestamp_append: Option<i64>,
+        timestamp_seal: Option<i64>,
+    },
+    FileManifest {
+        uuid: Uuid,
+        #[serde(serialize_with = "serialize_hex")]
+        sha512: Vec<u8>,
+        size: u64,
+        metadata: Vec<(String, String)>,
+        filename: Option<String>,
+    },
+    FileTag {
+        uuid: Uuid,
+        // An empty value removes the tag.
+        tags: Vec<(String, String)>,
+    },
+    FileRetentionSet {
+        uuid: Uuid,
+        retain_until: i64,
+    },
+    FileLegalHoldSet {
+        uuid: Uuid,
+        legal_hold: bool,
+    },
+    FileGrant {
+        uuid: Uuid,
+        #[serde(serialize_with = "serialize_hex")]
+        public_key: Vec<u8>,
+        #[serde(serialize_with = "serialize_display")]
+        rights: FileRights,
+    },
+    FileRevoke {
+        uuid: Uuid,
+        #[serde(serialize_with = "serialize_hex")]
+        public_key: Vec<u8>,
+        // All rights when empty.
+        #[serde(serialize_with = "serialize_display")]
+        rights: FileRights,
+    },
+    AliasSet {
+        alias: String,
+    },
+    AliasClear {
+        alias: String,
+    },
+    AccountMultisigSet {
+        threshold: u32,
+        #[serde(serialize_with = "serialize_hex_list")]
+        public_keys: Vec<Vec<u8>>,
+    },
+}
+
+fn parse_uuid(payload: &Payload) -> Result<Uuid, PayloadParseError> {
+    Uuid::from_slice(payload.get_uuid())
+        .map_err(|err| PayloadParseError::InvalidField(format!("Field 'uuid' is invalid: {}", err)))
+}
+
+fn parse_amount(payload: &Payload) -> Result<TokenAmount, PayloadParseError> {
+    TokenAmount::from_payload_fields(payload.get_amount(), payload.get_amount_wide())
+        .ok_or_else(|| PayloadParseError::InvalidField("Field 'amount_wide' is invalid".to_string()))
+}
+
+fn non_empty_string(s: &str) -> Option<String> {
+    if s.is_empty() { None } else { Some(s.to_string()) }
+}
+
+fn parse_key_values(entries: &[Payload_KeyValue]) -> Vec<(String, String)> {
+    entries
+        .iter()
+        .map(|entry| (entry.get_key().to_string(), entry.get_value().to_string()))
+        .collect()
+}
+
+fn non_zero(n: i64) -> Option<i64> {
+    if n == 0 { None } else { Some(n) }
+}
+
+impl PayloadView {
+    pub fn from_bytes(bytes: &[u8]) -> Result<PayloadView, PayloadParseError> {
+        let payload = Payload::parse_from_bytes(bytes)
+            .map_err(|err| PayloadParseError::DecodeError(format!("{}", err)))?;
+
+        PayloadView::try_from(&payload)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("PayloadView always serializes")
+    }
+
+    pub fn operation(&self) -> PayloadOperation {
+        match self {
+            PayloadView::FileCreate { .. } => PayloadOperation::FileCreate,
+            PayloadView::FileAppend { .. } => PayloadOperation::FileAppend,
+            PayloadView::FileSeal { .. } => PayloadOperation::FileSeal,
+            PayloadView::FileDestroy { .. } => PayloadOperation::FileDestroy,
+            PayloadView::AccountDeposit { .. } => PayloadOperation::AccountDeposit,
+            PayloadView::AccountTransfer { .. } => PayloadOperation::AccountTransfer,
+            PayloadView::AccountWithdraw { .. } => PayloadOperation::AccountWithdraw,
+            PayloadView::PermissionSet { .. } => PayloadOperation::PermissionSet,
+            PayloadView::PermissionClear { .. } => PayloadOperation::PermissionClear,
+            PayloadView::TimestampSet { .. } => PayloadOperation::TimestampSet,
+            PayloadView::FileManifest { .. } => PayloadOperation::FileManifest,
+            PayloadView::FileTag { .. } => PayloadOperation::FileTag,
+            PayloadView::FileRetentionSet { .. } => PayloadOperation::FileRetentionSet,
+            PayloadView::FileLegalHoldSet { .. } => PayloadOperation::FileLegalHoldSet,
+            PayloadView::FileGrant { .. } => PayloadOperation::FileGrant,
+            PayloadView::FileRevoke { .. } => PayloadOperation::FileRevoke,
+            PayloadView::AliasSet { .. } => PayloadOperation::AliasSet,
+            PayloadView::AliasClear { .. } => PayloadOperation::AliasClear,
+            PayloadView::AccountMultisigSet { .. } => PayloadOperation::AccountMultisigSet,
+        }
+    }
+
+    // The file an operation acts on, for those that act on one.
+    pub fn uuid(&self) -> Option<Uuid> {
+        match self {
+            PayloadView::FileCreate { uuid, .. }
+            | PayloadView::FileAppend { uuid, .. }
+            | PayloadView::FileSeal { uuid }
+            | PayloadView::FileDestroy { uuid }
+            | PayloadView::TimestampSet { uuid, .. }
+            | PayloadView::FileManifest { uuid, .. }
+            | PayloadView::FileTag { uuid, .. }
+            | PayloadView::FileRetentionSet { uuid, .. }
+            | PayloadView::FileLegalHoldSet { uuid, .. }
+            | PayloadView::FileGrant { uuid, .. }
+            | PayloadView::FileRevoke { uuid, .. } => Some(*uuid),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&Payload> for PayloadView {
+    type Error = PayloadParseError;
+
+    fn try_from(payload: &Payload) -> Result<Self, Self::Error> {
+        let view = match payload.get_operation() {
+            Payload_Operation::FILE_CREATE => PayloadView::FileCreate {
+                uuid: parse_uuid(payload)?,
+                mode: payload.get_mode().into(),
+                filename: non_empty_string(payload.get_filename()),
+                tags: parse_key_values(payload.get_tags()),
+            },
+            Payload_Operation::FILE_APPEND => {
+                if !payload.has_block() {
+                    return Err(PayloadParseError::InvalidField("Field 'block' is required".to_string()));
+                }
+                let block = payload.get_block();
+                if !block.get_reference() && sha2::Sha224::digest(block.get_data()).as_slice() != block.get_sha224() {
+                    return Err(PayloadParseError::InvalidField("Field 'block' has a sha224 that does not match its data".to_string()));
+                }
+
+                PayloadView::FileAppend {
+                    uuid: parse_uuid(payload)?,
+                    data: block.get_data().to_vec(),
+                    sha224: block.get_sha224().to_vec(),
+                    number: if block.get_indexed() { Some(block.get_number()) } else { None },
+                    total: if block.get_total() == 0 { None } else { Some(block.get_total()) },
+                    reference: if block.get_reference() { Some(block.get_reference_number()) } else { None },
+                    reference_uuid: if block.get_reference_uuid().is_empty() {
+                        None
+                    } else {
+                        Some(Uuid::from_slice(block.get_reference_uuid())
+                            .map_err(|err| PayloadParseError::InvalidField(format!("Field 'reference_uuid' is invalid: {}", err)))?)
+                    },
+                }
+            },
+            Payload_Operation::FILE_SEAL => PayloadView::FileSeal {
+                uuid: parse_uuid(payload)?,
+            },
+            Payload_Operation::FILE_DESTROY => PayloadView::FileDestroy {
+                uuid: parse_uuid(payload)?,
+            },
+            Payload_Operation::ACCOUNT_DEPOSIT => PayloadView::AccountDeposit {
+                address: payload.get_address().to_vec(),
+                amount: parse_amount(payload)?,
+            },
+            Payload_Operation::ACCOUNT_TRANSFER => PayloadView::AccountTransfer {
+                address: payload.get_address().to_vec(),
+                amount: parse_amount(payload)?,
+            },
+            Payload_Operation::ACCOUNT_WITHDRAW => {
+                let amount = parse_amount(payload)?;
+                if amount.is_zero() {
+                    return Err(PayloadParseError::InvalidField("Field 'amount' must be greater than zero".to_string()));
+                }
+
+                PayloadView::AccountWithdraw {
+                    amount,
+                    address: (!payload.get_address().is_empty()).then(|| payload.get_address().to_vec()),
+                }
+            },
+            Payload_Operation::PERMISSION_SET => {
+                let permission: Permission = payload.get_permission().into();
+                if permission == Permission::Unset {
+                    return Err(PayloadParseError::InvalidField("Field 'permission' is required".to_string()));
+                }
+                if payload.get_permission_public_key().is_empty() {
+                    return Err(PayloadParseError::InvalidField("Field 'permission_public_key' is required".to_string()));
+                }
+
+                PayloadView::PermissionSet {
+                    permission,
+                    public_key: payload.get_permission_public_key().to_vec(),
+                }
+            },
+            Payload_Operation::PERMISSION_CLEAR => {
+                let public_key = payload.get_permission_public_key();
+                PayloadView::PermissionClear {
+                    permission: payload.get_permission().into(),
+                    public_key: if public_key.is_empty() { None } else { Some(public_key.to_vec()) },
+                }
+            },
+            Payload_Operation::TIMESTAMP_SET => {
+                let timestamp_create = non_zero(payload.get_timestamp_create());
+                let timestamp_append = non_zero(payload.get_timestamp_append());
+                let timestamp_seal = non_zero(payload.get_timestamp_seal());
+                if timestamp_create.is_none() && timestamp_append.is_none() && timestamp_seal.is_none() {
+                    return Err(PayloadParseError::InvalidField("At least one of the the fields 'timestamp_create', 'timestamp_append' or 'timestamp_seal' must be set".to_string()));
+                }
+
+                PayloadView::TimestampSet {
+                    uuid: parse_uuid(payload)?,
+                    timestamp_create,
+                    timestamp_append,
+                    timestamp_seal,
+                }
+            },
+            Payload_Operation::FILE_MANIFEST => {
+                if !payload.has_manifest() {
+                    return Err(PayloadParseError::InvalidField("Field 'manifest' is required".to_string()));
+                }
+                let manifest = payload.get_manifest();
+
+                PayloadView::FileManifest {
+                    uuid: parse_uuid(payload)?,
+                    sha512: manifest.get_sha512().to_vec(),
+                    size: manifest.get_size(),
+                    metadata: parse_key_values(manifest.get_metadata()),
+                    filename: non_empty_string(payload.get_filename()),
+                }
+            },
+            Payload_Operation::FILE_TAG => {
+                if payload.get_tags().is_empty() {
+                    return Err(PayloadParseError::InvalidField("Field 'tags' is required".to_string()));
+                }
+
+                PayloadView::FileTag {
+                    uuid: parse_uuid(payload)?,
+                    tags: parse_key_values(payload.get_tags()),
+                }
+            },
+            Payload_Operation::FILE_RETENTION_SET => PayloadView::FileRetentionSet {
+                uuid: parse_uuid(payload)?,
+                retain_until: payload.get_retain_until(),
+            },
+            Payload_Operation::FILE_LEGAL_HOLD_SET => PayloadView::FileLegalHoldSet {
+                uuid: parse_uuid(payload)?,
+                legal_hold: payload.get_legal_hold(),
+            },
+            Payload_Operation::FILE_GRANT | Payload_Operation::FILE_REVOKE => {
+                if payload.get_grantee().is_empty() {
+                    return Err(PayloadParseError::InvalidField("Field 'grantee' is required".to_string()));
+                }
+                let rights = FileRights(payload.get_rights());
+
+                if payload.get_operation() == Payload_Operation::FILE_GRANT {
+                    if rights.is_empty() {
+                        return Err(PayloadParseError::InvalidField("Field 'rights' is required".to_string()));
+                    }
+                    PayloadView::FileGrant {
+                        uuid: parse_uuid(payload)?,
+                        public_key: payload.get_grantee().to_vec(),
+                        rights,
+                    }
+                } else {
+                    PayloadView::FileRevoke {
+                        uuid: parse_uuid(payload)?,
+                        public_key: payload.get_grantee().to_vec(),
+                        rights,
+                    }
+                }
+            },
+            Payload_Operation::ALIAS_SET | Payload_Operation::ALIAS_CLEAR => {
+                if !is_valid_alias(payload.get_alias()) {
+                    return Err(PayloadParseError::InvalidField("Field 'alias' is invalid".to_string()));
+                }
+
+                let alias = payload.get_alias().to_string();
+                if payload.get_operation() == Payload_Operation::ALIAS_SET {
+                    PayloadView::AliasSet { alias }
+                } else {
+                    PayloadView::AliasClear { alias }
+                }
+            },
+            Payload_Operation::ACCOUNT_MULTISIG_SET => {
+                let threshold = payload.multisig_threshold;
+                let public_keys = payload.multisig_public_keys.clone();
+                if public_keys.len() > MAX_MULTISIG_KEYS || public_keys.iter().any(|key| key.len() != 33) {
+                    return Err(PayloadParseError::InvalidField("Field 'multisig_public_keys' is invalid".to_string()));
+                }
+                if threshold as usize > public_keys.len() || (threshold == 0 && !public_keys.is_empty()) {
+                    return Err(PayloadParseError::InvalidField("Field 'multisig_threshold' is invalid".to_string()));
+                }
+
+                PayloadView::AccountMultisigSet { threshold, public_keys }
+            },
+        };
+
+        Ok(view)
+    }
+}
+
+pub trait PayloadExt {
+    fn parse_into(&self) -> Result<PayloadView, PayloadParseError>;
+}
+
+impl PayloadExt for Payload {
+    fn parse_into(&self) -> Result<PayloadView, PayloadParseError> {
+        PayloadView::try_from(self)
+    }
+}
@@ -0,0 +1,130 @@
+// A signer for semi-trusted automation, so it can hold a delegated key and only use it within
+// limits. Each transaction's payload is checked when it is approved, before its header is signed,
+// and only headers of approved payloads are signed. Anything else, such as batches, manifests or
+// headers built without approval, is refused.
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use prost::Message;
+use sha2::{Digest, Sha512};
+use uuid::Uuid;
+use crate::client::keys::{PublicKey, Signature, Signer, SigningError};
+use crate::client::payload::{PayloadOperation, PayloadView};
+use crate::common::FAMILY_NAME;
+use crate::protos::transaction::TransactionHeader;
+use crate::types::TokenAmount;
+
+type PolicyRule = Arc<dyn Fn(&PayloadView) -> Result<(), String> + Send + Sync>;
+
+fn refused(reason: &str) -> SigningError {
+    cylinder::SigningError::Internal(format!("Refused by policy: {}", reason)).into()
+}
+
+#[derive(Clone)]
+pub struct PolicySigner {
+    signer: Box<dyn Signer>,
+    operations: Option<Vec<PayloadOperation>>,
+    files: Option<Vec<Uuid>>,
+    transfer_limit: Option<TokenAmount>,
+    rules: Vec<PolicyRule>,
+    // Hashes of payloads approved but not yet signed, shared between clones.
+    approved: Arc<Mutex<HashSet<String>>>,
+}
+
+impl PolicySigner {
+    // Allows everything until restricted.
+    pub fn new(signer: Box<dyn Signer>) -> Self {
+        PolicySigner {
+            signer,
+            operations: None,
+            files: None,
+            transfer_limit: None,
+            rules: Vec::new(),
+            approved: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    pub fn with_operations(mut self, operations: Vec<PayloadOperation>) -> Self {
+        self.operations = Some(operations);
+        self
+    }
+
+    // Operations on files are only allowed on these.
+    pub fn with_files(mut self, files: Vec<Uuid>) -> Self {
+        self.files = Some(files);
+        self
+    }
+
+    // The most a single transfer or withdrawal may move.
+    pub fn with_transfer_limit(mut self, limit: impl Into<TokenAmount>) -> Self {
+        self.transfer_limit = Some(limit.into());
+        self
+    }
+
+    // Any other check; an Err refuses the payload, with the reason given.
+    pub fn with_rule<F>(mut self, rule: F) -> Self
+    where
+        F: Fn(&PayloadView) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    fn check(&self, view: &PayloadView) -> Result<(), String> {
+        let operation = view.operation();
+        if let Some(operations) = &self.operations {
+            if !operations.contains(&operation) {
+                return Err(format!("{:?} is not allowed", operation));
+            }
+        }
+
+        if let (Some(files), Some(uuid)) = (&self.files, view.uuid()) {
+            if !files.contains(&uuid) {
+                return Err(format!("File {} is not allowed", uuid));
+            }
+        }
+
+        if let Some(limit) = self.transfer_limit {
+            let amount = match view {
+                PayloadView::AccountTransfer { amount, .. } | PayloadView::AccountWithdraw { amount, .. } => Some(*amount),
+                _ => None,
+            };
+            if amount.is_some_and(|amount| amount > limit) {
+                return Err(format!("{:?} is over the limit of {}", operation, limit));
+            }
+        }
+
+        self.rules.iter().try_for_each(|rule| rule(view))
+    }
+}
+
+impl Signer for PolicySigner {
+    fn sign(&self, data: &[u8]) -> Result<Signature, SigningError> {
+        let header = TransactionHeader::decode(data)
+            .ok()
+            .filter(|header| header.family_name == FAMILY_NAME)
+            .ok_or_else(|| refused("Only transaction headers are signed"))?;
+
+        if !self.approved.lock().unwrap().remove(&header.payload_sha512) {
+            return Err(refused("Transaction payload was not approved"));
+        }
+
+        self.signer.sign(data)
+    }
+
+    fn public_key(&self) -> Result<PublicKey, SigningError> {
+        self.signer.public_key()
+    }
+
+    fn clone_box(&self) -> Box<dyn Signer> {
+        Box::new(self.clone())
+    }
+
+    fn approve_payload(&self, payload: &[u8]) -> Result<(), SigningError> {
+        let view = PayloadView::from_bytes(payload)
+            .map_err(|err| refused(&format!("Payload could not be parsed: {}", err)))?;
+        self.check(&view).map_err(|reason| refused(&reason))?;
+
+        self.approved.lock().unwrap().insert(hex::encode(Sha512::digest(payload)));
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
 use std::fmt::{Display, Formatter};
 use std::error::Error;
-use protobuf::{Message, RepeatedField};
+use prost::Message;
 use rand::{Rng, thread_rng};
+use serde::Serialize;
 use sha2::{Digest, Sha512};
 use crate::common::get_tfslite_prefix;
 use crate::common::{FAMILY_NAME, FAMILY_VERSION};
 use crate::client::keys::{PublicKey, Signature, Signer, SigningError, Verifier};
 use crate::protos::transaction::{Transaction, TransactionHeader};
 use crate::protos::payload::Payload;
+use crate::client::payload::PayloadView;
 
 #[derive(Debug)]
 pub enum TransactionBuildError {
@@ -40,7 +42,10 @@ pub struct TransactionBuilder {
     dependencies: Option<Vec<String>>,
     family_name: Option<String>,
     family_version: Option<String>,
+    inputs: Option<Vec<String>>,
+    outputs: Option<Vec<String>>,
     nonce: Option<Vec<u8>>,
+    deterministic_nonce: Option<u64>,
     payload: Option<Payload>
 }
 
@@ -51,7 +56,10 @@ impl Default for TransactionBuilder {
             dependencies: None,
             family_name: Some(FAMILY_NAME.to_string()),
             family_version: Some(FAMILY_VERSION.to_string()),
+            inputs: None,
+            outputs: None,
             nonce: None,
+            deterministic_nonce: None,
             payload: None,
         }
     }
@@ -81,93 +89,148 @@ impl TransactionBuilder {
         self
     }
 
+    // State addresses or prefixes the transaction may read; see the addressing module. The whole
+    // tfslite prefix when not set. The validator rejects any read outside them.
+    pub fn with_inputs(mut self, inputs: Vec<String>) -> Self {
+        self.inputs = Some(inputs);
+        self
+    }
+
+    // As with_inputs, for writes.
+    pub fn with_outputs(mut self, outputs: Vec<String>) -> Self {
+        self.outputs = Some(outputs);
+        self
+    }
+
     pub fn with_nonce(mut self, nonce: Vec<u8>) -> Self {
         self.nonce = Some(nonce);
         self
     }
 
+    // Derives the nonce from the signer, the payload hash and chunk_index rather than picking a
+    // random one, so building the same transaction again gives the same bytes and the same id,
+    // and a re-prepared upload duplicates rather than repeats its transactions. Two transactions
+    // with the same payload need different chunk indexes to tell them apart. with_nonce wins.
+    pub fn with_deterministic_nonce(mut self, chunk_index: u64) -> Self {
+        self.deterministic_nonce = Some(chunk_index);
+        self
+    }
+
     pub fn with_payload(mut self, payload: Payload) -> Self {
         self.payload = Some(payload);
         self
     }
 
     pub fn build(self, signer: &dyn Signer) -> Result<Transaction, TransactionBuildError> {
-        let mut tx_header = TransactionHeader::new();
+        let unsigned = self.build_unsigned(&signer.public_key()?)?;
+        signer.approve_payload(unsigned.payload_bytes())?;
+
+        let signature = signer
+            .sign(unsigned.header_bytes())
+            .map_err(|err| {
+                TransactionBuildError::SigningError(format!("Unable to sign tx: {}", err))
+            })?;
+
+        Ok(unsigned.finish(&signature))
+    }
+
+    // Builds everything but the signature, for signers that cannot sign synchronously.
+    pub fn build_unsigned(self, signer_public_key: &PublicKey) -> Result<UnsignedTransaction, TransactionBuildError> {
+        let mut tx_header = TransactionHeader::default();
 
         // Signer public key
-        let signer_public_key = signer.public_key()?;
-        tx_header.set_signer_public_key(signer_public_key.as_hex());
+        tx_header.signer_public_key = signer_public_key.as_hex();
 
         // Batcher public key
         let batcher_public_key = match self.batcher_public_key {
             Some(key_bytes) => PublicKey::load_from_bytes(key_bytes.as_slice()),
-            None => signer_public_key
+            None => PublicKey::load_from_bytes(signer_public_key.as_slice())
         };
-        tx_header.set_batcher_public_key(batcher_public_key.as_hex());
+        tx_header.batcher_public_key = batcher_public_key.as_hex();
 
         // Dependencies
-        let dependencies = self.dependencies.unwrap_or_default();
-        tx_header.set_dependencies(RepeatedField::from_vec(dependencies));
+        tx_header.dependencies = self.dependencies.unwrap_or_default();
 
         // Family name
-        let family_name = self.family_name.ok_or_else(|| {
+        tx_header.family_name = self.family_name.ok_or_else(|| {
             TransactionBuildError::MissingField("Field 'family_name' is required".to_string())
         })?;
-        tx_header.set_family_name(family_name);
 
         // Family version
-        let family_version = self.family_version.ok_or_else(|| {
+        tx_header.family_version = self.family_version.ok_or_else(|| {
             TransactionBuildError::MissingField("Field 'family_version' is required".to_string())
         })?;
-        tx_header.set_family_version(family_version);
 
         // Inputs
-        let inputs = vec![get_tfslite_prefix()];
-        tx_header.set_inputs(RepeatedField::from_vec(inputs));
+        tx_header.inputs = self.inputs.unwrap_or_else(|| vec![get_tfslite_prefix()]);
 
         // Outputs
-        let outputs = vec![get_tfslite_prefix()];
-        tx_header.set_outputs(RepeatedField::from(outputs));
-
-        // Nonce
-        let nonce = self.nonce.unwrap_or_else(|| {
-            let mut nonce = [0u8; 32];
-            thread_rng()
-                .fill(&mut nonce[..]);
-            nonce.to_vec()
-        });
-        tx_header.set_nonce(hex::encode(nonce));
+        tx_header.outputs = self.outputs.unwrap_or_else(|| vec![get_tfslite_prefix()]);
 
         let payload = self.payload.ok_or_else(|| {
             TransactionBuildError::MissingField("Field 'payload' is required".to_string())
         })?;
 
-        let payload_bytes = payload.write_to_bytes().map_err(|err| {
-            TransactionBuildError::SerializationError(format!("Unable to serialize payload: {}", err))
-        })?;
+        let payload_bytes = payload.encode_to_vec();
 
         let payload_hash = Sha512::digest(&payload_bytes).to_vec();
-        tx_header.set_payload_sha512(hex::encode(payload_hash));
 
-        let tx_header_bytes = tx_header
-            .write_to_bytes()
-            .map_err(|err| {
-            TransactionBuildError::SerializationError(format!("Unable to serialize tx header: {}", err))
-        })?;
+        // Nonce
+        let nonce = match (self.nonce, self.deterministic_nonce) {
+            (Some(nonce), _) => nonce,
+            (None, Some(chunk_index)) => {
+                let mut hasher = Sha512::new();
+                hasher.update(b"tfslite-nonce");
+                hasher.update(signer_public_key.as_slice());
+                hasher.update(&payload_hash);
+                hasher.update(chunk_index.to_be_bytes());
+                hasher.finalize()[..32].to_vec()
+            },
+            (None, None) => {
+                let mut nonce = [0u8; 32];
+                thread_rng()
+                    .fill(&mut nonce[..]);
+                nonce.to_vec()
+            },
+        };
+        tx_header.nonce = hex::encode(nonce);
 
-        let signature = signer
-            .sign(&tx_header_bytes)
-            .map_err(|err| {
-                TransactionBuildError::SigningError(format!("Unable to sign tx: {}", err))
-            })?;
+        tx_header.payload_sha512 = hex::encode(payload_hash);
+
+        let tx_header_bytes = tx_header.encode_to_vec();
 
-        let mut tx = Transaction::new();
+        Ok(UnsignedTransaction {
+            header: tx_header_bytes,
+            payload: payload_bytes,
+        })
+    }
+}
 
-        tx.set_header(tx_header_bytes.to_vec());
-        tx.set_header_signature(signature.as_hex());
-        tx.set_payload(payload_bytes);
+pub struct UnsignedTransaction {
+    header: Vec<u8>,
+    payload: Vec<u8>,
+}
 
-        Ok(tx)
+impl UnsignedTransaction {
+    // For reloading one that was built earlier; see OfflineSigningRequest::load.
+    pub fn from_parts(header: Vec<u8>, payload: Vec<u8>) -> Self {
+        UnsignedTransaction { header, payload }
+    }
+
+    pub fn header_bytes(&self) -> &[u8] {
+        &self.header
+    }
+
+    pub fn payload_bytes(&self) -> &[u8] {
+        &self.payload
+    }
+
+    pub fn finish(self, signature: &Signature) -> Transaction {
+        Transaction {
+            header: self.header,
+            header_signature: signature.as_hex(),
+            payload: self.payload,
+        }
     }
 }
 
@@ -182,33 +245,145 @@ impl Display for TransactionValidationError {
 
 impl Error for TransactionValidationError {}
 
+// A decoded transaction for logs, CLIs and explorers. The payload is summarized as in
+// PayloadView::to_json, so block data isn't included.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransactionView {
+    pub header_signature: String,
+    pub signer_public_key: String,
+    pub batcher_public_key: String,
+    pub family_name: String,
+    pub family_version: String,
+    pub dependencies: Vec<String>,
+    pub nonce: String,
+    pub payload: PayloadView,
+}
+
+impl TransactionView {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("TransactionView always serializes")
+    }
+}
+
+fn verify_signed(transaction: &Transaction, header: &TransactionHeader, public_key: &PublicKey) -> Result<(), TransactionValidationError> {
+    let signature = Signature::try_from(transaction.header_signature.as_str())
+        .map_err(|err| TransactionValidationError(format!("Error loading Transaction signature: {}", err)))?;
+
+    let verified = public_key.verify(&transaction.header, &signature)
+        .map_err(|err| TransactionValidationError(format!("Error during signature verification: {}", err)))?;
+
+    if !verified {
+        return Err(TransactionValidationError("Transaction signature is invalid".to_string()));
+    }
+
+    let payload_hash = hex::encode(Sha512::digest(&transaction.payload).to_vec());
+    if payload_hash != header.payload_sha512 {
+        return Err(TransactionValidationError("Transaction payload hash does not match header".to_string()));
+    }
+
+    Ok(())
+}
+
 pub trait TransactionExt {
     fn validate(&self) -> Result<(), TransactionValidationError>;
+    fn header(&self) -> Result<TransactionHeader, TransactionValidationError>;
+    fn payload_view(&self) -> Result<PayloadView, TransactionValidationError>;
+    // Doesn't check the signature; call validate for that.
+    fn view(&self) -> Result<TransactionView, TransactionValidationError>;
+    fn dependencies(&self) -> Result<Vec<String>, TransactionValidationError>;
+    fn signer_public_key(&self) -> Result<PublicKey, TransactionValidationError>;
 }
 
 impl TransactionExt for Transaction {
     fn validate(&self) -> Result<(), TransactionValidationError> {
-        let header = TransactionHeader::parse_from_bytes(self.get_header())
-            .map_err(|_err| TransactionValidationError(String::from("Transaction header could not be parsed")))?;
+        let header = self.header()?;
 
-        let public_key = PublicKey::load_from_hex(header.get_signer_public_key())
+        let public_key = PublicKey::load_from_hex(&header.signer_public_key)
             .map_err(|_err| TransactionValidationError(String::from("Transaction signer public key could not be loaded")))?;
 
-        let signature = Signature::try_from(self.get_header_signature())
-            .map_err(|err| TransactionValidationError(format!("Error loading Transaction signature: {}", err)))?;
+        verify_signed(self, &header, &public_key)
+    }
+
+    fn header(&self) -> Result<TransactionHeader, TransactionValidationError> {
+        TransactionHeader::decode(self.header.as_slice())
+            .map_err(|_err| TransactionValidationError(String::from("Transaction header could not be parsed")))
+    }
+
+    fn payload_view(&self) -> Result<PayloadView, TransactionValidationError> {
+        PayloadView::from_bytes(&self.payload)
+            .map_err(|err| TransactionValidationError(format!("Transaction payload could not be parsed: {}", err)))
+    }
+
+    fn view(&self) -> Result<TransactionView, TransactionValidationError> {
+        let header = self.header()?;
+
+        Ok(TransactionView {
+            header_signature: self.header_signature.clone(),
+            signer_public_key: header.signer_public_key,
+            batcher_public_key: header.batcher_public_key,
+            family_name: header.family_name,
+            family_version: header.family_version,
+            dependencies: header.dependencies,
+            nonce: header.nonce,
+            payload: self.payload_view()?,
+        })
+    }
+
+    fn dependencies(&self) -> Result<Vec<String>, TransactionValidationError> {
+        Ok(self.header()?.dependencies)
+    }
 
-        let verified = public_key.verify(self.get_header(), &signature)
-            .map_err(|err| TransactionValidationError(format!("Error during signature verification: {}", err)))?;
+    fn signer_public_key(&self) -> Result<PublicKey, TransactionValidationError> {
+        PublicKey::load_from_hex(&self.header()?.signer_public_key)
+            .map_err(|_err| TransactionValidationError(String::from("Transaction signer public key could not be loaded")))
+    }
+}
 
-        if !verified {
-            return Err(TransactionValidationError("Transaction signature is invalid".to_string()));
+// Below this many transactions verify_all doesn't bother with threads.
+#[cfg(not(target_arch = "wasm32"))]
+const PARALLEL_VERIFY_MIN: usize = 64;
+
+// For auditing exported transactions before they are (re)submitted.
+impl PublicKey {
+    // Checks that transaction was signed by this key, as well as everything validate checks.
+    pub fn verify_transaction(&self, transaction: &Transaction) -> Result<(), TransactionValidationError> {
+        let header = transaction.header()?;
+        if header.signer_public_key != self.as_hex() {
+            return Err(TransactionValidationError(format!("Transaction was signed by {}, not {}", header.signer_public_key, self.as_hex())));
         }
 
-        let payload_hash = hex::encode(Sha512::digest(self.get_payload()).to_vec());
-        if payload_hash.as_str() != header.get_payload_sha512() {
-            return Err(TransactionValidationError("Transaction payload hash does not match header".to_string()));
+        verify_signed(transaction, &header, self)
+    }
+
+    // One result per transaction, in order. Large sets are split across a thread per core on
+    // native targets.
+    pub fn verify_all(&self, transactions: &[Transaction]) -> Vec<Result<(), TransactionValidationError>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if transactions.len() >= PARALLEL_VERIFY_MIN {
+            let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+            let chunk_size = transactions.len().div_ceil(threads);
+
+            // Verifiers can't be shared between threads, so each thread loads its own.
+            let key_bytes = self.as_slice();
+            return std::thread::scope(|scope| {
+                let handles: Vec<_> = transactions
+                    .chunks(chunk_size)
+                    .map(|chunk| scope.spawn(move || {
+                        let public_key = PublicKey::load_from_bytes(key_bytes);
+                        chunk.iter()
+                            .map(|transaction| public_key.verify_transaction(transaction))
+                            .collect::<Vec<_>>()
+                    }))
+                    .collect();
+
+                handles.into_iter()
+                    .flat_map(|handle| handle.join().expect("Verification thread panicked"))
+                    .collect()
+            });
         }
 
-        Ok(())
+        transactions.iter()
+            .map(|transaction| self.verify_transaction(transaction))
+            .collect()
     }
 }
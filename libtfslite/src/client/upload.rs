@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+// How long to pause between status checks when the transport didn't hold the request.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub type UploadTxId = String;
+pub type UploadSubmitId = String;
+
+// How often a transaction the network lost is sent again before it is marked failed. The wait
+// before the nth resend is initial_delay doubled n - 1 times, up to max_delay.
+#[derive(Clone, Debug)]
+pub struct ResubmitPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for ResubmitPolicy {
+    fn default() -> Self {
+        ResubmitPolicy {
+            max_attempts: 20,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+// What the upload knows of a transaction. Local covers both never sent and sent but lost; which
+// one is told apart by whether it has a submit id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadTxStatus {
+    Local,
+    Queued,
+    Pending,
+    Committed,
+    Failed,
+}
+
+// How a chunk is put on chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChunkSource {
+    // The chunk's data itself.
+    Block,
+    // A copy of an earlier chunk of the same file, by chunk index.
+    Reference(u64),
+    // A copy of a block of the sealed base file.
+    FileReference(Uuid, u64),
+}
+
+#[derive(Clone, Debug)]
+pub struct UploadTx {
+    pub tx_id: UploadTxId,
+    pub submit_id: Option<UploadSubmitId>,
+    pub status: UploadTxStatus,
+}
+
+#[derive(Clone, Debug)]
+pub enum UploadInput {
+    // The file create transaction was prepared; chunks depend on it when indexed.
+    Created { tx_id: UploadTxId },
+    // The next chunk of the file was read.
+    ChunkRead { sha224: Vec<u8> },
+    // The transaction for the chunk last read was prepared.
+    ChunkPrepared { tx_id: UploadTxId },
+    // The whole file was read.
+    ChunksDone,
+    // The upload's transactions in order, as stored. Those from held_from on are only sent once
+    // everything before them has committed.
+    Loaded { txs: Vec<UploadTx>, held_from: usize },
+    // Starts sending everything not held back.
+    Send,
+    // The transport accepted the transactions, in the same order as the submit ids.
+    Submitted { tx_ids: Vec<UploadTxId>, submit_ids: Vec<UploadSubmitId>, now: i64 },
+    // Statuses by submit id. Waited is whether the transport held the request until one changed.
+    StatusUpdate { statuses: Vec<(UploadSubmitId, UploadTxStatus)>, waited: bool, now: i64 },
+    // Time to check on the transactions again.
+    Tick { now: i64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UploadEffect {
+    // Prepare the chunk last read, depending on the given transaction.
+    AppendChunk { index: u64, source: ChunkSource, dependency: UploadTxId },
+    Seal { dependency: UploadTxId },
+    // Submit these transactions, in order, and report the submit ids back.
+    Submit(Vec<UploadTxId>),
+    // Submit this transaction again; retry says whether it counts as a resend of a lost one.
+    Resubmit { tx_id: UploadTxId, retry: bool },
+    // Ask for the statuses of these submit ids, waiting for a change if wait is set.
+    PollStatuses { submit_ids: Vec<UploadSubmitId>, wait: bool },
+    // Store these statuses; the submit id is the one they were reported for.
+    Persist(Vec<(UploadTxId, UploadSubmitId, UploadTxStatus)>),
+    // These were seen committed for the first time.
+    Committed(Vec<UploadTxId>),
+    // The resubmit policy ran out for this transaction.
+    MarkFailed(UploadTxId),
+    Sleep(Duration),
+    Done,
+    // Later transactions depend on earlier ones, so once one has failed the upload can't finish.
+    Failed(usize),
+}
+
+// The decisions of an upload without any of its IO: which transaction each chunk depends on, what
+// to send and when, and when to give up. The caller reads the file, signs, stores and talks to the
+// transport, feeding each result back in as an input and carrying out the effects it returns.
+#[derive(Clone, Debug, Default)]
+pub struct UploadStateMachine {
+    policy: ResubmitPolicy,
+    indexed_chunks: bool,
+    deduplicate_chunks: bool,
+    base_chunks: HashMap<Vec<u8>, (Uuid, u64)>,
+
+    tx_id_create: Option<UploadTxId>,
+    tx_id_prev: Option<UploadTxId>,
+    chunk_index: u64,
+    chunk_sha224: Option<Vec<u8>>,
+    seen_chunks: HashMap<Vec<u8>, (u64, UploadTxId)>,
+
+    txs: Vec<UploadTx>,
+    held_from: usize,
+    // Resends so far and the earliest time (in milliseconds) for the next, by transaction.
+    resubmits: HashMap<UploadTxId, (u32, i64)>,
+    // When each transaction was first sent, and first seen committed, in milliseconds.
+    sent_at: HashMap<UploadTxId, i64>,
+    committed_at: HashMap<UploadTxId, i64>,
+}
+
+enum Resubmit {
+    Now,
+    Later,
+    GiveUp,
+}
+
+impl UploadStateMachine {
+    pub fn new() -> Self {
+        UploadStateMachine::default()
+    }
+
+    pub fn set_resubmit_policy(&mut self, policy: ResubmitPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn set_indexed_chunks(&mut self, indexed_chunks: bool) {
+        self.indexed_chunks = indexed_chunks;
+    }
+
+    pub fn set_deduplicate_chunks(&mut self, deduplicate_chunks: bool) {
+        self.deduplicate_chunks = deduplicate_chunks;
+    }
+
+    // Blocks of a sealed base file by sha224, for chunks to reference instead of resending.
+    pub fn set_base_chunks(&mut self, base_chunks: HashMap<Vec<u8>, (Uuid, u64)>) {
+        self.base_chunks = base_chunks;
+    }
+
+    pub fn handle(&mut self, input: UploadInput) -> Vec<UploadEffect> {
+        match input {
+            UploadInput::Created { tx_id } => {
+                self.tx_id_create = Some(tx_id.clone());
+                self.tx_id_prev = Some(tx_id);
+                self.chunk_index = 0;
+                self.seen_chunks.clear();
+                Vec::new()
+            },
+            UploadInput::ChunkRead { sha224 } => self.chunk_read(sha224),
+            UploadInput::ChunkPrepared { tx_id } => {
+                if let Some(sha224) = self.chunk_sha224.take() {
+                    if self.deduplicate_chunks {
+                        self.seen_chunks.entry(sha224).or_insert((self.chunk_index, tx_id.clone()));
+                    }
+                }
+                self.chunk_index += 1;
+                self.tx_id_prev = Some(tx_id);
+                Vec::new()
+            },
+            // With indexed chunks, ordering the seal after the chunks is left to sending.
+            UploadInput::ChunksDone => {
+                let dependency = if self.indexed_chunks { &self.tx_id_create } else { &self.tx_id_prev };
+                vec![UploadEffect::Seal { dependency: dependency.clone().unwrap_or_default() }]
+            },
+            UploadInput::Loaded { txs, held_from } => {
+                self.held_from = held_from.min(txs.len());
+                self.txs = txs;
+                Vec::new()
+            },
+            UploadInput::Send => {
+                let tx_ids: Vec<UploadTxId> = self.txs[..self.held_from]
+                    .iter()
+                    .map(|tx| tx.tx_id.clone())
+                    .collect();
+                if tx_ids.is_empty() {
+                    return Vec::new();
+                }
+                vec![UploadEffect::Submit(tx_ids)]
+            },
+            UploadInput::Submitted { tx_ids, submit_ids, now } => {
+                for (tx_id, submit_id) in tx_ids.into_iter().zip(submit_ids) {
+                    self.sent_at.entry(tx_id.clone()).or_insert(now);
+                    if let Some(tx) = self.txs.iter_mut().find(|tx| tx.tx_id == tx_id) {
+                        tx.submit_id = Some(submit_id);
+                    }
+                }
+                Vec::new()
+            },
+            UploadInput::Tick { now } => self.tick(now),
+            UploadInput::StatusUpdate { statuses, waited, now } => self.status_update(statuses, waited, now),
+        }
+    }
+
+    // A reference must be applied after the chunk it copies. Base file chunks are already
+    // committed, as the base file is sealed.
+    fn chunk_read(&mut self, sha224: Vec<u8>) -> Vec<UploadEffect> {
+        let tx_id_create = self.tx_id_create.clone().unwrap_or_default();
+        let tx_id_prev = self.tx_id_prev.clone().unwrap_or_default();
+
+        let reference = if self.deduplicate_chunks { self.seen_chunks.get(&sha224) } else { None };
+        let (source, dependency) = match (reference, self.base_chunks.get(&sha224)) {
+            (Some((reference_index, tx_id_reference)), _) => {
+                let dependency = if self.indexed_chunks { tx_id_reference.clone() } else { tx_id_prev };
+                (ChunkSource::Reference(*reference_index), dependency)
+            },
+            (None, Some((base_uuid, base_index))) => {
+                let dependency = if self.indexed_chunks { tx_id_create } else { tx_id_prev };
+                (ChunkSource::FileReference(*base_uuid, *base_index), dependency)
+            },
+            (None, None) => {
+                let dependency = if self.indexed_chunks { tx_id_create } else { tx_id_prev };
+                (ChunkSource::Block, dependency)
+            },
+        };
+
+        self.chunk_sha224 = Some(sha224);
+        vec![UploadEffect::AppendChunk { index: self.chunk_index, source, dependency }]
+    }
+
+    fn tick(&mut self, now: i64) -> Vec<UploadEffect> {
+        let failed = self.failed_count();
+        if failed > 0 {
+            return vec![UploadEffect::Failed(failed)];
+        }
+        if self.uncommitted_count() == 0 {
+            return vec![UploadEffect::Done];
+        }
+
+        // Transports that batch transactions share one submit id across several transactions.
+        let mut submit_ids: Vec<UploadSubmitId> = Vec::new();
+        for tx in self.txs.iter().filter(|tx| tx.status != UploadTxStatus::Committed) {
+            if let Some(submit_id) = tx.submit_id.as_ref() {
+                if !submit_ids.contains(submit_id) {
+                    submit_ids.push(submit_id.clone());
+                }
+            }
+        }
+
+        // Nothing was sent yet, so there is nothing to ask about.
+        if submit_ids.is_empty() {
+            return self.status_update(Vec::new(), false, now);
+        }
+        vec![UploadEffect::PollStatuses { submit_ids, wait: true }]
+    }
+
+    fn status_update(&mut self, statuses: Vec<(UploadSubmitId, UploadTxStatus)>, waited: bool, now: i64) -> Vec<UploadEffect> {
+        let mut effects = Vec::new();
+
+        let mut updates = Vec::new();
+        let mut newly_committed = Vec::new();
+        for (submit_id, status) in statuses {
+            let uncommitted = self.txs.iter_mut()
+                .filter(|tx| tx.status != UploadTxStatus::Committed && tx.status != UploadTxStatus::Failed)
+                .filter(|tx| tx.submit_id.as_ref() == Some(&submit_id));
+            for tx in uncommitted {
+                if status == UploadTxStatus::Committed {
+                    newly_committed.push(tx.tx_id.clone());
+                    self.committed_at.insert(tx.tx_id.clone(), now);
+                }
+                tx.status = status;
+                updates.push((tx.tx_id.clone(), submit_id.clone(), status));
+            }
+        }
+        if !updates.is_empty() {
+            effects.push(UploadEffect::Persist(updates));
+        }
+        if !newly_committed.is_empty() {
+            effects.push(UploadEffect::Committed(newly_committed));
+        }
+
+        // A transaction never sent was held back, and waits until everything sent before it has
+        // committed.
+        let mut waiting_on_sent = false;
+        let mut deferred = false;
+        for index in 0..self.txs.len() {
+            let tx = &self.txs[index];
+            if matches!(tx.status, UploadTxStatus::Committed | UploadTxStatus::Failed) {
+                continue;
+            }
+            if tx.submit_id.is_none() && waiting_on_sent {
+                break;
+            }
+
+            // A transaction without a submit id was never sent, whatever its status says.
+            let sent = tx.submit_id.is_some();
+            waiting_on_sent |= sent;
+            if sent && tx.status != UploadTxStatus::Local {
+                continue;
+            }
+
+            let tx_id = tx.tx_id.clone();
+            if sent {
+                match self.next_resubmit(&tx_id, now) {
+                    Resubmit::Now => {},
+                    Resubmit::Later => {
+                        deferred = true;
+                        continue;
+                    },
+                    Resubmit::GiveUp => {
+                        self.txs[index].status = UploadTxStatus::Failed;
+                        effects.push(UploadEffect::MarkFailed(tx_id));
+                        continue;
+                    },
+                }
+            }
+            effects.push(UploadEffect::Resubmit { tx_id, retry: sent });
+        }
+
+        // A wait ends at once while a lost transaction is waiting out its resubmit delay.
+        if self.uncommitted_count() > 0 && self.failed_count() == 0 && (!waited || deferred) {
+            effects.push(UploadEffect::Sleep(POLL_INTERVAL));
+        }
+
+        effects
+    }
+
+    fn next_resubmit(&mut self, tx_id: &UploadTxId, now: i64) -> Resubmit {
+        let (attempts, due_at) = self.resubmits.get(tx_id).cloned().unwrap_or((0, now));
+        if now < due_at {
+            return Resubmit::Later;
+        }
+        if attempts >= self.policy.max_attempts {
+            return Resubmit::GiveUp;
+        }
+
+        let delay = self.policy.initial_delay
+            .saturating_mul(2u32.saturating_pow(attempts))
+            .min(self.policy.max_delay);
+        self.resubmits.insert(tx_id.clone(), (attempts + 1, now + delay.as_millis() as i64));
+        Resubmit::Now
+    }
+
+    pub fn txs(&self) -> &[UploadTx] {
+        &self.txs
+    }
+
+    pub fn committed_count(&self) -> usize {
+        self.txs.iter().filter(|tx| tx.status == UploadTxStatus::Committed).count()
+    }
+
+    pub fn uncommitted_count(&self) -> usize {
+        self.txs.len() - self.committed_count()
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.txs.iter().filter(|tx| tx.status == UploadTxStatus::Failed).count()
+    }
+
+    pub fn resubmissions(&self, tx_id: &UploadTxId) -> u32 {
+        self.resubmits.get(tx_id).map(|(attempts, _)| *attempts).unwrap_or(0)
+    }
+
+    // From first sent to first seen committed, or from since for a transaction sent by an earlier
+    // upload. None unless it was seen committed.
+    pub fn commit_ms(&self, tx_id: &UploadTxId, since: i64) -> Option<u64> {
+        self.committed_at.get(tx_id).map(|committed_at| {
+            let sent_at = self.sent_at.get(tx_id).copied().unwrap_or(since);
+            (committed_at - sent_at).max(0) as u64
+        })
+    }
+}
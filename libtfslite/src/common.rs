@@ -1,9 +1,60 @@
+use prost::Message;
 use sha2::{Digest, Sha512};
+use crate::protos::payload::Payload;
 
 pub const FAMILY_NAME: &str = "tfslite";
 pub const FAMILY_VERSION: &str = "0.1";
 pub const FILE_CREATE_COST: u64 = 100000000;
+// Appends are not charged for yet.
+pub const FILE_APPEND_COST: u64 = 0;
+// Base units per token are 10^TOKEN_DECIMALS, so creating a file costs one token.
+pub const TOKEN_DECIMALS: u32 = 8;
+pub const TOKEN_SYMBOL: &str = "TFS";
+pub const MAX_FILE_TAGS: usize = 64;
+pub const MAX_ALIAS_LENGTH: usize = 64;
+pub const MAX_MULTISIG_KEYS: usize = 16;
+pub const MAX_FILENAME_LENGTH: usize = 255;
+// Keeps block numbers, and the work of destroying a file, bounded.
+pub const MAX_FILE_BLOCKS: u64 = 1 << 24;
+
+// What an upload of chunk_total chunks costs in all, for checking a balance before starting it.
+pub fn upload_cost(chunk_total: u64) -> u64 {
+    FILE_APPEND_COST
+        .saturating_mul(chunk_total)
+        .saturating_add(FILE_CREATE_COST)
+}
 
 pub fn get_tfslite_prefix() -> String {
     hex::encode(Sha512::digest(b"tfslite"))[..6].to_string()
 }
+
+// What a cosigner of account signs to approve payload: the payload without its cosignatures,
+// bound to the account and its multisig_sequence so the approval only counts once.
+pub fn cosigning_bytes(account: &[u8], sequence: u64, payload: &Payload) -> Vec<u8> {
+    let mut payload = payload.clone();
+    payload.cosignatures.clear();
+
+    let mut bytes = b"tfslite-cosign".to_vec();
+    bytes.extend_from_slice(account);
+    bytes.extend_from_slice(&sequence.to_be_bytes());
+    bytes.extend_from_slice(&payload.encode_to_vec());
+    bytes
+}
+
+// Filenames are free-form, but can't be empty or carry control characters that would garble
+// listings and terminals.
+pub fn is_valid_filename(filename: &str) -> bool {
+    !filename.is_empty()
+        && filename.len() <= MAX_FILENAME_LENGTH
+        && !filename.chars().any(|c| c.is_control())
+}
+
+// Aliases are lowercase so that two can't differ only by case, and can't be mistaken for a hex
+// public key.
+pub fn is_valid_alias(alias: &str) -> bool {
+    !alias.is_empty()
+        && alias.len() <= MAX_ALIAS_LENGTH
+        && alias.starts_with(|c: char| c.is_ascii_lowercase())
+        && alias.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_' || c == '.')
+        && !(alias.len() == 66 && alias.chars().all(|c| c.is_ascii_hexdigit()))
+}
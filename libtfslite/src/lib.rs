@@ -2,3 +2,7 @@ pub mod protos;
 pub mod client;
 pub mod common;
 pub mod types;
+pub mod addressing;
+
+#[cfg(feature = "traits")]
+pub mod processor;
@@ -0,0 +1,1052 @@
+use crate::protos::compat::Message;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha224};
+use uuid::Uuid;
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionContext, TransactionHandler};
+use crate::addressing::{account_address, alias_address, block_address, clock_address, directory_address, file_address, permission_address};
+use crate::client::keys::{PublicKey, Signature, Verifier};
+use crate::common::{cosigning_bytes, get_tfslite_prefix, is_valid_alias, FAMILY_NAME, FAMILY_VERSION, FILE_CREATE_COST, MAX_FILE_BLOCKS, MAX_FILE_TAGS, MAX_MULTISIG_KEYS};
+use crate::protos::payload::{Payload, Payload_Operation};
+use crate::types::{AccountRecord, AliasRecord, DirectoryEntry, FileGrant, FileManifestRecord, FileMode, FileRecord, FileRights, FileState, Permission, TokenAmount};
+
+fn invalid(msg: &str) -> ApplyError {
+    ApplyError::InvalidTransaction(msg.to_string())
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, ApplyError> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes)
+        .map_err(|err| ApplyError::InternalError(format!("Unable to encode state: {}", err)))?;
+    Ok(bytes)
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ApplyError> {
+    ciborium::de::from_reader(bytes)
+        .map_err(|err| ApplyError::InternalError(format!("Unable to decode state: {}", err)))
+}
+
+fn get_entry<T: DeserializeOwned>(context: &mut dyn TransactionContext, address: &str) -> Result<Option<T>, ApplyError> {
+    match context.get_state_entry(address)? {
+        Some(bytes) => Ok(Some(decode(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+fn set_entry<T: Serialize>(context: &mut dyn TransactionContext, address: String, value: &T) -> Result<(), ApplyError> {
+    context.set_state_entry(address, encode(value)?)?;
+    Ok(())
+}
+
+// Applies tfslite payloads to global state. State entries are CBOR encoded. Admin keys hold
+// every permission implicitly, which is how the first permission holders get appointed.
+pub struct TfsliteTransactionHandler {
+    family_name: String,
+    family_versions: Vec<String>,
+    namespaces: Vec<String>,
+    admin_public_keys: Vec<Vec<u8>>,
+}
+
+impl Default for TfsliteTransactionHandler {
+    fn default() -> Self {
+        TfsliteTransactionHandler {
+            family_name: FAMILY_NAME.to_string(),
+            family_versions: vec![FAMILY_VERSION.to_string()],
+            namespaces: vec![get_tfslite_prefix()],
+            admin_public_keys: Vec::new(),
+        }
+    }
+}
+
+impl TfsliteTransactionHandler {
+    pub fn new() -> Self {
+        TfsliteTransactionHandler::default()
+    }
+
+    pub fn with_admin_public_keys(mut self, admin_public_keys: Vec<Vec<u8>>) -> Self {
+        self.admin_public_keys = admin_public_keys;
+        self
+    }
+
+    fn has_permission(&self, context: &mut dyn TransactionContext, permission: &Permission, public_key: &[u8]) -> Result<bool, ApplyError> {
+        if self.admin_public_keys.iter().any(|key| key.as_slice() == public_key) {
+            return Ok(true);
+        }
+
+        Ok(context.get_state_entry(&permission_address(permission, public_key))?.is_some())
+    }
+
+    fn require_permission(&self, context: &mut dyn TransactionContext, permission: Permission, public_key: &[u8]) -> Result<(), ApplyError> {
+        if !self.has_permission(context, &permission, public_key)? {
+            return Err(ApplyError::InvalidTransaction(format!("Missing {}", permission)));
+        }
+        Ok(())
+    }
+
+    fn debit(&self, context: &mut dyn TransactionContext, public_key: &[u8], amount: TokenAmount) -> Result<(), ApplyError> {
+        let address = account_address(public_key);
+        let mut account: AccountRecord = get_entry(context, &address)?.unwrap_or_default();
+
+        account.balance = account.balance
+            .checked_sub(amount)
+            .ok_or_else(|| invalid("Insufficient balance"))?;
+
+        set_entry(context, address, &account)
+    }
+
+    fn credit(&self, context: &mut dyn TransactionContext, public_key: &[u8], amount: TokenAmount) -> Result<(), ApplyError> {
+        let address = account_address(public_key);
+        let mut account: AccountRecord = get_entry(context, &address)?.unwrap_or_default();
+
+        account.balance = account.balance
+            .checked_add(amount)
+            .ok_or_else(|| invalid("Balance overflow"))?;
+
+        set_entry(context, address, &account)
+    }
+
+    fn get_file(&self, context: &mut dyn TransactionContext, file_id: &Uuid) -> Result<FileRecord, ApplyError> {
+        get_entry(context, &file_address(file_id))?
+            .ok_or_else(|| invalid("File does not exist"))
+    }
+
+    fn get_owned_file(&self, context: &mut dyn TransactionContext, file_id: &Uuid, signer: &[u8]) -> Result<FileRecord, ApplyError> {
+        let file = self.get_file(context, file_id)?;
+        if file.owner != signer {
+            return Err(invalid("Signer does not own file"));
+        }
+        Ok(file)
+    }
+
+    // The owner, or a key the owner granted rights to.
+    fn get_file_with_rights(&self, context: &mut dyn TransactionContext, file_id: &Uuid, signer: &[u8], rights: FileRights) -> Result<FileRecord, ApplyError> {
+        let file = self.get_file(context, file_id)?;
+        if !file.rights_of(signer).contains(rights) {
+            return Err(invalid("Signer does not have rights to file"));
+        }
+        Ok(file)
+    }
+
+    fn file_create(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let address = file_address(&file_id);
+        if context.get_state_entry(&address)?.is_some() {
+            return Err(invalid("File already exists"));
+        }
+
+        self.debit(context, signer, TokenAmount::from(FILE_CREATE_COST))?;
+
+        let mut file = FileRecord {
+            file_id,
+            owner: signer.to_vec(),
+            file_name: payload.get_filename().to_string(),
+            mode: FileMode::from(payload.get_mode()),
+            state: FileState::Open,
+            block_count: 0,
+            missing_blocks: 0,
+            block_total: None,
+            size: 0,
+            timestamp_create: 0,
+            timestamp_append: 0,
+            timestamp_seal: 0,
+            manifest: None,
+            tags: Vec::new(),
+            retain_until: 0,
+            legal_hold: false,
+            grants: Vec::new(),
+            written_blocks: Vec::new(),
+        };
+        apply_tags(&mut file, payload)?;
+        set_entry(context, address, &file)?;
+
+        let address = directory_address(signer);
+        let mut directory: Vec<DirectoryEntry> = get_entry(context, &address)?.unwrap_or_default();
+        directory.push(DirectoryEntry {
+            file_id,
+            file_name: file.file_name,
+        });
+        set_entry(context, address, &directory)
+    }
+
+    fn file_append(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let mut file = self.get_file_with_rights(context, &file_id, signer, FileRights::APPEND)?;
+        if file.state != FileState::Open {
+            return Err(invalid("File is sealed"));
+        }
+
+        let block = payload.get_block();
+        let data = if block.get_reference() {
+            let reference_id = match block.get_reference_uuid() {
+                reference_uuid if reference_uuid.is_empty() => file_id,
+                reference_uuid => Uuid::from_slice(reference_uuid)
+                    .map_err(|err| ApplyError::InvalidTransaction(format!("Invalid reference uuid: {}", err)))?,
+            };
+            context.get_state_entry(&block_address(&reference_id, block.get_reference_number()))?
+                .ok_or_else(|| invalid("Referenced block does not exist"))?
+        } else {
+            block.get_data().to_vec()
+        };
+        if Sha224::digest(&data).as_slice() != block.get_sha224() {
+            return Err(invalid("Block hash does not match block data"));
+        }
+
+        // Unindexed blocks are numbered in append order. Indexed blocks may arrive out of order, so
+        // they must declare the file's block total, skipped numbers are counted as missing until
+        // filled, and a file with gaps cannot be sealed.
+        let number = if block.get_indexed() { block.get_number() } else { file.block_count };
+        if block.get_total() > MAX_FILE_BLOCKS {
+            return Err(invalid("Block total is over the block limit"));
+        }
+        if block.get_total() != 0 {
+            if file.block_total.is_some_and(|block_total| block_total != block.get_total()) {
+                return Err(invalid("Block total does not match earlier blocks"));
+            }
+            if number >= block.get_total() {
+                return Err(invalid("Block number is past the block total"));
+            }
+            file.block_total = Some(block.get_total());
+        } else if block.get_indexed() {
+            return Err(invalid("Indexed blocks must declare the block total"));
+        }
+        if number >= MAX_FILE_BLOCKS {
+            return Err(invalid("File is at the block limit"));
+        }
+        let address = block_address(&file_id, number);
+        if number >= file.block_count {
+            file.missing_blocks += number - file.block_count;
+            file.block_count = number
+                .checked_add(1)
+                .ok_or_else(|| invalid("Block number overflow"))?;
+        } else if context.get_state_entry(&address)?.is_none() {
+            file.missing_blocks -= 1;
+        } else {
+            return Err(invalid("Block already written"));
+        }
+
+        file.size = file.size
+            .checked_add(data.len() as u64)
+            .ok_or_else(|| invalid("File size overflow"))?;
+        file.mark_block_written(number);
+        context.set_state_entry(address, data)?;
+
+        set_entry(context, file_address(&file_id), &file)
+    }
+
+    fn file_seal(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let mut file = self.get_owned_file(context, &file_id, signer)?;
+        if file.state != FileState::Open {
+            return Err(invalid("File is already sealed"));
+        }
+        if file.missing_blocks > 0 || file.block_total.is_some_and(|block_total| block_total != file.block_count) {
+            return Err(invalid("File has missing blocks"));
+        }
+
+        file.state = FileState::Sealed;
+        set_entry(context, file_address(&file_id), &file)
+    }
+
+    fn file_destroy(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let file = self.get_file_with_rights(context, &file_id, signer, FileRights::DESTROY)?;
+        if file.mode != FileMode::Destroyable {
+            return Err(invalid("File is immutable"));
+        }
+        if file.legal_hold {
+            return Err(invalid("File is under legal hold"));
+        }
+        // Validators share no clock, so retention runs on the time a timestamp authority last
+        // recorded, and never ends while none has.
+        let clock: i64 = get_entry(context, &clock_address())?.unwrap_or(0);
+        if file.retain_until > clock {
+            return Err(invalid("File is under retention"));
+        }
+
+        for (start, end) in file.written_block_ranges() {
+            for number in start..end {
+                context.delete_state_entry(&block_address(&file_id, number))?;
+            }
+        }
+        context.delete_state_entry(&file_address(&file_id))?;
+
+        let address = directory_address(&file.owner);
+        let mut directory: Vec<DirectoryEntry> = get_entry(context, &address)?.unwrap_or_default();
+        directory.retain(|entry| entry.file_id != file_id);
+        set_entry(context, address, &directory)
+    }
+
+    fn file_manifest(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let mut file = self.get_owned_file(context, &file_id, signer)?;
+        if file.state != FileState::Sealed {
+            return Err(invalid("File must be sealed before its manifest is recorded"));
+        }
+
+        let manifest = payload.get_manifest();
+        if manifest.get_size() != file.size {
+            return Err(invalid("Manifest size does not match file size"));
+        }
+
+        file.manifest = Some(FileManifestRecord {
+            sha512: manifest.get_sha512().to_vec(),
+            size: manifest.get_size(),
+            metadata: manifest.get_metadata()
+                .iter()
+                .map(|entry| (entry.get_key().to_string(), entry.get_value().to_string()))
+                .collect(),
+        });
+        set_entry(context, file_address(&file_id), &file)
+    }
+
+    fn file_tag(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let mut file = self.get_owned_file(context, &file_id, signer)?;
+        if payload.get_tags().is_empty() {
+            return Err(invalid("No tags given"));
+        }
+
+        apply_tags(&mut file, payload)?;
+        set_entry(context, file_address(&file_id), &file)
+    }
+
+    fn file_retention_set(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let mut file = self.get_owned_file(context, &file_id, signer)?;
+        if payload.get_retain_until() < file.retain_until {
+            return Err(invalid("Retention can't be shortened"));
+        }
+
+        file.retain_until = payload.get_retain_until();
+        set_entry(context, file_address(&file_id), &file)
+    }
+
+    // The owner can place a hold but not lift it, or it could clear the hold and destroy the
+    // file. Lifting one takes the LegalHold permission, whose holders can also place holds on
+    // files they don't own.
+    fn file_legal_hold_set(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let custodian = self.has_permission(context, &Permission::LegalHold, signer)?;
+        let mut file = if custodian {
+            self.get_file(context, &file_id)?
+        } else {
+            self.get_owned_file(context, &file_id, signer)?
+        };
+        if file.legal_hold && !payload.get_legal_hold() && !custodian {
+            return Err(ApplyError::InvalidTransaction(format!("Missing {}", Permission::LegalHold)));
+        }
+
+        file.legal_hold = payload.get_legal_hold();
+        set_entry(context, file_address(&file_id), &file)
+    }
+
+    fn file_grant(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let mut file = self.get_owned_file(context, &file_id, signer)?;
+        let rights = FileRights(payload.get_rights());
+        if payload.get_grantee().is_empty() || payload.get_grantee() == file.owner.as_slice() {
+            return Err(invalid("Invalid grantee"));
+        }
+        if rights.is_empty() || !FileRights::ALL.contains(rights) {
+            return Err(invalid("Invalid rights"));
+        }
+
+        match file.grants.iter_mut().find(|grant| grant.public_key == payload.get_grantee()) {
+            Some(grant) => grant.rights = grant.rights.union(rights),
+            None => file.grants.push(FileGrant {
+                public_key: payload.get_grantee().to_vec(),
+                rights,
+            }),
+        }
+        set_entry(context, file_address(&file_id), &file)
+    }
+
+    fn file_revoke(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let file_id = parse_uuid(payload)?;
+        let mut file = self.get_owned_file(context, &file_id, signer)?;
+        let rights = match FileRights(payload.get_rights()) {
+            rights if rights.is_empty() => FileRights::ALL,
+            rights => rights,
+        };
+
+        for grant in file.grants.iter_mut().filter(|grant| grant.public_key == payload.get_grantee()) {
+            grant.rights = grant.rights.difference(rights);
+        }
+        file.grants.retain(|grant| !grant.rights.is_empty());
+        set_entry(context, file_address(&file_id), &file)
+    }
+
+    fn account_deposit(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8], batcher: &[u8]) -> Result<(), ApplyError> {
+        // Deposits are minted, so either the signer or the batcher that accepted the
+        // transaction must be allowed to deposit.
+        if !self.has_permission(context, &Permission::Deposit, signer)? && !self.has_permission(context, &Permission::Deposit, batcher)? {
+            return Err(ApplyError::InvalidTransaction(format!("Missing {}", Permission::Deposit)));
+        }
+
+        self.credit(context, payload.get_address(), parse_amount(payload)?)
+    }
+
+    fn account_transfer(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let amount = parse_amount(payload)?;
+        self.debit(context, signer, amount)?;
+        self.credit(context, payload.get_address(), amount)
+    }
+
+    // The amount leaves the chain; settling it is up to the gateway that accepted the
+    // transaction.
+    fn account_withdraw(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let amount = parse_amount(payload)?;
+        if amount.is_zero() {
+            return Err(invalid("Withdrawal amount must be greater than zero"));
+        }
+
+        self.debit(context, signer, amount)
+    }
+
+    // Once set, changing or turning off cosigning needs the current cosigners' approval like any
+    // other transaction of the account.
+    fn account_multisig_set(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let public_keys = &payload.multisig_public_keys;
+        let threshold = payload.multisig_threshold;
+        if public_keys.len() > MAX_MULTISIG_KEYS {
+            return Err(invalid("Too many multisig keys"));
+        }
+        if threshold as usize > public_keys.len() || (threshold == 0 && !public_keys.is_empty()) {
+            return Err(invalid("Invalid multisig threshold"));
+        }
+        for (index, public_key) in public_keys.iter().enumerate() {
+            if public_key.len() != 33 || public_keys[..index].contains(public_key) {
+                return Err(invalid("Invalid multisig key"));
+            }
+        }
+
+        let address = account_address(signer);
+        let mut account: AccountRecord = get_entry(context, &address)?.unwrap_or_default();
+        account.multisig_public_keys = public_keys.clone();
+        account.multisig_threshold = threshold;
+        set_entry(context, address, &account)
+    }
+
+    // An account with a threshold needs that many of its keys to approve each transaction it
+    // signs: the signer itself if it is one of them, and the others by cosigning the payload.
+    fn check_cosignatures(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let address = account_address(signer);
+        let mut account: AccountRecord = match get_entry(context, &address)? {
+            Some(account) => account,
+            None => return Ok(()),
+        };
+        if account.multisig_threshold == 0 {
+            return Ok(());
+        }
+
+        let message = cosigning_bytes(signer, account.multisig_sequence, payload);
+        let mut approvals: Vec<&[u8]> = Vec::new();
+        if account.multisig_public_keys.iter().any(|key| key.as_slice() == signer) {
+            approvals.push(signer);
+        }
+        for cosignature in payload.cosignatures.iter() {
+            if !account.multisig_public_keys.contains(&cosignature.public_key) {
+                return Err(invalid("Cosignature by a key that is not a cosigner"));
+            }
+            if approvals.contains(&cosignature.public_key.as_slice()) {
+                continue;
+            }
+
+            let signature = Signature::from(cylinder::Signature::new(cosignature.signature.clone()));
+            let verified = PublicKey::load_from_bytes(&cosignature.public_key)
+                .verify(&message, &signature)
+                .unwrap_or(false);
+            if !verified {
+                return Err(invalid("Invalid cosignature"));
+            }
+            approvals.push(&cosignature.public_key);
+        }
+        if approvals.len() < account.multisig_threshold as usize {
+            return Err(invalid("Not enough cosignatures"));
+        }
+
+        account.multisig_sequence += 1;
+        set_entry(context, address, &account)
+    }
+
+    // First come, first served; an alias stays with its key until that key clears it.
+    fn alias_set(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let alias = payload.get_alias();
+        if !is_valid_alias(alias) {
+            return Err(invalid("Invalid alias"));
+        }
+
+        let address = alias_address(alias);
+        if let Some(record) = get_entry::<AliasRecord>(context, &address)? {
+            if record.public_key != signer {
+                return Err(invalid("Alias is already registered"));
+            }
+            return Ok(());
+        }
+
+        set_entry(context, address, &AliasRecord {
+            alias: alias.to_string(),
+            public_key: signer.to_vec(),
+        })
+    }
+
+    fn alias_clear(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        let address = alias_address(payload.get_alias());
+        let record: AliasRecord = get_entry(context, &address)?
+            .ok_or_else(|| invalid("Alias is not registered"))?;
+        if record.public_key != signer {
+            return Err(invalid("Alias is registered to another key"));
+        }
+
+        context.delete_state_entry(&address)?;
+        Ok(())
+    }
+
+    fn permission_set(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        self.require_permission(context, Permission::SetPermission, signer)?;
+
+        let permission = Permission::from(payload.get_permission());
+        if let Permission::Unset = permission {
+            return Err(invalid("Permission must be set"));
+        }
+
+        let public_key = payload.get_permission_public_key();
+        context.set_state_entry(permission_address(&permission, public_key), public_key.to_vec())?;
+        Ok(())
+    }
+
+    fn permission_clear(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        // Without a public key the signer gives up its own permission, which needs no authority.
+        let public_key = match payload.get_permission_public_key() {
+            key if key.is_empty() => signer,
+            key => key,
+        };
+        if public_key != signer {
+            self.require_permission(context, Permission::SetPermission, signer)?;
+        }
+
+        let permission = Permission::from(payload.get_permission());
+        context.delete_state_entry(&permission_address(&permission, public_key))?;
+        Ok(())
+    }
+
+    fn timestamp_set(&self, context: &mut dyn TransactionContext, payload: &Payload, signer: &[u8]) -> Result<(), ApplyError> {
+        self.require_permission(context, Permission::Timestamp, signer)?;
+
+        let file_id = parse_uuid(payload)?;
+        let mut file = self.get_file(context, &file_id)?;
+
+        if payload.get_timestamp_create() != 0 {
+            file.timestamp_create = payload.get_timestamp_create();
+        }
+        if payload.get_timestamp_append() != 0 {
+            file.timestamp_append = payload.get_timestamp_append();
+        }
+        if payload.get_timestamp_seal() != 0 {
+            file.timestamp_seal = payload.get_timestamp_seal();
+        }
+
+        let clock: i64 = get_entry(context, &clock_address())?.unwrap_or(0);
+        let latest = payload.get_timestamp_create()
+            .max(payload.get_timestamp_append())
+            .max(payload.get_timestamp_seal());
+        if latest > clock {
+            set_entry(context, clock_address(), &latest)?;
+        }
+
+        set_entry(context, file_address(&file_id), &file)
+    }
+}
+
+// Sets each tag in the payload, replacing any with the same key, or removes it when the value is
+// empty.
+fn apply_tags(file: &mut FileRecord, payload: &Payload) -> Result<(), ApplyError> {
+    for entry in payload.get_tags() {
+        if entry.get_key().is_empty() {
+            return Err(invalid("Tag key is empty"));
+        }
+
+        file.tags.retain(|(key, _)| key != entry.get_key());
+        if !entry.get_value().is_empty() {
+            file.tags.push((entry.get_key().to_string(), entry.get_value().to_string()));
+        }
+    }
+    if file.tags.len() > MAX_FILE_TAGS {
+        return Err(invalid("Too many tags"));
+    }
+
+    Ok(())
+}
+
+fn parse_uuid(payload: &Payload) -> Result<Uuid, ApplyError> {
+    Uuid::from_slice(payload.get_uuid())
+        .map_err(|err| ApplyError::InvalidTransaction(format!("Invalid uuid: {}", err)))
+}
+
+fn parse_amount(payload: &Payload) -> Result<TokenAmount, ApplyError> {
+    TokenAmount::from_payload_fields(payload.get_amount(), payload.get_amount_wide())
+        .ok_or_else(|| invalid("Invalid amount"))
+}
+
+impl TransactionHandler for TfsliteTransactionHandler {
+    fn family_name(&self) -> String {
+        self.family_name.clone()
+    }
+
+    fn family_versions(&self) -> Vec<String> {
+        self.family_versions.clone()
+    }
+
+    fn namespaces(&self) -> Vec<String> {
+        self.namespaces.clone()
+    }
+
+    fn apply(&self, request: &TpProcessRequest, context: &mut dyn TransactionContext) -> Result<(), ApplyError> {
+        let header = request.get_header();
+
+        let signer = hex::decode(header.get_signer_public_key())
+            .map_err(|err| ApplyError::InvalidTransaction(format!("Invalid signer public key: {}", err)))?;
+        let batcher = hex::decode(header.get_batcher_public_key())
+            .map_err(|err| ApplyError::InvalidTransaction(format!("Invalid batcher public key: {}", err)))?;
+
+        self.require_permission(context, Permission::Batcher, &batcher)?;
+
+        let payload = Payload::parse_from_bytes(request.get_payload())
+            .map_err(|err| ApplyError::InvalidTransaction(format!("Unable to parse payload: {}", err)))?;
+
+        self.check_cosignatures(context, &payload, &signer)?;
+
+        match payload.get_operation() {
+            Payload_Operation::FILE_CREATE => self.file_create(context, &payload, &signer),
+            Payload_Operation::FILE_APPEND => self.file_append(context, &payload, &signer),
+            Payload_Operation::FILE_SEAL => self.file_seal(context, &payload, &signer),
+            Payload_Operation::FILE_DESTROY => self.file_destroy(context, &payload, &signer),
+            Payload_Operation::ACCOUNT_DEPOSIT => self.account_deposit(context, &payload, &signer, &batcher),
+            Payload_Operation::ACCOUNT_TRANSFER => self.account_transfer(context, &payload, &signer),
+            Payload_Operation::ACCOUNT_WITHDRAW => self.account_withdraw(context, &payload, &signer),
+            Payload_Operation::ACCOUNT_MULTISIG_SET => self.account_multisig_set(context, &payload, &signer),
+            Payload_Operation::PERMISSION_SET => self.permission_set(context, &payload, &signer),
+            Payload_Operation::PERMISSION_CLEAR => self.permission_clear(context, &payload, &signer),
+            Payload_Operation::TIMESTAMP_SET => self.timestamp_set(context, &payload, &signer),
+            Payload_Operation::FILE_MANIFEST => self.file_manifest(context, &payload, &signer),
+            Payload_Operation::FILE_TAG => self.file_tag(context, &payload, &signer),
+            Payload_Operation::FILE_RETENTION_SET => self.file_retention_set(context, &payload, &signer),
+            Payload_Operation::FILE_LEGAL_HOLD_SET => self.file_legal_hold_set(context, &payload, &signer),
+            Payload_Operation::FILE_GRANT => self.file_grant(context, &payload, &signer),
+            Payload_Operation::FILE_REVOKE => self.file_revoke(context, &payload, &signer),
+            Payload_Operation::ALIAS_SET => self.alias_set(context, &payload, &signer),
+            Payload_Operation::ALIAS_CLEAR => self.alias_clear(context, &payload, &signer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use sawtooth_sdk::messages::processor::TpProcessRequest;
+    use sawtooth_sdk::messages::transaction::TransactionHeader;
+    use sawtooth_sdk::processor::handler::{ApplyError, ContextError, TransactionContext, TransactionHandler};
+    use sha2::{Digest, Sha224};
+    use uuid::Uuid;
+    use crate::addressing::{block_address, clock_address, file_address};
+    use crate::client::keys::{PrivateKey, Signer};
+    use crate::client::payload::{PayloadBuilder, PayloadOperation};
+    use crate::common::{FILE_CREATE_COST, MAX_FILE_BLOCKS};
+    use crate::protos::compat::Message;
+    use crate::protos::payload::Payload;
+    use crate::types::{FileMode, FileRecord, FileRights, FileState, Permission};
+    use super::{get_entry, TfsliteTransactionHandler};
+
+    // Global state as a plain map.
+    #[derive(Default)]
+    struct MockContext {
+        state: RefCell<BTreeMap<String, Vec<u8>>>,
+    }
+
+    impl TransactionContext for MockContext {
+        fn get_state_entries(&self, addresses: &[String]) -> Result<Vec<(String, Vec<u8>)>, ContextError> {
+            let state = self.state.borrow();
+            Ok(addresses
+                .iter()
+                .filter_map(|address| state.get(address).map(|data| (address.clone(), data.clone())))
+                .collect())
+        }
+
+        fn set_state_entries(&self, entries: Vec<(String, Vec<u8>)>) -> Result<(), ContextError> {
+            self.state.borrow_mut().extend(entries);
+            Ok(())
+        }
+
+        fn delete_state_entries(&self, addresses: &[String]) -> Result<Vec<String>, ContextError> {
+            let mut state = self.state.borrow_mut();
+            Ok(addresses
+                .iter()
+                .filter(|address| state.remove(*address).is_some())
+                .cloned()
+                .collect())
+        }
+
+        fn add_receipt_data(&self, _data: &[u8]) -> Result<(), ContextError> {
+            Ok(())
+        }
+
+        fn add_event(&self, _event_type: String, _attributes: Vec<(String, String)>, _data: &[u8]) -> Result<(), ContextError> {
+            Ok(())
+        }
+    }
+
+    // The admin batches every transaction, and holds every permission.
+    struct Chain {
+        handler: TfsliteTransactionHandler,
+        context: MockContext,
+        admin: PrivateKey,
+    }
+
+    impl Chain {
+        fn new() -> Self {
+            let admin = PrivateKey::generate_random_key();
+            let handler = TfsliteTransactionHandler::new()
+                .with_admin_public_keys(vec![public_key(&admin)]);
+            Chain { handler, context: MockContext::default(), admin }
+        }
+
+        fn apply(&mut self, signer: &PrivateKey, payload: Payload) -> Result<(), ApplyError> {
+            let mut header = TransactionHeader::new();
+            header.set_signer_public_key(hex::encode(public_key(signer)));
+            header.set_batcher_public_key(hex::encode(public_key(&self.admin)));
+
+            let mut request = TpProcessRequest::new();
+            request.set_header(header);
+            request.set_payload(payload.write_to_bytes().unwrap());
+
+            self.handler.apply(&request, &mut self.context)
+        }
+
+        fn admin(&self) -> PrivateKey {
+            self.admin.clone()
+        }
+
+        fn file(&mut self, file_id: &Uuid) -> Option<FileRecord> {
+            get_entry(&mut self.context, &file_address(file_id)).unwrap()
+        }
+
+        fn fund(&mut self, account: &PrivateKey) {
+            let admin = self.admin();
+            let payload = PayloadBuilder::new(PayloadOperation::AccountDeposit)
+                .with_address(public_key(account))
+                .with_amount(FILE_CREATE_COST * 10)
+                .build()
+                .unwrap();
+            self.apply(&admin, payload).unwrap();
+        }
+
+        fn create(&mut self, owner: &PrivateKey, mode: FileMode) -> Uuid {
+            let file_id = Uuid::new_v4();
+            let payload = PayloadBuilder::new(PayloadOperation::FileCreate)
+                .with_uuid(file_id)
+                .with_mode(mode)
+                .build()
+                .unwrap();
+            self.apply(owner, payload).unwrap();
+            file_id
+        }
+    }
+
+    fn public_key(key: &PrivateKey) -> Vec<u8> {
+        key.public_key().unwrap().as_slice().to_vec()
+    }
+
+    fn append(file_id: Uuid, data: &[u8]) -> Payload {
+        PayloadBuilder::new(PayloadOperation::FileAppend)
+            .with_uuid(file_id)
+            .with_block(data.to_vec())
+            .build()
+            .unwrap()
+    }
+
+    fn append_indexed(file_id: Uuid, data: &[u8], index: u64, total: u64) -> Payload {
+        PayloadBuilder::new(PayloadOperation::FileAppend)
+            .with_uuid(file_id)
+            .with_block(data.to_vec())
+            .with_chunk_index(index)
+            .with_chunk_total(total)
+            .build()
+            .unwrap()
+    }
+
+    fn file_op(operation: PayloadOperation, file_id: Uuid) -> Payload {
+        PayloadBuilder::new(operation)
+            .with_uuid(file_id)
+            .build()
+            .unwrap()
+    }
+
+    fn legal_hold(file_id: Uuid, legal_hold: bool) -> Payload {
+        PayloadBuilder::new(PayloadOperation::FileLegalHoldSet)
+            .with_uuid(file_id)
+            .with_legal_hold(legal_hold)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_apply_create() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+
+        // Creating a file costs FILE_CREATE_COST, which an empty account can't pay.
+        let payload = PayloadBuilder::new(PayloadOperation::FileCreate)
+            .with_uuid(Uuid::new_v4())
+            .with_mode(FileMode::Immutable)
+            .build()
+            .unwrap();
+        assert!(chain.apply(&owner, payload.clone()).is_err());
+
+        chain.fund(&owner);
+        chain.apply(&owner, payload.clone()).unwrap();
+        assert!(chain.apply(&owner, payload.clone()).is_err(), "A file can only be created once");
+
+        let file = chain.file(&Uuid::from_slice(payload.get_uuid()).unwrap()).unwrap();
+        assert_eq!(file.owner, public_key(&owner));
+        assert_eq!(file.state, FileState::Open);
+        assert_eq!(file.block_count, 0);
+    }
+
+    #[test]
+    fn test_apply_append_in_order() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+        chain.fund(&owner);
+        let file_id = chain.create(&owner, FileMode::Immutable);
+
+        chain.apply(&owner, append(file_id, b"first")).unwrap();
+        chain.apply(&owner, append(file_id, b"second")).unwrap();
+
+        let file = chain.file(&file_id).unwrap();
+        assert_eq!(file.block_count, 2);
+        assert_eq!(file.size, 11);
+        assert_eq!(chain.context.get_state_entry(&block_address(&file_id, 1)).unwrap(), Some(b"second".to_vec()));
+
+        // The hash must match the data.
+        let mut bad = append(file_id, b"third");
+        bad.mut_block().set_sha224(Sha224::digest(b"other").to_vec());
+        assert!(chain.apply(&owner, bad).is_err());
+
+        // Only the owner, or a grantee, can append.
+        let stranger = PrivateKey::generate_random_key();
+        assert!(chain.apply(&stranger, append(file_id, b"third")).is_err());
+    }
+
+    #[test]
+    fn test_apply_append_out_of_order_and_seal_with_gaps() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+        chain.fund(&owner);
+        let file_id = chain.create(&owner, FileMode::Immutable);
+
+        chain.apply(&owner, append_indexed(file_id, b"two", 2, 3)).unwrap();
+        let file = chain.file(&file_id).unwrap();
+        assert_eq!(file.block_count, 3);
+        assert_eq!(file.missing_blocks, 2);
+        assert!(chain.apply(&owner, file_op(PayloadOperation::FileSeal, file_id)).is_err(), "Gaps block sealing");
+
+        assert!(chain.apply(&owner, append_indexed(file_id, b"two", 2, 3)).is_err(), "Blocks can't be rewritten");
+        assert!(chain.apply(&owner, append_indexed(file_id, b"three", 3, 3)).is_err(), "Blocks past the total are refused");
+        assert!(chain.apply(&owner, append_indexed(file_id, b"one", 1, 4)).is_err(), "The total can't change");
+
+        chain.apply(&owner, append_indexed(file_id, b"zero", 0, 3)).unwrap();
+        chain.apply(&owner, append_indexed(file_id, b"one", 1, 3)).unwrap();
+        let file = chain.file(&file_id).unwrap();
+        assert_eq!(file.missing_blocks, 0);
+        assert_eq!(file.written_blocks, vec![(0, 3)]);
+
+        chain.apply(&owner, file_op(PayloadOperation::FileSeal, file_id)).unwrap();
+        assert_eq!(chain.file(&file_id).unwrap().state, FileState::Sealed);
+        assert!(chain.apply(&owner, append(file_id, b"late")).is_err(), "Sealed files take no appends");
+        assert!(chain.apply(&owner, file_op(PayloadOperation::FileSeal, file_id)).is_err());
+    }
+
+    #[test]
+    fn test_apply_append_past_block_limit() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+        chain.fund(&owner);
+        let file_id = chain.create(&owner, FileMode::Destroyable);
+
+        // A huge index would otherwise overflow block_count, or leave destroy looping over it.
+        assert!(chain.apply(&owner, append_indexed(file_id, b"far", u64::MAX, 0)).is_err(), "Indexed blocks need a total");
+        assert!(chain.apply(&owner, append_indexed(file_id, b"far", u64::MAX, u64::MAX)).is_err());
+        assert!(chain.apply(&owner, append_indexed(file_id, b"far", 1 << 62, MAX_FILE_BLOCKS + 1)).is_err());
+        assert!(chain.apply(&owner, append_indexed(file_id, b"far", MAX_FILE_BLOCKS, MAX_FILE_BLOCKS)).is_err());
+        assert_eq!(chain.file(&file_id).unwrap().block_count, 0);
+
+        // The last block under the limit is fine, and destroying the file only visits what was written.
+        chain.apply(&owner, append_indexed(file_id, b"last", MAX_FILE_BLOCKS - 1, MAX_FILE_BLOCKS)).unwrap();
+        let file = chain.file(&file_id).unwrap();
+        assert_eq!(file.block_count, MAX_FILE_BLOCKS);
+        assert_eq!(file.written_blocks, vec![(MAX_FILE_BLOCKS - 1, MAX_FILE_BLOCKS)]);
+        chain.apply(&owner, file_op(PayloadOperation::FileDestroy, file_id)).unwrap();
+        assert!(chain.file(&file_id).is_none());
+        assert_eq!(chain.context.get_state_entry(&block_address(&file_id, MAX_FILE_BLOCKS - 1)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_apply_append_by_reference() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+        chain.fund(&owner);
+        let file_id = chain.create(&owner, FileMode::Immutable);
+        chain.apply(&owner, append(file_id, b"repeated")).unwrap();
+
+        let sha224 = Sha224::digest(b"repeated").to_vec();
+        let by_reference = PayloadBuilder::new(PayloadOperation::FileAppend)
+            .with_uuid(file_id)
+            .with_block_reference(sha224.clone(), 0)
+            .build()
+            .unwrap();
+        chain.apply(&owner, by_reference).unwrap();
+        assert_eq!(chain.context.get_state_entry(&block_address(&file_id, 1)).unwrap(), Some(b"repeated".to_vec()));
+        assert_eq!(chain.file(&file_id).unwrap().size, 16);
+
+        // Another file's block can be referenced too.
+        let copy_id = chain.create(&owner, FileMode::Immutable);
+        let cross_file = PayloadBuilder::new(PayloadOperation::FileAppend)
+            .with_uuid(copy_id)
+            .with_file_block_reference(sha224.clone(), file_id, 1)
+            .build()
+            .unwrap();
+        chain.apply(&owner, cross_file).unwrap();
+        assert_eq!(chain.context.get_state_entry(&block_address(&copy_id, 0)).unwrap(), Some(b"repeated".to_vec()));
+
+        let missing = PayloadBuilder::new(PayloadOperation::FileAppend)
+            .with_uuid(file_id)
+            .with_block_reference(sha224, 7)
+            .build()
+            .unwrap();
+        assert!(chain.apply(&owner, missing).is_err());
+    }
+
+    #[test]
+    fn test_apply_destroy() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+        chain.fund(&owner);
+
+        let immutable_id = chain.create(&owner, FileMode::Immutable);
+        assert!(chain.apply(&owner, file_op(PayloadOperation::FileDestroy, immutable_id)).is_err());
+
+        let file_id = chain.create(&owner, FileMode::Destroyable);
+        chain.apply(&owner, append(file_id, b"data")).unwrap();
+        let stranger = PrivateKey::generate_random_key();
+        assert!(chain.apply(&stranger, file_op(PayloadOperation::FileDestroy, file_id)).is_err());
+
+        chain.apply(&owner, file_op(PayloadOperation::FileDestroy, file_id)).unwrap();
+        assert!(chain.file(&file_id).is_none());
+        assert_eq!(chain.context.get_state_entry(&block_address(&file_id, 0)).unwrap(), None);
+        assert!(chain.file(&immutable_id).is_some());
+    }
+
+    #[test]
+    fn test_apply_grants() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+        let grantee = PrivateKey::generate_random_key();
+        chain.fund(&owner);
+        let file_id = chain.create(&owner, FileMode::Destroyable);
+
+        let grant = |rights: FileRights| PayloadBuilder::new(PayloadOperation::FileGrant)
+            .with_uuid(file_id)
+            .with_grantee(public_key(&grantee))
+            .with_rights(rights)
+            .build()
+            .unwrap();
+        assert!(chain.apply(&grantee, grant(FileRights::APPEND)).is_err(), "Only the owner grants");
+        assert!(chain.apply(&grantee, append(file_id, b"data")).is_err());
+
+        chain.apply(&owner, grant(FileRights::APPEND)).unwrap();
+        chain.apply(&grantee, append(file_id, b"data")).unwrap();
+        assert!(chain.apply(&grantee, file_op(PayloadOperation::FileDestroy, file_id)).is_err());
+
+        let revoke = PayloadBuilder::new(PayloadOperation::FileRevoke)
+            .with_uuid(file_id)
+            .with_grantee(public_key(&grantee))
+            .build()
+            .unwrap();
+        chain.apply(&owner, revoke).unwrap();
+        assert!(chain.file(&file_id).unwrap().grants.is_empty());
+        assert!(chain.apply(&grantee, append(file_id, b"more")).is_err());
+
+        chain.apply(&owner, grant(FileRights::DESTROY)).unwrap();
+        chain.apply(&grantee, file_op(PayloadOperation::FileDestroy, file_id)).unwrap();
+        assert!(chain.file(&file_id).is_none());
+    }
+
+    #[test]
+    fn test_apply_retention() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+        chain.fund(&owner);
+        let file_id = chain.create(&owner, FileMode::Destroyable);
+
+        let retain_until = |timestamp: i64| PayloadBuilder::new(PayloadOperation::FileRetentionSet)
+            .with_uuid(file_id)
+            .with_retain_until(timestamp)
+            .build()
+            .unwrap();
+        chain.apply(&owner, retain_until(2000)).unwrap();
+        assert!(chain.apply(&owner, retain_until(1000)).is_err(), "Retention can't be shortened");
+
+        // With no timestamp recorded yet, retention never ends.
+        assert!(chain.apply(&owner, file_op(PayloadOperation::FileDestroy, file_id)).is_err());
+
+        let admin = chain.admin();
+        let timestamp = |timestamp: i64| PayloadBuilder::new(PayloadOperation::TimestampSet)
+            .with_uuid(file_id)
+            .with_timestamp_append(timestamp)
+            .build()
+            .unwrap();
+        chain.apply(&admin, timestamp(1500)).unwrap();
+        assert!(chain.apply(&owner, file_op(PayloadOperation::FileDestroy, file_id)).is_err());
+
+        chain.apply(&admin, timestamp(2500)).unwrap();
+        let clock: Option<i64> = get_entry(&mut chain.context, &clock_address()).unwrap();
+        assert_eq!(clock, Some(2500));
+        chain.apply(&owner, file_op(PayloadOperation::FileDestroy, file_id)).unwrap();
+    }
+
+    #[test]
+    fn test_apply_legal_hold() {
+        let mut chain = Chain::new();
+        let owner = PrivateKey::generate_random_key();
+        let custodian = PrivateKey::generate_random_key();
+        chain.fund(&owner);
+        let file_id = chain.create(&owner, FileMode::Destroyable);
+
+        chain.apply(&owner, legal_hold(file_id, true)).unwrap();
+        assert!(chain.file(&file_id).unwrap().legal_hold);
+        assert!(chain.apply(&owner, file_op(PayloadOperation::FileDestroy, file_id)).is_err());
+
+        // The owner can't lift its own hold to get at the file.
+        assert!(chain.apply(&owner, legal_hold(file_id, false)).is_err());
+        assert!(chain.file(&file_id).unwrap().legal_hold);
+
+        assert!(chain.apply(&custodian, legal_hold(file_id, false)).is_err());
+        let admin = chain.admin();
+        let appoint = PayloadBuilder::new(PayloadOperation::PermissionSet)
+            .with_permission(Permission::LegalHold)
+            .with_permission_public_key(public_key(&custodian))
+            .build()
+            .unwrap();
+        chain.apply(&admin, appoint).unwrap();
+        chain.apply(&custodian, legal_hold(file_id, false)).unwrap();
+        assert!(!chain.file(&file_id).unwrap().legal_hold);
+
+        // Custodians can also place holds on files they don't own.
+        chain.apply(&custodian, legal_hold(file_id, true)).unwrap();
+        assert!(chain.apply(&owner, file_op(PayloadOperation::FileDestroy, file_id)).is_err());
+        chain.apply(&custodian, legal_hold(file_id, false)).unwrap();
+        chain.apply(&owner, file_op(PayloadOperation::FileDestroy, file_id)).unwrap();
+    }
+}
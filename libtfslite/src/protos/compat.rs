@@ -0,0 +1,1033 @@
+// The rust-protobuf style API the code was written against before the move to prost: new(),
+// get_/set_/mut_/take_/clear_ accessors, parse_from_bytes and write_to_bytes, the
+// Payload_Operation style names and SCREAMING_CASE enum values. New code should use the prost
+// types directly.
+use prost::{DecodeError, EncodeError};
+use super::payload::{DataBlock, FileManifest, FileMode, KeyValue, Operation, Payload, Permission};
+use super::transaction::{Transaction, TransactionHeader};
+use super::batch::{Batch, BatchHeader, BatchList};
+
+pub trait Message: prost::Message + Default {
+    fn parse_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode(bytes)
+    }
+
+    fn write_to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.encode(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn compute_size(&self) -> u32 {
+        self.encoded_len() as u32
+    }
+}
+
+impl<T: prost::Message + Default> Message for T {}
+
+static DEFAULT_DATA_BLOCK: DataBlock = DataBlock {
+    data: Vec::new(),
+    sha224: Vec::new(),
+    number: 0,
+    indexed: false,
+    total: 0,
+    reference: false,
+    reference_number: 0,
+    reference_uuid: Vec::new(),
+};
+
+static DEFAULT_FILE_MANIFEST: FileManifest = FileManifest {
+    sha512: Vec::new(),
+    size: 0,
+    metadata: Vec::new(),
+};
+
+impl Operation {
+    pub const FILE_CREATE: Operation = Operation::FileCreate;
+    pub const FILE_APPEND: Operation = Operation::FileAppend;
+    pub const FILE_SEAL: Operation = Operation::FileSeal;
+    pub const FILE_DESTROY: Operation = Operation::FileDestroy;
+    pub const ACCOUNT_DEPOSIT: Operation = Operation::AccountDeposit;
+    pub const ACCOUNT_TRANSFER: Operation = Operation::AccountTransfer;
+    pub const PERMISSION_SET: Operation = Operation::PermissionSet;
+    pub const PERMISSION_CLEAR: Operation = Operation::PermissionClear;
+    pub const TIMESTAMP_SET: Operation = Operation::TimestampSet;
+    pub const FILE_MANIFEST: Operation = Operation::FileManifest;
+    pub const FILE_TAG: Operation = Operation::FileTag;
+    pub const FILE_RETENTION_SET: Operation = Operation::FileRetentionSet;
+    pub const FILE_LEGAL_HOLD_SET: Operation = Operation::FileLegalHoldSet;
+    pub const FILE_GRANT: Operation = Operation::FileGrant;
+    pub const FILE_REVOKE: Operation = Operation::FileRevoke;
+    pub const ALIAS_SET: Operation = Operation::AliasSet;
+    pub const ALIAS_CLEAR: Operation = Operation::AliasClear;
+    pub const ACCOUNT_WITHDRAW: Operation = Operation::AccountWithdraw;
+    pub const ACCOUNT_MULTISIG_SET: Operation = Operation::AccountMultisigSet;
+}
+
+impl FileMode {
+    pub const IMMUTABLE: FileMode = FileMode::Immutable;
+    pub const DESTROYABLE: FileMode = FileMode::Destroyable;
+}
+
+impl Permission {
+    pub const UNSET: Permission = Permission::Unset;
+    pub const SET_PERMISSION: Permission = Permission::SetPermission;
+    pub const BATCHER: Permission = Permission::Batcher;
+    pub const DEPOSIT: Permission = Permission::Deposit;
+    pub const TIMESTAMP: Permission = Permission::Timestamp;
+    pub const LEGAL_HOLD: Permission = Permission::LegalHold;
+}
+
+impl Payload {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_operation(&self) -> Operation {
+        self.operation()
+    }
+
+    pub fn clear_operation(&mut self) {
+        self.operation = 0;
+    }
+
+    pub fn get_uuid(&self) -> &[u8] {
+        &self.uuid
+    }
+
+    pub fn clear_uuid(&mut self) {
+        self.uuid.clear();
+    }
+
+    pub fn set_uuid(&mut self, v: Vec<u8>) {
+        self.uuid = v;
+    }
+
+    pub fn mut_uuid(&mut self) -> &mut Vec<u8> {
+        &mut self.uuid
+    }
+
+    pub fn take_uuid(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.uuid)
+    }
+
+    pub fn get_mode(&self) -> FileMode {
+        self.mode()
+    }
+
+    pub fn clear_mode(&mut self) {
+        self.mode = 0;
+    }
+
+    pub fn has_block(&self) -> bool {
+        self.block.is_some()
+    }
+
+    pub fn get_block(&self) -> &DataBlock {
+        self.block.as_ref().unwrap_or(&DEFAULT_DATA_BLOCK)
+    }
+
+    pub fn clear_block(&mut self) {
+        self.block = None;
+    }
+
+    pub fn set_block(&mut self, v: DataBlock) {
+        self.block = Some(v);
+    }
+
+    pub fn mut_block(&mut self) -> &mut DataBlock {
+        self.block.get_or_insert_with(DataBlock::default)
+    }
+
+    pub fn take_block(&mut self) -> DataBlock {
+        self.block.take().unwrap_or_default()
+    }
+
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn clear_filename(&mut self) {
+        self.filename.clear();
+    }
+
+    pub fn set_filename(&mut self, v: String) {
+        self.filename = v;
+    }
+
+    pub fn mut_filename(&mut self) -> &mut String {
+        &mut self.filename
+    }
+
+    pub fn take_filename(&mut self) -> String {
+        std::mem::take(&mut self.filename)
+    }
+
+    pub fn get_amount(&self) -> u64 {
+        self.amount
+    }
+
+    pub fn clear_amount(&mut self) {
+        self.amount = 0;
+    }
+
+    pub fn set_amount(&mut self, v: u64) {
+        self.amount = v;
+    }
+
+    pub fn get_address(&self) -> &[u8] {
+        &self.address
+    }
+
+    pub fn clear_address(&mut self) {
+        self.address.clear();
+    }
+
+    pub fn set_address(&mut self, v: Vec<u8>) {
+        self.address = v;
+    }
+
+    pub fn mut_address(&mut self) -> &mut Vec<u8> {
+        &mut self.address
+    }
+
+    pub fn take_address(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.address)
+    }
+
+    pub fn get_permission(&self) -> Permission {
+        self.permission()
+    }
+
+    pub fn clear_permission(&mut self) {
+        self.permission = 0;
+    }
+
+    pub fn get_permission_public_key(&self) -> &[u8] {
+        &self.permission_public_key
+    }
+
+    pub fn clear_permission_public_key(&mut self) {
+        self.permission_public_key.clear();
+    }
+
+    pub fn set_permission_public_key(&mut self, v: Vec<u8>) {
+        self.permission_public_key = v;
+    }
+
+    pub fn mut_permission_public_key(&mut self) -> &mut Vec<u8> {
+        &mut self.permission_public_key
+    }
+
+    pub fn take_permission_public_key(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.permission_public_key)
+    }
+
+    pub fn get_timestamp_create(&self) -> i64 {
+        self.timestamp_create
+    }
+
+    pub fn clear_timestamp_create(&mut self) {
+        self.timestamp_create = 0;
+    }
+
+    pub fn set_timestamp_create(&mut self, v: i64) {
+        self.timestamp_create = v;
+    }
+
+    pub fn get_timestamp_append(&self) -> i64 {
+        self.timestamp_append
+    }
+
+    pub fn clear_timestamp_append(&mut self) {
+        self.timestamp_append = 0;
+    }
+
+    pub fn set_timestamp_append(&mut self, v: i64) {
+        self.timestamp_append = v;
+    }
+
+    pub fn get_timestamp_seal(&self) -> i64 {
+        self.timestamp_seal
+    }
+
+    pub fn clear_timestamp_seal(&mut self) {
+        self.timestamp_seal = 0;
+    }
+
+    pub fn set_timestamp_seal(&mut self, v: i64) {
+        self.timestamp_seal = v;
+    }
+
+    pub fn has_manifest(&self) -> bool {
+        self.manifest.is_some()
+    }
+
+    pub fn get_manifest(&self) -> &FileManifest {
+        self.manifest.as_ref().unwrap_or(&DEFAULT_FILE_MANIFEST)
+    }
+
+    pub fn clear_manifest(&mut self) {
+        self.manifest = None;
+    }
+
+    pub fn set_manifest(&mut self, v: FileManifest) {
+        self.manifest = Some(v);
+    }
+
+    pub fn mut_manifest(&mut self) -> &mut FileManifest {
+        self.manifest.get_or_insert_with(FileManifest::default)
+    }
+
+    pub fn take_manifest(&mut self) -> FileManifest {
+        self.manifest.take().unwrap_or_default()
+    }
+
+    pub fn get_tags(&self) -> &[KeyValue] {
+        &self.tags
+    }
+
+    pub fn clear_tags(&mut self) {
+        self.tags.clear();
+    }
+
+    pub fn set_tags(&mut self, v: Vec<KeyValue>) {
+        self.tags = v;
+    }
+
+    pub fn mut_tags(&mut self) -> &mut Vec<KeyValue> {
+        &mut self.tags
+    }
+
+    pub fn take_tags(&mut self) -> Vec<KeyValue> {
+        std::mem::take(&mut self.tags)
+    }
+
+    pub fn get_retain_until(&self) -> i64 {
+        self.retain_until
+    }
+
+    pub fn clear_retain_until(&mut self) {
+        self.retain_until = 0;
+    }
+
+    pub fn set_retain_until(&mut self, v: i64) {
+        self.retain_until = v;
+    }
+
+    pub fn get_legal_hold(&self) -> bool {
+        self.legal_hold
+    }
+
+    pub fn clear_legal_hold(&mut self) {
+        self.legal_hold = false;
+    }
+
+    pub fn set_legal_hold(&mut self, v: bool) {
+        self.legal_hold = v;
+    }
+
+    pub fn get_grantee(&self) -> &[u8] {
+        &self.grantee
+    }
+
+    pub fn clear_grantee(&mut self) {
+        self.grantee.clear();
+    }
+
+    pub fn set_grantee(&mut self, v: Vec<u8>) {
+        self.grantee = v;
+    }
+
+    pub fn mut_grantee(&mut self) -> &mut Vec<u8> {
+        &mut self.grantee
+    }
+
+    pub fn take_grantee(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.grantee)
+    }
+
+    pub fn get_rights(&self) -> u32 {
+        self.rights
+    }
+
+    pub fn clear_rights(&mut self) {
+        self.rights = 0;
+    }
+
+    pub fn set_rights(&mut self, v: u32) {
+        self.rights = v;
+    }
+
+    pub fn get_alias(&self) -> &str {
+        &self.alias
+    }
+
+    pub fn clear_alias(&mut self) {
+        self.alias.clear();
+    }
+
+    pub fn set_alias(&mut self, v: String) {
+        self.alias = v;
+    }
+
+    pub fn mut_alias(&mut self) -> &mut String {
+        &mut self.alias
+    }
+
+    pub fn take_alias(&mut self) -> String {
+        std::mem::take(&mut self.alias)
+    }
+
+    pub fn get_amount_wide(&self) -> &[u8] {
+        &self.amount_wide
+    }
+
+    pub fn clear_amount_wide(&mut self) {
+        self.amount_wide.clear();
+    }
+
+    pub fn set_amount_wide(&mut self, v: Vec<u8>) {
+        self.amount_wide = v;
+    }
+
+    pub fn mut_amount_wide(&mut self) -> &mut Vec<u8> {
+        &mut self.amount_wide
+    }
+
+    pub fn take_amount_wide(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.amount_wide)
+    }
+}
+
+impl DataBlock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn clear_data(&mut self) {
+        self.data.clear();
+    }
+
+    pub fn set_data(&mut self, v: Vec<u8>) {
+        self.data = v;
+    }
+
+    pub fn mut_data(&mut self) -> &mut Vec<u8> {
+        &mut self.data
+    }
+
+    pub fn take_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.data)
+    }
+
+    pub fn get_sha224(&self) -> &[u8] {
+        &self.sha224
+    }
+
+    pub fn clear_sha224(&mut self) {
+        self.sha224.clear();
+    }
+
+    pub fn set_sha224(&mut self, v: Vec<u8>) {
+        self.sha224 = v;
+    }
+
+    pub fn mut_sha224(&mut self) -> &mut Vec<u8> {
+        &mut self.sha224
+    }
+
+    pub fn take_sha224(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.sha224)
+    }
+
+    pub fn get_number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn clear_number(&mut self) {
+        self.number = 0;
+    }
+
+    pub fn set_number(&mut self, v: u64) {
+        self.number = v;
+    }
+
+    pub fn get_indexed(&self) -> bool {
+        self.indexed
+    }
+
+    pub fn clear_indexed(&mut self) {
+        self.indexed = false;
+    }
+
+    pub fn set_indexed(&mut self, v: bool) {
+        self.indexed = v;
+    }
+
+    pub fn get_total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn clear_total(&mut self) {
+        self.total = 0;
+    }
+
+    pub fn set_total(&mut self, v: u64) {
+        self.total = v;
+    }
+
+    pub fn get_reference(&self) -> bool {
+        self.reference
+    }
+
+    pub fn clear_reference(&mut self) {
+        self.reference = false;
+    }
+
+    pub fn set_reference(&mut self, v: bool) {
+        self.reference = v;
+    }
+
+    pub fn get_reference_number(&self) -> u64 {
+        self.reference_number
+    }
+
+    pub fn clear_reference_number(&mut self) {
+        self.reference_number = 0;
+    }
+
+    pub fn set_reference_number(&mut self, v: u64) {
+        self.reference_number = v;
+    }
+
+    pub fn get_reference_uuid(&self) -> &[u8] {
+        &self.reference_uuid
+    }
+
+    pub fn clear_reference_uuid(&mut self) {
+        self.reference_uuid.clear();
+    }
+
+    pub fn set_reference_uuid(&mut self, v: Vec<u8>) {
+        self.reference_uuid = v;
+    }
+
+    pub fn mut_reference_uuid(&mut self) -> &mut Vec<u8> {
+        &mut self.reference_uuid
+    }
+
+    pub fn take_reference_uuid(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.reference_uuid)
+    }
+}
+
+impl KeyValue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn clear_key(&mut self) {
+        self.key.clear();
+    }
+
+    pub fn set_key(&mut self, v: String) {
+        self.key = v;
+    }
+
+    pub fn mut_key(&mut self) -> &mut String {
+        &mut self.key
+    }
+
+    pub fn take_key(&mut self) -> String {
+        std::mem::take(&mut self.key)
+    }
+
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn clear_value(&mut self) {
+        self.value.clear();
+    }
+
+    pub fn set_value(&mut self, v: String) {
+        self.value = v;
+    }
+
+    pub fn mut_value(&mut self) -> &mut String {
+        &mut self.value
+    }
+
+    pub fn take_value(&mut self) -> String {
+        std::mem::take(&mut self.value)
+    }
+}
+
+impl FileManifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_sha512(&self) -> &[u8] {
+        &self.sha512
+    }
+
+    pub fn clear_sha512(&mut self) {
+        self.sha512.clear();
+    }
+
+    pub fn set_sha512(&mut self, v: Vec<u8>) {
+        self.sha512 = v;
+    }
+
+    pub fn mut_sha512(&mut self) -> &mut Vec<u8> {
+        &mut self.sha512
+    }
+
+    pub fn take_sha512(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.sha512)
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn clear_size(&mut self) {
+        self.size = 0;
+    }
+
+    pub fn set_size(&mut self, v: u64) {
+        self.size = v;
+    }
+
+    pub fn get_metadata(&self) -> &[KeyValue] {
+        &self.metadata
+    }
+
+    pub fn clear_metadata(&mut self) {
+        self.metadata.clear();
+    }
+
+    pub fn set_metadata(&mut self, v: Vec<KeyValue>) {
+        self.metadata = v;
+    }
+
+    pub fn mut_metadata(&mut self) -> &mut Vec<KeyValue> {
+        &mut self.metadata
+    }
+
+    pub fn take_metadata(&mut self) -> Vec<KeyValue> {
+        std::mem::take(&mut self.metadata)
+    }
+}
+
+impl TransactionHeader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_batcher_public_key(&self) -> &str {
+        &self.batcher_public_key
+    }
+
+    pub fn clear_batcher_public_key(&mut self) {
+        self.batcher_public_key.clear();
+    }
+
+    pub fn set_batcher_public_key(&mut self, v: String) {
+        self.batcher_public_key = v;
+    }
+
+    pub fn mut_batcher_public_key(&mut self) -> &mut String {
+        &mut self.batcher_public_key
+    }
+
+    pub fn take_batcher_public_key(&mut self) -> String {
+        std::mem::take(&mut self.batcher_public_key)
+    }
+
+    pub fn get_dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    pub fn clear_dependencies(&mut self) {
+        self.dependencies.clear();
+    }
+
+    pub fn set_dependencies(&mut self, v: Vec<String>) {
+        self.dependencies = v;
+    }
+
+    pub fn mut_dependencies(&mut self) -> &mut Vec<String> {
+        &mut self.dependencies
+    }
+
+    pub fn take_dependencies(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.dependencies)
+    }
+
+    pub fn get_family_name(&self) -> &str {
+        &self.family_name
+    }
+
+    pub fn clear_family_name(&mut self) {
+        self.family_name.clear();
+    }
+
+    pub fn set_family_name(&mut self, v: String) {
+        self.family_name = v;
+    }
+
+    pub fn mut_family_name(&mut self) -> &mut String {
+        &mut self.family_name
+    }
+
+    pub fn take_family_name(&mut self) -> String {
+        std::mem::take(&mut self.family_name)
+    }
+
+    pub fn get_family_version(&self) -> &str {
+        &self.family_version
+    }
+
+    pub fn clear_family_version(&mut self) {
+        self.family_version.clear();
+    }
+
+    pub fn set_family_version(&mut self, v: String) {
+        self.family_version = v;
+    }
+
+    pub fn mut_family_version(&mut self) -> &mut String {
+        &mut self.family_version
+    }
+
+    pub fn take_family_version(&mut self) -> String {
+        std::mem::take(&mut self.family_version)
+    }
+
+    pub fn get_inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn clear_inputs(&mut self) {
+        self.inputs.clear();
+    }
+
+    pub fn set_inputs(&mut self, v: Vec<String>) {
+        self.inputs = v;
+    }
+
+    pub fn mut_inputs(&mut self) -> &mut Vec<String> {
+        &mut self.inputs
+    }
+
+    pub fn take_inputs(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.inputs)
+    }
+
+    pub fn get_nonce(&self) -> &str {
+        &self.nonce
+    }
+
+    pub fn clear_nonce(&mut self) {
+        self.nonce.clear();
+    }
+
+    pub fn set_nonce(&mut self, v: String) {
+        self.nonce = v;
+    }
+
+    pub fn mut_nonce(&mut self) -> &mut String {
+        &mut self.nonce
+    }
+
+    pub fn take_nonce(&mut self) -> String {
+        std::mem::take(&mut self.nonce)
+    }
+
+    pub fn get_outputs(&self) -> &[String] {
+        &self.outputs
+    }
+
+    pub fn clear_outputs(&mut self) {
+        self.outputs.clear();
+    }
+
+    pub fn set_outputs(&mut self, v: Vec<String>) {
+        self.outputs = v;
+    }
+
+    pub fn mut_outputs(&mut self) -> &mut Vec<String> {
+        &mut self.outputs
+    }
+
+    pub fn take_outputs(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.outputs)
+    }
+
+    pub fn get_payload_sha512(&self) -> &str {
+        &self.payload_sha512
+    }
+
+    pub fn clear_payload_sha512(&mut self) {
+        self.payload_sha512.clear();
+    }
+
+    pub fn set_payload_sha512(&mut self, v: String) {
+        self.payload_sha512 = v;
+    }
+
+    pub fn mut_payload_sha512(&mut self) -> &mut String {
+        &mut self.payload_sha512
+    }
+
+    pub fn take_payload_sha512(&mut self) -> String {
+        std::mem::take(&mut self.payload_sha512)
+    }
+
+    pub fn get_signer_public_key(&self) -> &str {
+        &self.signer_public_key
+    }
+
+    pub fn clear_signer_public_key(&mut self) {
+        self.signer_public_key.clear();
+    }
+
+    pub fn set_signer_public_key(&mut self, v: String) {
+        self.signer_public_key = v;
+    }
+
+    pub fn mut_signer_public_key(&mut self) -> &mut String {
+        &mut self.signer_public_key
+    }
+
+    pub fn take_signer_public_key(&mut self) -> String {
+        std::mem::take(&mut self.signer_public_key)
+    }
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_header(&self) -> &[u8] {
+        &self.header
+    }
+
+    pub fn clear_header(&mut self) {
+        self.header.clear();
+    }
+
+    pub fn set_header(&mut self, v: Vec<u8>) {
+        self.header = v;
+    }
+
+    pub fn mut_header(&mut self) -> &mut Vec<u8> {
+        &mut self.header
+    }
+
+    pub fn take_header(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.header)
+    }
+
+    pub fn get_header_signature(&self) -> &str {
+        &self.header_signature
+    }
+
+    pub fn clear_header_signature(&mut self) {
+        self.header_signature.clear();
+    }
+
+    pub fn set_header_signature(&mut self, v: String) {
+        self.header_signature = v;
+    }
+
+    pub fn mut_header_signature(&mut self) -> &mut String {
+        &mut self.header_signature
+    }
+
+    pub fn take_header_signature(&mut self) -> String {
+        std::mem::take(&mut self.header_signature)
+    }
+
+    pub fn get_payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    pub fn clear_payload(&mut self) {
+        self.payload.clear();
+    }
+
+    pub fn set_payload(&mut self, v: Vec<u8>) {
+        self.payload = v;
+    }
+
+    pub fn mut_payload(&mut self) -> &mut Vec<u8> {
+        &mut self.payload
+    }
+
+    pub fn take_payload(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.payload)
+    }
+}
+
+impl BatchHeader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_signer_public_key(&self) -> &str {
+        &self.signer_public_key
+    }
+
+    pub fn clear_signer_public_key(&mut self) {
+        self.signer_public_key.clear();
+    }
+
+    pub fn set_signer_public_key(&mut self, v: String) {
+        self.signer_public_key = v;
+    }
+
+    pub fn mut_signer_public_key(&mut self) -> &mut String {
+        &mut self.signer_public_key
+    }
+
+    pub fn take_signer_public_key(&mut self) -> String {
+        std::mem::take(&mut self.signer_public_key)
+    }
+
+    pub fn get_transaction_ids(&self) -> &[String] {
+        &self.transaction_ids
+    }
+
+    pub fn clear_transaction_ids(&mut self) {
+        self.transaction_ids.clear();
+    }
+
+    pub fn set_transaction_ids(&mut self, v: Vec<String>) {
+        self.transaction_ids = v;
+    }
+
+    pub fn mut_transaction_ids(&mut self) -> &mut Vec<String> {
+        &mut self.transaction_ids
+    }
+
+    pub fn take_transaction_ids(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.transaction_ids)
+    }
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_header(&self) -> &[u8] {
+        &self.header
+    }
+
+    pub fn clear_header(&mut self) {
+        self.header.clear();
+    }
+
+    pub fn set_header(&mut self, v: Vec<u8>) {
+        self.header = v;
+    }
+
+    pub fn mut_header(&mut self) -> &mut Vec<u8> {
+        &mut self.header
+    }
+
+    pub fn take_header(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.header)
+    }
+
+    pub fn get_header_signature(&self) -> &str {
+        &self.header_signature
+    }
+
+    pub fn clear_header_signature(&mut self) {
+        self.header_signature.clear();
+    }
+
+    pub fn set_header_signature(&mut self, v: String) {
+        self.header_signature = v;
+    }
+
+    pub fn mut_header_signature(&mut self) -> &mut String {
+        &mut self.header_signature
+    }
+
+    pub fn take_header_signature(&mut self) -> String {
+        std::mem::take(&mut self.header_signature)
+    }
+
+    pub fn get_transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    pub fn clear_transactions(&mut self) {
+        self.transactions.clear();
+    }
+
+    pub fn set_transactions(&mut self, v: Vec<Transaction>) {
+        self.transactions = v;
+    }
+
+    pub fn mut_transactions(&mut self) -> &mut Vec<Transaction> {
+        &mut self.transactions
+    }
+
+    pub fn take_transactions(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.transactions)
+    }
+
+    pub fn get_trace(&self) -> bool {
+        self.trace
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.trace = false;
+    }
+
+    pub fn set_trace(&mut self, v: bool) {
+        self.trace = v;
+    }
+}
+
+impl BatchList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_batches(&self) -> &[Batch] {
+        &self.batches
+    }
+
+    pub fn clear_batches(&mut self) {
+        self.batches.clear();
+    }
+
+    pub fn set_batches(&mut self, v: Vec<Batch>) {
+        self.batches = v;
+    }
+
+    pub fn mut_batches(&mut self) -> &mut Vec<Batch> {
+        &mut self.batches
+    }
+
+    pub fn take_batches(&mut self) -> Vec<Batch> {
+        std::mem::take(&mut self.batches)
+    }
+}
@@ -1,9 +1,39 @@
-pub mod payload;
+// prost puts the messages of every .proto file into one module, as none of them has a package.
+#[allow(clippy::all)]
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/_.rs"));
+}
+
+pub mod compat;
+
+pub mod payload {
+    pub use super::generated::Payload;
+    pub use super::generated::payload::{Cosignature, DataBlock, FileManifest, FileMode, KeyValue, Operation, Permission};
+
+    // The names rust-protobuf gave the nested types.
+    #[allow(non_camel_case_types)]
+    pub type Payload_Operation = Operation;
+    #[allow(non_camel_case_types)]
+    pub type Payload_FileMode = FileMode;
+    #[allow(non_camel_case_types)]
+    pub type Payload_Permission = Permission;
+    #[allow(non_camel_case_types)]
+    pub type Payload_DataBlock = DataBlock;
+    #[allow(non_camel_case_types)]
+    pub type Payload_KeyValue = KeyValue;
+    #[allow(non_camel_case_types)]
+    pub type Payload_FileManifest = FileManifest;
+    #[allow(non_camel_case_types)]
+    pub type Payload_Cosignature = Cosignature;
+}
 
 pub mod transaction {
-    pub use sawtooth_sdk::messages::transaction::{TransactionHeader, Transaction};
+    pub use super::generated::{Transaction, TransactionHeader};
 }
 
 pub mod batch {
-    pub use sawtooth_sdk::messages::batch::{BatchHeader, Batch, BatchList};
+    pub use super::generated::{Batch, BatchHeader, BatchList};
 }
+
+#[cfg(feature = "sawtooth")]
+pub mod sawtooth;
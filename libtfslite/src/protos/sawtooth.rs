@@ -0,0 +1,115 @@
+// Conversions between the vendored messages and the Sawtooth SDK's, for code that talks to a
+// validator through the SDK. The messages are field for field the same.
+use protobuf::RepeatedField;
+use sawtooth_sdk::messages::batch as sdk_batch;
+use sawtooth_sdk::messages::transaction as sdk_transaction;
+use super::batch::{Batch, BatchHeader, BatchList};
+use super::transaction::{Transaction, TransactionHeader};
+
+impl From<TransactionHeader> for sdk_transaction::TransactionHeader {
+    fn from(header: TransactionHeader) -> Self {
+        let mut result = sdk_transaction::TransactionHeader::new();
+        result.set_batcher_public_key(header.batcher_public_key);
+        result.set_dependencies(RepeatedField::from_vec(header.dependencies));
+        result.set_family_name(header.family_name);
+        result.set_family_version(header.family_version);
+        result.set_inputs(RepeatedField::from_vec(header.inputs));
+        result.set_nonce(header.nonce);
+        result.set_outputs(RepeatedField::from_vec(header.outputs));
+        result.set_payload_sha512(header.payload_sha512);
+        result.set_signer_public_key(header.signer_public_key);
+        result
+    }
+}
+
+impl From<sdk_transaction::TransactionHeader> for TransactionHeader {
+    fn from(mut header: sdk_transaction::TransactionHeader) -> Self {
+        TransactionHeader {
+            batcher_public_key: header.take_batcher_public_key(),
+            dependencies: header.take_dependencies().into_vec(),
+            family_name: header.take_family_name(),
+            family_version: header.take_family_version(),
+            inputs: header.take_inputs().into_vec(),
+            nonce: header.take_nonce(),
+            outputs: header.take_outputs().into_vec(),
+            payload_sha512: header.take_payload_sha512(),
+            signer_public_key: header.take_signer_public_key(),
+        }
+    }
+}
+
+impl From<Transaction> for sdk_transaction::Transaction {
+    fn from(transaction: Transaction) -> Self {
+        let mut result = sdk_transaction::Transaction::new();
+        result.set_header(transaction.header);
+        result.set_header_signature(transaction.header_signature);
+        result.set_payload(transaction.payload);
+        result
+    }
+}
+
+impl From<sdk_transaction::Transaction> for Transaction {
+    fn from(mut transaction: sdk_transaction::Transaction) -> Self {
+        Transaction {
+            header: transaction.take_header(),
+            header_signature: transaction.take_header_signature(),
+            payload: transaction.take_payload(),
+        }
+    }
+}
+
+impl From<BatchHeader> for sdk_batch::BatchHeader {
+    fn from(header: BatchHeader) -> Self {
+        let mut result = sdk_batch::BatchHeader::new();
+        result.set_signer_public_key(header.signer_public_key);
+        result.set_transaction_ids(RepeatedField::from_vec(header.transaction_ids));
+        result
+    }
+}
+
+impl From<sdk_batch::BatchHeader> for BatchHeader {
+    fn from(mut header: sdk_batch::BatchHeader) -> Self {
+        BatchHeader {
+            signer_public_key: header.take_signer_public_key(),
+            transaction_ids: header.take_transaction_ids().into_vec(),
+        }
+    }
+}
+
+impl From<Batch> for sdk_batch::Batch {
+    fn from(batch: Batch) -> Self {
+        let mut result = sdk_batch::Batch::new();
+        result.set_header(batch.header);
+        result.set_header_signature(batch.header_signature);
+        result.set_transactions(batch.transactions.into_iter().map(Into::into).collect());
+        result.set_trace(batch.trace);
+        result
+    }
+}
+
+impl From<sdk_batch::Batch> for Batch {
+    fn from(mut batch: sdk_batch::Batch) -> Self {
+        Batch {
+            header: batch.take_header(),
+            header_signature: batch.take_header_signature(),
+            transactions: batch.take_transactions().into_iter().map(Into::into).collect(),
+            trace: batch.get_trace(),
+        }
+    }
+}
+
+impl From<BatchList> for sdk_batch::BatchList {
+    fn from(batch_list: BatchList) -> Self {
+        let mut result = sdk_batch::BatchList::new();
+        result.set_batches(batch_list.batches.into_iter().map(Into::into).collect());
+        result
+    }
+}
+
+impl From<sdk_batch::BatchList> for BatchList {
+    fn from(mut batch_list: sdk_batch::BatchList) -> Self {
+        BatchList {
+            batches: batch_list.take_batches().into_iter().map(Into::into).collect(),
+        }
+    }
+}
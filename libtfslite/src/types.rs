@@ -1,12 +1,14 @@
 use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Serialize_repr, Deserialize_repr};
 use uuid;
 use uuid::serde::compact;
+use crate::common::MAX_FILE_BLOCKS;
 use crate::protos::payload::{Payload_FileMode, Payload_Permission};
 
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, Copy, Clone)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Copy, Clone, PartialEq)]
 #[repr(u8)]
 pub enum FileState {
     Open = 1,
@@ -22,7 +24,7 @@ impl Display for FileState {
     }
 }
 
-#[derive(Serialize_repr, Deserialize_repr, Debug, Copy, Clone)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Copy, Clone, PartialEq)]
 #[repr(u8)]
 pub enum FileMode {
     Destroyable = 1,
@@ -64,13 +66,292 @@ pub struct DirectoryEntry {
     pub file_name: String,
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileManifestRecord {
+    pub sha512: Vec<u8>,
+    pub size: u64,
+    pub metadata: Vec<(String, String)>,
+}
+
+// Rights over a file the owner can grant to another key, as a bit set. Chain state is public,
+// so READ is only enforced by gateways that choose to.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct FileRights(pub u32);
+
+impl FileRights {
+    pub const NONE: FileRights = FileRights(0);
+    pub const READ: FileRights = FileRights(1);
+    pub const APPEND: FileRights = FileRights(2);
+    pub const DESTROY: FileRights = FileRights(4);
+    pub const ALL: FileRights = FileRights(7);
+
+    pub fn contains(&self, rights: FileRights) -> bool {
+        self.0 & rights.0 == rights.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(&self, rights: FileRights) -> FileRights {
+        FileRights(self.0 | rights.0)
+    }
+
+    pub fn difference(&self, rights: FileRights) -> FileRights {
+        FileRights(self.0 & !rights.0)
+    }
+}
+
+impl Display for FileRights {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let names: Vec<&str> = [(FileRights::READ, "READ"), (FileRights::APPEND, "APPEND"), (FileRights::DESTROY, "DESTROY")]
+            .into_iter()
+            .filter(|(rights, _)| self.contains(*rights))
+            .map(|(_, name)| name)
+            .collect();
+        write!(f, "{}", names.join("|"))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileGrant {
+    pub public_key: Vec<u8>,
+    pub rights: FileRights,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileRecord {
+    #[serde(with = "compact")]
+    pub file_id: uuid::Uuid,
+    pub owner: Vec<u8>,
+    pub file_name: String,
+    pub mode: FileMode,
+    pub state: FileState,
+    pub block_count: u64,
+    // Blocks below block_count not yet written by an indexed append.
+    #[serde(default)]
+    pub missing_blocks: u64,
+    // Number of blocks the file will have, when its appends declared it.
+    #[serde(default)]
+    pub block_total: Option<u64>,
+    pub size: u64,
+    pub timestamp_create: i64,
+    pub timestamp_append: i64,
+    pub timestamp_seal: i64,
+    pub manifest: Option<FileManifestRecord>,
+    #[serde(default)]
+    pub tags: Vec<(String, String)>,
+    #[serde(default)]
+    pub retain_until: i64,
+    #[serde(default)]
+    pub legal_hold: bool,
+    #[serde(default)]
+    pub grants: Vec<FileGrant>,
+    // Block numbers written so far, as sorted, non-overlapping [start, end) ranges. Appends in
+    // order keep this to a single range.
+    #[serde(default)]
+    pub written_blocks: Vec<(u64, u64)>,
+}
+
+impl FileRecord {
+    // The owner holds every right.
+    pub fn rights_of(&self, public_key: &[u8]) -> FileRights {
+        if self.owner == public_key {
+            return FileRights::ALL;
+        }
+
+        self.grants
+            .iter()
+            .find(|grant| grant.public_key == public_key)
+            .map(|grant| grant.rights)
+            .unwrap_or_default()
+    }
+
+    // Records from before written_blocks was kept list no ranges, and may have written any block
+    // below block_count.
+    pub fn written_block_ranges(&self) -> Vec<(u64, u64)> {
+        if self.written_blocks.is_empty() && self.block_count > 0 {
+            return vec![(0, self.block_count.min(MAX_FILE_BLOCKS))];
+        }
+        self.written_blocks.clone()
+    }
+
+    pub fn mark_block_written(&mut self, number: u64) {
+        let mut ranges = self.written_block_ranges();
+        let end = number.saturating_add(1);
+        let index = ranges.partition_point(|&(_, range_end)| range_end < number);
+        match ranges.get(index).copied() {
+            Some((start, range_end)) if start <= number && number < range_end => {},
+            Some((_, range_end)) if range_end == number => {
+                ranges[index].1 = end;
+                if ranges.get(index + 1).is_some_and(|&(next_start, _)| next_start == end) {
+                    ranges[index].1 = ranges.remove(index + 1).1;
+                }
+            },
+            Some((start, _)) if start == end => ranges[index].0 = number,
+            _ => ranges.insert(index, (number, end)),
+        }
+        self.written_blocks = ranges;
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AliasRecord {
+    pub alias: String,
+    pub public_key: Vec<u8>,
+}
+
+// A token amount in the smallest denomination. Serialized as a plain integer, so balances
+// stored as u64 read back unchanged.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct TokenAmount(pub u128);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenAmountParseError {
+    Invalid(String),
+    TooManyDecimals(u32),
+    Overflow,
+}
+
+impl Display for TokenAmountParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            TokenAmountParseError::Invalid(value) => write!(f, "Invalid token amount: {}", value),
+            TokenAmountParseError::TooManyDecimals(decimals) => write!(f, "Token amount has more than {} decimal places", decimals),
+            TokenAmountParseError::Overflow => write!(f, "Token amount is too large"),
+        }
+    }
+}
+
+impl std::error::Error for TokenAmountParseError {}
+
+impl TokenAmount {
+    pub const ZERO: TokenAmount = TokenAmount(0);
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(&self, amount: TokenAmount) -> Option<TokenAmount> {
+        self.0.checked_add(amount.0).map(TokenAmount)
+    }
+
+    pub fn checked_sub(&self, amount: TokenAmount) -> Option<TokenAmount> {
+        self.0.checked_sub(amount.0).map(TokenAmount)
+    }
+
+    pub fn checked_mul(&self, factor: u128) -> Option<TokenAmount> {
+        self.0.checked_mul(factor).map(TokenAmount)
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        u64::try_from(self.0).ok()
+    }
+
+    // Formats in whole tokens of 10^decimals base units, e.g. 1500000 with 6 decimals is "1.5".
+    pub fn format(&self, decimals: u32) -> String {
+        let digits = format!("{:0>width$}", self.0, width = decimals as usize + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{}.{}", whole, fraction)
+        }
+    }
+
+    // The inverse of format; "1.5" with 6 decimals is 1500000.
+    pub fn parse(value: &str, decimals: u32) -> std::result::Result<TokenAmount, TokenAmountParseError> {
+        let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+        let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+            return Err(TokenAmountParseError::Invalid(value.to_string()));
+        }
+        if fraction.len() > decimals as usize {
+            return Err(TokenAmountParseError::TooManyDecimals(decimals));
+        }
+
+        let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+        let digits = digits.trim_start_matches('0');
+        if digits.is_empty() {
+            return Ok(TokenAmount::ZERO);
+        }
+        digits.parse()
+            .map(TokenAmount)
+            .map_err(|_| TokenAmountParseError::Overflow)
+    }
+
+    // Payloads carry amounts that fit a u64 in amount and larger ones in amount_wide.
+    pub fn to_payload_fields(&self) -> (u64, Vec<u8>) {
+        match self.to_u64() {
+            Some(amount) => (amount, Vec::new()),
+            None => (0, self.0.to_be_bytes().to_vec()),
+        }
+    }
+
+    pub fn from_payload_fields(amount: u64, amount_wide: &[u8]) -> Option<TokenAmount> {
+        if amount_wide.is_empty() {
+            return Some(TokenAmount::from(amount));
+        }
+        if amount != 0 {
+            return None;
+        }
+
+        let bytes: [u8; 16] = amount_wide.try_into().ok()?;
+        Some(TokenAmount(u128::from_be_bytes(bytes)))
+    }
+}
+
+impl From<u64> for TokenAmount {
+    fn from(value: u64) -> Self {
+        TokenAmount(value as u128)
+    }
+}
+
+impl From<u128> for TokenAmount {
+    fn from(value: u128) -> Self {
+        TokenAmount(value)
+    }
+}
+
+// Base units, with no decimal point.
+impl Display for TokenAmount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for TokenAmount {
+    type Err = TokenAmountParseError;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        TokenAmount::parse(value, 0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AccountRecord {
+    pub balance: TokenAmount,
+    // Keys that cosign the account's transactions, and how many must; no cosigning when 0.
+    #[serde(default)]
+    pub multisig_public_keys: Vec<Vec<u8>>,
+    #[serde(default)]
+    pub multisig_threshold: u32,
+    // Cosigned transactions so far. Cosigners sign it too, so an approval can't be replayed.
+    #[serde(default)]
+    pub multisig_sequence: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Permission {
     Unset,
     SetPermission,
     Batcher,
     Deposit,
     Timestamp,
+    // Lifts legal holds, and can place them on any file.
+    LegalHold,
 }
 
 impl Permission {
@@ -81,6 +362,7 @@ impl Permission {
             Permission::Batcher => String::from("02"),
             Permission::Deposit => String::from("03"),
             Permission::Timestamp => String::from("04"),
+            Permission::LegalHold => String::from("05"),
         }
     }
 }
@@ -93,6 +375,7 @@ impl Display for Permission {
             Permission::Batcher => write!(f, "Permission::Batcher"),
             Permission::Deposit => write!(f, "Permission::Deposit"),
             Permission::Timestamp => write!(f, "Permission::Timestamp"),
+            Permission::LegalHold => write!(f, "Permission::LegalHold"),
         }
     }
 }
@@ -105,6 +388,7 @@ impl From<Payload_Permission> for Permission {
             Payload_Permission::BATCHER => Permission::Batcher,
             Payload_Permission::DEPOSIT => Permission::Deposit,
             Payload_Permission::TIMESTAMP => Permission::Timestamp,
+            Payload_Permission::LEGAL_HOLD => Permission::LegalHold,
         }
     }
 }
@@ -117,6 +401,7 @@ impl From<Permission> for Payload_Permission {
             Permission::Batcher => Payload_Permission::BATCHER,
             Permission::Deposit => Payload_Permission::DEPOSIT,
             Permission::Timestamp => Payload_Permission::TIMESTAMP,
+            Permission::LegalHold => Payload_Permission::LEGAL_HOLD,
         }
     }
 }
@@ -0,0 +1,283 @@
+// Node.js bindings for Electron and server side users, reading files straight from disk rather than
+// through the browser's File API. The SDK's futures aren't Send, so each client runs on a thread
+// of its own with a blocking client, and JS calls are queued to it as jobs.
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use tokio::sync::oneshot;
+use libtfslite::client::keys::{PrivateKey, Signer};
+use libtfslite::client::keys::keystore::{self, KeystoreError};
+use tfslite_sdk::blocking;
+use tfslite_sdk::client::{TFSLiteClientError, TFSLiteClientErrorType};
+
+fn client_error(err: TFSLiteClientError) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+fn keystore_error(err: KeystoreError) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+fn closed_error() -> Error {
+    Error::from_reason("Client has shut down")
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct UploadOptions {
+    pub chunk_size: Option<u32>,
+    pub filename: Option<String>,
+    pub deduplicate_chunks: Option<bool>,
+    pub indexed_chunks: Option<bool>,
+}
+
+// Phase is "prepare", "send" or "wait".
+#[napi(object)]
+pub struct UploadProgress {
+    pub phase: String,
+    pub processed: i64,
+    pub total: i64,
+}
+
+#[napi(object)]
+pub struct UploadResult {
+    pub uuid: String,
+    pub tx_count: u32,
+    pub elapsed_ms: i64,
+}
+
+type ProgressCallback = ThreadsafeFunction<UploadProgress, ErrorStrategy::Fatal>;
+
+fn progress(phase: &'static str, on_progress: ProgressCallback) -> impl FnMut(u64, u64) + 'static {
+    move |processed, total| {
+        on_progress.call(UploadProgress {
+            phase: phase.to_string(),
+            processed: processed as i64,
+            total: total as i64,
+        }, ThreadsafeFunctionCallMode::NonBlocking);
+    }
+}
+
+struct Worker {
+    client: blocking::TFSLiteClient,
+    signer: Option<PrivateKey>,
+}
+
+type Job = Box<dyn FnOnce(&mut Worker) + Send>;
+
+#[napi]
+pub struct TfsClient {
+    jobs: mpsc::Sender<Job>,
+}
+
+#[napi]
+impl TfsClient {
+    // State is kept in a redb database at storePath, or the SDK's default location.
+    #[napi(constructor)]
+    pub fn new(url: String, store_path: Option<String>) -> Result<Self> {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (ready, ready_receiver) = mpsc::channel::<std::result::Result<(), TFSLiteClientError>>();
+
+        thread::spawn(move || {
+            let client = match store_path {
+                Some(store_path) => blocking::TFSLiteClient::with_store_path(url, Path::new(&store_path)),
+                None => blocking::TFSLiteClient::new(url),
+            };
+            let mut worker = match client {
+                Ok(client) => Worker { client, signer: None },
+                Err(err) => {
+                    let _ = ready.send(Err(err));
+                    return;
+                },
+            };
+            let _ = ready.send(Ok(()));
+
+            // Ends once the JS object is collected and the sender dropped.
+            while let Ok(job) = job_receiver.recv() {
+                job(&mut worker);
+            }
+        });
+
+        ready_receiver.recv()
+            .map_err(|_| closed_error())?
+            .map_err(client_error)?;
+
+        Ok(TfsClient { jobs })
+    }
+
+    fn run<T, F>(&self, job: F) -> Result<oneshot::Receiver<std::result::Result<T, TFSLiteClientError>>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Worker) -> std::result::Result<T, TFSLiteClientError> + Send + 'static,
+    {
+        let (reply, receiver) = oneshot::channel();
+        self.jobs.send(Box::new(move |worker| {
+            let _ = reply.send(job(worker));
+        }))
+        .map_err(|_| closed_error())?;
+
+        Ok(receiver)
+    }
+
+    fn set_key(&self, key: PrivateKey) -> Result<String> {
+        let public_key = Signer::public_key(&key)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        self.jobs.send(Box::new(move |worker| {
+            if let Ok(account) = Signer::public_key(&key) {
+                worker.client.set_account(account);
+            }
+            worker.client.set_default_signer(&key);
+            worker.signer = Some(key);
+        }))
+        .map_err(|_| closed_error())?;
+
+        Ok(public_key.as_hex())
+    }
+
+    // Signs uploads with the given key; returns its public key.
+    #[napi]
+    pub fn set_signer_key(&self, private_key_hex: String) -> Result<String> {
+        let key = PrivateKey::load_from_hex(&private_key_hex)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        self.set_key(key)
+    }
+
+    // Signs uploads with a key from the keystore; returns its public key.
+    #[napi]
+    pub fn use_keystore_key(&self, keystore: &Keystore, name: String, passphrase: String) -> Result<String> {
+        let key = keystore.keystore.load(&name, &passphrase)
+            .map_err(keystore_error)?;
+
+        self.set_key(key)
+    }
+
+    // Balances can exceed what a JS number holds, so they are returned as strings.
+    #[napi]
+    pub async fn get_account_balance(&self) -> Result<String> {
+        let receiver = self.run(|worker| {
+            worker.client.get_account_balance()
+                .map(|balance| balance.to_string())
+        })?;
+
+        receiver.await.map_err(|_| closed_error())?.map_err(client_error)
+    }
+
+    #[napi(ts_return_type = "Promise<Array<{ id: string, state: string, mode: string, last_updated?: string, name?: string }>>")]
+    pub async fn get_account_files(&self) -> Result<serde_json::Value> {
+        let receiver = self.run(|worker| {
+            let files = worker.client.get_account_files()?;
+            serde_json::to_value(files)
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))
+        })?;
+
+        receiver.await.map_err(|_| closed_error())?.map_err(client_error)
+    }
+
+    #[napi(ts_return_type = "Promise<Array<{ file_id: string, filename: string, size: number, chunk_size: number, created_at: number }>>")]
+    pub async fn list_pending_uploads(&self) -> Result<serde_json::Value> {
+        let receiver = self.run(|worker| {
+            let uploads = worker.client.list_pending_uploads()?;
+            serde_json::to_value(uploads)
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))
+        })?;
+
+        receiver.await.map_err(|_| closed_error())?.map_err(client_error)
+    }
+
+    // Prepares, sends and waits for the file at path, reading it in chunks rather than all at
+    // once. Progress is reported for each phase as transactions are processed.
+    #[napi(ts_args_type = "path: string, options?: UploadOptions, onProgress?: (progress: UploadProgress) => void")]
+    pub async fn upload_file(&self, path: String, options: Option<UploadOptions>, on_progress: Option<ProgressCallback>) -> Result<UploadResult> {
+        let options = options.unwrap_or_default();
+
+        let receiver = self.run(move |worker| {
+            let signer = worker.signer.as_ref().ok_or_else(|| {
+                TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some("No signer set".to_string()))
+            })?;
+
+            let mut upload = worker.client.upload_file(&PathBuf::from(path))?;
+            upload.set_signer(signer);
+            if let Some(chunk_size) = options.chunk_size {
+                upload.set_chunk_size(chunk_size as usize);
+            }
+            if let Some(filename) = options.filename.as_deref() {
+                upload.set_filename(filename);
+            }
+            upload.set_deduplicate_chunks(options.deduplicate_chunks.unwrap_or(false));
+            upload.set_indexed_chunks(options.indexed_chunks.unwrap_or(false));
+            if let Some(on_progress) = on_progress {
+                upload.set_prepare_status_callback(progress("prepare", on_progress.clone()));
+                upload.set_send_status_callback(progress("send", on_progress.clone()));
+                upload.set_wait_status_callback(progress("wait", on_progress));
+            }
+
+            let report = upload.upload()?;
+            Ok(UploadResult {
+                uuid: upload.uuid().to_string(),
+                tx_count: report.tx_count() as u32,
+                elapsed_ms: report.elapsed_ms() as i64,
+            })
+        })?;
+
+        receiver.await.map_err(|_| closed_error())?.map_err(client_error)
+    }
+}
+
+// An encrypted directory of private keys, see libtfslite's keystore.
+#[napi]
+pub struct Keystore {
+    keystore: keystore::Keystore,
+}
+
+#[napi]
+impl Keystore {
+    #[napi(constructor)]
+    pub fn new(path: String) -> Result<Self> {
+        let keystore = keystore::Keystore::open(PathBuf::from(path))
+            .map_err(keystore_error)?;
+
+        Ok(Keystore { keystore })
+    }
+
+    #[napi]
+    pub fn list(&self) -> Result<Vec<String>> {
+        self.keystore.list().map_err(keystore_error)
+    }
+
+    // Generates a new key; returns its public key.
+    #[napi]
+    pub fn create(&self, name: String, passphrase: String) -> Result<String> {
+        let key = self.keystore.create(&name, &passphrase)
+            .map_err(keystore_error)?;
+
+        Signer::public_key(&key)
+            .map(|public_key| public_key.as_hex())
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    #[napi]
+    pub fn import(&self, name: String, private_key_hex: String, passphrase: String) -> Result<()> {
+        let key = PrivateKey::load_from_hex(&private_key_hex)
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+
+        self.keystore.import(&name, &key, &passphrase).map_err(keystore_error)
+    }
+
+    // Doesn't need the passphrase.
+    #[napi]
+    pub fn public_key(&self, name: String) -> Result<String> {
+        self.keystore.public_key(&name)
+            .map(|public_key| public_key.as_hex())
+            .map_err(keystore_error)
+    }
+
+    #[napi]
+    pub fn remove(&self, name: String) -> Result<()> {
+        self.keystore.remove(&name).map_err(keystore_error)
+    }
+}
@@ -0,0 +1,173 @@
+// Throughput of the upload pipeline: signing, local store writes and preparing a whole file.
+// Run with `cargo bench --features testing`; prepare needs a 1 GB scratch file, written once to
+// the temp directory.
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+use libtfslite::client::payload::{PayloadBuilder, PayloadOperation};
+use libtfslite::client::transaction::TransactionBuilder;
+use tfslite_sdk::client::TFSLiteClient;
+use tfslite_sdk::state::LocalStateStore;
+use tfslite_sdk::state_redb::RedbLocalStateStore;
+use tfslite_sdk::testing::{fixture_file_id, fixture_transactions, MockGateway, SeededSigner, MOCK_GATEWAY_URL};
+
+const BLOCK_SIZE: usize = 131072;
+const PREPARE_FILE_SIZE: u64 = 1 << 30;
+const STORE_BATCH: u64 = 100;
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+fn bench_signing(c: &mut Criterion) {
+    let signer = SeededSigner::new(1);
+    let mut group = c.benchmark_group("sign");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("file_seal", |b| {
+        b.iter(|| {
+            let payload = PayloadBuilder::new(PayloadOperation::FileSeal)
+                .with_uuid(Uuid::new_v4())
+                .build()
+                .unwrap();
+            TransactionBuilder::new()
+                .with_payload(payload)
+                .build(&signer)
+                .unwrap()
+        })
+    });
+
+    // Includes hashing the block into the payload and the payload into the header.
+    let block = vec![0x5a; BLOCK_SIZE];
+    group.bench_function("file_append_128k", |b| {
+        b.iter(|| {
+            let payload = PayloadBuilder::new(PayloadOperation::FileAppend)
+                .with_uuid(Uuid::new_v4())
+                .with_block(block.clone())
+                .build()
+                .unwrap();
+            TransactionBuilder::new()
+                .with_payload(payload)
+                .build(&signer)
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_redb_store(c: &mut Criterion) {
+    let runtime = runtime();
+    let path = std::env::temp_dir().join("tfslite-bench-store.db");
+    let _ = std::fs::remove_file(&path);
+    let store = runtime.block_on(RedbLocalStateStore::new(&path)).unwrap();
+
+    let file_id = fixture_file_id(0);
+    let transactions = fixture_transactions(&SeededSigner::new(1), file_id, STORE_BATCH - 2).unwrap();
+
+    let mut group = c.benchmark_group("redb_add_tx");
+    group.throughput(Throughput::Elements(STORE_BATCH));
+
+    // Only the adds are timed; the file is flushed between iterations.
+    group.bench_function("single", |b| {
+        b.iter_custom(|iters| {
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..iters {
+                let start = Instant::now();
+                for transaction in transactions.iter() {
+                    runtime.block_on(store.add_tx(&file_id, transaction)).unwrap();
+                }
+                elapsed += start.elapsed();
+                runtime.block_on(store.flush_txs(&file_id)).unwrap();
+            }
+            elapsed
+        })
+    });
+
+    group.bench_function("batched", |b| {
+        b.iter_custom(|iters| {
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..iters {
+                let start = Instant::now();
+                runtime.block_on(store.add_txs(&file_id, &transactions)).unwrap();
+                elapsed += start.elapsed();
+                runtime.block_on(store.flush_txs(&file_id)).unwrap();
+            }
+            elapsed
+        })
+    });
+
+    group.finish();
+    drop(store);
+    let _ = std::fs::remove_file(&path);
+}
+
+// Random rather than constant data, so no blocks are deduplicated.
+fn prepare_file() -> PathBuf {
+    let path = std::env::temp_dir().join("tfslite-bench-prepare.bin");
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() == PREPARE_FILE_SIZE) {
+        return path;
+    }
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+    let mut buffer = vec![0u8; 1 << 20];
+    for _ in 0..(PREPARE_FILE_SIZE / buffer.len() as u64) {
+        rng.fill(&mut buffer[..]);
+        file.write_all(&buffer).unwrap();
+    }
+    file.flush().unwrap();
+
+    path
+}
+
+fn bench_prepare(c: &mut Criterion) {
+    let runtime = runtime();
+    let file = prepare_file();
+    let store_path = std::env::temp_dir().join("tfslite-bench-prepare.db");
+    let _ = std::fs::remove_file(&store_path);
+    let store: Arc<dyn LocalStateStore> = Arc::new(runtime.block_on(RedbLocalStateStore::new(&store_path)).unwrap());
+
+    let mut client = TFSLiteClient::with_store(MOCK_GATEWAY_URL.to_string(), store.clone());
+    client.set_http_client(Arc::new(MockGateway::new()));
+    let signer = SeededSigner::new(1);
+
+    let mut group = c.benchmark_group("prepare");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(120));
+    group.throughput(Throughput::Bytes(PREPARE_FILE_SIZE));
+
+    group.bench_function("1gb", |b| {
+        b.iter_custom(|iters| {
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..iters {
+                let mut upload = runtime.block_on(client.upload_file(&file)).unwrap();
+                upload.set_signer(&signer);
+                upload.set_chunk_size(BLOCK_SIZE);
+
+                let start = Instant::now();
+                runtime.block_on(upload.prepare_transactions()).unwrap();
+                elapsed += start.elapsed();
+                runtime.block_on(store.flush_txs(&upload.uuid())).unwrap();
+            }
+            elapsed
+        })
+    });
+
+    group.finish();
+    drop(client);
+    drop(store);
+    let _ = std::fs::remove_file(&store_path);
+}
+
+criterion_group!(benches, bench_signing, bench_redb_store, bench_prepare);
+criterion_main!(benches);
@@ -0,0 +1,348 @@
+// Mounts an account's sealed files as a read-only filesystem, so archives can be browsed with
+// normal tools. File names containing '/' are laid out as directories. Reads are served a chunk
+// at a time from the gateway's file data, with recently read chunks kept in an LRU cache. The file
+// list is read once at mount.
+//
+// tfs-fuse --url <gateway> --account <public key or alias> --mount <dir> [--cache-mb <size>]
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::num::NonZeroUsize;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use fuser::{FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, ReplyOpen, Request, FUSE_ROOT_ID};
+use lru::LruCache;
+use uuid::Uuid;
+use libtfslite::types::{FileRecord, FileState};
+use tfslite_sdk::blocking::TFSLiteClient;
+use tfslite_sdk::client::TFSLiteClientError;
+
+const CHUNK_SIZE: u64 = 1 << 20;
+const DEFAULT_CACHE_MB: u64 = 256;
+// Nothing changes under the mount, so the kernel may keep attributes as long as it likes.
+const TTL: Duration = Duration::from_secs(3600);
+
+struct Config {
+    url: String,
+    account: String,
+    mount: PathBuf,
+    cache_mb: u64,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: tfs-fuse --url <gateway> --account <public key or alias> --mount <dir> [--cache-mb <size>]");
+    std::process::exit(2);
+}
+
+fn parse_args() -> Config {
+    let mut url: Option<String> = None;
+    let mut account: Option<String> = None;
+    let mut mount: Option<PathBuf> = None;
+    let mut cache_mb = DEFAULT_CACHE_MB;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--url" => url = Some(value),
+            "--account" => account = Some(value),
+            "--mount" => mount = Some(PathBuf::from(value)),
+            "--cache-mb" => cache_mb = value.parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+
+    match (url, account, mount) {
+        (Some(url), Some(account), Some(mount)) => Config { url, account, mount, cache_mb },
+        _ => usage(),
+    }
+}
+
+enum Node {
+    Dir {
+        parent: u64,
+        children: BTreeMap<String, u64>,
+    },
+    File {
+        parent: u64,
+        file: FileRecord,
+    },
+}
+
+impl Node {
+    fn parent(&self) -> u64 {
+        match self {
+            Node::Dir { parent, .. } | Node::File { parent, .. } => *parent,
+        }
+    }
+}
+
+fn timestamp(seconds: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64)
+}
+
+struct TfsFilesystem {
+    client: TFSLiteClient,
+    nodes: HashMap<u64, Node>,
+    cache: LruCache<(Uuid, u64), Arc<Vec<u8>>>,
+    uid: u32,
+    gid: u32,
+    mounted_at: SystemTime,
+}
+
+impl TfsFilesystem {
+    fn new(client: TFSLiteClient, files: Vec<FileRecord>, cache_chunks: NonZeroUsize, uid: u32, gid: u32) -> Self {
+        let mut fs = TfsFilesystem {
+            client,
+            nodes: HashMap::new(),
+            cache: LruCache::new(cache_chunks),
+            uid,
+            gid,
+            mounted_at: SystemTime::now(),
+        };
+        fs.nodes.insert(FUSE_ROOT_ID, Node::Dir { parent: FUSE_ROOT_ID, children: BTreeMap::new() });
+
+        // Older files of the same name are shown with their uuid appended, so nothing is hidden.
+        let mut files = files;
+        files.sort_by_key(|file| std::cmp::Reverse(file.timestamp_seal));
+        for file in files {
+            fs.add_file(file);
+        }
+
+        fs
+    }
+
+    fn add_file(&mut self, file: FileRecord) {
+        let name = match file.file_name.trim_matches('/') {
+            "" => file.file_id.to_string(),
+            name => name.to_string(),
+        };
+        let mut parts: Vec<&str> = name.split('/').filter(|part| !part.is_empty() && *part != "." && *part != "..").collect();
+        let leaf = parts.pop().map(str::to_string).unwrap_or_else(|| file.file_id.to_string());
+
+        let mut dir = FUSE_ROOT_ID;
+        for part in parts {
+            dir = match self.child(dir, part) {
+                Some(ino) if matches!(self.nodes.get(&ino), Some(Node::Dir { .. })) => ino,
+                _ => self.insert(dir, part.to_string(), Node::Dir { parent: dir, children: BTreeMap::new() }),
+            };
+        }
+
+        let leaf = match self.child(dir, &leaf) {
+            Some(_) => format!("{}~{}", leaf, file.file_id),
+            None => leaf,
+        };
+        self.insert(dir, leaf, Node::File { parent: dir, file });
+    }
+
+    fn child(&self, parent: u64, name: &str) -> Option<u64> {
+        match self.nodes.get(&parent) {
+            Some(Node::Dir { children, .. }) => children.get(name).copied(),
+            _ => None,
+        }
+    }
+
+    // Inode numbers are handed out in order, so a name clashing with an existing entry replaces
+    // it only in the directory listing and the old inode stays readable.
+    fn insert(&mut self, parent: u64, name: String, node: Node) -> u64 {
+        let ino = FUSE_ROOT_ID + self.nodes.len() as u64;
+        self.nodes.insert(ino, node);
+        if let Some(Node::Dir { children, .. }) = self.nodes.get_mut(&parent) {
+            children.insert(name, ino);
+        }
+
+        ino
+    }
+
+    fn attr(&self, ino: u64, node: &Node) -> FileAttr {
+        let (kind, perm, size, mtime, crtime) = match node {
+            Node::Dir { .. } => (FileType::Directory, 0o555, 0, self.mounted_at, self.mounted_at),
+            Node::File { file, .. } => (
+                FileType::RegularFile,
+                0o444,
+                file.size,
+                timestamp(file.timestamp_seal.max(file.timestamp_append)),
+                timestamp(file.timestamp_create),
+            ),
+        };
+
+        FileAttr {
+            ino,
+            size,
+            blocks: size.div_ceil(512),
+            atime: mtime,
+            mtime,
+            ctime: mtime,
+            crtime,
+            kind,
+            perm,
+            nlink: if kind == FileType::Directory { 2 } else { 1 },
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: CHUNK_SIZE as u32,
+            flags: 0,
+        }
+    }
+
+    // The gateway returns everything past an offset, which may run to several chunks; each is
+    // cached so sequential reads don't ask for the same data again.
+    fn chunk(&mut self, file: &FileRecord, index: u64) -> Result<Arc<Vec<u8>>, TFSLiteClientError> {
+        if let Some(chunk) = self.cache.get(&(file.file_id, index)) {
+            return Ok(chunk.clone());
+        }
+
+        let start = index * CHUNK_SIZE;
+        let end = (start + CHUNK_SIZE).min(file.size);
+        let mut reader = self.client.open_log_reader(file.file_id, start);
+        let mut data: Vec<u8> = Vec::new();
+        while start + (data.len() as u64) < end {
+            let read = self.client.block_on(reader.read())?;
+            if read.is_empty() {
+                break;
+            }
+            data.extend_from_slice(&read);
+        }
+        data.truncate((file.size - start) as usize);
+
+        let mut chunks = data.chunks(CHUNK_SIZE as usize).map(|chunk| Arc::new(chunk.to_vec()));
+        let first = chunks.next().unwrap_or_default();
+        for (offset, chunk) in chunks.enumerate() {
+            self.cache.put((file.file_id, index + 1 + offset as u64), chunk);
+        }
+        self.cache.put((file.file_id, index), first.clone());
+
+        Ok(first)
+    }
+
+    fn read_range(&mut self, file: &FileRecord, offset: u64, size: u64) -> Result<Vec<u8>, TFSLiteClientError> {
+        let end = (offset + size).min(file.size);
+        let mut data: Vec<u8> = Vec::with_capacity(end.saturating_sub(offset) as usize);
+
+        let mut position = offset;
+        while position < end {
+            let index = position / CHUNK_SIZE;
+            let chunk = self.chunk(file, index)?;
+            let from = (position - index * CHUNK_SIZE) as usize;
+            let to = ((end - index * CHUNK_SIZE) as usize).min(chunk.len());
+            if from >= to {
+                break;
+            }
+            data.extend_from_slice(&chunk[from..to]);
+            position += (to - from) as u64;
+        }
+
+        Ok(data)
+    }
+}
+
+impl Filesystem for TfsFilesystem {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let ino = name.to_str().and_then(|name| self.child(parent, name));
+        match ino.and_then(|ino| self.nodes.get(&ino).map(|node| self.attr(ino, node))) {
+            Some(attr) => reply.entry(&TTL, &attr, 0),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyAttr) {
+        match self.nodes.get(&ino) {
+            Some(node) => reply.attr(&TTL, &self.attr(ino, node)),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        match self.nodes.get(&ino) {
+            Some(Node::File { .. }) if flags & libc::O_ACCMODE != libc::O_RDONLY => reply.error(libc::EROFS),
+            Some(Node::File { .. }) => reply.opened(0, 0),
+            Some(Node::Dir { .. }) => reply.error(libc::EISDIR),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn read(&mut self, _req: &Request<'_>, ino: u64, _fh: u64, offset: i64, size: u32, _flags: i32, _lock_owner: Option<u64>, reply: ReplyData) {
+        let file = match self.nodes.get(&ino) {
+            Some(Node::File { file, .. }) => file.clone(),
+            Some(Node::Dir { .. }) => return reply.error(libc::EISDIR),
+            None => return reply.error(libc::ENOENT),
+        };
+
+        match self.read_range(&file, offset.max(0) as u64, size as u64) {
+            Ok(data) => reply.data(&data),
+            Err(err) => {
+                eprintln!("Reading {} failed: {}", file.file_id, err);
+                reply.error(libc::EIO);
+            },
+        }
+    }
+
+    fn readdir(&mut self, _req: &Request<'_>, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
+        let (parent, children) = match self.nodes.get(&ino) {
+            Some(Node::Dir { parent, children }) => (*parent, children),
+            Some(Node::File { .. }) => return reply.error(libc::ENOTDIR),
+            None => return reply.error(libc::ENOENT),
+        };
+
+        let mut entries: Vec<(u64, FileType, &str)> = vec![(ino, FileType::Directory, "."), (parent, FileType::Directory, "..")];
+        for (name, child) in children.iter() {
+            let kind = match self.nodes.get(child) {
+                Some(Node::Dir { .. }) => FileType::Directory,
+                _ => FileType::RegularFile,
+            };
+            entries.push((*child, kind, name));
+        }
+
+        for (index, (child, kind, name)) in entries.into_iter().enumerate().skip(offset.max(0) as usize) {
+            if reply.add(child, (index + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+fn main() {
+    let config = parse_args();
+
+    let mut client = TFSLiteClient::new(config.url.clone())
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to start client: {}", err);
+            std::process::exit(1);
+        });
+    let account = client.block_on(client.resolve_account(&config.account))
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to resolve account {}: {}", config.account, err);
+            std::process::exit(1);
+        });
+    client.set_account(account);
+
+    // Open files may still be appended to, so only sealed ones are mounted.
+    let files: Vec<FileRecord> = client.block_on(client.get_account_files_by_tags(&[]))
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to list files: {}", err);
+            std::process::exit(1);
+        })
+        .into_iter()
+        .filter(|file| file.state == FileState::Sealed)
+        .collect();
+
+    // Files are shown as owned by whoever owns the mount point.
+    let metadata = std::fs::metadata(&config.mount)
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to read mount point {}: {}", config.mount.display(), err);
+            std::process::exit(1);
+        });
+
+    let cache_chunks = NonZeroUsize::new(((config.cache_mb << 20) / CHUNK_SIZE) as usize).unwrap_or(NonZeroUsize::MIN);
+    eprintln!("Mounting {} sealed files at {}", files.len(), config.mount.display());
+    let fs = TfsFilesystem::new(client, files, cache_chunks, metadata.uid(), metadata.gid());
+
+    let options = [MountOption::RO, MountOption::FSName("tfs".to_string()), MountOption::DefaultPermissions];
+    if let Err(err) = fuser::mount2(fs, &config.mount, &options) {
+        eprintln!("Unable to mount {}: {}", config.mount.display(), err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,283 @@
+// Long-running uploader: watches a spool directory, uploads each file that lands in it and moves
+// it to <spool>/.done once sealed. The queue lives in the SDK's local state store, so uploads
+// interrupted by a restart are resumed. Status is served as JSON over a Unix socket, and over a
+// local HTTP port when one is given.
+//
+// tfslite-daemon --url <gateway> --key <key file> --spool <dir> [--socket <path>] [--http-port <port>] [--interval <secs>]
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, UnixListener};
+use tokio::task::LocalSet;
+use libtfslite::client::keys::{PrivateKey, Signer};
+use tfslite_sdk::client::{FileUpload, TFSLiteClient, TFSLiteClientError};
+
+const DONE_DIR: &str = ".done";
+const DEFAULT_SOCKET: &str = "/tmp/tfslite-daemon.sock";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+
+struct Config {
+    url: String,
+    key: PathBuf,
+    spool: PathBuf,
+    socket: PathBuf,
+    http_port: Option<u16>,
+    interval: Duration,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: tfslite-daemon --url <gateway> --key <key file> --spool <dir> [--socket <path>] [--http-port <port>] [--interval <secs>]");
+    std::process::exit(2);
+}
+
+fn parse_args() -> Config {
+    let mut url: Option<String> = None;
+    let mut key: Option<PathBuf> = None;
+    let mut spool: Option<PathBuf> = None;
+    let mut socket = PathBuf::from(DEFAULT_SOCKET);
+    let mut http_port: Option<u16> = None;
+    let mut interval = DEFAULT_INTERVAL;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--url" => url = Some(value),
+            "--key" => key = Some(PathBuf::from(value)),
+            "--spool" => spool = Some(PathBuf::from(value)),
+            "--socket" => socket = PathBuf::from(value),
+            "--http-port" => http_port = Some(value.parse().unwrap_or_else(|_| usage())),
+            "--interval" => interval = Duration::from_secs(value.parse().unwrap_or_else(|_| usage())),
+            _ => usage(),
+        }
+    }
+
+    match (url, key, spool) {
+        (Some(url), Some(key), Some(spool)) => Config { url, key, spool, socket, http_port, interval },
+        _ => usage(),
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "lowercase")]
+enum UploadState {
+    Queued,
+    Uploading,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Clone)]
+struct UploadStatus {
+    file_id: Option<String>,
+    state: UploadState,
+    error: Option<String>,
+}
+
+// Keyed by spool filename, shared between the upload loop and the status listeners.
+type Status = Arc<Mutex<BTreeMap<String, UploadStatus>>>;
+
+fn set_status(status: &Status, filename: &str, file_id: Option<String>, state: UploadState, error: Option<String>) {
+    status.lock().unwrap().insert(filename.to_string(), UploadStatus { file_id, state, error });
+}
+
+fn status_json(status: &Status) -> Vec<u8> {
+    serde_json::to_vec(&*status.lock().unwrap()).unwrap_or_default()
+}
+
+async fn serve_socket(listener: UnixListener, status: Status) {
+    loop {
+        if let Ok((mut stream, _)) = listener.accept().await {
+            let _ = stream.write_all(&status_json(&status)).await;
+            let _ = stream.shutdown().await;
+        }
+    }
+}
+
+// Answers every request with the status, whatever its path.
+async fn serve_http(listener: TcpListener, status: Status) {
+    loop {
+        if let Ok((mut stream, _)) = listener.accept().await {
+            let body = status_json(&status);
+            let header = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+            let _ = stream.write_all(header.as_bytes()).await;
+            let _ = stream.write_all(&body).await;
+            let _ = stream.shutdown().await;
+        }
+    }
+}
+
+// Files still being written are skipped until they have been left alone for an interval.
+fn spooled_files(spool: &Path, settle: Duration) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(spool) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("Unable to read spool {}: {}", spool.display(), err);
+            return Vec::new();
+        },
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter(|entry| {
+            entry.metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())
+                .and_then(|metadata| metadata.modified().ok())
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age >= settle)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+
+    files
+}
+
+async fn finish_upload(upload: &mut FileUpload) -> Result<(), TFSLiteClientError> {
+    upload.send_transactions().await?;
+    upload.wait_transactions().await?;
+    Ok(())
+}
+
+fn mark_done(spool: &Path, filename: &str) {
+    let done = spool.join(DONE_DIR);
+    let result = std::fs::create_dir_all(&done)
+        .and_then(|_| std::fs::rename(spool.join(filename), done.join(filename)));
+    if let Err(err) = result {
+        eprintln!("Unable to move {} out of the spool: {}", filename, err);
+    }
+}
+
+// Uploads interrupted after being fully prepared are sent again; partially prepared ones were
+// never sent, so they are dropped and their file is uploaded afresh by the next scan.
+async fn resume_pending(client: &TFSLiteClient, key: &PrivateKey, config: &Config, status: &Status, failed: &mut HashSet<String>) {
+    let pending = match client.list_pending_uploads().await {
+        Ok(pending) => pending,
+        Err(err) => {
+            eprintln!("Unable to list pending uploads: {}", err);
+            return;
+        },
+    };
+
+    for info in pending {
+        let filename = info.get_filename();
+        let file_id = info.get_file_id().to_string();
+
+        let result = async {
+            let mut upload = client.resume_upload(info.get_file_id()).await?;
+            upload.set_signer(key);
+            if !upload.is_prepared().await? {
+                upload.discard().await?;
+                return Ok(false);
+            }
+            set_status(status, &filename, Some(file_id.clone()), UploadState::Uploading, None);
+            finish_upload(&mut upload).await?;
+            Ok::<bool, TFSLiteClientError>(true)
+        }.await;
+
+        match result {
+            Ok(true) => {
+                mark_done(&config.spool, &filename);
+                set_status(status, &filename, Some(file_id), UploadState::Done, None);
+            },
+            Ok(false) => {},
+            Err(err) => {
+                eprintln!("Resuming {} failed: {}", filename, err);
+                failed.insert(filename.clone());
+                set_status(status, &filename, Some(file_id), UploadState::Failed, Some(err.to_string()));
+            },
+        }
+    }
+}
+
+async fn upload_file(client: &TFSLiteClient, key: &PrivateKey, path: &Path, filename: &str, status: &Status) -> Result<(), TFSLiteClientError> {
+    let mut upload = client.upload_file(path).await?;
+    upload.set_signer(key);
+    upload.set_filename(filename);
+
+    upload.prepare_transactions().await?;
+    set_status(status, filename, Some(upload.uuid().to_string()), UploadState::Uploading, None);
+    finish_upload(&mut upload).await
+}
+
+async fn run(config: Config, key: PrivateKey, status: Status) {
+    let mut client = TFSLiteClient::new(config.url.clone()).await;
+    client.set_account(Signer::public_key(&key).expect("Unable to derive public key"));
+
+    // Files that failed stay in the spool but are not retried until the daemon restarts.
+    let mut failed: HashSet<String> = HashSet::new();
+    resume_pending(&client, &key, &config, &status, &mut failed).await;
+
+    loop {
+        let files = spooled_files(&config.spool, config.interval);
+        for path in files.iter() {
+            let filename = path.file_name().unwrap().to_string_lossy().to_string();
+            if !failed.contains(&filename) {
+                set_status(&status, &filename, None, UploadState::Queued, None);
+            }
+        }
+
+        for path in files {
+            let filename = path.file_name().unwrap().to_string_lossy().to_string();
+            if failed.contains(&filename) {
+                continue;
+            }
+
+            match upload_file(&client, &key, &path, &filename, &status).await {
+                Ok(()) => {
+                    mark_done(&config.spool, &filename);
+                    let file_id = status.lock().unwrap().get(&filename).and_then(|entry| entry.file_id.clone());
+                    set_status(&status, &filename, file_id, UploadState::Done, None);
+                },
+                Err(err) => {
+                    eprintln!("Uploading {} failed: {}", filename, err);
+                    failed.insert(filename.clone());
+                    set_status(&status, &filename, None, UploadState::Failed, Some(err.to_string()));
+                },
+            }
+        }
+
+        tokio::time::sleep(config.interval).await;
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let config = parse_args();
+    let key = PrivateKey::load_from_file(config.key.clone())
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to load key {}: {}", config.key.display(), err);
+            std::process::exit(1);
+        });
+
+    let status: Status = Arc::new(Mutex::new(BTreeMap::new()));
+
+    let _ = std::fs::remove_file(&config.socket);
+    let socket = UnixListener::bind(&config.socket)
+        .unwrap_or_else(|err| {
+            eprintln!("Unable to bind {}: {}", config.socket.display(), err);
+            std::process::exit(1);
+        });
+    let http = match config.http_port {
+        Some(port) => Some(TcpListener::bind(("127.0.0.1", port)).await
+            .unwrap_or_else(|err| {
+                eprintln!("Unable to bind port {}: {}", port, err);
+                std::process::exit(1);
+            })),
+        None => None,
+    };
+
+    // FileUpload is not Send, so everything runs on this thread.
+    let local = LocalSet::new();
+    local.spawn_local(serve_socket(socket, status.clone()));
+    if let Some(http) = http {
+        local.spawn_local(serve_http(http, status.clone()));
+    }
+    local.run_until(run(config, key, status)).await;
+}
@@ -0,0 +1,191 @@
+// Blocking versions of the client and uploads, for tools that don't run an async runtime. Each
+// client owns a single threaded tokio runtime and blocks on it; the synchronous setters of the
+// async types are reached through Deref.
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+use libtfslite::client::keys::PublicKey;
+use crate::client::{self, TFSLiteClientError, TFSLiteClientErrorType};
+use crate::state::{LocalStateStore, LocalStateStoreStats};
+use crate::types::{AccountBalance, BuildInfo, CommitReport, FileList, PendingUploadList};
+
+fn new_runtime() -> Result<Arc<Runtime>, TFSLiteClientError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::InvalidConfig, Some(format!("Unable to start runtime: {}", err))))?;
+
+    Ok(Arc::new(runtime))
+}
+
+pub struct TFSLiteClient {
+    runtime: Arc<Runtime>,
+    client: client::TFSLiteClient,
+}
+
+impl TFSLiteClient {
+    pub fn new(url: String) -> Result<TFSLiteClient, TFSLiteClientError> {
+        let runtime = new_runtime()?;
+        let client = runtime.block_on(client::TFSLiteClient::new(url));
+
+        Ok(TFSLiteClient { runtime, client })
+    }
+
+    // Keeps state in a redb database at path rather than the default location.
+    pub fn with_store_path(url: String, path: &Path) -> Result<TFSLiteClient, TFSLiteClientError> {
+        let runtime = new_runtime()?;
+        let store = runtime.block_on(client::TFSLiteClient::init_state_store_at(path))?;
+
+        Ok(TFSLiteClient {
+            runtime,
+            client: client::TFSLiteClient::with_store(url, store),
+        })
+    }
+
+    pub fn with_store(url: String, store: Arc<dyn LocalStateStore>) -> Result<TFSLiteClient, TFSLiteClientError> {
+        Ok(TFSLiteClient {
+            runtime: new_runtime()?,
+            client: client::TFSLiteClient::with_store(url, store),
+        })
+    }
+
+    // Runs any other async client call to completion, e.g.
+    // `client.block_on(client.get_transaction_receipt(&tx_id))`.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn into_inner(self) -> client::TFSLiteClient {
+        self.client
+    }
+
+    pub fn get_build_info(&self) -> Result<BuildInfo, TFSLiteClientError> {
+        self.runtime.block_on(self.client.get_build_info())
+    }
+
+    pub fn get_batcher_public_key(&self) -> Result<PublicKey, TFSLiteClientError> {
+        self.runtime.block_on(self.client.get_batcher_public_key())
+    }
+
+    pub fn get_account_balance(&self) -> Result<AccountBalance, TFSLiteClientError> {
+        self.runtime.block_on(self.client.get_account_balance())
+    }
+
+    pub fn get_account_files(&self) -> Result<FileList, TFSLiteClientError> {
+        self.runtime.block_on(self.client.get_account_files())
+    }
+
+    pub fn upload_file(&self, file: &Path) -> Result<FileUpload, TFSLiteClientError> {
+        let upload = self.runtime.block_on(self.client.upload_file(file))?;
+
+        Ok(FileUpload { runtime: self.runtime.clone(), upload })
+    }
+
+    pub fn resume_upload(&self, uuid: Uuid) -> Result<FileUpload, TFSLiteClientError> {
+        let upload = self.runtime.block_on(self.client.resume_upload(uuid))?;
+
+        Ok(FileUpload { runtime: self.runtime.clone(), upload })
+    }
+
+    pub fn list_pending_uploads(&self) -> Result<PendingUploadList, TFSLiteClientError> {
+        self.runtime.block_on(self.client.list_pending_uploads())
+    }
+
+    pub fn collect_garbage(&self, older_than: Duration) -> Result<u64, TFSLiteClientError> {
+        self.runtime.block_on(self.client.collect_garbage(older_than))
+    }
+
+    pub fn get_store_stats(&self) -> Result<LocalStateStoreStats, TFSLiteClientError> {
+        self.runtime.block_on(self.client.get_store_stats())
+    }
+}
+
+impl Deref for TFSLiteClient {
+    type Target = client::TFSLiteClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl DerefMut for TFSLiteClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.client
+    }
+}
+
+// Shares its client's runtime, so it can outlive the client.
+pub struct FileUpload {
+    runtime: Arc<Runtime>,
+    upload: client::FileUpload,
+}
+
+impl FileUpload {
+    pub fn into_inner(self) -> client::FileUpload {
+        self.upload
+    }
+
+    pub fn prepare_transactions(&mut self) -> Result<(), TFSLiteClientError> {
+        self.runtime.block_on(self.upload.prepare_transactions())
+    }
+
+    pub fn send_transactions(&mut self) -> Result<(), TFSLiteClientError> {
+        self.runtime.block_on(self.upload.send_transactions())
+    }
+
+    pub fn wait_transactions(&mut self) -> Result<CommitReport, TFSLiteClientError> {
+        self.runtime.block_on(self.upload.wait_transactions())
+    }
+
+    // Prepares, sends and waits in one go.
+    pub fn upload(&mut self) -> Result<CommitReport, TFSLiteClientError> {
+        self.prepare_transactions()?;
+        self.send_transactions()?;
+        self.wait_transactions()
+    }
+
+    pub fn is_prepared(&self) -> Result<bool, TFSLiteClientError> {
+        self.runtime.block_on(self.upload.is_prepared())
+    }
+
+    pub fn base_file(&mut self, uuid: Uuid) -> Result<(), TFSLiteClientError> {
+        self.runtime.block_on(self.upload.base_file(uuid))
+    }
+
+    pub fn discard(&mut self) -> Result<(), TFSLiteClientError> {
+        self.runtime.block_on(self.upload.discard())
+    }
+
+    pub fn release_lease(&mut self) -> Result<(), TFSLiteClientError> {
+        self.runtime.block_on(self.upload.release_lease())
+    }
+}
+
+impl Deref for FileUpload {
+    type Target = client::FileUpload;
+
+    fn deref(&self) -> &Self::Target {
+        &self.upload
+    }
+}
+
+impl DerefMut for FileUpload {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.upload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::TFSLiteClientError;
+    use crate::tests::test_blocking_client_common;
+
+    #[test]
+    fn test_blocking_client() -> Result<(), TFSLiteClientError> {
+        test_blocking_client_common()
+    }
+}
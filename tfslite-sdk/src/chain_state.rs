@@ -0,0 +1,323 @@
+use std::sync::Arc;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+use sha2::{Digest, Sha224};
+use libtfslite::addressing::{alias_address, block_address_prefix, clock_address, directory_address, file_address, parse_address, StateAddress};
+use libtfslite::client::keys::PublicKey;
+use libtfslite::common::get_tfslite_prefix;
+use libtfslite::types::{AccountRecord, AliasRecord, DirectoryEntry, FileRecord, FileState, Permission};
+use crate::client::{TFSLiteClientError, TFSLiteClientErrorType};
+use crate::dialect::{DefaultGatewayDialect, GatewayDialect, GatewayEndpoint};
+use crate::http::{default_http_client, HttpClient, HttpRequest, STATUS_NOT_FOUND};
+use crate::proof::{verify_merkle_proof, FileCommitment};
+use crate::transport::{check_response, decode_response, http_error};
+use crate::debug::debug_println;
+
+#[derive(Deserialize)]
+struct StateEntryResponse {
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct StateListEntry {
+    address: String,
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct StatePaging {
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StateListResponse {
+    data: Vec<StateListEntry>,
+    paging: Option<StatePaging>,
+}
+
+// Nodes run from the state root down to the leaf holding the entry.
+#[derive(Deserialize)]
+struct StateProofResponse {
+    head: String,
+    state_root: String,
+    nodes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct BlockHeader {
+    state_root_hash: String,
+}
+
+#[derive(Deserialize)]
+struct Block {
+    header: BlockHeader,
+}
+
+#[derive(Deserialize)]
+struct BlockResponse {
+    data: Block,
+}
+
+fn verification_error<E: std::fmt::Display>(err: E) -> TFSLiteClientError {
+    TFSLiteClientError::new(TFSLiteClientErrorType::VerificationError, Some(format!("{}", err)))
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, TFSLiteClientError> {
+    STANDARD.decode(data)
+        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))
+}
+
+fn decode_cbor<T: DeserializeOwned>(data: &[u8]) -> Result<T, TFSLiteClientError> {
+    ciborium::de::from_reader(data)
+        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))
+}
+
+// A block of a file as found in state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockDigest {
+    pub number: u64,
+    pub size: u64,
+    pub sha224: Vec<u8>,
+}
+
+// Reads and decodes raw tfslite state rather than the gateway's JSON views. The default dialect
+// uses the Sawtooth REST API state layout, so this works against either the gateway or a
+// Sawtooth REST API directly.
+pub struct ChainStateReader {
+    url: String,
+    http: Arc<dyn HttpClient>,
+    dialect: Arc<dyn GatewayDialect>,
+}
+
+impl ChainStateReader {
+    pub fn new(url: String) -> Self {
+        ChainStateReader {
+            url,
+            http: default_http_client(),
+            dialect: Arc::new(DefaultGatewayDialect),
+        }
+    }
+
+    pub fn with_dialect(mut self, dialect: Arc<dyn GatewayDialect>) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    async fn fetch_json<T: DeserializeOwned>(&self, url: String, endpoint: &GatewayEndpoint) -> Result<Option<T>, TFSLiteClientError> {
+        debug_println!("GET {}", crate::debug::redact("url", &url));
+
+        let response = self.http.send(HttpRequest::get(url))
+            .await
+            .map_err(http_error)?;
+
+        if response.status == STATUS_NOT_FOUND {
+            return Ok(None);
+        }
+
+        let body = check_response(response)?
+            .json::<serde_json::Value>()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+        let body = self.dialect.parse_response(endpoint, body)
+            .map_err(|msg| TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(msg)))?;
+
+        decode_response(endpoint, body)
+            .map(Some)
+    }
+
+    pub async fn get_entry(&self, address: &str) -> Result<Option<Vec<u8>>, TFSLiteClientError> {
+        let endpoint = GatewayEndpoint::State(address.to_string());
+        let response: Option<StateEntryResponse> = self.fetch_json(self.dialect.url(&self.url, &endpoint), &endpoint)
+            .await?;
+
+        response
+            .map(|response| decode_base64(&response.data))
+            .transpose()
+    }
+
+    pub async fn list_entries(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, TFSLiteClientError> {
+        let endpoint = GatewayEndpoint::StateList(prefix.to_string());
+        let mut url = Some(self.dialect.url(&self.url, &endpoint));
+        let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+
+        while let Some(next_url) = url.take() {
+            let response: Option<StateListResponse> = self.fetch_json(next_url, &endpoint)
+                .await?;
+            let response = match response {
+                Some(response) => response,
+                None => break,
+            };
+
+            for entry in response.data {
+                entries.push((entry.address, decode_base64(&entry.data)?));
+            }
+
+            url = response.paging.and_then(|paging| paging.next);
+        }
+
+        Ok(entries)
+    }
+
+    pub async fn get_file_record(&self, file_id: &Uuid) -> Result<Option<FileRecord>, TFSLiteClientError> {
+        self.get_entry(&file_address(file_id))
+            .await?
+            .map(|data| decode_cbor(&data))
+            .transpose()
+    }
+
+    // Sha224 of each block of the file, by block number, as carried in FILE_APPEND payloads. The
+    // blocks are downloaded to hash them, as no hash list is kept in state.
+    pub async fn get_block_hashes(&self, file_id: &Uuid) -> Result<Vec<(u64, Vec<u8>)>, TFSLiteClientError> {
+        let digests = self.get_block_digests(file_id)
+            .await?;
+
+        Ok(digests.into_iter().map(|digest| (digest.number, digest.sha224)).collect())
+    }
+
+    // Like get_block_hashes, with each block's size so content can be split back into blocks.
+    pub async fn get_block_digests(&self, file_id: &Uuid) -> Result<Vec<BlockDigest>, TFSLiteClientError> {
+        let entries = self.list_entries(&block_address_prefix(file_id))
+            .await?;
+
+        let mut digests: Vec<BlockDigest> = Vec::new();
+        for (address, data) in entries {
+            match parse_address(&address) {
+                Ok(StateAddress::Block(_, number)) => {
+                    digests.push(BlockDigest {
+                        number,
+                        size: data.len() as u64,
+                        sha224: Sha224::digest(&data).to_vec(),
+                    });
+                },
+                Ok(_) => {},
+                Err(err) => {
+                    return Err(TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))));
+                },
+            }
+        }
+        digests.sort_by_key(|digest| digest.number);
+
+        Ok(digests)
+    }
+
+    pub async fn get_account_record(&self, account: &PublicKey) -> Result<Option<AccountRecord>, TFSLiteClientError> {
+        self.get_entry(&account.account_address())
+            .await?
+            .map(|data| decode_cbor(&data))
+            .transpose()
+    }
+
+    pub async fn get_directory(&self, account: &PublicKey) -> Result<Vec<DirectoryEntry>, TFSLiteClientError> {
+        let directory = self.get_entry(&directory_address(account.as_slice()))
+            .await?
+            .map(|data| decode_cbor(&data))
+            .transpose()?;
+
+        Ok(directory.unwrap_or_default())
+    }
+
+    // Latest time, in Unix seconds, recorded by a timestamp authority; retention periods end
+    // against this rather than the local clock. Zero when none has been recorded.
+    pub async fn get_clock(&self) -> Result<i64, TFSLiteClientError> {
+        let clock = self.get_entry(&clock_address())
+            .await?
+            .map(|data| decode_cbor(&data))
+            .transpose()?;
+
+        Ok(clock.unwrap_or(0))
+    }
+
+    // The key an alias is registered to, if any.
+    pub async fn resolve_alias(&self, alias: &str) -> Result<Option<PublicKey>, TFSLiteClientError> {
+        let record: Option<AliasRecord> = self.get_entry(&alias_address(alias))
+            .await?
+            .map(|data| decode_cbor(&data))
+            .transpose()?;
+
+        Ok(record.map(|record| PublicKey::load_from_bytes(&record.public_key)))
+    }
+
+    // Records of every file in the account's directory.
+    pub async fn get_files(&self, account: &PublicKey) -> Result<Vec<FileRecord>, TFSLiteClientError> {
+        let mut files: Vec<FileRecord> = Vec::new();
+        for entry in self.get_directory(account).await? {
+            if let Some(file) = self.get_file_record(&entry.file_id).await? {
+                files.push(file);
+            }
+        }
+
+        Ok(files)
+    }
+
+    // Checks that the file's sealed record is included under the state root of the block the
+    // proof was taken at, using only hashes the caller can recompute.
+    pub async fn verify_file_commitment(&self, file_id: &Uuid) -> Result<FileCommitment, TFSLiteClientError> {
+        let address = file_address(file_id);
+
+        let endpoint = GatewayEndpoint::StateProof(address.clone());
+        let proof: StateProofResponse = self.fetch_json(self.dialect.url(&self.url, &endpoint), &endpoint)
+            .await?
+            .ok_or_else(|| verification_error("No state proof for file"))?;
+
+        let endpoint = GatewayEndpoint::Block(proof.head.clone());
+        let block: BlockResponse = self.fetch_json(self.dialect.url(&self.url, &endpoint), &endpoint)
+            .await?
+            .ok_or_else(|| verification_error("Proof block not found"))?;
+
+        if block.data.header.state_root_hash != proof.state_root {
+            return Err(verification_error("Proof state root does not match block header"));
+        }
+
+        let nodes = proof.nodes
+            .iter()
+            .map(|node| decode_base64(node))
+            .collect::<Result<Vec<Vec<u8>>, TFSLiteClientError>>()?;
+
+        let data = verify_merkle_proof(&proof.state_root, &address, &nodes)
+            .map_err(verification_error)?;
+        let file: FileRecord = decode_cbor(&data)?;
+
+        if file.file_id != *file_id {
+            return Err(verification_error("Proof is for a different file"));
+        }
+        if file.state != FileState::Sealed {
+            return Err(verification_error("File is not sealed"));
+        }
+
+        Ok(FileCommitment {
+            file,
+            block_id: proof.head,
+            state_root: proof.state_root,
+        })
+    }
+
+    // Each permission entry stores the public key it was granted to.
+    pub async fn get_permissions(&self) -> Result<Vec<(Permission, PublicKey)>, TFSLiteClientError> {
+        let prefix = format!("{}04", get_tfslite_prefix());
+        let entries = self.list_entries(&prefix)
+            .await?;
+
+        let mut permissions: Vec<(Permission, PublicKey)> = Vec::new();
+        for (address, data) in entries {
+            match parse_address(&address) {
+                Ok(StateAddress::Permission(permission, _)) => {
+                    permissions.push((permission, PublicKey::load_from_bytes(&data)));
+                },
+                Ok(_) => {},
+                Err(err) => {
+                    return Err(TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))));
+                },
+            }
+        }
+
+        Ok(permissions)
+    }
+}
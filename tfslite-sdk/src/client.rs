@@ -1,27 +1,49 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Duration;
 use async_stream::stream;
+use chrono::{DateTime, Utc};
 use futures::stream::StreamExt;
 use futures_util::pin_mut;
-use reqwest::Response;
+use libtfslite::protos::compat::Message;
 use serde::Deserialize;
 use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha224, Sha512};
 use uuid::Uuid;
 use libtfslite::client::keys::{PublicKey, Signer};
 use libtfslite::client::payload::*;
 use libtfslite::client::transaction::*;
-use libtfslite::types::FileMode;
-use crate::state::{LocalStateStore, TransactionId, TransactionStatus, TransactionSubmitId};
-use crate::types::{BuildInfo, FileList, FileListEntry, FileListResponse, AccountBalance};
+use libtfslite::client::multisig::MultisigProposal;
+use libtfslite::client::batch::{build_batch_list, BatchBuilder, BatchExt};
+use libtfslite::client::upload::{ChunkSource, UploadEffect, UploadInput, UploadStateMachine, UploadTx};
+pub use libtfslite::client::upload::ResubmitPolicy;
+use libtfslite::protos::batch::BatchList;
+use libtfslite::protos::payload::{Payload, Payload_Operation};
+use libtfslite::protos::transaction::Transaction;
+use libtfslite::common::{is_valid_alias, MAX_FILE_BLOCKS};
+use libtfslite::types::{AccountRecord, FileMode, FileRecord, FileRights, FileState, TokenAmount};
+use crate::state::{LocalStateStore, LocalStateStoreError, LocalStateStoreStats, TransactionId, TransactionInfo, TransactionStatus, TransactionSubmitId, TransactionUpdate};
+use crate::types::{BuildInfo, CommitReport, TxCommitReport, UploadPhase, GatewayCapabilities, FileList, FileListEntry, FileListParseError, FileListResponse, AccountBalance, OverallStatus, PendingUploadList, TransactionReceipt, UploadInfo, UploadProgress};
+use crate::manifest::{verify_download, ManifestTransaction, SignedVerificationReport, UploadManifest};
+use crate::request_signing::{RequestSigner, ResponseVerifier};
+use crate::http::{default_http_client, HttpClient, HttpRequest, HttpResponse};
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+use crate::http::{ConnectionOptions, ReqwestHttpClient};
+use crate::transport::{decode_response, http_error, read_response_body, FailoverTransport, GatewayError, GatewayTransport, RetryPolicy, Transport, DEFAULT_STATUS_PAGE_SIZE};
+use crate::gateway_pool::GatewayPool;
+use crate::dialect::{DefaultGatewayDialect, GatewayDialect, GatewayEndpoint};
+use crate::log::{LogReader, LogWriter};
+use crate::chain_state::ChainStateReader;
+use crate::proof::FileCommitment;
+use crate::signing::{build_batch, build_transaction, sign_manifest, AsyncSigner};
+use crate::metrics::{MetricsSink, NoopMetricsSink};
 use crate::debug::debug_println;
 use cfg_if::cfg_if;
 
 cfg_if! {
     if #[cfg(not(target_arch = "wasm32"))] {
-        use std::thread;
         use std::path::{Path, PathBuf};
         use tokio::fs::File;
         use tokio::io::AsyncReadExt;
@@ -31,17 +53,63 @@ cfg_if! {
         use wasm_bindgen::JsValue;
         use wasm_bindgen_futures::js_sys;
         use futures::AsyncReadExt;
-        use crate::signing::JsSigner;
+        use crate::signing::{JsAsyncSigner, JsSigner};
+        use crate::types::{JsFileMode, StatusCallback};
     }
 }
 
-const DEFAULT_CHUNK_SIZE: usize = 131072;
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 131072;
+const DEFAULT_LEASE_TTL: Duration = Duration::from_secs(30);
+const UNCOMMITTED_STATUSES: [TransactionStatus; 5] = [
+    TransactionStatus::Local,
+    TransactionStatus::Queued,
+    TransactionStatus::Pending,
+    TransactionStatus::Unknown,
+    TransactionStatus::InvalidStatus,
+];
+// Room left in a gateway's payload limit for the transaction around a chunk.
+const PAYLOAD_OVERHEAD: usize = 4096;
+
+// The largest chunk that fits under a gateway's payload limit.
+fn max_block_size(max_payload_size: u64) -> usize {
+    (max_payload_size as usize).saturating_sub(PAYLOAD_OVERHEAD)
+}
+// Prepared transactions are written to the store this many at a time.
+const TX_BUFFER_SIZE: usize = 16;
+// A gateway whose queue is full is tried this many times in all, doubling the wait each time.
+const QUEUE_FULL_MAX_ATTEMPTS: u32 = 5;
+const QUEUE_FULL_BACKOFF: Duration = Duration::from_millis(500);
+// Namespace for uuids derived by FileUpload::set_content_uuid(). Changing it changes every derived uuid.
+const CONTENT_UUID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c2a3e_94b7_4d0b_8e55_1a7d3c9b2f40);
+
+fn upload_txs(tx_infos: &[TransactionInfo]) -> Vec<UploadTx> {
+    tx_infos.iter()
+        .map(|tx_info| UploadTx {
+            tx_id: tx_info.tx_id.clone(),
+            submit_id: tx_info.submit_id.clone(),
+            status: tx_info.status.clone().into(),
+        })
+        .collect()
+}
 
 #[derive(Debug)]
 pub enum TFSLiteClientErrorType {
     InvalidAccount,
     TransportError,
     DecodeError,
+    StoreError,
+    SigningError,
+    MissingFile,
+    LeaseHeld,
+    VerificationError,
+    QuotaExceeded,
+    InvalidConfig,
+    FailedToCommit,
+    InvalidState,
+    UntrustedResponse,
+    DeadlineExceeded,
+    // Rejected by the gateway for a reason it named.
+    GatewayError(GatewayError),
 }
 
 #[derive(Debug)]
@@ -58,6 +126,18 @@ impl Display for TFSLiteClientError {
             TFSLiteClientErrorType::InvalidAccount => write!(f, "InvalidAccountError"),
             TFSLiteClientErrorType::TransportError => write!(f, "TransportError: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
             TFSLiteClientErrorType::DecodeError => write!(f, "DecodeError: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::StoreError => write!(f, "StoreError: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::SigningError => write!(f, "SigningError: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::MissingFile => write!(f, "MissingFile: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::LeaseHeld => write!(f, "LeaseHeld: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::VerificationError => write!(f, "VerificationError: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::QuotaExceeded => write!(f, "QuotaExceeded: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::InvalidConfig => write!(f, "InvalidConfig: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::FailedToCommit => write!(f, "FailedToCommit: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::InvalidState => write!(f, "InvalidState: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::UntrustedResponse => write!(f, "UntrustedResponse: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::DeadlineExceeded => write!(f, "DeadlineExceeded: {}", self.error_msg.clone().unwrap_or("<no msg>".to_string())),
+            TFSLiteClientErrorType::GatewayError(ref err) => write!(f, "GatewayError ({}): {}", err, self.error_msg.clone().unwrap_or("<no msg>".to_string())),
         }
     }
 }
@@ -69,6 +149,17 @@ impl TFSLiteClientError {
             error_msg,
         }
     }
+
+    pub fn error_type(&self) -> &TFSLiteClientErrorType {
+        &self.error_type
+    }
+
+    pub fn gateway_error(&self) -> Option<&GatewayError> {
+        match self.error_type {
+            TFSLiteClientErrorType::GatewayError(ref err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -80,131 +171,172 @@ impl From<TFSLiteClientError> for JsValue {
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub struct TFSLiteClient {
-    url: String,
+    gateways: Arc<GatewayPool>,
+    transport: Arc<dyn Transport>,
+    // Set by set_transport, and kept when gateway settings change.
+    custom_transport: Option<Arc<dyn Transport>>,
+    http: Arc<dyn HttpClient>,
+    dialect: Arc<dyn GatewayDialect>,
     account: Option<PublicKey>,
-    store: Arc<Mutex<dyn LocalStateStore>>,
+    store: Arc<dyn LocalStateStore>,
+    metrics: Arc<dyn MetricsSink>,
+    retry_policy: RetryPolicy,
+    capabilities: Option<GatewayCapabilities>,
+    status_page_size: usize,
+    request_compression: bool,
+    // Defaults for uploads created by this client.
+    chunk_size: usize,
+    signer: Option<Box<dyn Signer>>,
+    request_signer: Option<Arc<RequestSigner>>,
+    response_verifier: Option<Arc<ResponseVerifier>>,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 impl TFSLiteClient {
     pub async fn new(url: String) -> TFSLiteClient {
-        TFSLiteClient {
-            url,
-            account: None,
-            store: Self::init_state_store().await
-        }
+        Self::with_store(url, Self::init_state_store().await)
     }
 
     // TODO: Figure out a standard file path for this database.
     #[cfg(not(target_arch = "wasm32"))]
-    async fn init_state_store() -> Arc<Mutex<dyn LocalStateStore>> {
-        use crate::state_redb;
-        Arc::new(Mutex::new(state_redb::RedbLocalStateStore::new("/tmp/redb-client.db").await.unwrap()))
+    async fn init_state_store() -> Arc<dyn LocalStateStore> {
+        Self::init_state_store_at("/tmp/redb-client.db").await.unwrap()
     }
 
+    // OPFS is used where the browser supports it, with IndexedDB as the fallback.
     #[cfg(target_arch = "wasm32")]
-    async fn init_state_store() -> Arc<Mutex<dyn LocalStateStore>> {
+    async fn init_state_store() -> Arc<dyn LocalStateStore> {
+        #[cfg(feature = "debug")]
         console_error_panic_hook::set_once();
 
-        use crate::state_indexeddb;
-        Arc::new(Mutex::new(state_indexeddb::IndexedDBLocalStateStore::new().await.unwrap()))
+        use crate::{state_indexeddb, state_opfs};
+        match state_opfs::OpfsLocalStateStore::new().await {
+            Ok(store) => {
+                if let Err(_err) = Self::adopt_indexeddb_state(&store).await {
+                    debug_println!("Moving IndexedDB state to OPFS failed: {:?}", _err);
+                }
+                Arc::new(store)
+            },
+            Err(_err) => {
+                debug_println!("OPFS unavailable, using IndexedDB: {:?}", _err);
+                Arc::new(state_indexeddb::IndexedDBLocalStateStore::new().await.unwrap())
+            },
+        }
     }
 
-    pub fn set_account(&mut self, account: PublicKey) {
-        self.account = Some(account);
-    }
+    // Moves uploads left pending in IndexedDB, from before OPFS was used, into the given store.
+    // Receipts are not moved; they can be fetched from the gateway again.
+    #[cfg(target_arch = "wasm32")]
+    async fn adopt_indexeddb_state(store: &dyn LocalStateStore) -> Result<(), LocalStateStoreError> {
+        let legacy = crate::state_indexeddb::IndexedDBLocalStateStore::new().await?;
 
-    async fn fetch_url(&self, url: String) -> Result<Response, TFSLiteClientError> {
-        let result = reqwest::get(url)
-            .await
-            .map_err(|err|TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("{}", err))))?;
+        let files = legacy.get_files().await?;
+        if files.is_empty() {
+            return Ok(());
+        }
 
-        Ok(result)
-    }
+        let mut dump: Vec<u8> = Vec::new();
+        legacy.export_store(&mut dump).await?;
+        store.import_store(&mut dump.as_slice()).await?;
 
-    async fn fetch_url_json<T: DeserializeOwned>(&self, url: String) -> Result<T, TFSLiteClientError> {
-        let result = self.fetch_url(url)
-            .await?
-            .json::<T>()
-            .await
-            .map_err(|err|TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        for file_id in files.iter() {
+            legacy.flush_txs(file_id).await?;
+        }
 
-        Ok(result)
+        Ok(())
     }
 
-    async fn fetch_url_object(&self, url: String) -> Result<serde_json::Map<String, serde_json::Value>, TFSLiteClientError> {
-        let result = self.fetch_url_json::<serde_json::Value>(url)
-            .await?
-            .as_object()
-            .unwrap()
-            .clone();
-
-        Ok(result)
+    pub fn set_account(&mut self, account: PublicKey) {
+        self.account = Some(account);
     }
 
-    pub async fn get_build_info(&self) -> Result<BuildInfo, TFSLiteClientError> {
-        let url = format!("{}/build-info", self.url);
+    async fn fetch_url(&self, url: String) -> Result<HttpResponse, TFSLiteClientError> {
+        debug_println!("GET {}", crate::debug::redact("url", &url));
+        let request = match &self.request_signer {
+            Some(request_signer) => request_signer.sign(HttpRequest::get(url))?,
+            None => HttpRequest::get(url),
+        };
 
-        self.fetch_url_json(url).await
+        self.http.send(request)
+            .await
+            .map_err(http_error)
     }
 
-    pub async fn get_batcher_public_key(&self) -> Result<PublicKey, TFSLiteClientError> {
-        let url = format!("{}/batcher-public-key", self.url);
-        let data = self.fetch_url_object(url)
+    // Tries each gateway in turn until one answers.
+    async fn fetch_gateway_url(&self, endpoint: &GatewayEndpoint) -> Result<HttpResponse, TFSLiteClientError> {
+        let mut attempt = 1;
+        loop {
+            let url = self.gateways.read_url().to_string();
+            match self.fetch_url(self.dialect.url(&url, endpoint)).await {
+                Err(_err) if attempt < self.gateways.len() => {
+                    debug_println!("Failing over from {}: {}", crate::debug::redact("url", &url), _err);
+                    self.gateways.mark_failed(&url);
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn fetch_url_json<T: DeserializeOwned>(&self, endpoint: GatewayEndpoint) -> Result<T, TFSLiteClientError> {
+        let response = self.fetch_gateway_url(&endpoint)
             .await?;
+        let body = read_response_body(self.response_verifier.as_deref(), &endpoint, response)?;
 
-        let key_string = data.get("batcher_public_key")
-            .unwrap()
-            .as_str()
-            .unwrap();
+        let body = self.dialect.parse_response(&endpoint, body)
+            .map_err(|msg| TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(msg)))?;
 
-        let result = hex::decode(key_string)
-            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        decode_response(&endpoint, body)
+    }
 
-        let public_key = PublicKey::load_from_bytes(result.as_slice());
+    pub async fn get_build_info(&self) -> Result<BuildInfo, TFSLiteClientError> {
+        self.fetch_url_json(GatewayEndpoint::BuildInfo).await
+    }
 
-        Ok(public_key)
+    pub async fn get_batcher_public_key(&self) -> Result<PublicKey, TFSLiteClientError> {
+        self.transport.get_batcher_public_key().await
     }
 
     pub async fn get_account_balance(&self) -> Result<AccountBalance, TFSLiteClientError> {
         let account = match &self.account {
-            Some(account) => hex::encode(account.as_slice()),
+            Some(account) => account.as_hex(),
             None => {
                 return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidAccount, None));
             },
         };
 
-        let url = format!("{}/account/balance/{}", self.url, account);
+        #[derive(Deserialize)]
+        struct AccountBalanceResponse {
+            balance: TokenAmount,
+        }
 
-        let data = self.fetch_url_object(url)
+        let response: AccountBalanceResponse = self.fetch_url_json(GatewayEndpoint::AccountBalance(account))
             .await?;
 
-        let balance = data.get("balance")
-            .unwrap()
-            .as_u64()
-            .unwrap();
-
-        Ok(AccountBalance(balance))
+        Ok(AccountBalance(response.balance))
     }
 
     pub async fn get_account_files(&self) -> Result<FileList, TFSLiteClientError> {
         let account = match &self.account {
-            Some(account) => hex::encode(account.as_slice()),
+            Some(account) => account.as_hex(),
             None => {
                 return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidAccount, None));
             },
         };
 
-        let url = format!("{}/account/files/{}", self.url, account);
-        let response: FileListResponse = self.fetch_url_json(url).await?;
+        let response: FileListResponse = self.fetch_url_json(GatewayEndpoint::AccountFiles(account)).await?;
 
-        let result: Vec<FileListEntry> = response.files.iter().map(|e| e.try_into().unwrap()).collect();
+        let result: Vec<FileListEntry> = response.files
+            .iter()
+            .map(|e| e.try_into())
+            .collect::<Result<_, _>>()
+            .map_err(|err: FileListParseError| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
 
         #[cfg(not(target_arch = "wasm32"))]
         return Ok(result);
 
         #[cfg(target_arch = "wasm32")]
-        return Ok(result.into_iter().map(JsValue::from).collect());
+        return Ok(result.into_iter().map(JsValue::from).collect::<js_sys::Array>().unchecked_into());
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -213,23 +345,7 @@ impl TFSLiteClient {
             self.get_batcher_public_key().await?.as_slice()
         );
 
-        let file_upload = FileUpload {
-            file: file.to_path_buf(),
-            url: self.url.clone(),
-            store: self.store.clone(),
-
-            signer: None,
-            batcher_public_key,
-            uuid: Uuid::new_v4(),
-            chunk_size: DEFAULT_CHUNK_SIZE,
-            filename: None,
-
-            prepare_status_callback: None,
-            send_status_callback: None,
-            wait_status_callback: None,
-        };
-
-        Ok(file_upload)
+        Ok(self.new_file_upload(Some(file.to_path_buf()), batcher_public_key, Uuid::new_v4()))
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -238,509 +354,2016 @@ impl TFSLiteClient {
             self.get_batcher_public_key().await?.as_slice()
         );
 
-        let file_upload = FileUpload {
-            file: file,
-            url: self.url.clone(),
-            store: self.store.clone(),
+        Ok(self.new_file_upload(Some(file), batcher_public_key, Uuid::new_v4()))
+    }
 
-            signer: None,
-            batcher_public_key,
-            uuid: Uuid::new_v4(),
-            chunk_size: DEFAULT_CHUNK_SIZE,
-            filename: None,
+    // Does not contact the gateway, so transactions can be prepared on an offline machine.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn upload_file_with_batcher_key(&self, file: &Path, batcher_public_key: PublicKey) -> FileUpload {
+        self.new_file_upload(Some(file.to_path_buf()), batcher_public_key, Uuid::new_v4())
+    }
 
-            prepare_status_callback: None,
-            send_status_callback: None,
-            wait_status_callback: None,
-        };
+    #[cfg(target_arch = "wasm32")]
+    pub fn upload_file_with_batcher_key(&self, file: web_sys::File, batcher_public_key: PublicKey) -> FileUpload {
+        self.new_file_upload(Some(file), batcher_public_key, Uuid::new_v4())
+    }
+
+    pub async fn import_transactions(&self, bundle: Vec<u8>) -> Result<FileUpload, TFSLiteClientError> {
+        let batch_list = BatchList::parse_from_bytes(&bundle)
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+        for batch in batch_list.get_batches() {
+            batch.validate()
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        }
+
+        let transactions: Vec<Transaction> = batch_list
+            .get_batches()
+            .iter()
+            .flat_map(|batch| batch.get_transactions().to_vec())
+            .collect();
+
+        // One bundle is one upload: every file transaction is for the same file, and every
+        // transaction names the same batcher. Deposits carry no file uuid.
+        let mut uuid: Option<Uuid> = None;
+        let mut batcher_public_key: Option<String> = None;
+        for tx in transactions.iter() {
+            tx.validate()
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+            let header = tx.header()
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+            match &batcher_public_key {
+                Some(batcher_public_key) if batcher_public_key != header.get_batcher_public_key() => {
+                    return Err(TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some("Bundle transactions name more than one batcher".to_string())));
+                },
+                Some(_) => {},
+                None => batcher_public_key = Some(header.get_batcher_public_key().to_string()),
+            }
+
+            let payload = Payload::parse_from_bytes(tx.get_payload())
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+            if payload.get_uuid().is_empty() {
+                continue;
+            }
+            let tx_uuid = Uuid::from_slice(payload.get_uuid())
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+            match uuid {
+                Some(uuid) if uuid != tx_uuid => {
+                    return Err(TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("Bundle holds transactions for both {} and {}", uuid, tx_uuid))));
+                },
+                Some(_) => {},
+                None => uuid = Some(tx_uuid),
+            }
+        }
+
+        let uuid = uuid.ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some("Bundle contains no file transactions".to_string()))
+        })?;
+        let batcher_public_key = batcher_public_key.ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some("Bundle contains no transactions".to_string()))
+        })?;
+        let batcher_public_key = PublicKey::load_from_hex(&batcher_public_key)
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+        let mut file_upload = self.new_file_upload(None, batcher_public_key, uuid);
+        self.store.add_txs(&uuid, &transactions)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+        for tx in transactions.iter() {
+            file_upload.record_manifest_tx(tx);
+        }
+        file_upload.phase = UploadPhase::Prepared;
 
         Ok(file_upload)
     }
-}
 
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-pub struct FileUpload {
-    #[cfg(not(target_arch = "wasm32"))]
-    file: PathBuf,
+    // Uploads prepared on this machine that have not finished, e.g. to offer resuming them.
+    pub async fn list_pending_uploads(&self) -> Result<PendingUploadList, TFSLiteClientError> {
+        let result = self.store.list_pending_uploads()
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
 
-    #[cfg(target_arch = "wasm32")]
-    file: web_sys::File,
+        #[cfg(not(target_arch = "wasm32"))]
+        return Ok(result);
 
-    url: String,
-    store: Arc<Mutex<dyn LocalStateStore>>,
+        #[cfg(target_arch = "wasm32")]
+        return Ok(result.into_iter().map(JsValue::from).collect::<js_sys::Array>().unchecked_into());
+    }
 
-    signer: Option<Box<dyn Signer>>,
-    batcher_public_key: PublicKey,
-    uuid: Uuid,
-    chunk_size: usize,
-    filename: Option<String>,
+    // Dumps the local store so pending uploads can be finished by a client on another backend,
+    // e.g. a desktop app picking up a session started in the browser.
+    pub async fn export_state(&self) -> Result<Vec<u8>, TFSLiteClientError> {
+        let mut dump: Vec<u8> = Vec::new();
+        self.store.export_store(&mut dump)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
 
-    #[cfg(not(target_arch = "wasm32"))]
-    prepare_status_callback: Option<Box<dyn FnMut(u64, u64)>>,
-    #[cfg(target_arch = "wasm32")]
-    prepare_status_callback: Option<Box<js_sys::Function>>,
+        Ok(dump)
+    }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    send_status_callback: Option<Box<dyn FnMut(u64, u64)>>,
-    #[cfg(target_arch = "wasm32")]
-    send_status_callback: Option<Box<js_sys::Function>>,
+    pub async fn import_state(&self, dump: Vec<u8>) -> Result<(), TFSLiteClientError> {
+        self.store.import_store(&mut dump.as_slice())
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
 
-    #[cfg(not(target_arch = "wasm32"))]
-    wait_status_callback: Option<Box<dyn FnMut(u64, u64)>>,
-    #[cfg(target_arch = "wasm32")]
-    wait_status_callback: Option<Box<js_sys::Function>>,
+        Ok(())
+    }
 }
 
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-impl FileUpload {
+impl TFSLiteClient {
+    // Like new, but keeps state in the given store, e.g. a MemoryLocalStateStore in tests.
+    pub fn with_store(url: String, store: Arc<dyn LocalStateStore>) -> TFSLiteClient {
+        TFSLiteClient {
+            transport: Arc::new(GatewayTransport::new(url.clone())),
+            custom_transport: None,
+            http: default_http_client(),
+            dialect: Arc::new(DefaultGatewayDialect),
+            gateways: Arc::new(GatewayPool::new(vec![url])),
+            account: None,
+            store,
+            metrics: Arc::new(NoopMetricsSink),
+            retry_policy: RetryPolicy::default(),
+            capabilities: None,
+            status_page_size: DEFAULT_STATUS_PAGE_SIZE,
+            request_compression: true,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            signer: None,
+            request_signer: None,
+            response_verifier: None,
+        }
+    }
 
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn set_signer(&mut self, signer: &dyn Signer) {
-        self.signer = Some(signer.clone_box());
+    pub(crate) async fn init_state_store_at(path: impl AsRef<std::path::Path>) -> Result<Arc<dyn LocalStateStore>, TFSLiteClientError> {
+        use crate::state_redb;
+        let store = state_redb::RedbLocalStateStore::new(path)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+
+        Ok(Arc::new(store))
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub fn set_signer(&mut self, signer: JsSigner) {
-        self.signer = Some(Box::new(signer));
+    // Drops locally stored transactions left older than retention by uploads that never finished,
+    // e.g. after a crash. A failed collection is not fatal; it is retried on the next startup.
+    pub async fn new_with_retention(url: String, retention: Duration) -> TFSLiteClient {
+        let client = Self::new(url).await;
+        if let Err(_err) = client.collect_garbage(retention).await {
+            debug_println!("Startup gc failed: {}", _err);
+        }
+
+        client
     }
 
-    pub fn set_chunk_size(&mut self, chunk_size: usize) {
-        self.chunk_size = chunk_size;
+    // Reopens an upload whose transactions are in the local store, e.g. one listed by
+    // list_pending_uploads() after a restart.
+    // Picks up the progress saved for uuid, so it can be shown before the upload carries on.
+    pub async fn resume_upload(&self, uuid: Uuid) -> Result<FileUpload, TFSLiteClientError> {
+        let batcher_public_key = self.get_batcher_public_key().await?;
+
+        let mut file_upload = self.new_file_upload(None, batcher_public_key, uuid);
+        let uploads = self.store.list_pending_uploads()
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+        if let Some(info) = uploads.into_iter().find(|info| info.file_id == uuid) {
+            file_upload.progress = info.progress;
+        }
+        Ok(file_upload)
     }
 
-    pub fn set_filename(&mut self, filename: &str) {
-        self.filename = Some(filename.to_string());
+    pub async fn collect_garbage(&self, older_than: Duration) -> Result<u64, TFSLiteClientError> {
+        self.store.gc(older_than)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn set_prepare_status_callback(&mut self, func: impl FnMut(u64, u64) + 'static) {
-        self.prepare_status_callback = Some(Box::new(func))
+    pub async fn get_store_stats(&self) -> Result<LocalStateStoreStats, TFSLiteClientError> {
+        self.store.stats()
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub fn set_prepare_status_callback(&mut self, func: js_sys::Function) {
-        self.prepare_status_callback = Some(Box::new(func))
+    // Sums up every upload in the store at once; the store is queried concurrently.
+    pub async fn overall_status(&self) -> Result<OverallStatus, TFSLiteClientError> {
+        let (stats, uploads, pending) = futures::join!(
+            self.store.stats(),
+            self.store.list_pending_uploads(),
+            self.store.get_all_txs_by_status(&UNCOMMITTED_STATUSES),
+        );
+        let store_error = |err: LocalStateStoreError| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err)));
+        let (stats, uploads, pending) = (stats.map_err(store_error)?, uploads.map_err(store_error)?, pending.map_err(store_error)?);
+
+        let now = Utc::now().timestamp_millis();
+        let files_with_pending_txs: HashSet<Uuid> = pending.iter().map(|(file_id, _)| *file_id).collect();
+        Ok(OverallStatus {
+            files: stats.files,
+            pending_uploads: uploads.len() as u64,
+            files_with_pending_txs: files_with_pending_txs.len() as u64,
+            txs_by_status: stats.txs_by_status,
+            pending_txs: pending.len() as u64,
+            oldest_pending_age_ms: pending.iter().map(|(_, tx_info)| (now - tx_info.created_at).max(0) as u64).max(),
+        })
     }
 
-    fn call_prepare_status_callback(&mut self, status: u64, total: u64) {
-        if self.prepare_status_callback.is_some() {
-            #[cfg(not(target_arch = "wasm32"))]
-            self.prepare_status_callback.as_mut().unwrap()(status, total);
+    // Sends transactions through transport rather than the gateways, until reset with None.
+    // Gateway settings changed in the meantime don't replace it.
+    pub fn set_transport(&mut self, transport: Option<Arc<dyn Transport>>) {
+        self.custom_transport = transport;
+        self.reset_transport();
+    }
 
-            #[cfg(target_arch = "wasm32")]
-            {
-                let func = self.prepare_status_callback.as_mut().unwrap();
-                let _ = func.call2(&JsValue::null(), &JsValue::from(status), &JsValue::from(total));
+    // Uploads created after this call report to sink.
+    pub fn set_metrics_sink(&mut self, sink: Arc<dyn MetricsSink>) {
+        self.metrics = sink;
+    }
+
+    // Transactions go through the gateways' own transport, failing over when there are several,
+    // unless set_transport gave another.
+    fn reset_transport(&mut self) {
+        if let Some(transport) = &self.custom_transport {
+            self.transport = transport.clone();
+            return;
+        }
+
+        let mut capabilities = self.capabilities.clone().unwrap_or_default();
+        if !self.request_compression {
+            capabilities.request_encoding = None;
+        }
+        self.transport = if self.gateways.len() == 1 {
+            Arc::new(GatewayTransport::new(self.gateways.write_url().to_string())
+                .with_http_client(self.http.clone())
+                .with_dialect(self.dialect.clone())
+                .with_retry_policy(self.retry_policy.clone())
+                .with_capabilities(&capabilities)
+                .with_status_page_size(self.status_page_size)
+                .with_request_signer(self.request_signer.clone())
+                .with_response_verifier(self.response_verifier.clone()))
+        } else {
+            Arc::new(FailoverTransport::new(self.gateways.clone(), self.dialect.clone(), self.retry_policy.clone())
+                .with_http_client(self.http.clone())
+                .with_capabilities(&capabilities)
+                .with_status_page_size(self.status_page_size)
+                .with_request_signer(self.request_signer.clone())
+                .with_response_verifier(self.response_verifier.clone()))
+        };
+    }
+
+    // Asks the gateway what it supports and adapts to it: status checks fall back to one call
+    // per transaction without the multiple-status endpoint, and the default chunk size shrinks
+    // to fit under the gateway's payload limit. Resets the transport.
+    pub async fn negotiate_capabilities(&mut self) -> Result<GatewayCapabilities, TFSLiteClientError> {
+        let build_info = self.get_build_info().await?;
+        let capabilities = GatewayCapabilities::from_build_info(&build_info);
+        debug_println!("Gateway capabilities: {:?}", capabilities);
+
+        if let Some(max_payload_size) = capabilities.max_payload_size {
+            let max_chunk_size = max_block_size(max_payload_size);
+            if max_chunk_size == 0 {
+                return Err(TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("Gateway payload limit of {} bytes is too small", max_payload_size))));
             }
+            self.chunk_size = self.chunk_size.min(max_chunk_size);
         }
+
+        self.capabilities = Some(capabilities.clone());
+        self.reset_transport();
+        Ok(capabilities)
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn set_send_status_callback(&mut self, func: impl FnMut(u64, u64) + 'static) {
-        self.send_status_callback = Some(Box::new(func))
+    pub fn get_capabilities(&self) -> Option<GatewayCapabilities> {
+        self.capabilities.clone()
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub fn set_send_status_callback(&mut self, func: js_sys::Function) {
-        self.send_status_callback = Some(Box::new(func))
+    // Also resets the transport, which then speaks the same dialect.
+    pub fn set_dialect(&mut self, dialect: Arc<dyn GatewayDialect>) {
+        self.dialect = dialect;
+        self.reset_transport();
     }
 
-    fn call_send_status_callback(&mut self, status: u64, total: u64) {
-        if self.send_status_callback.is_some() {
-            #[cfg(not(target_arch = "wasm32"))]
-            self.send_status_callback.as_mut().unwrap()(status, total);
+    // Sends the client's requests through http instead of the built in client (reqwest, or
+    // fetch in the browser). Like set_dialect, this resets the transport.
+    pub fn set_http_client(&mut self, http: Arc<dyn HttpClient>) {
+        self.http = http;
+        self.reset_transport();
+    }
 
-            #[cfg(target_arch = "wasm32")]
-            {
-                let func = self.send_status_callback.as_mut().unwrap();
-                let _ = func.call2(&JsValue::null(), &JsValue::from(status), &JsValue::from(total));
-            }
-        }
+    // Replaces the built in client with one tuned by options. Resets the transport.
+    #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+    pub fn set_connection_options(&mut self, options: &ConnectionOptions) -> Result<(), TFSLiteClientError> {
+        let http = ReqwestHttpClient::with_options(options)
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::InvalidConfig, Some(format!("{}", err))))?;
+        self.set_http_client(Arc::new(http));
+        Ok(())
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn set_wait_status_callback(&mut self, func: impl FnMut(u64, u64) + 'static) {
-        self.wait_status_callback = Some(Box::new(func))
+    // Like set_dialect, this resets the transport.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+        self.reset_transport();
     }
 
-    #[cfg(target_arch = "wasm32")]
-    pub fn set_wait_status_callback(&mut self, func: js_sys::Function) {
-        self.wait_status_callback = Some(Box::new(func))
+    // Status checks are split into requests of at most this many submit ids. Resets the transport.
+    pub fn set_status_page_size(&mut self, status_page_size: usize) {
+        self.status_page_size = status_page_size;
+        self.reset_transport();
     }
 
-    fn call_wait_status_callback(&mut self, status: u64, total: u64) {
-        if self.wait_status_callback.is_some() {
-            #[cfg(not(target_arch = "wasm32"))]
-            self.wait_status_callback.as_mut().unwrap()(status, total);
+    // Submissions are compressed when the gateway advertises support, unless turned off here.
+    // Resets the transport.
+    pub fn set_request_compression(&mut self, request_compression: bool) {
+        self.request_compression = request_compression;
+        self.reset_transport();
+    }
 
-            #[cfg(target_arch = "wasm32")]
-            {
-                let func = self.wait_status_callback.as_mut().unwrap();
-                let _ = func.call2(&JsValue::null(), &JsValue::from(status), &JsValue::from(total));
-            }
+    // Replaces the client's gateway with several fronting the same network. Transactions stay on
+    // one until it stops answering and then move to the next; reads do the same, or rotate across
+    // the healthy gateways with round_robin_reads. Also resets the transport.
+    pub fn set_gateways(&mut self, urls: Vec<String>, round_robin_reads: bool) -> Result<(), TFSLiteClientError> {
+        if urls.is_empty() {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidConfig, Some("No gateway urls given".to_string())));
         }
+
+        self.gateways = Arc::new(GatewayPool::new(urls).with_round_robin_reads(round_robin_reads));
+        self.reset_transport();
+        Ok(())
     }
 
-    pub async fn prepare_transactions(&mut self) -> Result<(), TFSLiteClientError> {
-        let mut filename: Option<String> = self.filename.clone();
+    // Probes every gateway, returning how many are up. Failed gateways are otherwise retried
+    // on their own a while after failing.
+    pub async fn check_gateway_health(&self) -> usize {
+        self.gateways.check_health(self.http.as_ref(), self.dialect.as_ref()).await
+    }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        let mut f = {
-            if filename.is_none() {
-                filename = Some(self.file.file_name().unwrap().to_str().unwrap().to_string());
-            }
+    // Uploads created after this call start with chunk_size, which set_chunk_size can still change.
+    pub fn set_default_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size;
+    }
 
-            File::open(self.file.as_path()).await.unwrap()
-        };
+    // Signs requests to the gateway with signer's key (see RequestSigner), or stops signing them
+    // when given None. Reads of chain state and logs stay unsigned. Resets the transport.
+    pub fn set_request_signer(&mut self, signer: Option<&dyn Signer>) -> Result<(), TFSLiteClientError> {
+        self.request_signer = signer.map(RequestSigner::new)
+            .transpose()?
+            .map(Arc::new);
+        self.reset_transport();
+        Ok(())
+    }
 
-        #[cfg(target_arch = "wasm32")]
-        let mut f = {
-            if filename.is_none() {
-                filename = Some(self.file.name());
-            }
-            let readable_stream = wasm_streams::ReadableStream::from_raw(self.file.stream());
-            readable_stream.into_async_read()
-        };
+    // With a gateway key set, balance, file list and status responses must be signed by it (see
+    // ResponseVerifier) or fail with UntrustedResponse. Resets the transport.
+    pub fn set_gateway_public_key(&mut self, public_key: Option<PublicKey>) {
+        self.response_verifier = public_key.map(|public_key| Arc::new(ResponseVerifier::new(public_key)));
+        self.reset_transport();
+    }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        let file_size = f.metadata().await.unwrap().len();
-        #[cfg(target_arch = "wasm32")]
-        let file_size = self.file.size() as u64;
+    // Uploads created after this call are signed by signer unless given another.
+    pub fn set_default_signer(&mut self, signer: &dyn Signer) {
+        self.signer = Some(signer.clone_box());
+    }
 
-        let chunk_size = self.chunk_size.clone();
+    pub async fn create_log(&self, signer: &dyn Signer, filename: &str) -> Result<LogWriter, TFSLiteClientError> {
+        let batcher_public_key = self.get_batcher_public_key().await?;
 
-        let mut processed_txs: u64 = 0;
-        let mut total_txs = file_size / (chunk_size as u64);
-        if file_size % (chunk_size as u64) > 0 {
-            total_txs += 1;
-        }
-        total_txs += 3;
+        Ok(LogWriter::new(self.transport.clone(), self.store.clone(), signer, batcher_public_key, Uuid::new_v4(), filename.to_string(), false))
+    }
 
-        let stream = stream ! {
-            let mut buffer: Vec<u8> = vec![0; chunk_size];
-            let slice = buffer.as_mut_slice();
+    // Reopens a log created earlier, resending any transactions queued locally but never sent.
+    pub async fn open_log(&self, signer: &dyn Signer, uuid: Uuid, filename: &str) -> Result<LogWriter, TFSLiteClientError> {
+        let batcher_public_key = self.get_batcher_public_key().await?;
 
-            while let Ok(bytes_read) = f.read(slice).await {
-                if bytes_read == 0 {
-                    break;
-                }
+        let mut log_writer = LogWriter::new(self.transport.clone(), self.store.clone(), signer, batcher_public_key, uuid, filename.to_string(), true);
+        log_writer.recover().await?;
 
-                yield slice[0..bytes_read].to_vec();
-            }
-        };
+        Ok(log_writer)
+    }
 
-        pin_mut!(stream);
-        debug_println!("Uuid: {}", self.uuid);
+    pub fn open_log_reader(&self, uuid: Uuid, offset: u64) -> LogReader {
+        LogReader::new(self.gateways.read_url().to_string(), self.http.clone(), self.dialect.clone(), uuid, offset)
+    }
 
-        use libtfslite::common::FILE_CREATE_COST;
-        let public_key = self.signer.as_ref().unwrap().public_key().unwrap();
-        let mut tx_id_prev: String;
+    pub fn chain_state_reader(&self) -> ChainStateReader {
+        ChainStateReader::new(self.gateways.read_url().to_string())
+            .with_http_client(self.http.clone())
+            .with_dialect(self.dialect.clone())
+    }
 
-        let payload = PayloadBuilder::new(PayloadOperation::AccountDeposit)
-            .with_address(public_key.as_slice().to_vec())
-            .with_amount(FILE_CREATE_COST*10)
+    pub async fn verify_file_commitment(&self, uuid: Uuid) -> Result<FileCommitment, TFSLiteClientError> {
+        self.chain_state_reader()
+            .verify_file_commitment(&uuid)
+            .await
+    }
+
+    // Downloads a file through the gateway and checks it against the chain: its manifest's
+    // sha512, when it has one, and the sha224 of each block in state. The report is signed by
+    // the default signer. A mismatch doesn't fail the call; it leaves report.verified unset, so
+    // the report still records what was found.
+    pub async fn download_verified(&self, uuid: Uuid) -> Result<(Vec<u8>, SignedVerificationReport), TFSLiteClientError> {
+        let signer = self.signer.as_ref().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some("Client has no default signer".to_string()))
+        })?;
+
+        let chain_state = self.chain_state_reader();
+        let file = chain_state.get_file_record(&uuid)
+            .await?
+            .ok_or_else(|| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("File {} not found", uuid))))?;
+        let blocks = chain_state.get_block_digests(&uuid)
+            .await?;
+
+        let mut reader = self.open_log_reader(uuid, 0);
+        let mut content: Vec<u8> = Vec::with_capacity(file.size as usize);
+        while (content.len() as u64) < file.size {
+            let data = reader.read().await?;
+            if data.is_empty() {
+                break;
+            }
+            content.extend_from_slice(&data);
+        }
+
+        let report = verify_download(&file, &content, &blocks)
+            .sign(&**signer)
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+        Ok((content, report))
+    }
+
+    // Seals an open file owned by the default signer's account, such as one appended to over time
+    // or left unsealed by a client that stopped partway through an upload. The transaction is
+    // stored, sent and waited on like an upload's.
+    pub async fn seal_file(&self, uuid: Uuid) -> Result<CommitReport, TFSLiteClientError> {
+        let file = self.get_owned_file(uuid).await?;
+        if file.state != FileState::Open {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("File {} is already sealed", uuid))));
+        }
+        if file.missing_blocks > 0 || file.block_total.is_some_and(|block_total| block_total != file.block_count) {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("File {} has missing blocks", uuid))));
+        }
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileSeal)
+            .with_uuid(uuid)
+            .build()
+            .unwrap();
+        self.commit_file_tx(uuid, payload).await
+    }
+
+    // Destroys a destroyable file the default signer's account owns or may destroy, waiting for it
+    // to commit.
+    pub async fn destroy_file(&self, uuid: Uuid) -> Result<CommitReport, TFSLiteClientError> {
+        let file = self.get_file_with_rights(uuid, FileRights::DESTROY).await?;
+        if file.mode != FileMode::Destroyable {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("File {} is {}", uuid, file.mode))));
+        }
+        if file.legal_hold {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("File {} is under legal hold", uuid))));
+        }
+        if file.retain_until > self.chain_state_reader().get_clock().await? {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("File {} is retained until {}", uuid, file.retain_until))));
+        }
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileDestroy)
+            .with_uuid(uuid)
+            .build()
+            .unwrap();
+        self.commit_file_tx(uuid, payload).await
+    }
+
+    // The file can't be destroyed before retain_until, as measured by the network's timestamp
+    // authority. Retention can be extended but never shortened.
+    pub async fn set_file_retention(&self, uuid: Uuid, retain_until: DateTime<Utc>) -> Result<CommitReport, TFSLiteClientError> {
+        let file = self.get_owned_file(uuid).await?;
+        if retain_until.timestamp() < file.retain_until {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("File {} is already retained until {}", uuid, file.retain_until))));
+        }
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileRetentionSet)
+            .with_uuid(uuid)
+            .with_retain_until(retain_until.timestamp())
+            .build()
+            .unwrap();
+        self.commit_file_tx(uuid, payload).await
+    }
+
+    // While a legal hold is set the file can't be destroyed, whatever its retention. The owner
+    // can place a hold; lifting one, or placing one on another account's file, takes the
+    // LegalHold permission, which is checked on chain.
+    pub async fn set_legal_hold(&self, uuid: Uuid, legal_hold: bool) -> Result<CommitReport, TFSLiteClientError> {
+        self.get_signer_file(uuid).await?;
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileLegalHoldSet)
+            .with_uuid(uuid)
+            .with_legal_hold(legal_hold)
+            .build()
+            .unwrap();
+        self.commit_file_tx(uuid, payload).await
+    }
+
+    // Sets tags on a file owned by the default signer's account, replacing any with the same key.
+    // An empty value removes the tag.
+    pub async fn tag_file(&self, uuid: Uuid, tags: Vec<(String, String)>) -> Result<CommitReport, TFSLiteClientError> {
+        self.get_owned_file(uuid).await?;
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileTag)
+            .with_uuid(uuid)
+            .with_tags(tags)
+            .build()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        self.commit_file_tx(uuid, payload).await
+    }
+
+    // Files of the client's account carrying every one of tags. The gateway's file list has no
+    // tags, so this reads each file's record from chain state.
+    pub async fn get_account_files_by_tags(&self, tags: &[(String, String)]) -> Result<Vec<FileRecord>, TFSLiteClientError> {
+        let account = self.account.as_ref()
+            .ok_or_else(|| TFSLiteClientError::new(TFSLiteClientErrorType::InvalidAccount, None))?;
+
+        let files = self.chain_state_reader()
+            .get_files(account)
+            .await?;
+
+        Ok(files.into_iter()
+            .filter(|file| tags.iter().all(|tag| file.tags.contains(tag)))
+            .collect())
+    }
+
+    // Gives another key rights over a file owned by the default signer's account, adding to any
+    // it already has.
+    pub async fn share_file(&self, uuid: Uuid, public_key: &PublicKey, rights: FileRights) -> Result<CommitReport, TFSLiteClientError> {
+        self.get_owned_file(uuid).await?;
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileGrant)
+            .with_uuid(uuid)
+            .with_grantee(public_key.as_slice().to_vec())
+            .with_rights(rights)
+            .build()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        self.commit_file_tx(uuid, payload).await
+    }
+
+    // Takes rights back from a key; FileRights::ALL removes its grant entirely.
+    pub async fn revoke_file_access(&self, uuid: Uuid, public_key: &PublicKey, rights: FileRights) -> Result<CommitReport, TFSLiteClientError> {
+        self.get_owned_file(uuid).await?;
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileRevoke)
+            .with_uuid(uuid)
+            .with_grantee(public_key.as_slice().to_vec())
+            .with_rights(rights)
+            .build()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        self.commit_file_tx(uuid, payload).await
+    }
+
+    // Registers alias for the default signer's key, so others can name the account by it in
+    // resolve_account. Aliases aren't tied to a file, so each is committed under a uuid of its own.
+    pub async fn register_alias(&self, alias: &str) -> Result<CommitReport, TFSLiteClientError> {
+        if !is_valid_alias(alias) {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidAccount, Some(format!("Invalid alias {}", alias))));
+        }
+
+        let payload = PayloadBuilder::new(PayloadOperation::AliasSet)
+            .with_alias(alias.to_string())
+            .build()
+            .unwrap();
+        self.commit_file_tx(Uuid::new_v4(), payload).await
+    }
+
+    // Gives up an alias registered to the default signer's key.
+    pub async fn clear_alias(&self, alias: &str) -> Result<CommitReport, TFSLiteClientError> {
+        let payload = PayloadBuilder::new(PayloadOperation::AliasClear)
+            .with_alias(alias.to_string())
             .build()
             .unwrap();
+        self.commit_file_tx(Uuid::new_v4(), payload).await
+    }
+
+    // From then on the default signer's account needs threshold of public_keys to approve each of
+    // its transactions; see new_multisig_proposal. A threshold of 0 with no keys turns it off.
+    // Once on, changing it needs the current cosigners too, so commit it as a proposal instead.
+    pub async fn set_multisig(&self, threshold: u32, public_keys: &[PublicKey]) -> Result<CommitReport, TFSLiteClientError> {
+        let payload = PayloadBuilder::new(PayloadOperation::AccountMultisigSet)
+            .with_multisig(threshold, public_keys.iter().map(|key| key.as_slice().to_vec()).collect())
+            .build()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        self.commit_file_tx(Uuid::new_v4(), payload).await
+    }
+
+    // Starts collecting cosignatures for payload, a transaction of the default signer's account.
+    pub async fn new_multisig_proposal(&self, payload: Payload) -> Result<MultisigProposal, TFSLiteClientError> {
+        let (public_key, record) = self.get_multisig_record().await?;
+        Ok(MultisigProposal::new(&public_key, &record, payload))
+    }
+
+    // Commits a proposal once it has enough approvals, under the uuid of the file it acts on if any.
+    pub async fn commit_cosigned(&self, proposal: MultisigProposal) -> Result<CommitReport, TFSLiteClientError> {
+        let payload = proposal.into_payload()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+        let uuid = Uuid::from_slice(&payload.uuid).unwrap_or_else(|_| Uuid::new_v4());
+        self.commit_file_tx(uuid, payload).await
+    }
+
+    async fn get_multisig_record(&self) -> Result<(PublicKey, AccountRecord), TFSLiteClientError> {
+        let signer = self.signer.as_ref().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some("Client has no default signer".to_string()))
+        })?;
+        let public_key = signer.public_key()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+
+        let record = self.chain_state_reader()
+            .get_account_record(&public_key)
+            .await?
+            .unwrap_or_default();
+        Ok((public_key, record))
+    }
+
+    // Takes either a registered alias or a hex public key.
+    pub async fn resolve_account(&self, name_or_key: &str) -> Result<PublicKey, TFSLiteClientError> {
+        if is_valid_alias(name_or_key) {
+            if let Some(public_key) = self.chain_state_reader().resolve_alias(name_or_key).await? {
+                return Ok(public_key);
+            }
+        }
+
+        PublicKey::load_from_hex(name_or_key)
+            .map_err(|_| TFSLiteClientError::new(TFSLiteClientErrorType::InvalidAccount, Some(format!("No account or alias {}", name_or_key))))
+    }
+
+    // Moves amount from the default signer's account to recipient, given as an alias or hex key.
+    pub async fn transfer(&self, recipient: &str, amount: impl Into<TokenAmount>) -> Result<CommitReport, TFSLiteClientError> {
+        let recipient = self.resolve_account(recipient).await?;
+
+        let payload = PayloadBuilder::new(PayloadOperation::AccountTransfer)
+            .with_address(recipient.as_slice().to_vec())
+            .with_amount(amount)
+            .build()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        self.commit_file_tx(Uuid::new_v4(), payload).await
+    }
+
+    // Takes amount out of the default signer's account for the gateway to settle off-chain, to
+    // settlement_address if given.
+    pub async fn withdraw(&self, amount: impl Into<TokenAmount>, settlement_address: Option<Vec<u8>>) -> Result<CommitReport, TFSLiteClientError> {
+        let amount = amount.into();
+        if amount.is_zero() {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidAccount, Some("Withdrawal amount must be greater than zero".to_string())));
+        }
 
+        let mut builder = PayloadBuilder::new(PayloadOperation::AccountWithdraw)
+            .with_amount(amount);
+        if let Some(settlement_address) = settlement_address {
+            builder = builder.with_address(settlement_address);
+        }
+        let payload = builder.build()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        self.commit_file_tx(Uuid::new_v4(), payload).await
+    }
+
+    async fn get_signer_file(&self, uuid: Uuid) -> Result<(FileRecord, PublicKey), TFSLiteClientError> {
+        let signer = self.signer.as_ref().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some("Client has no default signer".to_string()))
+        })?;
+        let public_key = signer.public_key()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+
+        let file = self.chain_state_reader()
+            .get_file_record(&uuid)
+            .await?
+            .ok_or_else(|| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("File {} not found", uuid))))?;
+
+        Ok((file, public_key))
+    }
+
+    async fn get_owned_file(&self, uuid: Uuid) -> Result<FileRecord, TFSLiteClientError> {
+        let (file, public_key) = self.get_signer_file(uuid).await?;
+        if file.owner != public_key.as_slice() {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidAccount, Some(format!("File {} is owned by another account", uuid))));
+        }
+
+        Ok(file)
+    }
+
+    // The default signer must own the file or have been granted rights over it.
+    async fn get_file_with_rights(&self, uuid: Uuid, rights: FileRights) -> Result<FileRecord, TFSLiteClientError> {
+        let (file, public_key) = self.get_signer_file(uuid).await?;
+        if !file.rights_of(public_key.as_slice()).contains(rights) {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidAccount, Some(format!("File {} does not grant {} to this account", uuid, rights))));
+        }
+
+        Ok(file)
+    }
+
+    // Signs payload with the default signer and commits it as a one transaction upload of uuid.
+    // Refuses while an upload of uuid still has transactions in the store, which should be
+    // resumed instead.
+    async fn commit_file_tx(&self, uuid: Uuid, payload: Payload) -> Result<CommitReport, TFSLiteClientError> {
+        let signer = self.signer.as_ref().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some("Client has no default signer".to_string()))
+        })?;
+
+        let pending = self.store.get_txs_by_status(&uuid, &UNCOMMITTED_STATUSES)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+        if !pending.is_empty() {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("Upload {} has {} transaction(s) pending", uuid, pending.len()))));
+        }
+
+        let batcher_public_key = self.get_batcher_public_key().await?;
         let tx = TransactionBuilder::new()
             .with_payload(payload)
-            .with_batcher_public_key(self.batcher_public_key.as_slice().to_vec())
-            .build(self.signer.as_ref().unwrap().as_ref())
+            .with_batcher_public_key(batcher_public_key.as_slice().to_vec())
+            .build(signer.as_ref())
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+
+        let mut file_upload = self.new_file_upload(None, batcher_public_key, uuid);
+        self.store.add_tx(&uuid, &tx)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+        file_upload.phase = UploadPhase::Prepared;
+
+        file_upload.send_transactions().await?;
+        file_upload.wait_transactions().await
+    }
+
+    fn new_file_upload(&self, file: Option<UploadSource>, batcher_public_key: PublicKey, uuid: Uuid) -> FileUpload {
+        let max_block_size = self.capabilities
+            .as_ref()
+            .and_then(|capabilities| capabilities.max_payload_size)
+            .map(max_block_size);
+
+        FileUpload {
+            file,
+            transport: self.transport.clone(),
+            store: self.store.clone(),
+            metrics: self.metrics.clone(),
+
+            signer: self.signer.as_ref().map(|signer| Box::new(signer.clone_box()) as Box<dyn AsyncSigner>),
+            batch_signer: None,
+            batcher_public_key,
+            uuid,
+            mode: FileMode::Immutable,
+            chunk_size: max_block_size.map_or(self.chunk_size, |max_block_size| self.chunk_size.min(max_block_size)),
+            chain_state: self.chain_state_reader(),
+            filename: None,
+            manifest_txs: Vec::new(),
+            create_manifest: false,
+            deterministic_nonces: false,
+            progress: UploadProgress::default(),
+            deadline: None,
+            max_block_size,
+            manifest_metadata: Vec::new(),
+            tags: Vec::new(),
+            manifest_tx: None,
+            lease_owner: Uuid::new_v4().to_string(),
+            lease_renewed_at: 0,
+            phase: UploadPhase::New,
+            machine: UploadStateMachine::new(),
+
+            prepare_status_callback: None,
+            send_status_callback: None,
+            wait_status_callback: None,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn import_and_send(&self, path: &Path) -> Result<FileUpload, TFSLiteClientError> {
+        let bundle = tokio::fs::read(path)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("{}", err))))?;
+
+        let mut file_upload = self.import_transactions(bundle).await?;
+        file_upload.send_transactions().await?;
+        file_upload.wait_transactions().await?;
+
+        Ok(file_upload)
+    }
+
+    pub async fn get_transaction_receipt(&self, tx_id: &TransactionId) -> Result<TransactionReceipt, TFSLiteClientError> {
+        let stored = self.store.get_receipt(tx_id)
+            .await;
+
+        match stored {
+            Ok(receipt) => return Ok(receipt),
+            Err(LocalStateStoreError::NoSuchReceipt) => {},
+            Err(err) => {
+                return Err(TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))));
+            },
+        }
+
+        let receipt: TransactionReceipt = self.fetch_url_json(GatewayEndpoint::TransactionReceipt(tx_id.clone())).await?;
+
+        self.store.add_receipt(&receipt)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+
+        Ok(receipt)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type UploadSource = PathBuf;
+#[cfg(target_arch = "wasm32")]
+type UploadSource = web_sys::File;
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct FileUpload {
+    file: Option<UploadSource>,
+
+    transport: Arc<dyn Transport>,
+    store: Arc<dyn LocalStateStore>,
+    metrics: Arc<dyn MetricsSink>,
+
+    signer: Option<Box<dyn AsyncSigner>>,
+    batch_signer: Option<Box<dyn Signer>>,
+    batcher_public_key: PublicKey,
+    uuid: Uuid,
+    mode: FileMode,
+    chunk_size: usize,
+    chain_state: ChainStateReader,
+    filename: Option<String>,
+    manifest_txs: Vec<ManifestTransaction>,
+    create_manifest: bool,
+    deterministic_nonces: bool,
+    progress: UploadProgress,
+    // Unix time in milliseconds.
+    deadline: Option<i64>,
+    // From the gateway's payload limit, when negotiated.
+    max_block_size: Option<usize>,
+    manifest_metadata: Vec<(String, String)>,
+    tags: Vec<(String, String)>,
+    manifest_tx: Option<Transaction>,
+    lease_owner: String,
+    lease_renewed_at: i64,
+    phase: UploadPhase,
+    // Decides chunk dependencies, what to send and when to give up; this upload does the IO.
+    machine: UploadStateMachine,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    prepare_status_callback: Option<Box<dyn FnMut(u64, u64)>>,
+    #[cfg(target_arch = "wasm32")]
+    prepare_status_callback: Option<Box<js_sys::Function>>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    send_status_callback: Option<Box<dyn FnMut(u64, u64)>>,
+    #[cfg(target_arch = "wasm32")]
+    send_status_callback: Option<Box<js_sys::Function>>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    wait_status_callback: Option<Box<dyn FnMut(u64, u64)>>,
+    #[cfg(target_arch = "wasm32")]
+    wait_status_callback: Option<Box<js_sys::Function>>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl FileUpload {
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_signer(&mut self, signer: &dyn Signer) {
+        self.signer = Some(Box::new(signer.clone_box()));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_signer(&mut self, signer: JsSigner) {
+        let signer: Box<dyn Signer> = Box::new(signer);
+        self.signer = Some(Box::new(signer));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_async_signer(&mut self, signer: Box<dyn AsyncSigner>) {
+        self.signer = Some(signer);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_async_signer(&mut self, signer: JsAsyncSigner) {
+        self.signer = Some(Box::new(signer));
+    }
+
+    // Transactions name the batch signer as their batcher, so it must be set before
+    // prepare_transactions.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_batch_signer(&mut self, signer: &dyn Signer) -> Result<(), TFSLiteClientError> {
+        self._set_batch_signer(signer.clone_box())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_batch_signer(&mut self, signer: JsSigner) -> Result<(), TFSLiteClientError> {
+        self._set_batch_signer(Box::new(signer))
+    }
+
+    // Clamped to what fits under the gateway's payload limit, when that is known.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = match self.max_block_size {
+            Some(max_block_size) if chunk_size > max_block_size => {
+                debug_println!("Chunk size {} clamped to {}", chunk_size, max_block_size);
+                max_block_size
+            },
+            _ => chunk_size,
+        };
+    }
+
+    // For uploads made before the client negotiated capabilities, or from a limit learned some
+    // other way. Shrinks the chunk size to fit if needed.
+    pub fn set_max_payload_size(&mut self, max_payload_size: u64) -> Result<(), TFSLiteClientError> {
+        if self.phase != UploadPhase::New {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("Upload {} is already {:?}", self.uuid, self.phase))));
+        }
+        let max_block_size = max_block_size(max_payload_size);
+        if max_block_size == 0 {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("Gateway payload limit of {} bytes is too small", max_payload_size))));
+        }
+
+        self.max_block_size = Some(max_block_size);
+        self.set_chunk_size(self.chunk_size);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn uuid(&self) -> String {
+        self.uuid.to_string()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_uuid(&mut self, uuid: Uuid) -> Result<(), TFSLiteClientError> {
+        self._set_uuid(uuid)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_uuid(&mut self, uuid: String) -> Result<(), TFSLiteClientError> {
+        let uuid = Uuid::parse_str(&uuid)
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        self._set_uuid(uuid)
+    }
+
+    // Uploads are immutable unless set otherwise. Only destroyable files can be passed to
+    // TFSLiteClient::destroy_file later.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_mode(&mut self, mode: FileMode) -> Result<(), TFSLiteClientError> {
+        self._set_mode(mode)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_mode(&mut self, mode: JsFileMode) -> Result<(), TFSLiteClientError> {
+        let mode = match mode.as_string().as_deref() {
+            Some("IMMUTABLE") => FileMode::Immutable,
+            Some("DESTROYABLE") => FileMode::Destroyable,
+            _ => {
+                return Err(TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("Unknown file mode {:?}", mode.as_string()))));
+            },
+        };
+        self._set_mode(mode)
+    }
+
+    // Replaces the random uuid with one derived from the signer's key and the file's contents, so
+    // uploading the same file from the same account again gives the same uuid. Reads the whole file.
+    pub async fn set_content_uuid(&mut self) -> Result<(), TFSLiteClientError> {
+        let public_key = self.signer.as_ref()
+            .ok_or_else(|| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some("Upload has no signer".to_string())))?
+            .public_key()
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+        let content_hash = self.content_hash().await?;
+
+        let mut name = public_key.as_slice().to_vec();
+        name.extend_from_slice(&content_hash);
+        self._set_uuid(Uuid::new_v5(&CONTENT_UUID_NAMESPACE, &name))
+    }
+
+    // From now, prepare, send and wait fail with DeadlineExceeded once duration has passed. The
+    // upload is left as it was, with its lease released, so it can be resumed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_deadline(&mut self, duration: Duration) {
+        self._set_deadline(duration)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_deadline(&mut self, duration_ms: f64) {
+        self._set_deadline(Duration::from_millis(duration_ms.max(0.0) as u64))
+    }
+
+    // As last saved to the store, which happens as prepared transactions are stored and as they
+    // are submitted and committed.
+    pub fn progress(&self) -> UploadProgress {
+        self.progress
+    }
+
+    // Set by set_filename(), or taken from the source file once transactions are prepared.
+    pub fn filename(&self) -> Option<String> {
+        self.filename.clone()
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn phase(&self) -> UploadPhase {
+        self.phase
+    }
+
+    // Chunks normally depend on the chunk before them, so the validator commits them one at a time.
+    // Indexed chunks depend only on FILE_CREATE and are ordered by their chunk index; the seal is
+    // then held back until every chunk has committed.
+    pub fn set_indexed_chunks(&mut self, indexed_chunks: bool) {
+        self.machine.set_indexed_chunks(indexed_chunks);
+    }
+
+    // Chunks identical to an earlier chunk of the file are sent as a reference to it rather than
+    // as data, which keeps sparse images full of zero blocks small.
+    pub fn set_deduplicate_chunks(&mut self, deduplicate_chunks: bool) {
+        self.machine.set_deduplicate_chunks(deduplicate_chunks);
+    }
+
+    pub fn set_filename(&mut self, filename: &str) {
+        self.filename = Some(filename.to_string());
+    }
+
+    pub fn set_lease_owner(&mut self, owner_id: &str) {
+        self.lease_owner = owner_id.to_string();
+    }
+
+    pub async fn release_lease(&mut self) -> Result<(), TFSLiteClientError> {
+        let result = self.store.release_lease(&self.uuid, &self.lease_owner)
+            .await;
+
+        self.lease_renewed_at = 0;
+        result.map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))
+    }
+
+    pub fn set_create_manifest(&mut self, create_manifest: bool) {
+        self.create_manifest = create_manifest;
+    }
+
+    // Preparing the same file again then gives byte-identical transactions, so a re-prepared
+    // upload is rejected as a duplicate of what was already sent rather than charged twice.
+    pub fn set_deterministic_nonces(&mut self, deterministic_nonces: bool) {
+        self.deterministic_nonces = deterministic_nonces;
+    }
+
+    pub fn add_manifest_metadata(&mut self, key: &str, value: &str) {
+        self.manifest_metadata.push((key.to_string(), value.to_string()));
+    }
+
+    // Tags are recorded with the file when it is created; use TFSLiteClient::tag_file afterwards.
+    pub fn add_tag(&mut self, key: &str, value: &str) {
+        self.tags.push((key.to_string(), value.to_string()));
+    }
+
+    pub fn get_manifest_transaction(&self) -> Option<Vec<u8>> {
+        self.manifest_tx
+            .as_ref()
+            .map(|tx| tx.write_to_bytes().unwrap())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_prepare_status_callback(&mut self, func: impl FnMut(u64, u64) + 'static) {
+        self.prepare_status_callback = Some(Box::new(func))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_prepare_status_callback(&mut self, func: StatusCallback) {
+        self.prepare_status_callback = Some(Box::new(func.unchecked_into()))
+    }
+
+    fn call_prepare_status_callback(&mut self, status: u64, total: u64) {
+        if self.prepare_status_callback.is_some() {
+            #[cfg(not(target_arch = "wasm32"))]
+            self.prepare_status_callback.as_mut().unwrap()(status, total);
+
+            #[cfg(target_arch = "wasm32")]
+            {
+                let func = self.prepare_status_callback.as_mut().unwrap();
+                let _ = func.call2(&JsValue::null(), &JsValue::from(status), &JsValue::from(total));
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_send_status_callback(&mut self, func: impl FnMut(u64, u64) + 'static) {
+        self.send_status_callback = Some(Box::new(func))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_send_status_callback(&mut self, func: StatusCallback) {
+        self.send_status_callback = Some(Box::new(func.unchecked_into()))
+    }
+
+    fn call_send_status_callback(&mut self, status: u64, total: u64) {
+        if self.send_status_callback.is_some() {
+            #[cfg(not(target_arch = "wasm32"))]
+            self.send_status_callback.as_mut().unwrap()(status, total);
+
+            #[cfg(target_arch = "wasm32")]
+            {
+                let func = self.send_status_callback.as_mut().unwrap();
+                let _ = func.call2(&JsValue::null(), &JsValue::from(status), &JsValue::from(total));
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_wait_status_callback(&mut self, func: impl FnMut(u64, u64) + 'static) {
+        self.wait_status_callback = Some(Box::new(func))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn set_wait_status_callback(&mut self, func: StatusCallback) {
+        self.wait_status_callback = Some(Box::new(func.unchecked_into()))
+    }
+
+    fn call_wait_status_callback(&mut self, status: u64, total: u64) {
+        if self.wait_status_callback.is_some() {
+            #[cfg(not(target_arch = "wasm32"))]
+            self.wait_status_callback.as_mut().unwrap()(status, total);
+
+            #[cfg(target_arch = "wasm32")]
+            {
+                let func = self.wait_status_callback.as_mut().unwrap();
+                let _ = func.call2(&JsValue::null(), &JsValue::from(status), &JsValue::from(total));
+            }
+        }
+    }
+
+    pub async fn prepare_transactions(&mut self) -> Result<(), TFSLiteClientError> {
+        let mut filename: Option<String> = self.filename.clone();
+
+        let file = self.file.clone().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some("Upload has no source file".to_string()))
+        })?;
+
+        self.hold_lease().await?;
+        self.phase = UploadPhase::Preparing;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut f = {
+            if filename.is_none() {
+                filename = Some(file.file_name().unwrap().to_str().unwrap().to_string());
+            }
+
+            File::open(file.as_path())
+                .await
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("Unable to open {}: {}", file.display(), err))))?
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        let mut f = {
+            if filename.is_none() {
+                filename = Some(file.name());
+            }
+            let readable_stream = wasm_streams::ReadableStream::from_raw(file.stream());
+            readable_stream.into_async_read()
+        };
+
+        self.filename = filename.clone();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let file_size = f.metadata().await.unwrap().len();
+        #[cfg(target_arch = "wasm32")]
+        let file_size = file.size() as u64;
+
+        let chunk_size = self.chunk_size.clone();
+
+        let mut upload_info = UploadInfo::new(self.uuid, &filename.clone().unwrap_or_default(), file_size, chunk_size as u64);
+        self.progress = UploadProgress {
+            started_at: upload_info.created_at,
+            updated_at: upload_info.created_at,
+            ..Default::default()
+        };
+        upload_info.progress = self.progress;
+        self.store.set_upload_info(&upload_info)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+
+        let mut processed_txs: u64 = 0;
+        let mut chunk_total = file_size / (chunk_size as u64);
+        if file_size % (chunk_size as u64) > 0 {
+            chunk_total += 1;
+        }
+        if chunk_total > MAX_FILE_BLOCKS {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidConfig, Some(format!("Upload of {} chunks is over the limit of {}, use a larger chunk size", chunk_total, MAX_FILE_BLOCKS))));
+        }
+        let mut total_txs = chunk_total + 3;
+        if self.create_manifest {
+            total_txs += 1;
+        }
+
+        let stream = stream ! {
+            let mut buffer: Vec<u8> = vec![0; chunk_size];
+            let slice = buffer.as_mut_slice();
+
+            // Reads can come back short, so fill each chunk to keep the count matching chunk_total.
+            'chunks: loop {
+                let mut bytes_read = 0;
+                while bytes_read < chunk_size {
+                    match f.read(&mut slice[bytes_read..]).await {
+                        Ok(0) => break,
+                        Ok(n) => bytes_read += n,
+                        Err(err) => {
+                            yield Err(err);
+                            break 'chunks;
+                        },
+                    }
+                }
+                if bytes_read == 0 {
+                    break;
+                }
+
+                yield Ok(slice[0..bytes_read].to_vec());
+                if bytes_read < chunk_size {
+                    break;
+                }
+            }
+        };
+
+        pin_mut!(stream);
+        debug_println!("Uuid: {}, Filename: {}", self.uuid, crate::debug::redact("filename", filename.clone().unwrap_or_default()));
+
+        use libtfslite::common::{upload_cost, FILE_CREATE_COST};
+        let public_key = self.upload_signer()?
+            .public_key()
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+        let mut file_hasher = Sha512::new();
+        let mut file_bytes_read: u64 = 0;
+        let mut pending_txs: Vec<Transaction> = Vec::with_capacity(TX_BUFFER_SIZE);
+
+        // Only top the account up when its balance can't already cover the whole upload. An
+        // account that doesn't exist yet has nothing in it.
+        let cost = upload_cost(chunk_total);
+        let balance = self.chain_state.get_account_record(&public_key)
+            .await?
+            .map(|account| AccountBalance(account.balance))
+            .unwrap_or_default();
+        let deposit = !balance.can_afford(&AccountBalance::from(cost));
+        let mut create_dependencies = Vec::new();
+        if deposit {
+            let payload = PayloadBuilder::new(PayloadOperation::AccountDeposit)
+                .with_address(public_key.as_slice().to_vec())
+                .with_amount(cost.max(FILE_CREATE_COST*10))
+                .build()
+                .unwrap();
+
+            let tx_builder = self.new_tx_builder(payload, 0);
+            let tx = self.sign_tx(tx_builder)
+                .await?;
+
+            self.record_manifest_tx(&tx);
+
+            create_dependencies.push(tx.get_header_signature().to_string());
+            self.buffer_tx(&mut pending_txs, tx).await?;
+        } else {
+            total_txs -= 1;
+        }
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileCreate)
+            .with_uuid(self.uuid)
+            .with_mode(self.mode)
+            .with_filename(filename.unwrap())
+            .with_tags(self.tags.clone())
+            .build()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+        let tx_builder = self.new_tx_builder(payload, 0)
+            .with_dependencies(create_dependencies);
+        let tx = self.sign_tx(tx_builder)
+            .await?;
+
+        self.record_manifest_tx(&tx);
+
+        self.machine.handle(UploadInput::Created { tx_id: tx.get_header_signature().to_string() });
+        self.buffer_tx(&mut pending_txs, tx).await?;
+
+        processed_txs += if deposit { 2 } else { 1 };
+        self.call_prepare_status_callback(processed_txs, total_txs);
+
+        while let Some(data) = stream.next().await {
+            let data = data
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("Unable to read {}: {}", self.uuid, err))))?;
+            debug_println!("Len: {}", data.len());
+            self.hold_lease().await?;
+            file_hasher.update(&data);
+            file_bytes_read += data.len() as u64;
+
+            let sha224 = Sha224::digest(&data).to_vec();
+            let (chunk_index, source, dependency) = match self.machine.handle(UploadInput::ChunkRead { sha224: sha224.clone() }).pop() {
+                Some(UploadEffect::AppendChunk { index, source, dependency }) => (index, source, dependency),
+                _ => return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("No chunk to append to {}", self.uuid)))),
+            };
+
+            let mut payload_builder = PayloadBuilder::new(PayloadOperation::FileAppend)
+                .with_uuid(self.uuid)
+                .with_chunk_index(chunk_index)
+                .with_chunk_total(chunk_total);
+            if let Some(max_block_size) = self.max_block_size {
+                payload_builder = payload_builder.with_max_block_size(max_block_size);
+            }
+            let payload_builder = match source {
+                ChunkSource::Block => payload_builder.with_block(data),
+                ChunkSource::Reference(reference_index) => payload_builder.with_block_reference(sha224, reference_index),
+                ChunkSource::FileReference(base_uuid, base_index) => payload_builder.with_file_block_reference(sha224, base_uuid, base_index),
+            };
+            let payload = payload_builder
+                .build()
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+            let tx_builder = self.new_tx_builder(payload, chunk_index)
+                .with_dependencies(vec![dependency]);
+            let tx = self.sign_tx(tx_builder)
+                .await?;
+
+            self.record_manifest_tx(&tx);
+
+            self.machine.handle(UploadInput::ChunkPrepared { tx_id: tx.get_header_signature().to_string() });
+            self.buffer_tx(&mut pending_txs, tx).await?;
+            // Only counted once the chunk's transaction is in the store.
+            if pending_txs.is_empty() {
+                self.progress.bytes_read = file_bytes_read;
+                self.save_progress().await;
+            }
+
+            processed_txs += 1;
+            self.call_prepare_status_callback(processed_txs, total_txs);
+        }
+
+        let seal_dependency = match self.machine.handle(UploadInput::ChunksDone).pop() {
+            Some(UploadEffect::Seal { dependency }) => dependency,
+            _ => return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("No seal for {}", self.uuid)))),
+        };
+        let payload = PayloadBuilder::new(PayloadOperation::FileSeal)
+            .with_uuid(self.uuid)
+            .build()
             .unwrap();
+        let tx_builder = self.new_tx_builder(payload, 0)
+            .with_dependencies(vec![seal_dependency]);
+        let tx = self.sign_tx(tx_builder)
+            .await?;
+
+        self.record_manifest_tx(&tx);
+
+        let tx_id_seal = tx.get_header_signature().to_string();
+        self.buffer_tx(&mut pending_txs, tx).await?;
+
+        processed_txs += 1;
+        self.call_prepare_status_callback(processed_txs, total_txs);
+
+        if self.create_manifest {
+            let mut payload_builder = PayloadBuilder::new(PayloadOperation::FileManifest)
+                .with_uuid(self.uuid)
+                .with_manifest(file_hasher.finalize().to_vec(), file_bytes_read, self.manifest_metadata.clone());
+            if let Some(filename) = self.filename.clone() {
+                payload_builder = payload_builder.with_filename(filename);
+            }
+            let payload = payload_builder
+                .build()
+                .unwrap();
+            let tx_builder = self.new_tx_builder(payload, 0)
+                .with_dependencies(vec![tx_id_seal]);
+            let tx = self.sign_tx(tx_builder)
+                .await?;
+
+            self.record_manifest_tx(&tx);
+            self.manifest_tx = Some(tx.clone());
+            self.buffer_tx(&mut pending_txs, tx).await?;
+
+            processed_txs += 1;
+            self.call_prepare_status_callback(processed_txs, total_txs);
+        }
+
+        self.flush_tx_buffer(&mut pending_txs).await?;
+        self.progress.bytes_read = file_bytes_read;
+        self.save_progress().await;
+        self.phase = UploadPhase::Prepared;
+        Ok(())
+    }
+
+    async fn submit_transaction(&mut self, tx_id: &TransactionId) -> Result<TransactionSubmitId, TFSLiteClientError> {
+        let tx_bytes = self.store.get_tx_bytes(tx_id)
+            .await.unwrap();
+
+        let submit_ids = self.submit_txs(vec![tx_bytes])
+            .await?;
+
+        submit_ids.into_iter().next().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some("No submit id returned".to_string()))
+        })
+    }
+
+    pub async fn send_transactions(&mut self) -> Result<(), TFSLiteClientError> {
+        debug_println!("send_transactions({})", self.uuid);
+        self.hold_lease().await?;
+        self.phase = UploadPhase::Sending;
+
+        let tx_infos = self.store.get_txs(&self.uuid)
+            .await
+            .unwrap();
+        let held_from = self.held_from(&tx_infos).await;
+        self.machine.handle(UploadInput::Loaded { txs: upload_txs(&tx_infos), held_from });
+
+        let tx_ids: Vec<TransactionId> = self.machine.handle(UploadInput::Send)
+            .into_iter()
+            .flat_map(|effect| match effect {
+                UploadEffect::Submit(tx_ids) => tx_ids,
+                _ => Vec::new(),
+            })
+            .collect();
+
+        let mut processed_txs: u64 = 0;
+        let total_txs: u64 = tx_ids.len() as u64;
+        let submitted_before = tx_infos.iter().filter(|tx_info| tx_info.submit_id.is_some()).count() as u64;
+
+        let max_txs = self.transport.max_transactions_per_submit().max(1);
+        for tx_id_chunk in tx_ids.chunks(max_txs) {
+            debug_println!("tx_ids: {:?}", tx_id_chunk);
+            self.hold_lease().await?;
+
+            let mut txs: Vec<Vec<u8>> = Vec::new();
+            for tx_id in tx_id_chunk {
+                txs.push(self.store.get_tx_bytes(tx_id)
+                    .await.unwrap());
+            }
+
+            let tx_submit_ids = self.submit_txs(txs)
+                .await?;
+
+            let mut updates: Vec<TransactionUpdate> = Vec::new();
+            for (tx_id, tx_submit_id) in tx_id_chunk.iter().zip(tx_submit_ids.iter()) {
+                self.record_submit(tx_id, tx_submit_id);
+                updates.push((tx_id.clone(), Some(tx_submit_id.clone()), None));
+                processed_txs += 1;
+            }
+            self.machine.handle(UploadInput::Submitted {
+                tx_ids: tx_id_chunk.to_vec(),
+                submit_ids: tx_submit_ids,
+                now: Utc::now().timestamp_millis(),
+            });
+
+            self.store.update_txs(&updates)
+                .await.unwrap();
+            self.progress.txs_submitted = submitted_before + processed_txs;
+            self.save_progress().await;
+            self.call_send_status_callback(processed_txs, total_txs);
+        }
+
+        self.phase = UploadPhase::Sent;
+        Ok(())
+    }
+
+    // A full queue is waited out. Any other rejection the gateway names won't go away by
+    // resending, so the lease is let go for whoever fixes the cause and resumes the upload.
+    async fn submit_txs(&mut self, txs: Vec<Vec<u8>>) -> Result<Vec<TransactionSubmitId>, TFSLiteClientError> {
+        self.metrics.bytes_submitted(txs.iter().map(|tx| tx.len() as u64).sum());
 
-        let store = self.store.lock().unwrap();
-        let _ = store.add_tx(&self.uuid, &tx)
-            .await;
-        drop(store);
+        let mut backoff = QUEUE_FULL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let started_at = Utc::now().timestamp_millis();
+            let err = match self.transport.submit_transactions(txs.clone()).await {
+                Ok(submit_ids) => {
+                    self.record_submit_latency(started_at);
+                    return Ok(submit_ids);
+                },
+                Err(err) => err,
+            };
+
+            let retryable = err.gateway_error().is_some_and(GatewayError::is_retryable);
+            if !retryable || attempt >= QUEUE_FULL_MAX_ATTEMPTS {
+                if err.gateway_error().is_some() {
+                    let _ = self.release_lease().await;
+                }
+                return Err(err);
+            }
 
-        tx_id_prev = tx.get_header_signature().to_string();
+            debug_println!("Gateway queue full, retrying in {:?} (attempt {} of {})", backoff, attempt + 1, QUEUE_FULL_MAX_ATTEMPTS);
+            #[cfg(not(target_arch = "wasm32"))]
+            tokio::time::sleep(backoff).await;
+            #[cfg(target_arch = "wasm32")]
+            gloo_timers::future::sleep(backoff).await;
 
-        let payload = PayloadBuilder::new(PayloadOperation::FileCreate)
-            .with_uuid(self.uuid)
-            .with_mode(FileMode::Immutable)
-            .with_filename(filename.unwrap())
-            .build()
-            .unwrap();
-        let tx = TransactionBuilder::new()
-            .with_payload(payload)
-            .with_batcher_public_key(self.batcher_public_key.as_slice().to_vec())
-            .with_dependencies(vec![tx_id_prev])
-            .build(self.signer.as_ref().unwrap().as_ref())
-            .unwrap();
+            backoff *= 2;
+            attempt += 1;
+            self.hold_lease().await?;
+        }
+    }
 
-        let store = self.store.lock().unwrap();
-        let _ = store.add_tx(&self.uuid, &tx)
-            .await;
-        drop(store);
+    // With wait, asks the transport to hold the request until a status changes instead of
+    // answering straight away.
+    async fn poll_statuses(&self, submit_ids: Vec<TransactionSubmitId>, wait: bool) -> Result<UploadInput, TFSLiteClientError> {
+        debug_println!("poll_statuses({})", self.uuid);
 
-        tx_id_prev = tx.get_header_signature().to_string();
+        let mut tx_statuses = None;
+        if wait {
+            tx_statuses = self.transport.wait_transaction_statuses(submit_ids.clone())
+                .await?;
+        }
+        let waited = tx_statuses.is_some();
+        let tx_statuses = match tx_statuses {
+            Some(tx_statuses) => tx_statuses,
+            None => self.transport.get_transaction_statuses(submit_ids)
+                .await?,
+        };
 
-        processed_txs += 2;
-        self.call_prepare_status_callback(processed_txs, total_txs);
+        let statuses = tx_statuses.into_iter()
+            .map(|(submit_id, status)| {
+                debug_println!("{} -> {:?}", submit_id, status);
+                (submit_id, status.into())
+            })
+            .collect();
 
-        while let Some(data) = stream.next().await {
-            debug_println!("Len: {}", data.len());
+        Ok(UploadInput::StatusUpdate { statuses, waited, now: Utc::now().timestamp_millis() })
+    }
 
-            let payload = PayloadBuilder::new(PayloadOperation::FileAppend)
-                .with_uuid(self.uuid)
-                .with_block(data)
-                .build()
-                .unwrap();
-            let tx = TransactionBuilder::new()
-                .with_payload(payload)
-                .with_batcher_public_key(self.batcher_public_key.as_slice().to_vec())
-                .with_dependencies(vec![tx_id_prev])
-                .build(self.signer.as_ref().unwrap().as_ref())
-                .unwrap();
+    pub async fn wait_transactions(&mut self) -> Result<CommitReport, TFSLiteClientError> {
+        debug_println!("wait_transactions({})", self.uuid);
+        self.hold_lease().await?;
+        self.phase = UploadPhase::Waiting;
+        let started_at = Utc::now().timestamp_millis();
+
+        let tx_infos = self.store.get_txs(&self.uuid)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+        // Whatever send_transactions() held back has no submit id, and is sent once it may be.
+        self.machine.handle(UploadInput::Loaded { txs: upload_txs(&tx_infos), held_from: tx_infos.len() });
 
-            let store = self.store.lock().unwrap();
-            let _ = store.add_tx(&self.uuid, &tx)
-                .await;
-            drop(store);
+        let mut processed_txs: u64 = 0;
+        let total_txs: u64 = tx_infos.len() as u64;
 
-            tx_id_prev = tx.get_header_signature().to_string();
+        self.call_wait_status_callback(processed_txs, total_txs);
 
-            processed_txs += 1;
-            self.call_prepare_status_callback(processed_txs, total_txs);
+        let mut effects: VecDeque<UploadEffect> = VecDeque::new();
+        loop {
+            let effect = match effects.pop_front() {
+                Some(effect) => effect,
+                None => {
+                    let committed_count = self.machine.committed_count() as u64;
+                    if committed_count > processed_txs {
+                        processed_txs = committed_count;
+                        self.progress.txs_committed = committed_count;
+                        self.save_progress().await;
+                        self.call_wait_status_callback(processed_txs, total_txs);
+                    }
+
+                    self.hold_lease().await?;
+                    effects.extend(self.machine.handle(UploadInput::Tick { now: Utc::now().timestamp_millis() }));
+                    continue;
+                },
+            };
+
+            match effect {
+                UploadEffect::PollStatuses { submit_ids, wait } => {
+                    let input = self.poll_statuses(submit_ids, wait)
+                        .await?;
+                    effects.extend(self.machine.handle(input));
+                },
+                UploadEffect::Persist(statuses) => {
+                    let updates: Vec<TransactionUpdate> = statuses.into_iter()
+                        .map(|(tx_id, submit_id, status)| (tx_id, Some(submit_id), Some(status.into())))
+                        .collect();
+                    let _ = self.store.update_txs(&updates)
+                        .await;
+                },
+                UploadEffect::Committed(tx_ids) => {
+                    self.metrics.txs_committed(tx_ids.len() as u64);
+                },
+                UploadEffect::MarkFailed(tx_id) => {
+                    debug_println!("Giving up on tx: {:?}", tx_id);
+                    self.store.update_tx(&tx_id, None, Some(TransactionStatus::FailedToCommit))
+                        .await
+                        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+                },
+                UploadEffect::Resubmit { tx_id, retry } => {
+                    if retry {
+                        self.metrics.retries(1);
+                    }
+                    debug_println!("Resubmitting tx: {:?}", tx_id);
+                    let tx_submit_id = self.submit_transaction(&tx_id)
+                        .await?;
+                    self.record_submit(&tx_id, &tx_submit_id);
+
+                    self.store.update_tx(&tx_id, Some(tx_submit_id.clone()), None)
+                        .await
+                        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+                    self.machine.handle(UploadInput::Submitted {
+                        tx_ids: vec![tx_id],
+                        submit_ids: vec![tx_submit_id],
+                        now: Utc::now().timestamp_millis(),
+                    });
+                },
+                UploadEffect::Sleep(duration) => {
+                    debug_println!("Sleeping...");
+                    #[cfg(not(target_arch = "wasm32"))]
+                    tokio::time::sleep(duration).await;
+                    #[cfg(target_arch = "wasm32")]
+                    gloo_timers::future::sleep(duration).await;
+                    debug_println!("Done sleeping...");
+                },
+                UploadEffect::Done => break,
+                // What is left stays in the store rather than being flushed.
+                UploadEffect::Failed(failed_txs) => {
+                    self.phase = UploadPhase::FailedToCommit;
+                    self.release_lease().await?;
+                    return Err(TFSLiteClientError::new(TFSLiteClientErrorType::FailedToCommit, Some(format!("{} transaction(s) of {} failed to commit", failed_txs, self.uuid))));
+                },
+                UploadEffect::AppendChunk { .. } | UploadEffect::Seal { .. } | UploadEffect::Submit(_) => {},
+            }
         }
 
-        let payload = PayloadBuilder::new(PayloadOperation::FileSeal)
-            .with_uuid(self.uuid)
-            .build()
-            .unwrap();
-        let tx = TransactionBuilder::new()
-            .with_payload(payload)
-            .with_batcher_public_key(self.batcher_public_key.as_slice().to_vec())
-            .with_dependencies(vec![tx_id_prev])
-            .build(self.signer.as_ref().unwrap().as_ref())
-            .unwrap();
+        let txs = tx_infos.into_iter()
+            .map(|tx_info| TxCommitReport {
+                resubmissions: self.machine.resubmissions(&tx_info.tx_id),
+                commit_ms: self.machine.commit_ms(&tx_info.tx_id, started_at),
+                tx_id: tx_info.tx_id,
+                status: TransactionStatus::Committed,
+            })
+            .collect();
+        let report = CommitReport {
+            txs,
+            elapsed_ms: (Utc::now().timestamp_millis() - started_at).max(0) as u64,
+        };
 
-        let store = self.store.lock().unwrap();
-        let _ = store.add_tx(&self.uuid, &tx)
+        let _ = self.store.flush_txs(&self.uuid)
             .await;
-        drop(store);
 
-        processed_txs += 1;
-        self.call_prepare_status_callback(processed_txs, total_txs);
-
-        Ok(())
+        self.phase = UploadPhase::Committed;
+        self.release_lease().await?;
+        Ok(report)
     }
 
-    async fn submit_transaction(&self, tx_id: &TransactionId) -> Result<TransactionSubmitId, TFSLiteClientError> {
-        #[derive(Deserialize)]
-        struct SubmitResponse {
-            submit_id: String,
-        }
+    pub async fn export_manifest(&self) -> Result<String, TFSLiteClientError> {
+        let signer = self.signer.as_ref().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some("No signer set".to_string()))
+        })?;
 
-        let store = self.store.lock().unwrap();
-        let tx_bytes = store.get_tx_bytes(tx_id)
-            .await.unwrap();
-        drop(store);
+        let public_key = signer.public_key()
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
 
-        let http_client = reqwest::Client::new();
+        let manifest = UploadManifest {
+            uuid: self.uuid,
+            filename: self.filename.clone(),
+            signer_public_key: public_key.as_hex(),
+            transactions: self.stored_manifest_txs().await?,
+        };
 
-        let response = http_client
-            .post(format!("{}/transaction/submit", self.url.as_str()))
-            .header("Content-Type", "application/octet-stream")
-            .body(tx_bytes)
-            .send()
+        sign_manifest(manifest, signer.as_ref())
             .await
-            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("{}", err))))?;
+            .and_then(|signed| signed.to_json())
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))
+    }
 
-        if response.status().is_success() {
-            let response_data = response
-                .json::<SubmitResponse>()
-                .await
-                .unwrap();
+    // The bundle is a serialized BatchList holding a single batch signed by the batch signer,
+    // which is also the Sawtooth .batch file format. Validators only take the batch when its
+    // signer is the batcher every transaction names, so the batch signer must have been set
+    // before the transactions were prepared.
+    pub async fn export_transactions_bytes(&self) -> Result<Vec<u8>, TFSLiteClientError> {
+        let signer = self.batch_signer.as_ref().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some("No batch signer set".to_string()))
+        })?;
+        let batcher_public_key = Signer::public_key(signer.as_ref())
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?
+            .as_hex();
+
+        let tx_infos = self.store.get_txs(&self.uuid)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
 
-            Ok(response_data.submit_id)
-        } else {
-            let status = response.status();
-            let msg = response
-                .text()
+        let mut transactions: Vec<Transaction> = Vec::new();
+        for tx_info in tx_infos {
+            let tx_bytes = self.store.get_tx_bytes(&tx_info.tx_id)
                 .await
-                .unwrap_or(String::from("(No Message Found)"));
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+            let tx = Transaction::parse_from_bytes(&tx_bytes)
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+            let header = tx.header()
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+            if header.get_batcher_public_key() != batcher_public_key {
+                return Err(TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("Transaction {} names batcher {}, not the batch signer", tx_info.tx_id, header.get_batcher_public_key()))));
+            }
+            transactions.push(tx);
+        }
+
+        let batch_builder = BatchBuilder::new()
+            .with_transactions(transactions);
+        let batch = build_batch(batch_builder, signer)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+
+        build_batch_list(vec![batch])
+            .write_to_bytes()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))
+    }
+}
+
+impl FileUpload {
+    pub(crate) fn _set_signer(&mut self, signer: &dyn Signer) {
+        self.signer = Some(Box::new(signer.clone_box()));
+    }
 
-            Err(TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("Response Code: {}, Message: {}", status, msg))))
+    // The uuid is written into every transaction, so it can only change before they are prepared.
+    pub(crate) fn _set_uuid(&mut self, uuid: Uuid) -> Result<(), TFSLiteClientError> {
+        if self.phase != UploadPhase::New {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("Upload {} is already {:?}", self.uuid, self.phase))));
         }
+
+        self.uuid = uuid;
+        Ok(())
     }
 
-    async fn get_transaction_statuses(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<HashMap<TransactionSubmitId, TransactionStatus>, TFSLiteClientError> {
-        let http_client = reqwest::Client::new();
+    pub(crate) fn _set_deadline(&mut self, duration: Duration) {
+        self.deadline = Some(Utc::now().timestamp_millis().saturating_add(duration.as_millis() as i64));
+    }
+
+    pub(crate) fn _set_mode(&mut self, mode: FileMode) -> Result<(), TFSLiteClientError> {
+        if self.phase != UploadPhase::New {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::InvalidState, Some(format!("Upload {} is already {:?}", self.uuid, self.phase))));
+        }
+
+        self.mode = mode;
+        Ok(())
+    }
 
-        let mut request: HashMap<&str, Vec<String>> = HashMap::new();
-        request.insert("submit_ids", submit_ids);
-        debug_println!("{:?}", request);
+    async fn content_hash(&self) -> Result<Vec<u8>, TFSLiteClientError> {
+        let file = self.file.clone().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some("Upload has no source file".to_string()))
+        })?;
 
-        let response = http_client
-            .post(format!("{}/transaction/status/multiple", self.url.as_str()))
-            .json(&request)
-            .send()
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut f = File::open(file.as_path())
             .await
-            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("{}", err))))?;
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("{}", err))))?;
+        #[cfg(target_arch = "wasm32")]
+        let mut f = wasm_streams::ReadableStream::from_raw(file.stream()).into_async_read();
 
-        if response.status().is_success() {
-            let response_data = response
-                .json::<HashMap<String, String>>()
+        let mut hasher = Sha512::new();
+        let mut buffer: Vec<u8> = vec![0; self.chunk_size.max(1)];
+        loop {
+            let bytes_read = f.read(buffer.as_mut_slice())
                 .await
-                .unwrap();
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("{}", err))))?;
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[0..bytes_read]);
+        }
 
-            let mut response: HashMap<TransactionSubmitId, TransactionStatus> = HashMap::new();
-            response_data.iter().for_each(|(k,v)| {
-               response.insert(k.clone(), v.clone().into());
-            });
+        Ok(hasher.finalize().to_vec())
+    }
 
-            Ok(response)
-        } else {
-            let status = response.status();
-            let msg = response
-                .text()
-                .await
-                .unwrap_or(String::from("(No Message Found)"));
+    // Sets the sink this upload reports to, overriding the one inherited from the client.
+    pub fn set_metrics_sink(&mut self, sink: Arc<dyn MetricsSink>) {
+        self.metrics = sink;
+    }
 
-            Err(TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("Response Code: {}, Message: {}", status, msg))))
-        }
+    pub fn set_resubmit_policy(&mut self, resubmit_policy: ResubmitPolicy) {
+        self.machine.set_resubmit_policy(resubmit_policy);
     }
 
-    pub async fn send_transactions(&mut self) -> Result<(), TFSLiteClientError> {
-        debug_println!("send_transactions({})", self.uuid);
+    // Chunks of the new file that match a block of the sealed file uuid are sent as references to
+    // it, so only changed chunks are uploaded. Blocks are compared by hash at chunk boundaries, so
+    // the chunk size should match the one the base file was uploaded with.
+    pub async fn base_file(&mut self, uuid: Uuid) -> Result<(), TFSLiteClientError> {
+        let file = self.chain_state.get_file_record(&uuid)
+            .await?
+            .ok_or_else(|| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("No base file {}", uuid))))?;
+        if file.state != FileState::Sealed {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("Base file {} is not sealed", uuid))));
+        }
 
-        let store = self.store.lock().unwrap();
-        let tx_infos = store.get_txs(&self.uuid)
-            .await
-            .unwrap();
-        drop(store);
+        let block_hashes = self.chain_state.get_block_hashes(&uuid)
+            .await?;
 
-        let mut processed_txs: u64 = 0;
-        let total_txs: u64 = tx_infos.len() as u64;
+        let mut base_chunks = HashMap::new();
+        for (number, sha224) in block_hashes {
+            base_chunks.entry(sha224).or_insert((uuid, number));
+        }
+        self.machine.set_base_chunks(base_chunks);
 
-        for tx_info in tx_infos {
-            debug_println!("tx_info: {:?}", tx_info);
-            let tx_submit_id = self.submit_transaction(&tx_info.tx_id).await?;
+        Ok(())
+    }
 
-            let store = self.store.lock().unwrap();
-            store.update_tx(&tx_info.tx_id, Some(tx_submit_id), None)
-                .await.unwrap();
-            drop(store);
+    // True once prepare_transactions() has stored the seal, so a resumed upload can be sent as is.
+    pub async fn is_prepared(&self) -> Result<bool, TFSLiteClientError> {
+        let tx_infos = self.store.get_txs(&self.uuid)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
 
-            processed_txs += 1;
-            self.call_send_status_callback(processed_txs, total_txs);
+        for tx_info in tx_infos.iter().rev() {
+            let tx_bytes = self.store.get_tx_bytes(&tx_info.tx_id)
+                .await
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+            let tx = Transaction::parse_from_bytes(&tx_bytes)
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+            match tx.payload_view() {
+                Ok(PayloadView::FileSeal { .. }) => return Ok(true),
+                Ok(PayloadView::FileAppend { .. }) => return Ok(false),
+                _ => {},
+            }
         }
 
-        Ok(())
+        Ok(false)
     }
 
-    async fn update_tx_statuses(&self) -> Result<(), TFSLiteClientError> {
-        debug_println!("update_tx_status({})", self.uuid);
+    // Drops the upload's transactions from the local store without sending them.
+    pub async fn discard(&mut self) -> Result<(), TFSLiteClientError> {
+        self.hold_lease().await?;
 
-        let store = self.store.lock().unwrap();
-        let tx_infos = store.get_txs(&self.uuid)
+        self.store.flush_txs(&self.uuid)
             .await
-            .unwrap();
-        drop(store);
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
 
-        let tx_map: HashMap<TransactionSubmitId, TransactionId> = tx_infos.iter().map(|tx_info| {
-            let submit_id = tx_info.submit_id.clone().unwrap();
-            let tx_id = tx_info.tx_id.clone();
-            (submit_id, tx_id)
-        }).collect();
-        let submit_ids_check: Vec<TransactionSubmitId> = tx_infos.iter().map(|tx_info| tx_info.submit_id.clone().unwrap()).collect();
-
-        let tx_statuses = self.get_transaction_statuses(submit_ids_check)
-            .await?;
+        self.release_lease().await
+    }
 
-        for (submit_id, mut status) in tx_statuses {
-            let tx_id = tx_map.get(&submit_id).unwrap();
-            if status == TransactionStatus::Unknown {
-                status = TransactionStatus::Local
+    // Index of the first transaction send_transactions() holds back: a seal that does not depend on
+    // the transaction before it, as prepared with indexed chunks.
+    async fn held_from(&self, tx_infos: &[TransactionInfo]) -> usize {
+        for index in (1..tx_infos.len()).rev() {
+            let tx = match self.store.get_tx_bytes(&tx_infos[index].tx_id).await {
+                Ok(tx_bytes) => match Transaction::parse_from_bytes(&tx_bytes) {
+                    Ok(tx) => tx,
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            };
+
+            match tx.payload_view() {
+                Ok(PayloadView::FileSeal { .. }) => {
+                    let chained = tx.dependencies()
+                        .map(|dependencies| dependencies.contains(&tx_infos[index - 1].tx_id))
+                        .unwrap_or(true);
+                    return if chained { tx_infos.len() } else { index };
+                },
+                Ok(PayloadView::FileAppend { .. }) => break,
+                _ => {},
             }
-            debug_println!("{} -> {:?}", tx_id, status);
-            let store = self.store.lock().unwrap();
-            let _ = store.update_tx(tx_id, Some(submit_id), Some(status))
-                .await;
-            drop(store);
         }
 
+        tx_infos.len()
+    }
+
+    fn record_submit_latency(&self, started_at: i64) {
+        let elapsed = (Utc::now().timestamp_millis() - started_at).max(0);
+        self.metrics.submit_latency(Duration::from_millis(elapsed as u64));
+    }
+
+    // Progress is only for display, so failing to save it doesn't fail the upload.
+    async fn save_progress(&mut self) {
+        self.progress.updated_at = Utc::now().timestamp_millis();
+        let _ = self.store.set_upload_progress(&self.uuid, &self.progress)
+            .await;
+    }
+
+    async fn buffer_tx(&self, pending_txs: &mut Vec<Transaction>, tx: Transaction) -> Result<(), TFSLiteClientError> {
+        self.metrics.bytes_signed(tx.get_payload().len() as u64);
+        pending_txs.push(tx);
+        if pending_txs.len() >= TX_BUFFER_SIZE {
+            self.flush_tx_buffer(pending_txs).await?;
+        }
         Ok(())
     }
 
-    pub async fn wait_transactions(&mut self) -> Result<(), TFSLiteClientError> {
-        debug_println!("wait_transactions({})", self.uuid);
+    async fn flush_tx_buffer(&self, pending_txs: &mut Vec<Transaction>) -> Result<(), TFSLiteClientError> {
+        if pending_txs.is_empty() {
+            return Ok(());
+        }
 
-        let store = self.store.lock().unwrap();
-        let tx_infos = store.get_txs(&self.uuid)
-            .await
-            .unwrap();
-        drop(store);
+        let result = self.store.add_txs(&self.uuid, pending_txs)
+            .await;
 
+        result.map_err(|err| match err {
+            LocalStateStoreError::QuotaExceeded(needed, available) => {
+                TFSLiteClientError::new(TFSLiteClientErrorType::QuotaExceeded, Some(format!("Upload {} needs {} bytes, {} available", self.uuid, needed, available)))
+            },
+            err => TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))),
+        })?;
 
-        let mut committed_txs: HashMap<TransactionId, ()> = HashMap::new();
-        let mut processed_txs: u64 = 0;
-        let total_txs: u64 = tx_infos.len() as u64;
+        pending_txs.clear();
+        Ok(())
+    }
 
-        self.call_wait_status_callback(processed_txs, total_txs);
+    fn _set_batch_signer(&mut self, signer: Box<dyn Signer>) -> Result<(), TFSLiteClientError> {
+        self.batcher_public_key = Signer::public_key(signer.as_ref())
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+        self.batch_signer = Some(signer);
+        Ok(())
+    }
 
-        loop {
-            let mut uncommited_count = 0;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn export_transactions(&self, path: &Path) -> Result<(), TFSLiteClientError> {
+        let bundle = self.export_transactions_bytes().await?;
 
-            self.update_tx_statuses()
-                .await?;
+        tokio::fs::write(path, bundle)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("{}", err))))
+    }
 
-            let store = self.store.lock().unwrap();
-            let tx_infos = store.get_txs(&self.uuid)
-                .await
-                .unwrap();
-            drop(store);
-
-            for tx_info in tx_infos {
-                debug_println!("tx_info: {:?}", tx_info);
-                if tx_info.status == TransactionStatus::Committed {
-                    committed_txs.insert(tx_info.tx_id.clone(), ());
-                } else {
-                    uncommited_count += 1;
-                }
+    // Acquiring is idempotent for the current owner, so this doubles as the heartbeat. Renewal
+    // is throttled to half the lease TTL to keep store writes off the per-chunk hot path.
+    // Every step of the pipeline holds the lease first, so this is where the deadline is checked.
+    async fn hold_lease(&mut self) -> Result<(), TFSLiteClientError> {
+        let now = Utc::now().timestamp_millis();
+        if self.deadline.is_some_and(|deadline| now > deadline) {
+            let _ = self.release_lease().await;
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::DeadlineExceeded, Some(format!("Upload {} passed its deadline while {:?}", self.uuid, self.phase))));
+        }
+        if now - self.lease_renewed_at < (DEFAULT_LEASE_TTL.as_millis() / 2) as i64 {
+            return Ok(());
+        }
 
-                if tx_info.status == TransactionStatus::Local {
-                    debug_println!("Resubmitting tx: {:?}", tx_info.tx_id);
-                    let tx_submit_id = self.submit_transaction(&tx_info.tx_id)
-                        .await?;
+        let result = self.store.acquire_lease(&self.uuid, &self.lease_owner, DEFAULT_LEASE_TTL)
+            .await;
 
-                    let store = self.store.lock().unwrap();
-                    store.update_tx(&tx_info.tx_id, Some(tx_submit_id), None)
-                        .await.unwrap();
-                    drop(store);
-                }
-            }
+        result.map_err(|err| match err {
+            LocalStateStoreError::LeaseHeld(owner_id) => {
+                TFSLiteClientError::new(TFSLiteClientErrorType::LeaseHeld, Some(format!("Upload {} is held by {}", self.uuid, owner_id)))
+            },
+            err => TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))),
+        })?;
 
-            if committed_txs.len() as u64 > processed_txs {
-                processed_txs = committed_txs.len() as u64;
-                self.call_wait_status_callback(processed_txs, total_txs);
-            }
+        self.lease_renewed_at = now;
+        Ok(())
+    }
 
-            if uncommited_count == 0 {
-                break;
-            }
+    fn upload_signer(&self) -> Result<&dyn AsyncSigner, TFSLiteClientError> {
+        self.signer.as_deref().ok_or_else(|| {
+            TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("Upload {} has no signer", self.uuid)))
+        })
+    }
 
-            debug_println!("Sleeping...");
-            #[cfg(not(target_arch = "wasm32"))]
-            thread::sleep(Duration::from_millis(500));
-            #[cfg(target_arch = "wasm32")]
-            gloo_timers::future::sleep(Duration::from_millis(500)).await;
-            debug_println!("Done sleeping...");
+    // A signer may refuse the payload, or fail to reach a remote key; either fails the upload.
+    async fn sign_tx(&self, tx_builder: TransactionBuilder) -> Result<Transaction, TFSLiteClientError> {
+        build_transaction(tx_builder, self.upload_signer()?)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))
+    }
+
+    fn new_tx_builder(&self, payload: Payload, chunk_index: u64) -> TransactionBuilder {
+        let builder = TransactionBuilder::new()
+            .with_payload(payload)
+            .with_batcher_public_key(self.batcher_public_key.as_slice().to_vec());
+        if self.deterministic_nonces {
+            builder.with_deterministic_nonce(chunk_index)
+        } else {
+            builder
         }
+    }
 
-        let store = self.store.lock().unwrap();
-        let _ = store.flush_txs(&self.uuid)
-            .await;
-        drop(store);
+    fn record_manifest_tx(&mut self, tx: &Transaction) {
+        let payload = Payload::parse_from_bytes(tx.get_payload()).unwrap();
+        let chunk_sha224 = match payload.get_operation() {
+            Payload_Operation::FILE_APPEND => Some(hex::encode(payload.get_block().get_sha224())),
+            _ => None,
+        };
 
-        Ok(())
+        self.manifest_txs.push(ManifestTransaction {
+            tx_id: tx.get_header_signature().to_string(),
+            operation: format!("{:?}", payload.get_operation()),
+            chunk_sha224,
+            submit_id: None,
+            submitted_at: None,
+        });
     }
-}
 
-impl FileUpload {
-    pub(crate) fn _set_signer(&mut self, signer: &dyn Signer) {
-        self.signer = Some(signer.clone_box());
+    // The manifest entries for every transaction the store holds for this upload, so an upload
+    // that was resumed or imported exports all of them and not just the ones this instance built.
+    // Submit times are only known for transactions this instance sent.
+    async fn stored_manifest_txs(&self) -> Result<Vec<ManifestTransaction>, TFSLiteClientError> {
+        let tx_infos = self.store.get_txs(&self.uuid)
+            .await
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+        if tx_infos.is_empty() {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("No transactions stored for {}", self.uuid))));
+        }
+
+        let mut manifest_txs = Vec::with_capacity(tx_infos.len());
+        for tx_info in tx_infos {
+            let tx_bytes = self.store.get_tx_bytes(&tx_info.tx_id)
+                .await
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err))))?;
+            let tx = Transaction::parse_from_bytes(&tx_bytes)
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+            let payload = Payload::parse_from_bytes(tx.get_payload())
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+            let chunk_sha224 = match payload.get_operation() {
+                Payload_Operation::FILE_APPEND => Some(hex::encode(payload.get_block().get_sha224())),
+                _ => None,
+            };
+            let submitted_at = self.manifest_txs.iter()
+                .find(|entry| entry.tx_id == tx_info.tx_id)
+                .and_then(|entry| entry.submitted_at);
+
+            manifest_txs.push(ManifestTransaction {
+                tx_id: tx_info.tx_id,
+                operation: format!("{:?}", payload.get_operation()),
+                chunk_sha224,
+                submit_id: tx_info.submit_id,
+                submitted_at,
+            });
+        }
+
+        Ok(manifest_txs)
+    }
+
+    fn record_submit(&mut self, tx_id: &TransactionId, submit_id: &TransactionSubmitId) {
+        if let Some(entry) = self.manifest_txs.iter_mut().find(|entry| &entry.tx_id == tx_id) {
+            entry.submit_id = Some(submit_id.clone());
+            entry.submitted_at = Some(Utc::now());
+        }
     }
 }
 
@@ -760,4 +2383,113 @@ mod tests {
     async fn test_client() -> Result<(), TFSLiteClientError> {
         test_client_common().await
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_overall_status() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_overall_status_common().await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    async fn test_overall_status() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_overall_status_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_upload_signing_errors() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_upload_signing_errors_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_upload_accessors() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_upload_accessors_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_upload_set_uuid() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_upload_set_uuid_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_upload_set_mode() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_upload_set_mode_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_upload_max_payload_size() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_upload_max_payload_size_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_custom_transport() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_custom_transport_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_upload_balance_error() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_upload_balance_error_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_export_manifest() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_export_manifest_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_transaction_bundle() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_transaction_bundle_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_gateway_errors() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_gateway_errors_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_upload_deadline() -> Result<(), TFSLiteClientError> {
+        crate::tests::test_upload_deadline_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_upload_state_machine() {
+        crate::tests::test_upload_state_machine_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_upload_state_machine() {
+        crate::tests::test_upload_state_machine_common()
+    }
+
+    // Bundles are checked with these parsers on import.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_parsing_properties() {
+        crate::tests::test_parsing_properties_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_payload_validation() {
+        crate::tests::test_payload_validation_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_payload_validation() {
+        crate::tests::test_payload_validation_common()
+    }
 }
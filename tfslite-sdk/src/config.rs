@@ -0,0 +1,269 @@
+// Client settings read from TFS_* environment variables or a TOML file, e.g.
+//
+//   url = "https://gateway.example.com"
+//   fallback_urls = ["https://gateway-2.example.com"]
+//   round_robin_reads = false
+//   key_file = "/etc/tfs/client.priv"
+//   sign_requests = true
+//   gateway_public_key = "02a1..."
+//   state_store = "/var/lib/tfs/client.db"
+//   chunk_size = 131072
+//   status_page_size = 1000
+//
+//   [retry]
+//   max_attempts = 5
+//   backoff_ms = 500
+//
+//   [connection]
+//   pool_max_idle_per_host = 4
+//   http2_prior_knowledge = false
+//   tcp_keepalive_secs = 30
+//   request_timeout_secs = 120
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use serde::Deserialize;
+use libtfslite::client::keys::{PrivateKey, PublicKey, Signer};
+use crate::client::{TFSLiteClient, TFSLiteClientError, TFSLiteClientErrorType};
+use crate::http::ConnectionOptions;
+use crate::transport::RetryPolicy;
+
+const DEFAULT_STATE_STORE: &str = "/tmp/redb-client.db";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    pub url: Option<String>,
+    // Further gateways to fail over to, tried in order after url.
+    #[serde(default)]
+    pub fallback_urls: Vec<String>,
+    pub round_robin_reads: Option<bool>,
+    pub key_file: Option<PathBuf>,
+    // Sign gateway requests with the key in key_file.
+    pub sign_requests: Option<bool>,
+    // Hex key that balance, file list and status responses must be signed with.
+    pub gateway_public_key: Option<String>,
+    pub state_store: Option<PathBuf>,
+    pub chunk_size: Option<usize>,
+    pub status_page_size: Option<usize>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub connection: ConnectionConfig,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_attempts: Option<u32>,
+    pub backoff_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionConfig {
+    pub pool_max_idle_per_host: Option<usize>,
+    pub http2_prior_knowledge: Option<bool>,
+    pub tcp_keepalive_secs: Option<u64>,
+    pub request_timeout_secs: Option<u64>,
+}
+
+impl ConnectionConfig {
+    fn is_empty(&self) -> bool {
+        self.pool_max_idle_per_host.is_none()
+            && self.http2_prior_knowledge.is_none()
+            && self.tcp_keepalive_secs.is_none()
+            && self.request_timeout_secs.is_none()
+    }
+}
+
+fn config_error(msg: String) -> TFSLiteClientError {
+    TFSLiteClientError::new(TFSLiteClientErrorType::InvalidConfig, Some(msg))
+}
+
+fn parse_var<T: FromStr>(var: &impl Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>, TFSLiteClientError> {
+    match var(name) {
+        Some(value) => value.parse()
+            .map(Some)
+            .map_err(|_| config_error(format!("Invalid value for {}: {}", name, value))),
+        None => Ok(None),
+    }
+}
+
+impl ClientConfig {
+    pub fn from_toml(toml: &str) -> Result<Self, TFSLiteClientError> {
+        toml::from_str(toml)
+            .map_err(|err| config_error(format!("{}", err)))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TFSLiteClientError> {
+        let toml = std::fs::read_to_string(path.as_ref())
+            .map_err(|err| config_error(format!("Unable to read {}: {}", path.as_ref().display(), err)))?;
+
+        Self::from_toml(&toml)
+    }
+
+    // Starts from the file named by TFS_CONFIG, if any, with the other variables taking precedence.
+    pub fn from_env() -> Result<Self, TFSLiteClientError> {
+        let mut config = match std::env::var_os("TFS_CONFIG") {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.merge_env(|name| std::env::var(name).ok())?;
+
+        Ok(config)
+    }
+
+    pub fn merge_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), TFSLiteClientError> {
+        if let Some(url) = var("TFS_URL") {
+            self.url = Some(url);
+        }
+        if let Some(fallback_urls) = var("TFS_FALLBACK_URLS") {
+            self.fallback_urls = fallback_urls.split(',')
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .collect();
+        }
+        if let Some(round_robin_reads) = parse_var(&var, "TFS_ROUND_ROBIN_READS")? {
+            self.round_robin_reads = Some(round_robin_reads);
+        }
+        if let Some(key_file) = var("TFS_KEY_FILE") {
+            self.key_file = Some(PathBuf::from(key_file));
+        }
+        if let Some(sign_requests) = parse_var(&var, "TFS_SIGN_REQUESTS")? {
+            self.sign_requests = Some(sign_requests);
+        }
+        if let Some(gateway_public_key) = var("TFS_GATEWAY_PUBLIC_KEY") {
+            self.gateway_public_key = Some(gateway_public_key);
+        }
+        if let Some(state_store) = var("TFS_STATE_STORE") {
+            self.state_store = Some(PathBuf::from(state_store));
+        }
+        if let Some(chunk_size) = parse_var(&var, "TFS_CHUNK_SIZE")? {
+            self.chunk_size = Some(chunk_size);
+        }
+        if let Some(status_page_size) = parse_var(&var, "TFS_STATUS_PAGE_SIZE")? {
+            self.status_page_size = Some(status_page_size);
+        }
+        if let Some(max_attempts) = parse_var(&var, "TFS_RETRY_MAX_ATTEMPTS")? {
+            self.retry.max_attempts = Some(max_attempts);
+        }
+        if let Some(backoff_ms) = parse_var(&var, "TFS_RETRY_BACKOFF_MS")? {
+            self.retry.backoff_ms = Some(backoff_ms);
+        }
+        if let Some(pool_max_idle_per_host) = parse_var(&var, "TFS_POOL_MAX_IDLE_PER_HOST")? {
+            self.connection.pool_max_idle_per_host = Some(pool_max_idle_per_host);
+        }
+        if let Some(http2_prior_knowledge) = parse_var(&var, "TFS_HTTP2_PRIOR_KNOWLEDGE")? {
+            self.connection.http2_prior_knowledge = Some(http2_prior_knowledge);
+        }
+        if let Some(tcp_keepalive_secs) = parse_var(&var, "TFS_TCP_KEEPALIVE_SECS")? {
+            self.connection.tcp_keepalive_secs = Some(tcp_keepalive_secs);
+        }
+        if let Some(request_timeout_secs) = parse_var(&var, "TFS_REQUEST_TIMEOUT_SECS")? {
+            self.connection.request_timeout_secs = Some(request_timeout_secs);
+        }
+
+        Ok(())
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            max_attempts: self.retry.max_attempts.unwrap_or(default.max_attempts),
+            backoff: self.retry.backoff_ms.map(Duration::from_millis).unwrap_or(default.backoff),
+        }
+    }
+
+    pub fn connection_options(&self) -> ConnectionOptions {
+        ConnectionOptions {
+            pool_max_idle_per_host: self.connection.pool_max_idle_per_host,
+            http2_prior_knowledge: self.connection.http2_prior_knowledge.unwrap_or(false),
+            tcp_keepalive: self.connection.tcp_keepalive_secs.map(Duration::from_secs),
+            request_timeout: self.connection.request_timeout_secs.map(Duration::from_secs),
+        }
+    }
+}
+
+impl TFSLiteClient {
+    pub async fn from_env() -> Result<TFSLiteClient, TFSLiteClientError> {
+        Self::from_client_config(&ClientConfig::from_env()?).await
+    }
+
+    pub async fn from_config(path: impl AsRef<Path>) -> Result<TFSLiteClient, TFSLiteClientError> {
+        Self::from_client_config(&ClientConfig::from_file(path)?).await
+    }
+
+    // The key, when one is given, becomes the client's account and signs its uploads.
+    pub async fn from_client_config(config: &ClientConfig) -> Result<TFSLiteClient, TFSLiteClientError> {
+        let url = config.url.clone()
+            .ok_or_else(|| config_error("No gateway url given (url or TFS_URL)".to_string()))?;
+        if config.chunk_size == Some(0) {
+            return Err(config_error("chunk_size must be greater than zero".to_string()));
+        }
+        if config.sign_requests == Some(true) && config.key_file.is_none() {
+            return Err(config_error("sign_requests needs a key_file".to_string()));
+        }
+        if config.retry.max_attempts == Some(0) {
+            return Err(config_error("retry.max_attempts must be at least one".to_string()));
+        }
+        if config.connection.request_timeout_secs == Some(0) {
+            return Err(config_error("connection.request_timeout_secs must be greater than zero".to_string()));
+        }
+
+        let state_store = config.state_store.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_STATE_STORE));
+        let mut client = Self::with_store(url.clone(), Self::init_state_store_at(state_store).await?);
+        if !config.connection.is_empty() {
+            #[cfg(feature = "reqwest")]
+            client.set_connection_options(&config.connection_options())?;
+            #[cfg(not(feature = "reqwest"))]
+            return Err(config_error("connection settings need the reqwest feature".to_string()));
+        }
+        client.set_retry_policy(config.retry_policy());
+        if !config.fallback_urls.is_empty() || config.round_robin_reads == Some(true) {
+            let urls = std::iter::once(url).chain(config.fallback_urls.iter().cloned()).collect();
+            client.set_gateways(urls, config.round_robin_reads.unwrap_or(false))?;
+        }
+        if let Some(gateway_public_key) = &config.gateway_public_key {
+            let public_key = PublicKey::load_from_hex(gateway_public_key)
+                .map_err(|err| config_error(format!("Invalid gateway_public_key: {}", err)))?;
+            client.set_gateway_public_key(Some(public_key));
+        }
+        if let Some(chunk_size) = config.chunk_size {
+            client.set_default_chunk_size(chunk_size);
+        }
+        if let Some(status_page_size) = config.status_page_size {
+            client.set_status_page_size(status_page_size);
+        }
+
+        if let Some(key_file) = &config.key_file {
+            let key = PrivateKey::load_from_file(key_file.clone())
+                .map_err(|err| config_error(format!("Unable to load key {}: {}", key_file.display(), err)))?;
+            let public_key = Signer::public_key(&key)
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+            client.set_account(public_key);
+            client.set_default_signer(&key);
+            if config.sign_requests == Some(true) {
+                client.set_request_signer(Some(&key))?;
+            }
+        }
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_config_from_toml_common, test_config_merge_env_common};
+
+    #[test]
+    fn test_config_from_toml() {
+        test_config_from_toml_common()
+    }
+
+    #[test]
+    fn test_config_merge_env() {
+        test_config_merge_env_common()
+    }
+}
@@ -15,3 +15,62 @@ cfg_if! {
         pub use wasm_bindgen_test::console_log as debug_println;
     }
 }
+
+use std::fmt::Display;
+use std::sync::RwLock;
+
+pub const REDACTED: &str = "<redacted>";
+pub const DEFAULT_REDACTED_FIELDS: &[&str] = &["url", "filename", "tx_bytes", "authorization"];
+
+static REDACTED_FIELDS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+// Replaces the deny-list of fields whose values are hidden in debug output. An empty list
+// disables redaction entirely.
+pub fn set_redacted_fields(fields: Vec<String>) {
+    *REDACTED_FIELDS.write().unwrap() = Some(fields);
+}
+
+pub fn add_redacted_field(field: &str) {
+    let mut fields = REDACTED_FIELDS.write().unwrap();
+    fields
+        .get_or_insert_with(default_redacted_fields)
+        .push(field.to_string());
+}
+
+pub fn is_redacted(field: &str) -> bool {
+    match REDACTED_FIELDS.read().unwrap().as_ref() {
+        Some(fields) => fields.iter().any(|f| f == field),
+        None => DEFAULT_REDACTED_FIELDS.contains(&field),
+    }
+}
+
+pub fn redact<T: Display>(field: &str, value: T) -> String {
+    if is_redacted(field) {
+        REDACTED.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn default_redacted_fields() -> Vec<String> {
+    DEFAULT_REDACTED_FIELDS.iter().map(|f| f.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_redaction_common;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_redaction() {
+        test_redaction_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_redaction() {
+        test_redaction_common()
+    }
+}
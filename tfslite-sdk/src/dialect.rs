@@ -0,0 +1,73 @@
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GatewayEndpoint {
+    BuildInfo,
+    BatcherPublicKey,
+    AccountBalance(String),
+    AccountFiles(String),
+    TransactionSubmit,
+    TransactionStatus(String),
+    TransactionStatusMultiple,
+    TransactionStatusWait,
+    TransactionReceipt(String),
+    FileData(String, u64),
+    State(String),
+    StateList(String),
+    StateProof(String),
+    Block(String),
+}
+
+// Describes how a gateway lays out its API. Integrators that proxy the gateway behind their own
+// paths or wrap responses in an envelope implement this instead of patching the client.
+pub trait GatewayDialect {
+    fn url(&self, base_url: &str, endpoint: &GatewayEndpoint) -> String;
+
+    // Extracts the gateway payload from the response body, or returns an error message if the
+    // envelope reports a failure.
+    fn parse_response(&self, _endpoint: &GatewayEndpoint, body: Value) -> Result<Value, String> {
+        Ok(body)
+    }
+}
+
+pub struct DefaultGatewayDialect;
+
+impl GatewayDialect for DefaultGatewayDialect {
+    fn url(&self, base_url: &str, endpoint: &GatewayEndpoint) -> String {
+        match endpoint {
+            GatewayEndpoint::BuildInfo => format!("{}/build-info", base_url),
+            GatewayEndpoint::BatcherPublicKey => format!("{}/batcher-public-key", base_url),
+            GatewayEndpoint::AccountBalance(account) => format!("{}/account/balance/{}", base_url, account),
+            GatewayEndpoint::AccountFiles(account) => format!("{}/account/files/{}", base_url, account),
+            GatewayEndpoint::TransactionSubmit => format!("{}/transaction/submit", base_url),
+            GatewayEndpoint::TransactionStatus(submit_id) => format!("{}/transaction/status/{}", base_url, submit_id),
+            GatewayEndpoint::TransactionStatusMultiple => format!("{}/transaction/status/multiple", base_url),
+            GatewayEndpoint::TransactionStatusWait => format!("{}/transaction/status/wait", base_url),
+            GatewayEndpoint::TransactionReceipt(tx_id) => format!("{}/transaction/receipt/{}", base_url, tx_id),
+            GatewayEndpoint::FileData(file_id, offset) => format!("{}/file/{}/data?offset={}", base_url, file_id, offset),
+            GatewayEndpoint::State(address) => format!("{}/state/{}", base_url, address),
+            GatewayEndpoint::StateList(prefix) => format!("{}/state?address={}", base_url, prefix),
+            GatewayEndpoint::StateProof(address) => format!("{}/state/proof/{}", base_url, address),
+            GatewayEndpoint::Block(block_id) => format!("{}/blocks/{}", base_url, block_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_dialect_common;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_dialect() {
+        test_dialect_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_dialect() {
+        test_dialect_common()
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use chrono::{DateTime, Duration, Utc};
+use crate::dialect::{GatewayDialect, GatewayEndpoint};
+use crate::http::{HttpClient, HttpRequest};
+use crate::debug::debug_println;
+
+// A gateway that failed is skipped for this long, then tried again.
+const RECHECK_AFTER_SECS: i64 = 30;
+
+// Gateways fronting the same network. Writes stay on one gateway until it fails, so submits and
+// status checks go to the same place; reads can be spread across all the healthy ones.
+pub struct GatewayPool {
+    urls: Vec<String>,
+    failed_at: Mutex<Vec<Option<DateTime<Utc>>>>,
+    current: AtomicUsize,
+    next_read: AtomicUsize,
+    round_robin_reads: bool,
+}
+
+impl GatewayPool {
+    // urls must not be empty.
+    pub fn new(urls: Vec<String>) -> Self {
+        assert!(!urls.is_empty(), "GatewayPool needs at least one url");
+
+        GatewayPool {
+            failed_at: Mutex::new(vec![None; urls.len()]),
+            urls,
+            current: AtomicUsize::new(0),
+            next_read: AtomicUsize::new(0),
+            round_robin_reads: false,
+        }
+    }
+
+    pub fn with_round_robin_reads(mut self, round_robin_reads: bool) -> Self {
+        self.round_robin_reads = round_robin_reads;
+        self
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    pub fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    fn is_healthy(&self, index: usize) -> bool {
+        match self.failed_at.lock().unwrap()[index] {
+            Some(failed_at) => Utc::now() - failed_at >= Duration::seconds(RECHECK_AFTER_SECS),
+            None => true,
+        }
+    }
+
+    // The first healthy gateway at or after start, or start itself when none are.
+    fn next_healthy(&self, start: usize) -> usize {
+        (0..self.urls.len())
+            .map(|offset| (start + offset) % self.urls.len())
+            .find(|index| self.is_healthy(*index))
+            .unwrap_or(start % self.urls.len())
+    }
+
+    pub fn write_url(&self) -> &str {
+        let current = self.current.load(Ordering::SeqCst);
+        &self.urls[current]
+    }
+
+    pub fn read_url(&self) -> &str {
+        if !self.round_robin_reads {
+            return self.write_url();
+        }
+
+        let index = self.next_healthy(self.next_read.fetch_add(1, Ordering::SeqCst));
+        &self.urls[index]
+    }
+
+    // Moves writes to the next healthy gateway if url was the one taking them.
+    pub fn mark_failed(&self, url: &str) {
+        let Some(index) = self.urls.iter().position(|candidate| candidate == url) else {
+            return;
+        };
+        debug_println!("Gateway {} failed", crate::debug::redact("url", url));
+        self.failed_at.lock().unwrap()[index] = Some(Utc::now());
+
+        if self.current.load(Ordering::SeqCst) == index {
+            self.current.store(self.next_healthy(index + 1), Ordering::SeqCst);
+        }
+    }
+
+    pub fn mark_healthy(&self, url: &str) {
+        if let Some(index) = self.urls.iter().position(|candidate| candidate == url) {
+            self.failed_at.lock().unwrap()[index] = None;
+        }
+    }
+
+    // Probes every gateway's build info endpoint and returns how many answered.
+    pub async fn check_health(&self, http: &dyn HttpClient, dialect: &dyn GatewayDialect) -> usize {
+        let mut healthy = 0;
+        for url in self.urls.iter() {
+            if self.probe(http, url, dialect).await {
+                self.mark_healthy(url);
+                healthy += 1;
+            } else {
+                self.mark_failed(url);
+            }
+        }
+
+        healthy
+    }
+
+    pub(crate) async fn probe(&self, http: &dyn HttpClient, url: &str, dialect: &dyn GatewayDialect) -> bool {
+        let response = http.send(HttpRequest::get(dialect.url(url, &GatewayEndpoint::BuildInfo))).await;
+        matches!(response, Ok(response) if response.is_success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_gateway_pool_common;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_gateway_pool() {
+        test_gateway_pool_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_gateway_pool() {
+        test_gateway_pool_common()
+    }
+}
@@ -0,0 +1,427 @@
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use cfg_if::cfg_if;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+pub const STATUS_NOT_FOUND: u16 = 404;
+pub const STATUS_CONFLICT: u16 = 409;
+pub const CONTENT_ENCODING_HEADER: &str = "Content-Encoding";
+
+// Compression a gateway may accept on request bodies, named as in Content-Encoding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RequestEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl RequestEncoding {
+    pub fn from_name(name: &str) -> Option<RequestEncoding> {
+        match name {
+            "gzip" => Some(RequestEncoding::Gzip),
+            "deflate" => Some(RequestEncoding::Deflate),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RequestEncoding::Gzip => "gzip",
+            RequestEncoding::Deflate => "deflate",
+        }
+    }
+
+    pub fn encode(&self, body: &[u8]) -> Vec<u8> {
+        let level = flate2::Compression::default();
+        // Writing to a Vec can't fail.
+        match self {
+            RequestEncoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+                encoder.write_all(body).unwrap();
+                encoder.finish().unwrap()
+            },
+            RequestEncoding::Deflate => {
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), level);
+                encoder.write_all(body).unwrap();
+                encoder.finish().unwrap()
+            },
+        }
+    }
+
+    pub fn decode(&self, body: &[u8]) -> Result<Vec<u8>, HttpError> {
+        let mut decoded = Vec::new();
+        let result = match self {
+            RequestEncoding::Gzip => flate2::read::GzDecoder::new(body).read_to_end(&mut decoded),
+            RequestEncoding::Deflate => flate2::read::ZlibDecoder::new(body).read_to_end(&mut decoded),
+        };
+        result.map_err(|err| HttpError(format!("Invalid {} body: {}", self.name(), err)))?;
+        Ok(decoded)
+    }
+}
+
+// An HTTP request as the SDK sends it, independent of the HTTP stack underneath.
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    // Where the response came from, after any redirects.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HttpError(pub String);
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+// Sends requests to gateways and signing services. Embedders with their own HTTP stack
+// implement this and hand it to the client with set_http_client.
+#[async_trait(?Send)]
+pub trait HttpClient {
+    // Only failures to get a response are errors; any status the server answers with is a
+    // response.
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpError>;
+}
+
+// The path and query of url, e.g. "/state?address=abcd" for "http://gateway/state?address=abcd".
+pub fn path_and_query(url: &str) -> String {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let rest = rest.split('#').next().unwrap_or_default();
+    match rest.find(['/', '?']) {
+        Some(index) if rest[index..].starts_with('/') => rest[index..].to_string(),
+        Some(index) => format!("/{}", &rest[index..]),
+        None => "/".to_string(),
+    }
+}
+
+fn encode_query_component(value: &str) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+impl HttpRequest {
+    pub fn new(method: &str, url: impl Into<String>) -> Self {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new("GET", url)
+    }
+
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new("POST", url)
+    }
+
+    // Replaces any header of the same name.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.set_header(name, value);
+        self
+    }
+
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.headers.retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn with_query(mut self, name: &str, value: &str) -> Self {
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        self.url = format!("{}{}{}={}", self.url, separator, encode_query_component(name), encode_query_component(value));
+        self
+    }
+
+    pub fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn with_json<T: Serialize + ?Sized>(self, value: &T) -> Result<Self, HttpError> {
+        let body = serde_json::to_vec(value)
+            .map_err(|err| HttpError(format!("{}", err)))?;
+
+        Ok(self.with_header("Content-Type", "application/json").with_body(body))
+    }
+
+    pub fn path(&self) -> String {
+        path_and_query(&self.url)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.status)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn path(&self) -> String {
+        path_and_query(&self.url)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        serde_json::from_slice(&self.body)
+            .map_err(|err| HttpError(format!("{}", err)))
+    }
+}
+
+// Tuning for the built in client off the browser, where fetch leaves these to the browser.
+// Unset fields keep reqwest's defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectionOptions {
+    // Idle connections kept open to each gateway.
+    pub pool_max_idle_per_host: Option<usize>,
+    // Speak HTTP/2 from the start rather than HTTP/1.1, for gateways known to support it.
+    pub http2_prior_knowledge: bool,
+    // Keeps idle connections through NATs and load balancers that drop silent ones.
+    pub tcp_keepalive: Option<Duration>,
+    // Covers a whole request, from connecting to reading the last of the body.
+    pub request_timeout: Option<Duration>,
+}
+
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+pub struct ReqwestHttpClient {
+    client: reqwest::Client,
+}
+
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+impl ReqwestHttpClient {
+    pub fn new() -> Self {
+        ReqwestHttpClient {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_options(options: &ConnectionOptions) -> Result<Self, HttpError> {
+        let mut builder = reqwest::Client::builder()
+            .tcp_keepalive(options.tcp_keepalive);
+        if let Some(pool_max_idle_per_host) = options.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(pool_max_idle_per_host);
+        }
+        if options.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(request_timeout) = options.request_timeout {
+            builder = builder.timeout(request_timeout);
+        }
+
+        let client = builder.build()
+            .map_err(|err| HttpError(format!("{}", err)))?;
+        Ok(ReqwestHttpClient { client })
+    }
+}
+
+// Says which way a request failed, since "error sending request" alone doesn't help tell a
+// timeout from a dropped connection.
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+fn reqwest_error(err: reqwest::Error) -> HttpError {
+    let kind = if err.is_timeout() {
+        "Timed out"
+    } else if err.is_connect() {
+        "Connection failed"
+    } else if err.is_body() || err.is_decode() {
+        "Response body failed"
+    } else {
+        "Request failed"
+    };
+
+    match std::error::Error::source(&err) {
+        Some(source) => HttpError(format!("{}: {} ({})", kind, err, source)),
+        None => HttpError(format!("{}: {}", kind, err)),
+    }
+}
+
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+impl Default for ReqwestHttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+#[async_trait(?Send)]
+impl HttpClient for ReqwestHttpClient {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .map_err(|err| HttpError(format!("{}", err)))?;
+
+        let mut builder = self.client.request(method, request.url.as_str());
+        for (name, value) in request.headers.iter() {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        if !request.body.is_empty() {
+            builder = builder.body(request.body);
+        }
+
+        let response = builder.send()
+            .await
+            .map_err(reqwest_error)?;
+
+        let status = response.status().as_u16();
+        let url = response.url().to_string();
+        let headers = response.headers()
+            .iter()
+            .filter_map(|(name, value)| value.to_str().ok().map(|value| (name.to_string(), value.to_string())))
+            .collect();
+        let body = response.bytes()
+            .await
+            .map_err(reqwest_error)?
+            .to_vec();
+
+        Ok(HttpResponse {
+            status,
+            url,
+            headers,
+            body,
+        })
+    }
+}
+
+// Uses the browser's fetch, from a window or a worker.
+#[cfg(target_arch = "wasm32")]
+pub struct FetchHttpClient;
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(err: wasm_bindgen::JsValue) -> HttpError {
+    HttpError(format!("{:?}", err))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[async_trait(?Send)]
+impl HttpClient for FetchHttpClient {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+        use wasm_bindgen::JsCast;
+        use wasm_bindgen_futures::JsFuture;
+
+        let headers = web_sys::Headers::new()
+            .map_err(js_error)?;
+        for (name, value) in request.headers.iter() {
+            headers.set(name, value).map_err(js_error)?;
+        }
+
+        let init = web_sys::RequestInit::new();
+        init.set_method(&request.method);
+        init.set_headers(&headers);
+        if !request.body.is_empty() {
+            init.set_body(&js_sys::Uint8Array::from(request.body.as_slice()));
+        }
+        let js_request = web_sys::Request::new_with_str_and_init(&request.url, &init)
+            .map_err(js_error)?;
+
+        let global = js_sys::global();
+        let promise = match global.dyn_ref::<web_sys::Window>() {
+            Some(window) => window.fetch_with_request(&js_request),
+            None => global.unchecked_ref::<web_sys::WorkerGlobalScope>().fetch_with_request(&js_request),
+        };
+        let response: web_sys::Response = JsFuture::from(promise)
+            .await
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+
+        let mut response_headers = Vec::new();
+        if let Some(entries) = js_sys::try_iter(&response.headers()).map_err(js_error)? {
+            for entry in entries {
+                let entry: js_sys::Array = entry.map_err(js_error)?.dyn_into().map_err(js_error)?;
+                if let (Some(name), Some(value)) = (entry.get(0).as_string(), entry.get(1).as_string()) {
+                    response_headers.push((name, value));
+                }
+            }
+        }
+
+        let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?)
+            .await
+            .map_err(js_error)?;
+
+        Ok(HttpResponse {
+            status: response.status(),
+            url: response.url(),
+            headers: response_headers,
+            body: js_sys::Uint8Array::new(&buffer).to_vec(),
+        })
+    }
+}
+
+// Stands in when the SDK was built without an HTTP stack, until one is set.
+pub struct MissingHttpClient;
+
+#[async_trait(?Send)]
+impl HttpClient for MissingHttpClient {
+    async fn send(&self, _request: HttpRequest) -> Result<HttpResponse, HttpError> {
+        Err(HttpError("No HTTP client; enable the reqwest feature or set one with set_http_client".to_string()))
+    }
+}
+
+// fetch in the browser, reqwest elsewhere.
+pub fn default_http_client() -> Arc<dyn HttpClient> {
+    cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            Arc::new(FetchHttpClient)
+        } else if #[cfg(feature = "reqwest")] {
+            Arc::new(ReqwestHttpClient::new())
+        } else {
+            Arc::new(MissingHttpClient)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_http_common;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_http() {
+        test_http_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_http() {
+        test_http_common()
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::Mutex;
+use async_trait::async_trait;
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::{MessageType, SigningAlgorithmSpec};
+use k256::ecdsa;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::pkcs8::DecodePublicKey;
+use sha2::{Digest, Sha256};
+use libtfslite::client::keys::{PublicKey, Signature, SigningError};
+use crate::signing::AsyncSigner;
+use crate::debug::debug_println;
+
+fn signing_error<E: std::fmt::Display>(err: E) -> SigningError {
+    cylinder::SigningError::Internal(format!("{}", err)).into()
+}
+
+// Signs with an asymmetric AWS KMS key of spec ECC_SECG_P256K1, so the private key never leaves
+// KMS. KMS returns DER signatures that may have a high S value, which are converted to the
+// compact low-S form Sawtooth validators accept.
+pub struct KmsSigner {
+    client: aws_sdk_kms::Client,
+    key_id: String,
+    public_key: Mutex<Option<Vec<u8>>>,
+}
+
+impl KmsSigner {
+    // Uses the standard AWS credential and region resolution from the environment.
+    pub async fn new(key_id: &str) -> Self {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest())
+            .await;
+        KmsSigner::from_client(aws_sdk_kms::Client::new(&config), key_id)
+    }
+
+    pub fn from_client(client: aws_sdk_kms::Client, key_id: &str) -> Self {
+        KmsSigner {
+            client,
+            key_id: key_id.to_string(),
+            public_key: Mutex::new(None),
+        }
+    }
+
+    async fn fetch_public_key(&self) -> Result<Vec<u8>, SigningError> {
+        if let Some(public_key) = self.public_key.lock().unwrap().clone() {
+            return Ok(public_key);
+        }
+
+        debug_println!("KMS GetPublicKey {}", crate::debug::redact("key_id", &self.key_id));
+        let response = self.client
+            .get_public_key()
+            .key_id(&self.key_id)
+            .send()
+            .await
+            .map_err(signing_error)?;
+
+        let der = response.public_key()
+            .ok_or_else(|| signing_error("KMS returned no public key"))?;
+        let public_key = k256::PublicKey::from_public_key_der(der.as_ref())
+            .map_err(signing_error)?
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec();
+
+        *self.public_key.lock().unwrap() = Some(public_key.clone());
+        Ok(public_key)
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncSigner for KmsSigner {
+    async fn sign(&self, data: &[u8]) -> Result<Signature, SigningError> {
+        debug_println!("KMS Sign {}", crate::debug::redact("key_id", &self.key_id));
+        let response = self.client
+            .sign()
+            .key_id(&self.key_id)
+            .message(Blob::new(Sha256::digest(data).to_vec()))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(SigningAlgorithmSpec::EcdsaSha256)
+            .send()
+            .await
+            .map_err(signing_error)?;
+
+        let der = response.signature()
+            .ok_or_else(|| signing_error("KMS returned no signature"))?;
+        let signature = ecdsa::Signature::from_der(der.as_ref())
+            .map_err(signing_error)?;
+        let signature = signature.normalize_s().unwrap_or(signature);
+
+        Signature::try_from(hex::encode(signature.to_bytes()).as_str())
+            .map_err(signing_error)
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, SigningError> {
+        let public_key = self.fetch_public_key()
+            .await?;
+
+        Ok(PublicKey::load_from_bytes(&public_key))
+    }
+}
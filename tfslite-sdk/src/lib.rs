@@ -1,15 +1,47 @@
 pub mod client;
 pub mod types;
 pub mod state;
+pub mod state_memory;
 pub mod signing;
+pub mod remote_signer;
+pub mod manifest;
+pub mod transport;
+pub mod dialect;
+pub mod log;
+pub mod chain_state;
+pub mod proof;
+pub mod metrics;
+pub mod gateway_pool;
+pub mod http;
+pub mod request_signing;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod state_redb;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod blocking;
 #[cfg(target_arch = "wasm32")]
 pub mod state_indexeddb;
+#[cfg(target_arch = "wasm32")]
+pub mod state_opfs;
+#[cfg(all(not(target_arch = "wasm32"), feature = "postgres"))]
+pub mod state_postgres;
+#[cfg(all(not(target_arch = "wasm32"), feature = "zmq"))]
+pub mod transport_zmq;
+#[cfg(all(not(target_arch = "wasm32"), feature = "kms"))]
+pub mod kms_signer;
+#[cfg(all(not(target_arch = "wasm32"), feature = "prometheus"))]
+pub mod metrics_prometheus;
+#[cfg(all(not(target_arch = "wasm32"), feature = "watch"))]
+pub mod upload_manager;
+#[cfg(all(not(target_arch = "wasm32"), feature = "config"))]
+pub mod config;
+#[cfg(all(not(target_arch = "wasm32"), feature = "object_store"))]
+pub mod object_store_adapter;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 #[cfg(test)]
 mod tests;
-mod debug;
+pub mod debug;
 #[cfg(test)]
 wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
@@ -0,0 +1,349 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use uuid::Uuid;
+use libtfslite::client::keys::{PublicKey, Signer};
+use libtfslite::client::payload::*;
+use libtfslite::client::transaction::*;
+use libtfslite::common::FILE_CREATE_COST;
+use libtfslite::protos::payload::Payload;
+use libtfslite::types::FileMode;
+use crate::client::{TFSLiteClientError, TFSLiteClientErrorType, DEFAULT_CHUNK_SIZE};
+use crate::dialect::{GatewayDialect, GatewayEndpoint};
+use crate::http::{HttpClient, HttpRequest};
+use crate::state::{LocalStateStore, TransactionId, TransactionStatus, TransactionSubmitId, TransactionUpdate};
+use crate::transport::{check_response, http_error, Transport};
+use crate::debug::debug_println;
+
+const DEFAULT_FLUSH_SIZE: usize = 65536;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+async fn sleep(duration: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::time::sleep(duration).await;
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(duration).await;
+}
+
+fn store_error<E: std::fmt::Debug>(err: E) -> TFSLiteClientError {
+    TFSLiteClientError::new(TFSLiteClientErrorType::StoreError, Some(format!("{:?}", err)))
+}
+
+// Appends to an open, destroyable file. Appends are buffered and written out as FILE_APPEND
+// transactions once the buffer reaches the flush size or the flush interval has elapsed.
+// Transactions are stored locally before they are sent, so a writer reopened with
+// TFSLiteClient::open_log after a crash resends anything that never reached the network.
+// There is no background timer; callers should call tick() periodically.
+pub struct LogWriter {
+    transport: Arc<dyn Transport>,
+    store: Arc<dyn LocalStateStore>,
+
+    signer: Box<dyn Signer>,
+    batcher_public_key: PublicKey,
+    uuid: Uuid,
+    filename: String,
+    created: bool,
+
+    buffer: Vec<u8>,
+    flush_size: usize,
+    flush_interval: Duration,
+    last_flush_at: i64,
+    tx_id_prev: Option<TransactionId>,
+}
+
+impl LogWriter {
+    pub(crate) fn new(transport: Arc<dyn Transport>, store: Arc<dyn LocalStateStore>, signer: &dyn Signer, batcher_public_key: PublicKey, uuid: Uuid, filename: String, created: bool) -> Self {
+        LogWriter {
+            transport,
+            store,
+            signer: signer.clone_box(),
+            batcher_public_key,
+            uuid,
+            filename,
+            created,
+            buffer: Vec::new(),
+            flush_size: DEFAULT_FLUSH_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            last_flush_at: Utc::now().timestamp_millis(),
+            tx_id_prev: None,
+        }
+    }
+
+    pub fn get_uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    pub fn set_flush_size(&mut self, flush_size: usize) {
+        self.flush_size = flush_size;
+    }
+
+    pub fn set_flush_interval(&mut self, flush_interval: Duration) {
+        self.flush_interval = flush_interval;
+    }
+
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub async fn append(&mut self, data: &[u8]) -> Result<(), TFSLiteClientError> {
+        self.buffer.extend_from_slice(data);
+
+        if self.buffer.len() >= self.flush_size {
+            self.flush().await
+        } else {
+            self.tick().await
+        }
+    }
+
+    pub async fn tick(&mut self) -> Result<(), TFSLiteClientError> {
+        let elapsed = Utc::now().timestamp_millis() - self.last_flush_at;
+        if !self.buffer.is_empty() && elapsed >= self.flush_interval.as_millis() as i64 {
+            return self.flush().await;
+        }
+
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<(), TFSLiteClientError> {
+        debug_println!("flush({}, {} bytes)", self.uuid, self.buffer.len());
+
+        if !self.created {
+            let public_key = self.signer.public_key()
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+
+            let payload = PayloadBuilder::new(PayloadOperation::AccountDeposit)
+                .with_address(public_key.as_slice().to_vec())
+                .with_amount(FILE_CREATE_COST*10)
+                .build()
+                .unwrap();
+            self.queue_tx(payload).await?;
+
+            let payload = PayloadBuilder::new(PayloadOperation::FileCreate)
+                .with_uuid(self.uuid)
+                .with_mode(FileMode::Destroyable)
+                .with_filename(self.filename.clone())
+                .build()
+                .unwrap();
+            self.queue_tx(payload).await?;
+
+            self.created = true;
+        }
+
+        let buffer = std::mem::take(&mut self.buffer);
+        for block in buffer.chunks(DEFAULT_CHUNK_SIZE) {
+            let payload = PayloadBuilder::new(PayloadOperation::FileAppend)
+                .with_uuid(self.uuid)
+                .with_block(block.to_vec())
+                .build()
+                .unwrap();
+            self.queue_tx(payload).await?;
+        }
+        self.last_flush_at = Utc::now().timestamp_millis();
+
+        self.send_pending().await
+    }
+
+    // Sends any stored transactions for this log that do not yet have a submit id.
+    pub async fn send_pending(&mut self) -> Result<(), TFSLiteClientError> {
+        let tx_infos = self.store.get_txs(&self.uuid)
+            .await
+            .map_err(store_error)?;
+
+        let pending: Vec<TransactionId> = tx_infos
+            .into_iter()
+            .filter(|tx_info| tx_info.submit_id.is_none())
+            .map(|tx_info| tx_info.tx_id)
+            .collect();
+
+        let max_txs = self.transport.max_transactions_per_submit().max(1);
+        for tx_id_chunk in pending.chunks(max_txs) {
+            let mut txs: Vec<Vec<u8>> = Vec::new();
+            for tx_id in tx_id_chunk {
+                txs.push(self.store.get_tx_bytes(tx_id)
+                    .await
+                    .map_err(store_error)?);
+            }
+
+            let submit_ids = self.transport.submit_transactions(txs)
+                .await?;
+
+            let updates: Vec<TransactionUpdate> = tx_id_chunk
+                .iter()
+                .zip(submit_ids)
+                .map(|(tx_id, submit_id)| (tx_id.clone(), Some(submit_id), None))
+                .collect();
+
+            self.store.update_txs(&updates)
+                .await
+                .map_err(store_error)?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn seal(&mut self) -> Result<(), TFSLiteClientError> {
+        if !self.buffer.is_empty() || !self.created {
+            self.flush().await?;
+        }
+
+        let payload = PayloadBuilder::new(PayloadOperation::FileSeal)
+            .with_uuid(self.uuid)
+            .build()
+            .unwrap();
+        self.queue_tx(payload).await?;
+
+        self.send_pending().await
+    }
+
+    // Flushes the buffer and waits for every queued transaction to commit, then clears the
+    // local queue for this log.
+    pub async fn close(&mut self) -> Result<(), TFSLiteClientError> {
+        if !self.buffer.is_empty() {
+            self.flush().await?;
+        }
+
+        loop {
+            self.send_pending().await?;
+
+            let tx_infos = self.store.get_txs(&self.uuid)
+                .await
+                .map_err(store_error)?;
+
+            let submit_ids: Vec<TransactionSubmitId> = tx_infos
+                .iter()
+                .filter(|tx_info| tx_info.status != TransactionStatus::Committed)
+                .filter_map(|tx_info| tx_info.submit_id.clone())
+                .collect();
+            if submit_ids.is_empty() {
+                break;
+            }
+
+            let statuses = self.transport.get_transaction_statuses(submit_ids)
+                .await?;
+
+            for tx_info in tx_infos.iter() {
+                let mut submit_id = tx_info.submit_id.clone();
+                let status = match submit_id.as_ref().and_then(|submit_id| statuses.get(submit_id)) {
+                    Some(TransactionStatus::Unknown) => TransactionStatus::Local,
+                    Some(status) => status.clone(),
+                    None => continue,
+                };
+
+                // The network lost this transaction, so send it again.
+                if status == TransactionStatus::Local {
+                    let tx_bytes = self.store.get_tx_bytes(&tx_info.tx_id)
+                        .await
+                        .map_err(store_error)?;
+
+                    submit_id = self.transport.submit_transactions(vec![tx_bytes])
+                        .await?
+                        .into_iter()
+                        .next();
+                }
+
+                self.store.update_tx(&tx_info.tx_id, submit_id, Some(status))
+                    .await
+                    .map_err(store_error)?;
+            }
+
+            sleep(DEFAULT_POLL_INTERVAL).await;
+        }
+
+        self.store.flush_txs(&self.uuid)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn recover(&mut self) -> Result<(), TFSLiteClientError> {
+        let tx_infos = self.store.get_txs(&self.uuid)
+            .await
+            .map_err(store_error)?;
+
+        self.tx_id_prev = tx_infos
+            .iter()
+            .max_by_key(|tx_info| tx_info.order)
+            .map(|tx_info| tx_info.tx_id.clone());
+
+        self.send_pending().await
+    }
+
+    async fn queue_tx(&mut self, payload: Payload) -> Result<(), TFSLiteClientError> {
+        let mut builder = TransactionBuilder::new()
+            .with_payload(payload)
+            .with_batcher_public_key(self.batcher_public_key.as_slice().to_vec());
+        if let Some(tx_id_prev) = self.tx_id_prev.take() {
+            builder = builder.with_dependencies(vec![tx_id_prev]);
+        }
+        let tx = builder
+            .build(self.signer.as_ref())
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))?;
+
+        self.store.add_tx(&self.uuid, &tx)
+            .await
+            .map_err(store_error)?;
+
+        self.tx_id_prev = Some(tx.get_header_signature().to_string());
+        Ok(())
+    }
+}
+
+// Follows a log from a byte offset, returning data as it is committed.
+pub struct LogReader {
+    url: String,
+    http: Arc<dyn HttpClient>,
+    dialect: Arc<dyn GatewayDialect>,
+    uuid: Uuid,
+    offset: u64,
+    poll_interval: Duration,
+}
+
+impl LogReader {
+    pub(crate) fn new(url: String, http: Arc<dyn HttpClient>, dialect: Arc<dyn GatewayDialect>, uuid: Uuid, offset: u64) -> Self {
+        LogReader {
+            url,
+            http,
+            dialect,
+            uuid,
+            offset,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    pub fn get_offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    // Returns whatever has been committed past the current offset, which may be empty.
+    pub async fn read(&mut self) -> Result<Vec<u8>, TFSLiteClientError> {
+        let url = self.dialect.url(&self.url, &GatewayEndpoint::FileData(self.uuid.to_string(), self.offset));
+        debug_println!("GET {}", crate::debug::redact("url", &url));
+
+        let response = self.http.send(HttpRequest::get(url))
+            .await
+            .map_err(http_error)?;
+
+        let data = check_response(response)?.body;
+
+        self.offset += data.len() as u64;
+        Ok(data)
+    }
+
+    // Polls until new data is available.
+    pub async fn tail(&mut self) -> Result<Vec<u8>, TFSLiteClientError> {
+        loop {
+            let data = self.read().await?;
+            if !data.is_empty() {
+                return Ok(data);
+            }
+
+            sleep(self.poll_interval).await;
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use chrono::prelude::*;
+use serde::{Serialize, Deserialize};
+use libtfslite::protos::compat::Message;
+use sha2::{Digest, Sha224, Sha512};
+use libtfslite::client::keys::{PublicKey, Signature, Signer, Verifier};
+use libtfslite::client::transaction::TransactionExt;
+use libtfslite::protos::payload::{Payload, Payload_Operation};
+use libtfslite::protos::transaction::Transaction;
+use libtfslite::types::FileRecord;
+use crate::chain_state::BlockDigest;
+use crate::state::{TransactionId, TransactionSubmitId};
+
+#[derive(Debug)]
+pub enum ManifestError {
+    SerializationError(String),
+    SigningError(String),
+    VerificationError(String),
+}
+
+impl Error for ManifestError {}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ManifestError::SerializationError(ref s) => write!(f, "SerializationError: {}", s),
+            ManifestError::SigningError(ref s) => write!(f, "SigningError: {}", s),
+            ManifestError::VerificationError(ref s) => write!(f, "VerificationError: {}", s),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestTransaction {
+    pub tx_id: TransactionId,
+    pub operation: String,
+    pub chunk_sha224: Option<String>,
+    pub submit_id: Option<TransactionSubmitId>,
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UploadManifest {
+    pub uuid: uuid::Uuid,
+    pub filename: Option<String>,
+    pub signer_public_key: String,
+    pub transactions: Vec<ManifestTransaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedUploadManifest {
+    pub manifest: UploadManifest,
+    pub signature: String,
+}
+
+impl UploadManifest {
+    pub fn signing_bytes(&self) -> Result<Vec<u8>, ManifestError> {
+        serde_json::to_vec(self)
+            .map_err(|err| ManifestError::SerializationError(format!("Unable to serialize manifest: {}", err)))
+    }
+
+    pub fn sign(self, signer: &dyn Signer) -> Result<SignedUploadManifest, ManifestError> {
+        let signature = signer
+            .sign(&self.signing_bytes()?)
+            .map_err(|err| ManifestError::SigningError(format!("Unable to sign manifest: {}", err)))?;
+
+        Ok(SignedUploadManifest {
+            manifest: self,
+            signature: signature.as_hex(),
+        })
+    }
+}
+
+impl SignedUploadManifest {
+    pub fn from_json(json: &str) -> Result<Self, ManifestError> {
+        serde_json::from_str(json)
+            .map_err(|err| ManifestError::SerializationError(format!("Unable to parse manifest: {}", err)))
+    }
+
+    pub fn to_json(&self) -> Result<String, ManifestError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| ManifestError::SerializationError(format!("Unable to serialize manifest: {}", err)))
+    }
+
+    pub fn verify(&self) -> Result<(), ManifestError> {
+        let public_key = PublicKey::load_from_hex(self.manifest.signer_public_key.as_str())
+            .map_err(|err| ManifestError::VerificationError(format!("Unable to load signer public key: {}", err)))?;
+
+        let signature = Signature::try_from(self.signature.as_str())
+            .map_err(|err| ManifestError::VerificationError(format!("Unable to load signature: {}", err)))?;
+
+        let manifest_bytes = serde_json::to_vec(&self.manifest)
+            .map_err(|err| ManifestError::SerializationError(format!("Unable to serialize manifest: {}", err)))?;
+
+        let verified = public_key.verify(&manifest_bytes, &signature)
+            .map_err(|err| ManifestError::VerificationError(format!("Error during signature verification: {}", err)))?;
+
+        if !verified {
+            return Err(ManifestError::VerificationError("Manifest signature is invalid".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+// Checks downloaded content against a signed FILE_MANIFEST transaction and returns the
+// public key of the signer, which callers should compare against the expected file owner.
+pub fn verify_manifest_transaction(tx_bytes: &[u8], content: &[u8]) -> Result<String, ManifestError> {
+    let tx = Transaction::parse_from_bytes(tx_bytes)
+        .map_err(|err| ManifestError::SerializationError(format!("Unable to parse transaction: {}", err)))?;
+
+    tx.validate()
+        .map_err(|err| ManifestError::VerificationError(format!("{}", err)))?;
+
+    let header = tx.header()
+        .map_err(|err| ManifestError::SerializationError(format!("{}", err)))?;
+
+    let payload = Payload::parse_from_bytes(tx.get_payload())
+        .map_err(|err| ManifestError::SerializationError(format!("Unable to parse payload: {}", err)))?;
+
+    if payload.get_operation() != Payload_Operation::FILE_MANIFEST {
+        return Err(ManifestError::VerificationError("Transaction is not a FILE_MANIFEST transaction".to_string()));
+    }
+
+    let manifest = payload.get_manifest();
+    if manifest.get_size() != content.len() as u64 {
+        return Err(ManifestError::VerificationError("Content size does not match manifest".to_string()));
+    }
+
+    if Sha512::digest(content).as_slice() != manifest.get_sha512() {
+        return Err(ManifestError::VerificationError("Content hash does not match manifest".to_string()));
+    }
+
+    Ok(header.get_signer_public_key().to_string())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockVerification {
+    pub number: u64,
+    // Of the block as recorded on chain.
+    pub sha224: String,
+    pub matches: bool,
+}
+
+// What a download was checked against, for handing to an auditor once signed. verified is
+// set only when the size, the manifest's sha512 (if the file has a manifest) and every
+// block's sha224 all match, and the blocks account for all of the content.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DownloadVerificationReport {
+    pub file_id: uuid::Uuid,
+    pub file_name: String,
+    pub owner: String,
+    pub size: u64,
+    pub sha512: String,
+    pub manifest_sha512: Option<String>,
+    pub blocks: Vec<BlockVerification>,
+    pub verified: bool,
+    pub verified_at: DateTime<Utc>,
+    // Filled in by sign().
+    pub verifier_public_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedVerificationReport {
+    pub report: DownloadVerificationReport,
+    pub signature: String,
+}
+
+// Checks downloaded content against the file's record and the blocks found in its state.
+pub fn verify_download(file: &FileRecord, content: &[u8], blocks: &[BlockDigest]) -> DownloadVerificationReport {
+    let sha512 = Sha512::digest(content).to_vec();
+    let manifest_sha512 = file.manifest.as_ref().map(|manifest| manifest.sha512.clone());
+
+    let mut offset: usize = 0;
+    let block_results: Vec<BlockVerification> = blocks
+        .iter()
+        .map(|block| {
+            let end = offset.saturating_add(block.size as usize);
+            let matches = content.get(offset..end)
+                .is_some_and(|data| Sha224::digest(data).as_slice() == block.sha224.as_slice());
+            offset = end;
+            BlockVerification {
+                number: block.number,
+                sha224: hex::encode(&block.sha224),
+                matches,
+            }
+        })
+        .collect();
+
+    let verified = content.len() as u64 == file.size
+        && offset == content.len()
+        && blocks.len() as u64 == file.block_count
+        && manifest_sha512.iter().all(|manifest_sha512| *manifest_sha512 == sha512)
+        && block_results.iter().all(|block| block.matches);
+
+    DownloadVerificationReport {
+        file_id: file.file_id,
+        file_name: file.file_name.clone(),
+        owner: hex::encode(&file.owner),
+        size: content.len() as u64,
+        sha512: hex::encode(sha512),
+        manifest_sha512: manifest_sha512.map(hex::encode),
+        blocks: block_results,
+        verified,
+        verified_at: Utc::now(),
+        verifier_public_key: String::new(),
+    }
+}
+
+impl DownloadVerificationReport {
+    pub fn signing_bytes(&self) -> Result<Vec<u8>, ManifestError> {
+        serde_json::to_vec(self)
+            .map_err(|err| ManifestError::SerializationError(format!("Unable to serialize report: {}", err)))
+    }
+
+    pub fn sign(mut self, signer: &dyn Signer) -> Result<SignedVerificationReport, ManifestError> {
+        self.verifier_public_key = signer
+            .public_key()
+            .map_err(|err| ManifestError::SigningError(format!("Unable to load signer public key: {}", err)))?
+            .as_hex();
+
+        let signature = signer
+            .sign(&self.signing_bytes()?)
+            .map_err(|err| ManifestError::SigningError(format!("Unable to sign report: {}", err)))?;
+
+        Ok(SignedVerificationReport {
+            report: self,
+            signature: signature.as_hex(),
+        })
+    }
+}
+
+impl SignedVerificationReport {
+    pub fn from_json(json: &str) -> Result<Self, ManifestError> {
+        serde_json::from_str(json)
+            .map_err(|err| ManifestError::SerializationError(format!("Unable to parse report: {}", err)))
+    }
+
+    pub fn to_json(&self) -> Result<String, ManifestError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| ManifestError::SerializationError(format!("Unable to serialize report: {}", err)))
+    }
+
+    // Checks the signature only; whether the download matched is in report.verified.
+    pub fn verify(&self) -> Result<(), ManifestError> {
+        let public_key = PublicKey::load_from_hex(self.report.verifier_public_key.as_str())
+            .map_err(|err| ManifestError::VerificationError(format!("Unable to load verifier public key: {}", err)))?;
+
+        let signature = Signature::try_from(self.signature.as_str())
+            .map_err(|err| ManifestError::VerificationError(format!("Unable to load signature: {}", err)))?;
+
+        let verified = public_key.verify(&self.report.signing_bytes()?, &signature)
+            .map_err(|err| ManifestError::VerificationError(format!("Error during signature verification: {}", err)))?;
+
+        if !verified {
+            return Err(ManifestError::VerificationError("Report signature is invalid".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_download_verification_common, test_manifest_common};
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_manifest() {
+        test_manifest_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_manifest() {
+        test_manifest_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_download_verification() {
+        test_download_verification_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_download_verification() {
+        test_download_verification_common()
+    }
+}
@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+// Receives upload throughput measurements from FileUpload. Every method does nothing by default,
+// so a sink only implements what it records.
+pub trait MetricsSink {
+    // Payload bytes of the transactions signed while preparing an upload.
+    fn bytes_signed(&self, _bytes: u64) {}
+
+    // Transaction bytes sent to the gateway, resubmissions included.
+    fn bytes_submitted(&self, _bytes: u64) {}
+
+    fn txs_committed(&self, _count: u64) {}
+
+    // Transactions sent again after the network lost them.
+    fn retries(&self, _count: u64) {}
+
+    // Time taken by one submit call to the transport.
+    fn submit_latency(&self, _latency: Duration) {}
+}
+
+pub struct NoopMetricsSink;
+
+impl MetricsSink for NoopMetricsSink {}
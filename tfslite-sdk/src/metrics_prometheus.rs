@@ -0,0 +1,81 @@
+use std::time::Duration;
+use prometheus::{Histogram, HistogramOpts, IntCounter, Registry};
+
+use crate::metrics::MetricsSink;
+
+pub struct PrometheusMetricsSink {
+    bytes_signed: IntCounter,
+    bytes_submitted: IntCounter,
+    txs_committed: IntCounter,
+    retries: IntCounter,
+    submit_latency: Histogram,
+}
+
+impl PrometheusMetricsSink {
+    // Registers the tfslite_* metrics with registry, which the caller exposes for scraping.
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let sink = PrometheusMetricsSink {
+            bytes_signed: IntCounter::new("tfslite_bytes_signed_total", "Payload bytes of transactions signed")?,
+            bytes_submitted: IntCounter::new("tfslite_bytes_submitted_total", "Transaction bytes submitted to the gateway")?,
+            txs_committed: IntCounter::new("tfslite_txs_committed_total", "Transactions committed")?,
+            retries: IntCounter::new("tfslite_retries_total", "Transactions resubmitted after being lost")?,
+            submit_latency: Histogram::with_opts(HistogramOpts::new("tfslite_submit_latency_seconds", "Latency of transaction submits"))?,
+        };
+
+        registry.register(Box::new(sink.bytes_signed.clone()))?;
+        registry.register(Box::new(sink.bytes_submitted.clone()))?;
+        registry.register(Box::new(sink.txs_committed.clone()))?;
+        registry.register(Box::new(sink.retries.clone()))?;
+        registry.register(Box::new(sink.submit_latency.clone()))?;
+
+        Ok(sink)
+    }
+}
+
+impl MetricsSink for PrometheusMetricsSink {
+    fn bytes_signed(&self, bytes: u64) {
+        self.bytes_signed.inc_by(bytes);
+    }
+
+    fn bytes_submitted(&self, bytes: u64) {
+        self.bytes_submitted.inc_by(bytes);
+    }
+
+    fn txs_committed(&self, count: u64) {
+        self.txs_committed.inc_by(count);
+    }
+
+    fn retries(&self, count: u64) {
+        self.retries.inc_by(count);
+    }
+
+    fn submit_latency(&self, latency: Duration) {
+        self.submit_latency.observe(latency.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use prometheus::Registry;
+    use crate::metrics::MetricsSink;
+    use crate::metrics_prometheus::PrometheusMetricsSink;
+
+    #[test]
+    fn test_prometheus_metrics_sink() {
+        let registry = Registry::new();
+        let sink = PrometheusMetricsSink::new(&registry).unwrap();
+
+        sink.bytes_signed(100);
+        sink.bytes_signed(28);
+        sink.submit_latency(Duration::from_millis(250));
+
+        let families = registry.gather();
+        let bytes_signed = families.iter().find(|family| family.get_name() == "tfslite_bytes_signed_total").unwrap();
+        assert_eq!(bytes_signed.get_metric()[0].get_counter().get_value(), 128.0);
+        let submit_latency = families.iter().find(|family| family.get_name() == "tfslite_submit_latency_seconds").unwrap();
+        assert_eq!(submit_latency.get_metric()[0].get_histogram().get_sample_count(), 1);
+
+        PrometheusMetricsSink::new(&registry).expect_err("Metrics should only register once per registry");
+    }
+}
@@ -0,0 +1,338 @@
+// An object_store backend on TFS, so Arrow and Parquet tooling can read and write it directly. A
+// put uploads and seals a file named after the path, a get reads back the newest sealed file of
+// that name, and a list walks the account's files. Files can't be changed once sealed, so a put
+// to an existing path adds a newer file rather than replacing it.
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::mpsc;
+use std::thread;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use object_store::path::Path;
+use object_store::{Error, GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta, ObjectStore, PutMode, PutOptions, PutResult, Result};
+use tokio::io::AsyncWrite;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+use libtfslite::types::{FileMode, FileRecord, FileState};
+use crate::blocking;
+use crate::client::TFSLiteClientError;
+
+const STORE_NAME: &str = "TFS";
+
+impl From<TFSLiteClientError> for Error {
+    fn from(err: TFSLiteClientError) -> Self {
+        Error::Generic { store: STORE_NAME, source: Box::new(err) }
+    }
+}
+
+fn closed_error() -> Error {
+    Error::Generic { store: STORE_NAME, source: "Client thread has shut down".into() }
+}
+
+fn not_found(location: &Path) -> Error {
+    Error::NotFound { path: location.to_string(), source: format!("No sealed file named {}", location).into() }
+}
+
+// Timestamps are set by a timestamp authority and may be missing, in which case the epoch is used.
+fn last_modified(file: &FileRecord) -> DateTime<Utc> {
+    let timestamp = file.timestamp_seal.max(file.timestamp_append).max(file.timestamp_create);
+    DateTime::from_timestamp(timestamp, 0).unwrap_or_default()
+}
+
+fn object_meta(file: &FileRecord) -> ObjectMeta {
+    ObjectMeta {
+        location: Path::from(file.file_name.as_str()),
+        last_modified: last_modified(file),
+        size: file.size as usize,
+        e_tag: Some(file.file_id.to_string()),
+        version: Some(file.file_id.to_string()),
+    }
+}
+
+// The newest sealed file of each name.
+fn sealed_files(client: &blocking::TFSLiteClient) -> Result<HashMap<String, FileRecord>, TFSLiteClientError> {
+    let mut files: HashMap<String, FileRecord> = HashMap::new();
+    for file in client.block_on(client.get_account_files_by_tags(&[]))? {
+        if file.state != FileState::Sealed {
+            continue;
+        }
+        match files.get(&file.file_name) {
+            Some(newest) if last_modified(newest) >= last_modified(&file) => {},
+            _ => {
+                files.insert(file.file_name.clone(), file);
+            },
+        }
+    }
+
+    Ok(files)
+}
+
+fn read_file(client: &blocking::TFSLiteClient, file: &FileRecord) -> Result<Vec<u8>, TFSLiteClientError> {
+    let mut reader = client.open_log_reader(file.file_id, 0);
+    let mut data: Vec<u8> = Vec::with_capacity(file.size as usize);
+    while (data.len() as u64) < file.size {
+        let chunk = client.block_on(reader.read())?;
+        if chunk.is_empty() {
+            break;
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok(data)
+}
+
+// Uploads read from disk, so the object is staged in the temp directory first.
+fn upload(client: &blocking::TFSLiteClient, location: &Path, bytes: &[u8], mode: FileMode) -> Result<Uuid, TFSLiteClientError> {
+    use crate::client::TFSLiteClientErrorType;
+
+    let staging = std::env::temp_dir().join(format!("tfslite-object-{}", Uuid::new_v4()));
+    std::fs::write(&staging, bytes)
+        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("{}", err))))?;
+
+    let result = client.upload_file(&staging).and_then(|mut upload| {
+        upload.set_filename(location.as_ref());
+        upload.set_mode(mode)?;
+        upload.upload()?;
+        Ok(upload.uuid())
+    });
+    let _ = std::fs::remove_file(&staging);
+
+    result
+}
+
+type Job = Box<dyn FnOnce(&mut blocking::TFSLiteClient) + Send>;
+
+// The SDK's futures aren't Send, as ObjectStore requires, so the client lives on a thread of its
+// own and calls are queued to it.
+pub struct TfsObjectStore {
+    jobs: mpsc::Sender<Job>,
+    mode: FileMode,
+}
+
+impl TfsObjectStore {
+    // connect runs on the client's thread, and should return a client with a default signer and
+    // account set.
+    pub fn new<F>(connect: F) -> Result<Self, TFSLiteClientError>
+    where
+        F: FnOnce() -> Result<blocking::TFSLiteClient, TFSLiteClientError> + Send + 'static,
+    {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (ready, ready_receiver) = mpsc::channel::<Result<(), TFSLiteClientError>>();
+
+        thread::spawn(move || {
+            let mut client = match connect() {
+                Ok(client) => client,
+                Err(err) => {
+                    let _ = ready.send(Err(err));
+                    return;
+                },
+            };
+            let _ = ready.send(Ok(()));
+
+            while let Ok(job) = job_receiver.recv() {
+                job(&mut client);
+            }
+        });
+
+        ready_receiver.recv()
+            .map_err(|_| TFSLiteClientError::new(crate::client::TFSLiteClientErrorType::InvalidConfig, Some("Client thread has shut down".to_string())))??;
+
+        Ok(TfsObjectStore {
+            jobs,
+            mode: FileMode::Immutable,
+        })
+    }
+
+    // Objects are immutable by default; destroyable ones can be deleted.
+    pub fn with_mode(mut self, mode: FileMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    async fn run<T, F>(&self, job: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut blocking::TFSLiteClient) -> Result<T> + Send + 'static,
+    {
+        let (reply, receiver) = oneshot::channel();
+        self.jobs.send(Box::new(move |client| {
+            let _ = reply.send(job(client));
+        }))
+        .map_err(|_| closed_error())?;
+
+        receiver.await.map_err(|_| closed_error())?
+    }
+
+    async fn list_objects(&self, prefix: Option<Path>) -> Result<Vec<ObjectMeta>> {
+        self.run(move |client| {
+            let mut objects: Vec<ObjectMeta> = sealed_files(client)?
+                .values()
+                .map(object_meta)
+                .filter(|meta| prefix.as_ref().map_or(true, |prefix| meta.location.prefix_matches(prefix)))
+                .collect();
+            objects.sort_by(|a, b| a.location.cmp(&b.location));
+
+            Ok(objects)
+        }).await
+    }
+}
+
+impl Display for TfsObjectStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TfsObjectStore")
+    }
+}
+
+impl Debug for TfsObjectStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TfsObjectStore")
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl ObjectStore for TfsObjectStore {
+    async fn put_opts(&self, location: &Path, bytes: Bytes, opts: PutOptions) -> Result<PutResult> {
+        let create = match opts.mode {
+            PutMode::Overwrite => false,
+            PutMode::Create => true,
+            // There is nothing to update in place.
+            PutMode::Update(_) => return Err(Error::NotImplemented),
+        };
+
+        let location = location.clone();
+        let mode = self.mode;
+        self.run(move |client| {
+            if create && sealed_files(client)?.contains_key(location.as_ref()) {
+                return Err(Error::AlreadyExists { path: location.to_string(), source: "A sealed file has this name".into() });
+            }
+
+            let uuid = upload(client, &location, &bytes, mode)?;
+            Ok(PutResult {
+                e_tag: Some(uuid.to_string()),
+                version: Some(uuid.to_string()),
+            })
+        }).await
+    }
+
+    // Files are uploaded whole.
+    async fn put_multipart(&self, _location: &Path) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        Err(Error::NotImplemented)
+    }
+
+    async fn abort_multipart(&self, _location: &Path, _multipart_id: &MultipartId) -> Result<()> {
+        Err(Error::NotImplemented)
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        let location = location.clone();
+        self.run(move |client| {
+            let file = sealed_files(client)?
+                .remove(location.as_ref())
+                .ok_or_else(|| not_found(&location))?;
+            let meta = object_meta(&file);
+            options.check_preconditions(&meta)?;
+
+            if options.head {
+                return Ok(GetResult {
+                    payload: GetResultPayload::Stream(stream::empty().boxed()),
+                    range: 0..meta.size,
+                    meta,
+                });
+            }
+
+            let data = Bytes::from(read_file(client, &file)?);
+            let range = options.range.clone().unwrap_or(0..data.len());
+            if range.start > range.end || range.end > data.len() {
+                return Err(Error::Generic { store: STORE_NAME, source: format!("Range {:?} is outside {} bytes", range, data.len()).into() });
+            }
+
+            let body = data.slice(range.clone());
+            Ok(GetResult {
+                payload: GetResultPayload::Stream(stream::once(async move { Ok(body) }).boxed()),
+                range,
+                meta,
+            })
+        }).await
+    }
+
+    // Destroys every file of the name, so older versions don't show through. Only works for
+    // destroyable files.
+    async fn delete(&self, location: &Path) -> Result<()> {
+        let location = location.clone();
+        self.run(move |client| {
+            let files: Vec<FileRecord> = client.block_on(client.get_account_files_by_tags(&[]))?
+                .into_iter()
+                .filter(|file| file.state == FileState::Sealed && file.file_name == location.as_ref())
+                .collect();
+            if files.is_empty() {
+                return Err(not_found(&location));
+            }
+
+            for file in files {
+                client.block_on(client.destroy_file(file.file_id))?;
+            }
+
+            Ok(())
+        }).await
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, Result<ObjectMeta>> {
+        let prefix = prefix.cloned();
+        stream::once(self.list_objects(prefix))
+            .flat_map(|result| {
+                let objects: Vec<Result<ObjectMeta>> = match result {
+                    Ok(objects) => objects.into_iter().map(Ok).collect(),
+                    Err(err) => vec![Err(err)],
+                };
+                stream::iter(objects)
+            })
+            .boxed()
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        let depth = prefix.map_or(0, |prefix| prefix.parts().count());
+
+        let mut common_prefixes: BTreeSet<Path> = BTreeSet::new();
+        let mut objects: Vec<ObjectMeta> = Vec::new();
+        for meta in self.list_objects(prefix.cloned()).await? {
+            let parts: Vec<_> = meta.location.parts().collect();
+            if parts.len() > depth + 1 {
+                common_prefixes.insert(parts[..=depth].iter().cloned().collect());
+            } else {
+                objects.push(meta);
+            }
+        }
+
+        Ok(ListResult {
+            common_prefixes: common_prefixes.into_iter().collect(),
+            objects,
+        })
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        let bytes = self.get(from).await?.bytes().await?;
+        self.put(to, bytes).await?;
+        Ok(())
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        let bytes = self.get(from).await?.bytes().await?;
+        self.put_opts(to, bytes, PutOptions::from(PutMode::Create)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object_store::Error;
+    use crate::tests::test_object_store_common;
+
+    #[tokio::test]
+    async fn test_object_store() -> Result<(), Error> {
+        test_object_store_common().await
+    }
+}
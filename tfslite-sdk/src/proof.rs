@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use ciborium::value::Value;
+use sha2::{Digest, Sha512};
+use libtfslite::types::FileRecord;
+
+#[derive(Debug)]
+pub enum ProofError {
+    DecodeError(String),
+    HashMismatch(String),
+    MissingNode(String),
+}
+
+impl Error for ProofError {}
+
+impl Display for ProofError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            ProofError::DecodeError(ref s) => write!(f, "DecodeError: {}", s),
+            ProofError::HashMismatch(ref s) => write!(f, "HashMismatch: {}", s),
+            ProofError::MissingNode(ref s) => write!(f, "MissingNode: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileCommitment {
+    pub file: FileRecord,
+    pub block_id: String,
+    pub state_root: String,
+}
+
+// Sawtooth's merkle-radix trie stores each node as a CBOR map of {"c": children, "v": value},
+// with children keyed by the next byte of the address in hex and valued by the child's hash.
+pub fn encode_node(children: &BTreeMap<String, String>, value: Option<&[u8]>) -> Vec<u8> {
+    let children = children
+        .iter()
+        .map(|(token, hash)| (Value::Text(token.clone()), Value::Text(hash.clone())))
+        .collect();
+    let value = match value {
+        Some(value) => Value::Bytes(value.to_vec()),
+        None => Value::Null,
+    };
+    let node = Value::Map(vec![
+        (Value::Text(String::from("c")), Value::Map(children)),
+        (Value::Text(String::from("v")), value),
+    ]);
+
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(&node, &mut bytes).unwrap();
+    bytes
+}
+
+pub fn hash_node(node: &[u8]) -> String {
+    hex::encode(Sha512::digest(node))
+}
+
+fn decode_node(node: &[u8]) -> Result<(BTreeMap<String, String>, Option<Vec<u8>>), ProofError> {
+    let value: Value = ciborium::de::from_reader(node)
+        .map_err(|err| ProofError::DecodeError(format!("{}", err)))?;
+
+    let entries = match value {
+        Value::Map(entries) => entries,
+        _ => return Err(ProofError::DecodeError("Node is not a map".to_string())),
+    };
+
+    let mut children: BTreeMap<String, String> = BTreeMap::new();
+    let mut data: Option<Vec<u8>> = None;
+    for (key, value) in entries {
+        match (key.as_text(), value) {
+            (Some("c"), Value::Map(child_entries)) => {
+                for (token, hash) in child_entries {
+                    match (token, hash) {
+                        (Value::Text(token), Value::Text(hash)) => {
+                            children.insert(token, hash);
+                        },
+                        _ => return Err(ProofError::DecodeError("Invalid child entry".to_string())),
+                    }
+                }
+            },
+            (Some("v"), Value::Bytes(bytes)) => {
+                data = Some(bytes);
+            },
+            (Some("v"), Value::Null) => {},
+            _ => return Err(ProofError::DecodeError("Unexpected node field".to_string())),
+        }
+    }
+
+    Ok((children, data))
+}
+
+// Walks the nodes from the root down to the leaf for `address`, checking each node against the
+// hash recorded by its parent, and returns the value stored at the leaf.
+pub fn verify_merkle_proof(state_root: &str, address: &str, nodes: &[Vec<u8>]) -> Result<Vec<u8>, ProofError> {
+    let depth = address.len() / 2;
+    if nodes.len() != depth + 1 {
+        return Err(ProofError::MissingNode(format!("Expected {} nodes, got {}", depth + 1, nodes.len())));
+    }
+
+    let mut expected_hash = state_root.to_string();
+    for (level, node) in nodes.iter().enumerate() {
+        let node_hash = hash_node(node);
+        if node_hash != expected_hash {
+            return Err(ProofError::HashMismatch(format!("Node at depth {} does not match its parent", level)));
+        }
+
+        let (children, data) = decode_node(node)?;
+        if level == depth {
+            return data.ok_or_else(|| ProofError::MissingNode("Leaf node has no value".to_string()));
+        }
+
+        let token = &address[level * 2..level * 2 + 2];
+        expected_hash = children
+            .get(token)
+            .cloned()
+            .ok_or_else(|| ProofError::MissingNode(format!("No child {} at depth {}", token, level)))?;
+    }
+
+    Err(ProofError::MissingNode("Proof ended before the leaf".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::test_proof_common;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_proof() {
+        test_proof_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_proof() {
+        test_proof_common()
+    }
+}
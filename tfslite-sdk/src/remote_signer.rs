@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use libtfslite::client::keys::{PublicKey, Signature, SigningError, Verifier};
+use crate::http::{default_http_client, HttpClient, HttpRequest};
+use crate::signing::AsyncSigner;
+use crate::debug::debug_println;
+
+const TIMESTAMP_HEADER: &str = "X-TFS-Timestamp";
+const SIGNATURE_HEADER: &str = "X-TFS-Signature";
+
+#[derive(Serialize)]
+struct SignRequest<'a> {
+    key_id: Option<&'a str>,
+    digest: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct PublicKeyResponse {
+    public_key: String,
+}
+
+fn signing_error<E: std::fmt::Display>(err: E) -> SigningError {
+    cylinder::SigningError::Internal(format!("{}", err)).into()
+}
+
+// Signs through a signing service so the private key never leaves it. The service receives the
+// SHA-256 digest of the data (the digest secp256k1 signs) at POST {url}/sign and replies with
+// the hex signature; the key is fetched once from GET {url}/public_key. Requests can carry a
+// bearer token and an HMAC-SHA256 over "{timestamp}\n{body}" keyed with a shared secret.
+pub struct RemoteSigner {
+    url: String,
+    http: Arc<dyn HttpClient>,
+    key_id: Option<String>,
+    auth_token: Option<String>,
+    hmac_secret: Option<Vec<u8>>,
+    public_key: Mutex<Option<String>>,
+}
+
+impl RemoteSigner {
+    pub fn new(url: String) -> Self {
+        RemoteSigner {
+            url,
+            http: default_http_client(),
+            key_id: None,
+            auth_token: None,
+            hmac_secret: None,
+            public_key: Mutex::new(None),
+        }
+    }
+
+    pub fn with_key_id(mut self, key_id: &str) -> Self {
+        self.key_id = Some(key_id.to_string());
+        self
+    }
+
+    pub fn with_auth_token(mut self, auth_token: &str) -> Self {
+        self.auth_token = Some(auth_token.to_string());
+        self
+    }
+
+    pub fn with_hmac_secret(mut self, hmac_secret: Vec<u8>) -> Self {
+        self.hmac_secret = Some(hmac_secret);
+        self
+    }
+
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    fn authorize(&self, request: HttpRequest) -> Result<HttpRequest, SigningError> {
+        let mut request = request;
+
+        if let Some(auth_token) = &self.auth_token {
+            request = request.with_header("Authorization", &format!("Bearer {}", auth_token));
+        }
+
+        if let Some(hmac_secret) = &self.hmac_secret {
+            let timestamp = Utc::now().timestamp().to_string();
+            let mut mac = Hmac::<Sha256>::new_from_slice(hmac_secret)
+                .map_err(signing_error)?;
+            mac.update(timestamp.as_bytes());
+            mac.update(b"\n");
+            mac.update(&request.body);
+
+            request = request
+                .with_header(TIMESTAMP_HEADER, &timestamp)
+                .with_header(SIGNATURE_HEADER, &hex::encode(mac.finalize().into_bytes()));
+        }
+
+        Ok(request)
+    }
+
+    async fn send<T: for<'de> Deserialize<'de>>(&self, request: HttpRequest) -> Result<T, SigningError> {
+        let response = self.http
+            .send(self.authorize(request)?)
+            .await
+            .map_err(signing_error)?;
+
+        if !response.is_success() {
+            return Err(signing_error(format!("Signing service returned {}", response.status)));
+        }
+
+        response
+            .json::<T>()
+            .map_err(signing_error)
+    }
+
+    async fn fetch_public_key(&self) -> Result<String, SigningError> {
+        if let Some(public_key) = self.public_key.lock().unwrap().clone() {
+            return Ok(public_key);
+        }
+
+        let mut request = HttpRequest::get(format!("{}/public_key", self.url));
+        if let Some(key_id) = &self.key_id {
+            request = request.with_query("key_id", key_id);
+        }
+        debug_println!("GET {}/public_key", crate::debug::redact("url", &self.url));
+
+        let response: PublicKeyResponse = self.send(request)
+            .await?;
+
+        // Make sure the reply is a usable key before caching it.
+        PublicKey::load_from_hex(&response.public_key)
+            .map_err(signing_error)?;
+        *self.public_key.lock().unwrap() = Some(response.public_key.clone());
+
+        Ok(response.public_key)
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncSigner for RemoteSigner {
+    async fn sign(&self, data: &[u8]) -> Result<Signature, SigningError> {
+        let request = HttpRequest::post(format!("{}/sign", self.url))
+            .with_json(&SignRequest {
+                key_id: self.key_id.as_deref(),
+                digest: hex::encode(Sha256::digest(data)),
+            })
+            .map_err(signing_error)?;
+        debug_println!("POST {}/sign", crate::debug::redact("url", &self.url));
+
+        let response: SignResponse = self.send(request)
+            .await?;
+
+        let signature = Signature::try_from(response.signature.as_str())
+            .map_err(signing_error)?;
+
+        // A misconfigured key id would otherwise only surface when the validator rejects the
+        // transaction.
+        let public_key = self.public_key().await?;
+        let verified = public_key.verify(data, &signature)
+            .map_err(signing_error)?;
+        if !verified {
+            return Err(signing_error("Signing service returned a signature for a different key"));
+        }
+
+        Ok(signature)
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, SigningError> {
+        let public_key = self.fetch_public_key()
+            .await?;
+
+        PublicKey::load_from_hex(&public_key)
+            .map_err(signing_error)
+    }
+}
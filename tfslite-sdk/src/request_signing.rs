@@ -0,0 +1,206 @@
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use libtfslite::client::keys::{PublicKey, Signature, Signer, Verifier};
+use crate::client::{TFSLiteClientError, TFSLiteClientErrorType};
+use crate::dialect::GatewayEndpoint;
+use crate::http::HttpRequest;
+
+pub const SIGNATURE_HEADER: &str = "X-TFS-Signature";
+pub const RESPONSE_SIGNATURE_HEADER: &str = "X-TFS-Response-Signature";
+// How long a signed request stays valid by default.
+const DEFAULT_VALIDITY_SECS: i64 = 60;
+
+// Signs gateway requests with an account key, so the gateway can tell who is calling without
+// separate credentials. The X-TFS-Signature header carries the public key, timestamp, expiry,
+// a random nonce and a signature over
+//
+//   {method}\n{path and query}\n{hex sha256 of body}\n{timestamp}\n{expires}\n{nonce}
+//
+// A gateway rejects expired requests and remembers nonces until they expire to refuse replays.
+pub struct RequestSigner {
+    signer: Box<dyn Signer>,
+    public_key: PublicKey,
+    validity_secs: i64,
+}
+
+// The parts of a verified X-TFS-Signature header the gateway needs for replay protection.
+pub struct SignedRequest {
+    pub public_key: PublicKey,
+    pub timestamp: i64,
+    pub expires: i64,
+    pub nonce: String,
+}
+
+fn signing_error<E: std::fmt::Display>(err: E) -> TFSLiteClientError {
+    TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err)))
+}
+
+fn signed_message(method: &str, path: &str, body: &[u8], timestamp: i64, expires: i64, nonce: &str) -> Vec<u8> {
+    format!("{}\n{}\n{}\n{}\n{}\n{}", method, path, hex::encode(Sha256::digest(body)), timestamp, expires, nonce).into_bytes()
+}
+
+impl RequestSigner {
+    pub fn new(signer: &dyn Signer) -> Result<Self, TFSLiteClientError> {
+        let public_key = signer.public_key()
+            .map_err(signing_error)?;
+
+        Ok(RequestSigner {
+            signer: signer.clone_box(),
+            public_key,
+            validity_secs: DEFAULT_VALIDITY_SECS,
+        })
+    }
+
+    pub fn with_validity_secs(mut self, validity_secs: i64) -> Self {
+        self.validity_secs = validity_secs;
+        self
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    // Each call gets a fresh timestamp and nonce, so a retried request is signed again.
+    pub fn sign(&self, mut request: HttpRequest) -> Result<HttpRequest, TFSLiteClientError> {
+        let path = request.path();
+
+        let timestamp = Utc::now().timestamp();
+        let expires = timestamp + self.validity_secs;
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+        let signature = self.signer.sign(&signed_message(&request.method, &path, &request.body, timestamp, expires, &nonce))
+            .map_err(signing_error)?;
+
+        let header = format!("key={}, timestamp={}, expires={}, nonce={}, signature={}",
+            self.public_key.as_hex(), timestamp, expires, nonce, signature.as_hex());
+        request.set_header(SIGNATURE_HEADER, &header);
+
+        Ok(request)
+    }
+}
+
+// For gateways: checks an X-TFS-Signature header against the request it came with and the
+// gateway's clock. The caller still has to refuse a nonce it has seen before expires.
+pub fn verify_request_signature(header: &str, method: &str, path: &str, body: &[u8], now: i64) -> Result<SignedRequest, String> {
+    let field = |name: &str| -> Result<&str, String> {
+        header.split(',')
+            .filter_map(|part| part.trim().split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| format!("Signature header has no {}", name))
+    };
+    let number = |name: &str| -> Result<i64, String> {
+        field(name)?.parse().map_err(|_| format!("Signature header has an invalid {}", name))
+    };
+
+    let public_key = PublicKey::load_from_hex(field("key")?)
+        .map_err(|err| format!("Invalid key: {}", err))?;
+    let timestamp = number("timestamp")?;
+    let expires = number("expires")?;
+    let nonce = field("nonce")?.to_string();
+    let signature = Signature::try_from(field("signature")?)
+        .map_err(|err| format!("Invalid signature: {}", err))?;
+
+    if expires < timestamp || now > expires {
+        return Err("Signature has expired".to_string());
+    }
+
+    let verified = public_key.verify(&signed_message(method, path, body, timestamp, expires, &nonce), &signature)
+        .map_err(|err| format!("{}", err))?;
+    if !verified {
+        return Err("Signature does not match the request".to_string());
+    }
+
+    Ok(SignedRequest {
+        public_key,
+        timestamp,
+        expires,
+        nonce,
+    })
+}
+
+// Checks responses against a gateway key the client was configured with, for deployments that
+// don't trust the network between them. The gateway signs "{path and query}\n{hex sha256 of
+// body}" and sends the hex signature in X-TFS-Response-Signature; the path ties a response to the
+// account or submit ids it was asked about.
+pub struct ResponseVerifier {
+    public_key: PublicKey,
+}
+
+fn response_message(path: &str, body: &[u8]) -> Vec<u8> {
+    format!("{}\n{}", path, hex::encode(Sha256::digest(body))).into_bytes()
+}
+
+fn untrusted(msg: String) -> TFSLiteClientError {
+    TFSLiteClientError::new(TFSLiteClientErrorType::UntrustedResponse, Some(msg))
+}
+
+impl ResponseVerifier {
+    pub fn new(public_key: PublicKey) -> Self {
+        ResponseVerifier {
+            public_key,
+        }
+    }
+
+    // Balance, file list and status responses are the ones gateways sign.
+    pub fn covers(endpoint: &GatewayEndpoint) -> bool {
+        matches!(endpoint,
+            GatewayEndpoint::AccountBalance(_) |
+            GatewayEndpoint::AccountFiles(_) |
+            GatewayEndpoint::TransactionStatus(_) |
+            GatewayEndpoint::TransactionStatusMultiple |
+            GatewayEndpoint::TransactionStatusWait)
+    }
+
+    pub fn verify(&self, path: &str, body: &[u8], signature: Option<&str>) -> Result<(), TFSLiteClientError> {
+        let signature = signature
+            .ok_or_else(|| untrusted(format!("Response to {} is not signed", path)))?;
+        let signature = Signature::try_from(signature)
+            .map_err(|err| untrusted(format!("Response to {} has an invalid signature: {}", path, err)))?;
+
+        let verified = self.public_key.verify(&response_message(path, body), &signature)
+            .map_err(|err| untrusted(format!("{}", err)))?;
+        if !verified {
+            return Err(untrusted(format!("Response to {} was not signed by the gateway key", path)));
+        }
+
+        Ok(())
+    }
+}
+
+// For gateways: the X-TFS-Response-Signature value for a response to path.
+pub fn sign_response(signer: &dyn Signer, path: &str, body: &[u8]) -> Result<String, TFSLiteClientError> {
+    signer.sign(&response_message(path, body))
+        .map(|signature| signature.as_hex())
+        .map_err(signing_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_request_signing_common, test_response_verification_common};
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_request_signing() {
+        test_request_signing_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_request_signing() {
+        test_request_signing_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_response_verification() {
+        test_response_verification_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_response_verification() {
+        test_response_verification_common()
+    }
+}
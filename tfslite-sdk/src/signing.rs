@@ -1,18 +1,94 @@
 pub use libtfslite::client::keys::{PrivateKey, PublicKey, Signature, SigningError};
 
+use async_trait::async_trait;
+use libtfslite::client::keys::Signer;
+use libtfslite::client::batch::{BatchBuildError, BatchBuilder};
+use libtfslite::client::transaction::{TransactionBuildError, TransactionBuilder};
+use libtfslite::protos::batch::Batch;
+use libtfslite::protos::transaction::Transaction;
+use crate::manifest::{ManifestError, SignedUploadManifest, UploadManifest};
 use cfg_if::cfg_if;
 
 cfg_if! {
     if #[cfg(target_arch = "wasm32")] {
         use wasm_bindgen::prelude::*;
-        use libtfslite::client::keys::Signer;
+        use wasm_bindgen::convert::TryFromJsValue;
+        use wasm_bindgen_futures::{js_sys, JsFuture};
     }
 }
 
+// Signers that may need to wait on something else to sign, such as WebCrypto, a browser
+// extension or a hardware wallet. Every synchronous Signer can be used where one is expected.
+#[async_trait(?Send)]
+pub trait AsyncSigner {
+    async fn sign(&self, data: &[u8]) -> Result<Signature, SigningError>;
+    async fn public_key(&self) -> Result<PublicKey, SigningError>;
+
+    // As Signer::approve_payload.
+    async fn approve_payload(&self, _payload: &[u8]) -> Result<(), SigningError> {
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncSigner for Box<dyn Signer> {
+    async fn sign(&self, data: &[u8]) -> Result<Signature, SigningError> {
+        Signer::sign(self.as_ref(), data)
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, SigningError> {
+        Signer::public_key(self.as_ref())
+    }
+
+    async fn approve_payload(&self, payload: &[u8]) -> Result<(), SigningError> {
+        Signer::approve_payload(self.as_ref(), payload)
+    }
+}
+
+pub async fn build_transaction(builder: TransactionBuilder, signer: &dyn AsyncSigner) -> Result<Transaction, TransactionBuildError> {
+    let unsigned = builder.build_unsigned(&signer.public_key().await?)?;
+    signer.approve_payload(unsigned.payload_bytes()).await?;
+
+    let signature = signer
+        .sign(unsigned.header_bytes())
+        .await
+        .map_err(|err| {
+            TransactionBuildError::SigningError(format!("Unable to sign tx: {}", err))
+        })?;
+
+    Ok(unsigned.finish(&signature))
+}
+
+pub async fn build_batch(builder: BatchBuilder, signer: &dyn AsyncSigner) -> Result<Batch, BatchBuildError> {
+    let unsigned = builder.build_unsigned(&signer.public_key().await?)?;
+
+    let signature = signer
+        .sign(unsigned.header_bytes())
+        .await
+        .map_err(|err| {
+            BatchBuildError::SigningError(format!("Unable to sign batch: {}", err))
+        })?;
+
+    Ok(unsigned.finish(&signature))
+}
+
+pub async fn sign_manifest(manifest: UploadManifest, signer: &dyn AsyncSigner) -> Result<SignedUploadManifest, ManifestError> {
+    let signature = signer
+        .sign(&manifest.signing_bytes()?)
+        .await
+        .map_err(|err| ManifestError::SigningError(format!("Unable to sign manifest: {}", err)))?;
+
+    Ok(SignedUploadManifest {
+        manifest,
+        signature: signature.as_hex(),
+    })
+}
+
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[derive(Debug, Clone)]
+    #[wasm_bindgen(typescript_type = "TFSLiteSigner")]
     pub type JsSigner;
 
     #[wasm_bindgen(structural, method, catch)]
@@ -39,9 +115,44 @@ impl Signer for JsSigner {
     }
 }
 
+// A JS signer whose sign() returns a Promise. public_key() may return either a key or a Promise
+// of one.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[derive(Debug, Clone)]
+    #[wasm_bindgen(typescript_type = "TFSLiteAsyncSigner")]
+    pub type JsAsyncSigner;
+
+    #[wasm_bindgen(structural, method, catch, js_name = sign)]
+    fn sign_promise(this: &JsAsyncSigner, buf: &[u8]) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(structural, method, catch, js_name = public_key)]
+    fn public_key_promise(this: &JsAsyncSigner) -> Result<JsValue, JsValue>;
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn resolve(value: JsValue) -> Result<JsValue, JsValue> {
+    JsFuture::from(js_sys::Promise::resolve(&value)).await
+}
+
+#[cfg(target_arch = "wasm32")]
+#[async_trait(?Send)]
+impl AsyncSigner for JsAsyncSigner {
+    async fn sign(&self, data: &[u8]) -> Result<Signature, SigningError> {
+        let value = resolve(self.sign_promise(data)?).await?;
+        Ok(Signature::try_from_js_value(value)?)
+    }
+
+    async fn public_key(&self) -> Result<PublicKey, SigningError> {
+        let value = resolve(self.public_key_promise()?).await?;
+        Ok(PublicKey::try_from_js_value(value)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::tests::test_signing_common;
+    use crate::tests::{test_account_address_common, test_deterministic_nonce_common, test_multisig_proposal_common, test_offline_signing_common, test_key_encoding_common, test_policy_signer_common, test_signing_common, test_verify_transactions_common};
     #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::wasm_bindgen_test;
 
@@ -56,4 +167,88 @@ mod tests {
     fn test_signing() {
         test_signing_common()
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_policy_signer() {
+        test_policy_signer_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_policy_signer() {
+        test_policy_signer_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_multisig_proposal() {
+        test_multisig_proposal_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_multisig_proposal() {
+        test_multisig_proposal_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_offline_signing() {
+        test_offline_signing_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_offline_signing() {
+        test_offline_signing_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_deterministic_nonce() {
+        test_deterministic_nonce_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_deterministic_nonce() {
+        test_deterministic_nonce_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_key_encoding() {
+        test_key_encoding_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_key_encoding() {
+        test_key_encoding_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_account_address() {
+        test_account_address_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_account_address() {
+        test_account_address_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_verify_transactions() {
+        test_verify_transactions_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_verify_transactions() {
+        test_verify_transactions_common()
+    }
 }
@@ -1,8 +1,14 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use libtfslite::client::upload::UploadTxStatus;
 use libtfslite::protos::transaction::Transaction;
 use async_trait::async_trait;
+use cfg_if::cfg_if;
+use prost::Message;
 use serde::{Serialize, Deserialize};
+use crate::types::{TransactionReceipt, UploadInfo, UploadProgress};
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TransactionStatus {
     Local = 0,
     Queued = 1,
@@ -10,6 +16,8 @@ pub enum TransactionStatus {
     Committed = 3,
     Unknown = 4,
     InvalidStatus = 5,
+    // Given up on after the resubmission policy ran out; kept in the store for inspection.
+    FailedToCommit = 6,
 }
 
 impl From<TransactionStatus> for String {
@@ -21,6 +29,7 @@ impl From<TransactionStatus> for String {
             TransactionStatus::Committed => String::from("COMMITTED"),
             TransactionStatus::Unknown => String::from("UNKNOWN"),
             TransactionStatus::InvalidStatus => String::from("INVALID_STATUS"),
+            TransactionStatus::FailedToCommit => String::from("FAILED_TO_COMMIT"),
         }
     }
 }
@@ -34,14 +43,42 @@ impl From<String> for TransactionStatus {
             "COMMITTED" => TransactionStatus::Committed,
             "UNKNOWN" => TransactionStatus::Unknown,
             "INVALID_STATUS" => TransactionStatus::InvalidStatus,
+            "FAILED_TO_COMMIT" => TransactionStatus::FailedToCommit,
             &_ => TransactionStatus::InvalidStatus,
         }
 
     }
 }
 
+// An upload only acts on whether the network has a transaction; one it reported Unknown is as good
+// as never sent.
+impl From<TransactionStatus> for UploadTxStatus {
+    fn from(value: TransactionStatus) -> Self {
+        match value {
+            TransactionStatus::Local | TransactionStatus::Unknown => UploadTxStatus::Local,
+            TransactionStatus::Queued => UploadTxStatus::Queued,
+            TransactionStatus::Pending | TransactionStatus::InvalidStatus => UploadTxStatus::Pending,
+            TransactionStatus::Committed => UploadTxStatus::Committed,
+            TransactionStatus::FailedToCommit => UploadTxStatus::Failed,
+        }
+    }
+}
+
+impl From<UploadTxStatus> for TransactionStatus {
+    fn from(value: UploadTxStatus) -> Self {
+        match value {
+            UploadTxStatus::Local => TransactionStatus::Local,
+            UploadTxStatus::Queued => TransactionStatus::Queued,
+            UploadTxStatus::Pending => TransactionStatus::Pending,
+            UploadTxStatus::Committed => TransactionStatus::Committed,
+            UploadTxStatus::Failed => TransactionStatus::FailedToCommit,
+        }
+    }
+}
+
 pub type TransactionId = String;
 pub type TransactionSubmitId = String;
+pub type TransactionUpdate = (TransactionId, Option<TransactionSubmitId>, Option<TransactionStatus>);
 
 #[derive(Debug)]
 pub struct TransactionInfo {
@@ -49,21 +86,222 @@ pub struct TransactionInfo {
     pub tx_id: TransactionId,
     pub submit_id: Option<TransactionSubmitId>,
     pub status: TransactionStatus,
+    // Milliseconds since the epoch, as recorded by add_tx()/add_txs().
+    pub created_at: i64,
+}
+
+// tx_bytes counts stored transaction bytes only; size_on_disk is what the backend reports for
+// everything it holds, where it can tell.
+#[derive(Debug, Clone, Default)]
+pub struct LocalStateStoreStats {
+    pub files: u64,
+    pub txs_by_status: HashMap<TransactionStatus, u64>,
+    pub tx_bytes: u64,
+    pub receipts: u64,
+    pub size_on_disk: Option<u64>,
+}
+
+impl LocalStateStoreStats {
+    pub fn total_txs(&self) -> u64 {
+        self.txs_by_status.values().sum()
+    }
 }
 
 #[derive(Debug)]
 pub enum LocalStateStoreError {
     NoSuchFile,
     NoSuchTransaction,
+    NoSuchReceipt,
+    LeaseHeld(String),
+    LeaseLost,
+    // The store was written by a newer schema (found, supported).
+    IncompatibleSchema(u32, u32),
+    // Not enough browser storage left for a write (needed, available), in bytes.
+    QuotaExceeded(u64, u64),
+    // A stored record that can't be encoded or decoded.
+    DecodeError(String),
     ImplementationError(String),
 }
 
-#[async_trait(?Send)]
-pub trait LocalStateStore {
+// Each backend lists its migrations in order; migrations[i] upgrades a store from schema version
+// i to i + 1, so version 0 is an empty store and the current version is migrations.len().
+pub(crate) fn pending_migrations<T>(migrations: &[T], version: u32) -> Result<&[T], LocalStateStoreError> {
+    let supported = migrations.len() as u32;
+    if version > supported {
+        return Err(LocalStateStoreError::IncompatibleSchema(version, supported));
+    }
+    Ok(&migrations[version as usize..])
+}
+
+// Transactions created at or before the returned time (in milliseconds since the epoch) are stale as
+// far as gc() is concerned.
+pub(crate) fn gc_cutoff(older_than: Duration) -> i64 {
+    chrono::Utc::now().timestamp_millis() - older_than.as_millis() as i64
+}
+
+// Version of the dump written by export_store().
+const STORE_DUMP_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct StoreDump {
+    version: u32,
+    files: Vec<FileDump>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FileDump {
+    file_id: uuid::Uuid,
+    txs: Vec<TxDump>,
+    #[serde(default)]
+    upload: Option<UploadInfo>,
+}
+
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize)]
+struct TxDump {
+    tx_id: TransactionId,
+    submit_id: Option<TransactionSubmitId>,
+    status: TransactionStatus,
+    #[serde_as(as = "serde_with::Bytes")]
+    tx_bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lease {
+    pub owner_id: String,
+    pub expires_at: i64,
+}
+
+impl Lease {
+    pub fn new(owner_id: &str, ttl: Duration) -> Self {
+        Lease {
+            owner_id: owner_id.to_string(),
+            expires_at: chrono::Utc::now().timestamp_millis() + ttl.as_millis() as i64,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now().timestamp_millis()
+    }
+
+    // A lease blocks other owners until it expires, after which it may be taken over.
+    pub fn check_owner(&self, owner_id: &str) -> Result<(), LocalStateStoreError> {
+        if self.owner_id != owner_id && !self.is_expired() {
+            return Err(LocalStateStoreError::LeaseHeld(self.owner_id.clone()));
+        }
+        Ok(())
+    }
+}
+
+// Native stores are shared between threads, so must be Send + Sync. Browser stores hold JS
+// handles, which are neither, and only ever run on one thread.
+cfg_if! {
+    if #[cfg(not(target_arch = "wasm32"))] {
+        pub trait MaybeSendSync: Send + Sync {}
+        impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
+    } else {
+        pub trait MaybeSendSync {}
+        impl<T: ?Sized> MaybeSendSync for T {}
+    }
+}
+
+// Every method takes &self and backends do their own synchronization, so a store can be shared
+// through a plain Arc without a lock around it.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait LocalStateStore: MaybeSendSync {
     async fn get_files(&self) -> Result<Vec<uuid::Uuid>, LocalStateStoreError>;
     async fn get_txs(&self, file_id: &uuid::Uuid) -> Result<Vec<TransactionInfo>, LocalStateStoreError>;
+    async fn get_txs_by_status(&self, file_id: &uuid::Uuid, statuses: &[TransactionStatus]) -> Result<Vec<TransactionInfo>, LocalStateStoreError>;
+    async fn get_all_txs_by_status(&self, statuses: &[TransactionStatus]) -> Result<Vec<(uuid::Uuid, TransactionInfo)>, LocalStateStoreError>;
     async fn get_tx_bytes(&self, tx_id: &TransactionId) -> Result<Vec<u8>, LocalStateStoreError>;
     async fn update_tx(&self, tx_id: &TransactionId, submit_id: Option<TransactionSubmitId>, status: Option<TransactionStatus>) -> Result<(), LocalStateStoreError>;
+    // Applies all the updates or, if any transaction is missing, none of them.
+    async fn update_txs(&self, updates: &[TransactionUpdate]) -> Result<(), LocalStateStoreError>;
     async fn flush_txs(&self, file_id: &uuid::Uuid) -> Result<(), LocalStateStoreError>;
     async fn add_tx(&self, file_id: &uuid::Uuid, transaction: &Transaction) -> Result<(), LocalStateStoreError>;
+    // Adds the transactions in order, all or none of them.
+    async fn add_txs(&self, file_id: &uuid::Uuid, transactions: &[Transaction]) -> Result<(), LocalStateStoreError>;
+    // Receipts are kept after flush_txs() so a commit can still be proven later.
+    async fn add_receipt(&self, receipt: &TransactionReceipt) -> Result<(), LocalStateStoreError>;
+    async fn get_receipt(&self, tx_id: &TransactionId) -> Result<TransactionReceipt, LocalStateStoreError>;
+    async fn acquire_lease(&self, file_id: &uuid::Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError>;
+    async fn renew_lease(&self, file_id: &uuid::Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError>;
+    async fn release_lease(&self, file_id: &uuid::Uuid, owner_id: &str) -> Result<(), LocalStateStoreError>;
+    // Records what is being uploaded as info.file_id, adding the file if it has no transactions
+    // yet. The info is removed with the file by flush_txs().
+    async fn set_upload_info(&self, info: &UploadInfo) -> Result<(), LocalStateStoreError>;
+    // Replaces the progress in a file's upload info; files without upload info are left alone.
+    async fn set_upload_progress(&self, file_id: &uuid::Uuid, progress: &UploadProgress) -> Result<(), LocalStateStoreError>;
+    // Files that have upload info, i.e. uploads that were prepared but not yet flushed.
+    async fn list_pending_uploads(&self) -> Result<Vec<UploadInfo>, LocalStateStoreError>;
+    async fn stats(&self) -> Result<LocalStateStoreStats, LocalStateStoreError>;
+    // Flushes every file whose transactions are all older than older_than and that no unexpired
+    // lease is held on, returning how many files were removed. Receipts are kept.
+    async fn gc(&self, older_than: Duration) -> Result<u64, LocalStateStoreError>;
+
+    // Writes every file's transactions as a CBOR dump that import_store() on any backend can
+    // read. Receipts and leases are not included.
+    async fn export_store(&self, writer: &mut (dyn std::io::Write + Send)) -> Result<(), LocalStateStoreError> {
+        let mut dump = StoreDump {
+            version: STORE_DUMP_VERSION,
+            files: Vec::new(),
+        };
+
+        let mut uploads: HashMap<uuid::Uuid, UploadInfo> = self.list_pending_uploads()
+            .await?
+            .into_iter()
+            .map(|upload| (upload.file_id, upload))
+            .collect();
+
+        for file_id in self.get_files().await? {
+            let mut txs: Vec<TxDump> = Vec::new();
+            for tx_info in self.get_txs(&file_id).await? {
+                let tx_bytes = self.get_tx_bytes(&tx_info.tx_id).await?;
+                txs.push(TxDump {
+                    tx_id: tx_info.tx_id,
+                    submit_id: tx_info.submit_id,
+                    status: tx_info.status,
+                    tx_bytes,
+                });
+            }
+            dump.files.push(FileDump { file_id, txs, upload: uploads.remove(&file_id) });
+        }
+
+        ciborium::ser::into_writer(&dump, writer)
+            .map_err(|err| LocalStateStoreError::ImplementationError(format!("ciborium::ser::Error: {}", err)))
+    }
+
+    // Files in the dump replace any copy of them already in the store. Imported transactions are
+    // dated to the import, so gc() gives them a full retention period.
+    async fn import_store(&self, reader: &mut (dyn std::io::Read + Send)) -> Result<(), LocalStateStoreError> {
+        let dump: StoreDump = ciborium::de::from_reader(reader)
+            .map_err(|err| LocalStateStoreError::ImplementationError(format!("ciborium::de::Error: {}", err)))?;
+        if dump.version > STORE_DUMP_VERSION {
+            return Err(LocalStateStoreError::IncompatibleSchema(dump.version, STORE_DUMP_VERSION));
+        }
+
+        for file in dump.files {
+            let transactions = file.txs
+                .iter()
+                .map(|tx| Transaction::decode(tx.tx_bytes.as_slice()))
+                .collect::<Result<Vec<Transaction>, _>>()
+                .map_err(|err| LocalStateStoreError::ImplementationError(format!("ProtobufError: {}", err)))?;
+
+            self.flush_txs(&file.file_id).await?;
+            self.add_txs(&file.file_id, &transactions).await?;
+
+            let updates: Vec<TransactionUpdate> = file.txs
+                .into_iter()
+                .map(|tx| (tx.tx_id, tx.submit_id, Some(tx.status)))
+                .collect();
+            self.update_txs(&updates).await?;
+
+            if let Some(upload) = file.upload {
+                self.set_upload_info(&upload).await?;
+            }
+        }
+
+        Ok(())
+    }
 }
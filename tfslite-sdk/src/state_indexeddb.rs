@@ -1,14 +1,17 @@
+use std::time::Duration;
 use uuid::Uuid;
 use async_trait::async_trait;
+use futures::future::LocalBoxFuture;
 
 use rexie::{Rexie, Error, ObjectStore, Index, TransactionMode, KeyRange};
 
 use wasm_bindgen::JsValue;
 use gloo_utils::format::JsValueSerdeExt;
-use protobuf::Message;
+use prost::Message;
 
 use libtfslite::protos::transaction::Transaction;
-use crate::state::{LocalStateStore, LocalStateStoreError, TransactionId, TransactionInfo, TransactionStatus, TransactionSubmitId};
+use crate::state::{gc_cutoff, pending_migrations, Lease, LocalStateStore, LocalStateStoreError, LocalStateStoreStats, TransactionId, TransactionInfo, TransactionStatus, TransactionSubmitId, TransactionUpdate};
+use crate::types::{TransactionReceipt, UploadInfo, UploadProgress};
 use crate::debug::debug_println;
 
 use serde::{Serialize, Deserialize};
@@ -17,6 +20,8 @@ use serde::{Serialize, Deserialize};
 struct FileInfo {
     file_id: String,
     next_order: u64,
+    #[serde(default)]
+    upload: Option<UploadInfo>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,6 +32,8 @@ struct TxInfo {
     tx_id: String,
     submit_id: Option<String>,
     status: String,
+    #[serde(default)]
+    created_at: i64,
 }
 
 impl From<TxInfo> for TransactionInfo {
@@ -36,6 +43,7 @@ impl From<TxInfo> for TransactionInfo {
             tx_id: value.tx_id,
             submit_id: value.submit_id,
             status: value.status.into(),
+            created_at: value.created_at,
         }
     }
 }
@@ -45,6 +53,93 @@ impl From<Error> for LocalStateStoreError {
     }
 }
 
+fn decode_error(err: serde_json::Error) -> LocalStateStoreError {
+    LocalStateStoreError::DecodeError(format!("serde_json::Error: {}", err))
+}
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+// Object stores and indexes are created by the IndexedDB upgrade in new(); migrations are for
+// rewriting records whose layout changes.
+type Migration = fn(&Rexie) -> LocalBoxFuture<'_, Result<(), LocalStateStoreError>>;
+
+const MIGRATIONS: [Migration; 2] = [
+    migrate_baseline,
+    migrate_add_tx_created,
+];
+
+fn migrate_baseline(_db: &Rexie) -> LocalBoxFuture<'_, Result<(), LocalStateStoreError>> {
+    Box::pin(async { Ok(()) })
+}
+
+// Records stored before creation times were recorded are dated to the upgrade, so they get a
+// full retention period before gc() considers them.
+fn migrate_add_tx_created(db: &Rexie) -> LocalBoxFuture<'_, Result<(), LocalStateStoreError>> {
+    Box::pin(async move {
+        let tx = db.transaction(&["tx_info"], TransactionMode::ReadWrite)?;
+        let store = tx.store("tx_info")?;
+
+        let tx_infos: Vec<TxInfo> = store.get_all(None, None, None, None)
+            .await?
+            .into_iter()
+            .map(|(_k, v)| v.into_serde().unwrap())
+            .collect();
+
+        let now = chrono::Utc::now().timestamp_millis();
+        for mut tx_info in tx_infos {
+            tx_info.created_at = now;
+            let value = JsValue::from_serde(&tx_info).unwrap();
+            store.put(&value, None).await?;
+        }
+        tx.done().await?;
+
+        Ok(())
+    })
+}
+
+// The origin's (usage, quota) as estimated by the browser. Unavailable outside a window context.
+async fn storage_estimate() -> Option<(u64, u64)> {
+    let window = web_sys::window()?;
+    let estimate = window.navigator().storage().estimate().ok()?;
+    let estimate = wasm_bindgen_futures::JsFuture::from(estimate).await.ok()?;
+
+    let field = |name: &str| {
+        js_sys::Reflect::get(&estimate, &JsValue::from_str(name))
+            .ok()?
+            .as_f64()
+            .map(|value| value as u64)
+    };
+    Some((field("usage")?, field("quota")?))
+}
+
+// IndexedDB has no per-database size, so this is the origin's total usage.
+pub(crate) async fn storage_usage() -> Option<u64> {
+    storage_estimate().await.map(|(usage, _quota)| usage)
+}
+
+// Fails early when a write of needed bytes would not fit, rather than partway through with an
+// opaque storage error. Passes when the browser gives no estimate.
+pub(crate) async fn check_quota(needed: u64) -> Result<(), LocalStateStoreError> {
+    if let Some((usage, quota)) = storage_estimate().await {
+        let available = quota.saturating_sub(usage);
+        if needed > available {
+            return Err(LocalStateStoreError::QuotaExceeded(needed, available));
+        }
+    }
+
+    Ok(())
+}
+
+// Asks the browser not to evict our data under storage pressure. The browser may say no, or
+// ask the user, so the outcome is ignored.
+async fn request_persistence() {
+    if let Some(window) = web_sys::window() {
+        if let Ok(promise) = window.navigator().storage().persist() {
+            let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+        }
+    }
+}
+
 pub struct IndexedDBLocalStateStore {
     db: Rexie,
 }
@@ -52,7 +147,7 @@ pub struct IndexedDBLocalStateStore {
 impl IndexedDBLocalStateStore {
     pub async fn new() -> Result<Self, LocalStateStoreError> {
         let db = Rexie::builder("tfslite")
-            .version(3)
+            .version(7)
             .add_object_store(
                 ObjectStore::new("files")
                     .key_path("file_id")
@@ -62,19 +157,59 @@ impl IndexedDBLocalStateStore {
                     .key_path("tx_id")
                     .add_index(Index::new("file_id", "file_id"))
                     .add_index(Index::new("order", "order"))
+                    .add_index(Index::new("status", "status"))
             )
             .add_object_store(
                 ObjectStore::new("tx_bytes")
             )
+            .add_object_store(
+                ObjectStore::new("tx_receipts")
+                    .key_path("tx_id")
+            )
+            .add_object_store(
+                ObjectStore::new("leases")
+            )
+            .add_object_store(
+                ObjectStore::new("meta")
+            )
             .build().await?;
 
         let result = IndexedDBLocalStateStore{
             db
         };
+        result.migrate().await?;
+        request_persistence().await;
 
         Ok(result)
     }
 
+    async fn migrate(&self) -> Result<(), LocalStateStoreError> {
+        let key: JsValue = SCHEMA_VERSION_KEY.into();
+
+        let tx = self.db.transaction(&["meta"], TransactionMode::ReadOnly)?;
+        let value = tx.store("meta")?.get(&key).await?;
+        tx.done().await?;
+
+        let version: u32 = if value.is_undefined() { 0 } else { value.into_serde().unwrap() };
+        let migrations = pending_migrations(&MIGRATIONS, version)?;
+        if migrations.is_empty() {
+            return Ok(());
+        }
+
+        // IndexedDB transactions cannot span awaits on other work, so the version is recorded
+        // after each migration and an interrupted upgrade resumes where it stopped.
+        for (index, migration) in migrations.iter().enumerate() {
+            migration(&self.db).await?;
+
+            let tx = self.db.transaction(&["meta"], TransactionMode::ReadWrite)?;
+            let value = JsValue::from_serde(&(version + index as u32 + 1)).unwrap();
+            tx.store("meta")?.put(&value, Some(&key)).await?;
+            tx.done().await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn set_has_file(&self, file_id: &uuid::Uuid) -> Result<(), LocalStateStoreError> {
         let tx = self.db.transaction(&["files"], TransactionMode::ReadWrite)?;
         let files = tx.store("files")?;
@@ -82,6 +217,7 @@ impl IndexedDBLocalStateStore {
         let entry = FileInfo {
             file_id: file_id.to_string(),
             next_order: 1,
+            upload: None,
         };
 
         let entry = JsValue::from_serde(&entry).unwrap();
@@ -92,6 +228,70 @@ impl IndexedDBLocalStateStore {
         Ok(())
     }
 
+    async fn write_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration, require_owner: bool) -> Result<(), LocalStateStoreError> {
+        let tx = self.db.transaction(&["leases"], TransactionMode::ReadWrite)?;
+        let store = tx.store("leases")?;
+
+        let key = JsValue::from_serde(&file_id.to_string()).unwrap();
+        let value = store.get(&key).await?;
+
+        if value.is_undefined() {
+            if require_owner {
+                return Err(LocalStateStoreError::LeaseLost);
+            }
+        } else {
+            let lease: Lease = value.into_serde().unwrap();
+            lease.check_owner(owner_id)?;
+            if require_owner && lease.owner_id != owner_id {
+                return Err(LocalStateStoreError::LeaseLost);
+            }
+        }
+
+        let lease = Lease::new(owner_id, ttl);
+        let value = JsValue::from_serde(&lease).unwrap();
+        store.put(&value, Some(&key)).await?;
+        tx.done().await?;
+
+        Ok(())
+    }
+
+    async fn current_lease(&self, file_id: &Uuid) -> Result<Option<Lease>, LocalStateStoreError> {
+        let tx = self.db.transaction(&["leases"], TransactionMode::ReadOnly)?;
+        let store = tx.store("leases")?;
+
+        let key = JsValue::from_serde(&file_id.to_string()).unwrap();
+        let value = store.get(&key).await?;
+        tx.done().await?;
+
+        if value.is_undefined() {
+            return Ok(None);
+        }
+
+        Ok(Some(value.into_serde().unwrap()))
+    }
+
+    // Reads through the status index, so only records with a matching status are loaded.
+    async fn get_tx_infos_by_status(&self, statuses: &[TransactionStatus]) -> Result<Vec<TxInfo>, LocalStateStoreError> {
+        let tx = self.db.transaction(&["tx_info"], TransactionMode::ReadOnly)?;
+        let store = tx.store("tx_info")?;
+        let index = store.index("status")?;
+
+        let mut tx_infos: Vec<TxInfo> = Vec::new();
+        for status in statuses {
+            let key: JsValue = String::from(status.clone()).into();
+            let range = KeyRange::only(&key)?;
+
+            let mut matching: Vec<TxInfo> = index.get_all(Some(&range), None, None, None)
+                .await?
+                .into_iter()
+                .map(|(_k, v)| v.into_serde().unwrap())
+                .collect();
+            tx_infos.append(&mut matching);
+        }
+
+        Ok(tx_infos)
+    }
+
     pub async fn check_has_file(&self, file_id: &uuid::Uuid) -> Result<(), LocalStateStoreError> {
         let tx = self.db.transaction(&["files"], TransactionMode::ReadOnly)?;
         let store = tx.store("files")?;
@@ -146,6 +346,33 @@ impl LocalStateStore for IndexedDBLocalStateStore {
         Ok(results)
     }
 
+    async fn get_txs_by_status(&self, file_id: &Uuid, statuses: &[TransactionStatus]) -> Result<Vec<TransactionInfo>, LocalStateStoreError> {
+        self.check_has_file(file_id).await?;
+
+        let file_id = file_id.to_string();
+        let mut results: Vec<TransactionInfo> = self.get_tx_infos_by_status(statuses)
+            .await?
+            .into_iter()
+            .filter(|tx_info| tx_info.file_id == file_id)
+            .map(|tx_info| tx_info.into())
+            .collect();
+        results.sort_by(|a, b| a.order.cmp(&b.order));
+
+        Ok(results)
+    }
+
+    async fn get_all_txs_by_status(&self, statuses: &[TransactionStatus]) -> Result<Vec<(Uuid, TransactionInfo)>, LocalStateStoreError> {
+        let mut results: Vec<(Uuid, TransactionInfo)> = Vec::new();
+        for tx_info in self.get_tx_infos_by_status(statuses).await? {
+            let file_id = Uuid::parse_str(&tx_info.file_id)
+                .map_err(|err| LocalStateStoreError::ImplementationError(format!("uuid::Error: {}", err)))?;
+            results.push((file_id, tx_info.into()));
+        }
+        results.sort_by(|a, b| (a.0, a.1.order).cmp(&(b.0, b.1.order)));
+
+        Ok(results)
+    }
+
     async fn get_tx_bytes(&self, tx_id: &TransactionId) -> Result<Vec<u8>, LocalStateStoreError> {
         let tx = self.db.transaction(&["tx_bytes"], TransactionMode::ReadOnly)?;
         let store = tx.store("tx_bytes")?;
@@ -162,35 +389,42 @@ impl LocalStateStore for IndexedDBLocalStateStore {
     }
 
     async fn update_tx(&self, tx_id: &TransactionId, submit_id: Option<TransactionSubmitId>, status: Option<TransactionStatus>) -> Result<(), LocalStateStoreError> {
+        self.update_txs(&[(tx_id.clone(), submit_id, status)]).await
+    }
+
+    async fn update_txs(&self, updates: &[TransactionUpdate]) -> Result<(), LocalStateStoreError> {
         let tx = self.db.transaction(&["tx_info"], TransactionMode::ReadWrite)?;
         let store = tx.store("tx_info")?;
 
-        let key = JsValue::from_serde(&tx_id).unwrap();
-        let value = store.get(&key).await?;
-        if value.is_undefined() {
-            return Err(LocalStateStoreError::NoSuchTransaction);
-        }
+        for (tx_id, submit_id, status) in updates {
+            let key = JsValue::from_serde(&tx_id).unwrap();
+            let value = store.get(&key).await?;
+            if value.is_undefined() {
+                // Abort so none of the updates made so far are kept.
+                tx.abort().await?;
+                return Err(LocalStateStoreError::NoSuchTransaction);
+            }
 
-        let mut tx_info: TxInfo = value.into_serde().unwrap();
-        let mut need_update = false;
+            let mut tx_info: TxInfo = value.into_serde().unwrap();
+            let mut need_update = false;
 
-        if let Some(submit_id) = submit_id {
-            tx_info.submit_id = Some(submit_id);
-            need_update = true;
-        }
+            if let Some(submit_id) = submit_id {
+                tx_info.submit_id = Some(submit_id.clone());
+                need_update = true;
+            }
 
-        if let Some(status) = status {
-            tx_info.status = status.into();
-            need_update = true;
-        }
+            if let Some(status) = status {
+                tx_info.status = status.clone().into();
+                need_update = true;
+            }
 
-        if need_update {
-            let value_updated = JsValue::from_serde(&tx_info).unwrap();
-            store.put(&value_updated, None).await?;
+            if need_update {
+                let value_updated = JsValue::from_serde(&tx_info).unwrap();
+                store.put(&value_updated, None).await?;
+            }
         }
         tx.done().await?;
 
-
         Ok(())
     }
 
@@ -223,6 +457,13 @@ impl LocalStateStore for IndexedDBLocalStateStore {
     }
 
     async fn add_tx(&self, file_id: &Uuid, transaction: &Transaction) -> Result<(), LocalStateStoreError> {
+        self.add_txs(file_id, std::slice::from_ref(transaction)).await
+    }
+
+    async fn add_txs(&self, file_id: &Uuid, transactions: &[Transaction]) -> Result<(), LocalStateStoreError> {
+        let needed: u64 = transactions.iter().map(|transaction| transaction.encoded_len() as u64).sum();
+        check_quota(needed).await?;
+
         let tx = self.db.transaction(&["files", "tx_info", "tx_bytes"], TransactionMode::ReadWrite)?;
 
         let store_files = tx.store("files")?;
@@ -233,34 +474,40 @@ impl LocalStateStore for IndexedDBLocalStateStore {
         if value.is_undefined() {
             file_info = FileInfo{
                 file_id: file_id.to_string(),
-                next_order: 0
+                next_order: 0,
+                upload: None,
             }
         } else {
             file_info = value.into_serde().unwrap();
         }
 
-        // Add tx info
         let store_tx_info = tx.store("tx_info")?;
-        let tx_info = TxInfo {
-            file_id: file_id.to_string(),
-            tx_id: transaction.get_header_signature().to_string(),
-            submit_id: None,
-            status: TransactionStatus::Local.into(),
-            order: file_info.next_order,
-        };
-        let value = JsValue::from_serde(&tx_info).unwrap();
-        store_tx_info.add(&value, None).await?;
-
-        // Add tx bytes
         let store_tx_bytes = tx.store("tx_bytes")?;
-        let bytes = transaction.write_to_bytes().unwrap();
-        let key = JsValue::from_serde(&transaction.get_header_signature().to_string()).unwrap();
-        let value = JsValue::from_serde(bytes.as_slice()).unwrap();
-        debug_println!("Bytes: {:?}", value);
-        store_tx_bytes.add(&value, Some(&key)).await?;
+        let created_at = chrono::Utc::now().timestamp_millis();
+        for transaction in transactions {
+            // Add tx info
+            let tx_info = TxInfo {
+                file_id: file_id.to_string(),
+                tx_id: transaction.get_header_signature().to_string(),
+                submit_id: None,
+                status: TransactionStatus::Local.into(),
+                order: file_info.next_order,
+                created_at,
+            };
+            let value = JsValue::from_serde(&tx_info).unwrap();
+            store_tx_info.add(&value, None).await?;
+
+            // Add tx bytes
+            let bytes = transaction.encode_to_vec();
+            let key = JsValue::from_serde(&transaction.get_header_signature().to_string()).unwrap();
+            let value = JsValue::from_serde(bytes.as_slice()).unwrap();
+            debug_println!("Bytes: {}", crate::debug::redact("tx_bytes", format!("{:?}", value)));
+            store_tx_bytes.add(&value, Some(&key)).await?;
+
+            file_info.next_order += 1;
+        }
 
         // Update file info
-        file_info.next_order += 1;
         let value = JsValue::from_serde(&file_info).unwrap();
         store_files.put(&value, None).await?;
 
@@ -268,6 +515,167 @@ impl LocalStateStore for IndexedDBLocalStateStore {
 
         Ok(())
     }
+
+    async fn add_receipt(&self, receipt: &TransactionReceipt) -> Result<(), LocalStateStoreError> {
+        let tx = self.db.transaction(&["tx_receipts"], TransactionMode::ReadWrite)?;
+        let store = tx.store("tx_receipts")?;
+
+        let value = JsValue::from_serde(receipt).map_err(decode_error)?;
+        store.put(&value, None).await?;
+        tx.done().await?;
+
+        Ok(())
+    }
+
+    async fn get_receipt(&self, tx_id: &TransactionId) -> Result<TransactionReceipt, LocalStateStoreError> {
+        let tx = self.db.transaction(&["tx_receipts"], TransactionMode::ReadOnly)?;
+        let store = tx.store("tx_receipts")?;
+
+        let key = JsValue::from_serde(&tx_id).map_err(decode_error)?;
+        let value = store.get(&key).await?;
+        if value.is_undefined() {
+            return Err(LocalStateStoreError::NoSuchReceipt);
+        }
+
+        let receipt: TransactionReceipt = value.into_serde().map_err(decode_error)?;
+
+        Ok(receipt)
+    }
+
+    async fn acquire_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        self.write_lease(file_id, owner_id, ttl, false).await
+    }
+
+    async fn renew_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        self.write_lease(file_id, owner_id, ttl, true).await
+    }
+
+    async fn release_lease(&self, file_id: &Uuid, owner_id: &str) -> Result<(), LocalStateStoreError> {
+        let tx = self.db.transaction(&["leases"], TransactionMode::ReadWrite)?;
+        let store = tx.store("leases")?;
+
+        let key = JsValue::from_serde(&file_id.to_string()).unwrap();
+        let value = store.get(&key).await?;
+
+        if !value.is_undefined() {
+            let lease: Lease = value.into_serde().unwrap();
+            if lease.owner_id == owner_id {
+                store.delete(&key).await?;
+            }
+        }
+        tx.done().await?;
+
+        Ok(())
+    }
+
+    async fn set_upload_info(&self, info: &UploadInfo) -> Result<(), LocalStateStoreError> {
+        let tx = self.db.transaction(&["files"], TransactionMode::ReadWrite)?;
+        let store = tx.store("files")?;
+
+        let key: JsValue = info.file_id.to_string().into();
+        let value = store.get(&key).await?;
+
+        let mut file_info: FileInfo = if value.is_undefined() {
+            FileInfo {
+                file_id: info.file_id.to_string(),
+                next_order: 0,
+                upload: None,
+            }
+        } else {
+            value.into_serde().unwrap()
+        };
+        file_info.upload = Some(info.clone());
+
+        let value = JsValue::from_serde(&file_info).unwrap();
+        store.put(&value, None).await?;
+        tx.done().await?;
+
+        Ok(())
+    }
+
+    async fn set_upload_progress(&self, file_id: &Uuid, progress: &UploadProgress) -> Result<(), LocalStateStoreError> {
+        let tx = self.db.transaction(&["files"], TransactionMode::ReadWrite)?;
+        let store = tx.store("files")?;
+
+        let key: JsValue = file_id.to_string().into();
+        let value = store.get(&key).await?;
+        if !value.is_undefined() {
+            let mut file_info: FileInfo = value.into_serde().unwrap();
+            if let Some(upload) = file_info.upload.as_mut() {
+                upload.progress = *progress;
+
+                let value = JsValue::from_serde(&file_info).unwrap();
+                store.put(&value, None).await?;
+            }
+        }
+        tx.done().await?;
+
+        Ok(())
+    }
+
+    async fn list_pending_uploads(&self) -> Result<Vec<UploadInfo>, LocalStateStoreError> {
+        let tx = self.db.transaction(&["files"], TransactionMode::ReadOnly)?;
+        let store = tx.store("files")?;
+
+        let results: Vec<UploadInfo> = store.get_all(None, None, None, None)
+            .await?
+            .into_iter()
+            .filter_map(|(_k, v)| v.into_serde::<FileInfo>().unwrap().upload)
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn stats(&self) -> Result<LocalStateStoreStats, LocalStateStoreError> {
+        let tx = self.db.transaction(&["files", "tx_info", "tx_bytes", "tx_receipts"], TransactionMode::ReadOnly)?;
+
+        let mut stats = LocalStateStoreStats {
+            files: tx.store("files")?.count(None).await? as u64,
+            receipts: tx.store("tx_receipts")?.count(None).await? as u64,
+            ..Default::default()
+        };
+
+        let tx_infos: Vec<TxInfo> = tx.store("tx_info")?
+            .get_all(None, None, None, None)
+            .await?
+            .into_iter()
+            .map(|(_k, v)| v.into_serde().unwrap())
+            .collect();
+        for tx_info in tx_infos {
+            *stats.txs_by_status.entry(tx_info.status.into()).or_default() += 1;
+        }
+
+        for (_k, v) in tx.store("tx_bytes")?.get_all(None, None, None, None).await? {
+            let bytes: Vec<u8> = v.into_serde().unwrap();
+            stats.tx_bytes += bytes.len() as u64;
+        }
+        tx.done().await?;
+
+        stats.size_on_disk = storage_usage().await;
+
+        Ok(stats)
+    }
+
+    async fn gc(&self, older_than: Duration) -> Result<u64, LocalStateStoreError> {
+        let cutoff = gc_cutoff(older_than);
+
+        let mut collected = 0;
+        for file_id in self.get_files().await? {
+            if let Some(lease) = self.current_lease(&file_id).await? {
+                if !lease.is_expired() {
+                    continue;
+                }
+            }
+
+            let txs = self.get_txs(&file_id).await?;
+            if txs.iter().all(|tx_info| tx_info.created_at <= cutoff) {
+                self.flush_txs(&file_id).await?;
+                collected += 1;
+            }
+        }
+
+        Ok(collected)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+use async_trait::async_trait;
+use prost::Message;
+
+use libtfslite::protos::transaction::Transaction;
+use crate::state::{gc_cutoff, Lease, LocalStateStore, LocalStateStoreError, LocalStateStoreStats, TransactionId, TransactionInfo, TransactionStatus, TransactionSubmitId, TransactionUpdate};
+use crate::types::{TransactionReceipt, UploadInfo, UploadProgress};
+
+struct TxEntry {
+    order: u64,
+    file_id: Uuid,
+    submit_id: Option<TransactionSubmitId>,
+    status: TransactionStatus,
+    created_at: i64,
+    tx_bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    // Next order number for each file.
+    files: HashMap<Uuid, u64>,
+    txs: HashMap<TransactionId, TxEntry>,
+    receipts: HashMap<TransactionId, TransactionReceipt>,
+    leases: HashMap<Uuid, Lease>,
+    uploads: HashMap<Uuid, UploadInfo>,
+}
+
+impl MemoryState {
+    fn tx_infos(&self, file_id: &Uuid) -> Vec<TransactionInfo> {
+        let mut results: Vec<TransactionInfo> = self.txs
+            .iter()
+            .filter(|(_tx_id, entry)| entry.file_id == *file_id)
+            .map(|(tx_id, entry)| to_transaction_info(tx_id, entry))
+            .collect();
+        results.sort_by(|a, b| a.order.cmp(&b.order));
+
+        results
+    }
+
+    fn flush(&mut self, file_id: &Uuid) {
+        self.txs.retain(|_tx_id, entry| entry.file_id != *file_id);
+        self.files.remove(file_id);
+        self.uploads.remove(file_id);
+    }
+}
+
+fn to_transaction_info(tx_id: &TransactionId, entry: &TxEntry) -> TransactionInfo {
+    TransactionInfo {
+        order: entry.order,
+        tx_id: tx_id.clone(),
+        submit_id: entry.submit_id.clone(),
+        status: entry.status.clone(),
+        created_at: entry.created_at,
+    }
+}
+
+// Keeps everything in memory and loses it when dropped. Meant for tests and short-lived tools
+// that don't need uploads to survive a restart.
+#[derive(Default)]
+pub struct MemoryLocalStateStore {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryLocalStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn write_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration, require_owner: bool) -> Result<(), LocalStateStoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.leases.get(file_id) {
+            Some(lease) => {
+                lease.check_owner(owner_id)?;
+                if require_owner && lease.owner_id != owner_id {
+                    return Err(LocalStateStoreError::LeaseLost);
+                }
+            },
+            None => {
+                if require_owner {
+                    return Err(LocalStateStoreError::LeaseLost);
+                }
+            },
+        }
+
+        state.leases.insert(*file_id, Lease::new(owner_id, ttl));
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl LocalStateStore for MemoryLocalStateStore {
+    async fn get_files(&self) -> Result<Vec<Uuid>, LocalStateStoreError> {
+        Ok(self.state.lock().unwrap().files.keys().copied().collect())
+    }
+
+    async fn get_txs(&self, file_id: &Uuid) -> Result<Vec<TransactionInfo>, LocalStateStoreError> {
+        let state = self.state.lock().unwrap();
+        if !state.files.contains_key(file_id) {
+            return Err(LocalStateStoreError::NoSuchFile);
+        }
+
+        Ok(state.tx_infos(file_id))
+    }
+
+    async fn get_txs_by_status(&self, file_id: &Uuid, statuses: &[TransactionStatus]) -> Result<Vec<TransactionInfo>, LocalStateStoreError> {
+        let results = self.get_txs(file_id)
+            .await?
+            .into_iter()
+            .filter(|tx_info| statuses.contains(&tx_info.status))
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn get_all_txs_by_status(&self, statuses: &[TransactionStatus]) -> Result<Vec<(Uuid, TransactionInfo)>, LocalStateStoreError> {
+        let state = self.state.lock().unwrap();
+
+        let mut results: Vec<(Uuid, TransactionInfo)> = state.txs
+            .iter()
+            .filter(|(_tx_id, entry)| statuses.contains(&entry.status))
+            .map(|(tx_id, entry)| (entry.file_id, to_transaction_info(tx_id, entry)))
+            .collect();
+        results.sort_by(|a, b| (a.0, a.1.order).cmp(&(b.0, b.1.order)));
+
+        Ok(results)
+    }
+
+    async fn get_tx_bytes(&self, tx_id: &TransactionId) -> Result<Vec<u8>, LocalStateStoreError> {
+        self.state.lock().unwrap().txs
+            .get(tx_id)
+            .map(|entry| entry.tx_bytes.clone())
+            .ok_or(LocalStateStoreError::NoSuchTransaction)
+    }
+
+    async fn update_tx(&self, tx_id: &TransactionId, submit_id: Option<TransactionSubmitId>, status: Option<TransactionStatus>) -> Result<(), LocalStateStoreError> {
+        self.update_txs(&[(tx_id.clone(), submit_id, status)]).await
+    }
+
+    async fn update_txs(&self, updates: &[TransactionUpdate]) -> Result<(), LocalStateStoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        // Checked up front so a missing transaction leaves the rest unchanged.
+        if updates.iter().any(|(tx_id, _, _)| !state.txs.contains_key(tx_id)) {
+            return Err(LocalStateStoreError::NoSuchTransaction);
+        }
+
+        for (tx_id, submit_id, status) in updates {
+            if let Some(entry) = state.txs.get_mut(tx_id) {
+                if let Some(submit_id) = submit_id {
+                    entry.submit_id = Some(submit_id.clone());
+                }
+
+                if let Some(status) = status {
+                    entry.status = status.clone();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn flush_txs(&self, file_id: &Uuid) -> Result<(), LocalStateStoreError> {
+        self.state.lock().unwrap().flush(file_id);
+        Ok(())
+    }
+
+    async fn add_tx(&self, file_id: &Uuid, transaction: &Transaction) -> Result<(), LocalStateStoreError> {
+        self.add_txs(file_id, std::slice::from_ref(transaction)).await
+    }
+
+    async fn add_txs(&self, file_id: &Uuid, transactions: &[Transaction]) -> Result<(), LocalStateStoreError> {
+        let mut state = self.state.lock().unwrap();
+        let created_at = chrono::Utc::now().timestamp_millis();
+
+        let mut next_order = state.files.get(file_id).copied().unwrap_or(0);
+        for transaction in transactions {
+            state.txs.insert(transaction.get_header_signature().to_string(), TxEntry {
+                order: next_order,
+                file_id: *file_id,
+                submit_id: None,
+                status: TransactionStatus::Local,
+                created_at,
+                tx_bytes: transaction.encode_to_vec(),
+            });
+            next_order += 1;
+        }
+        state.files.insert(*file_id, next_order);
+
+        Ok(())
+    }
+
+    async fn add_receipt(&self, receipt: &TransactionReceipt) -> Result<(), LocalStateStoreError> {
+        self.state.lock().unwrap().receipts.insert(receipt.tx_id.clone(), receipt.clone());
+        Ok(())
+    }
+
+    async fn get_receipt(&self, tx_id: &TransactionId) -> Result<TransactionReceipt, LocalStateStoreError> {
+        self.state.lock().unwrap().receipts
+            .get(tx_id)
+            .cloned()
+            .ok_or(LocalStateStoreError::NoSuchReceipt)
+    }
+
+    async fn acquire_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        self.write_lease(file_id, owner_id, ttl, false)
+    }
+
+    async fn renew_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        self.write_lease(file_id, owner_id, ttl, true)
+    }
+
+    async fn release_lease(&self, file_id: &Uuid, owner_id: &str) -> Result<(), LocalStateStoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.leases.get(file_id).is_some_and(|lease| lease.owner_id == owner_id) {
+            state.leases.remove(file_id);
+        }
+
+        Ok(())
+    }
+
+    async fn set_upload_info(&self, info: &UploadInfo) -> Result<(), LocalStateStoreError> {
+        let mut state = self.state.lock().unwrap();
+
+        state.files.entry(info.file_id).or_insert(0);
+        state.uploads.insert(info.file_id, info.clone());
+
+        Ok(())
+    }
+
+    async fn set_upload_progress(&self, file_id: &Uuid, progress: &UploadProgress) -> Result<(), LocalStateStoreError> {
+        if let Some(upload) = self.state.lock().unwrap().uploads.get_mut(file_id) {
+            upload.progress = *progress;
+        }
+
+        Ok(())
+    }
+
+    async fn list_pending_uploads(&self) -> Result<Vec<UploadInfo>, LocalStateStoreError> {
+        Ok(self.state.lock().unwrap().uploads.values().cloned().collect())
+    }
+
+    async fn stats(&self) -> Result<LocalStateStoreStats, LocalStateStoreError> {
+        let state = self.state.lock().unwrap();
+
+        let mut stats = LocalStateStoreStats {
+            files: state.files.len() as u64,
+            receipts: state.receipts.len() as u64,
+            ..Default::default()
+        };
+
+        for entry in state.txs.values() {
+            *stats.txs_by_status.entry(entry.status.clone()).or_default() += 1;
+            stats.tx_bytes += entry.tx_bytes.len() as u64;
+        }
+
+        Ok(stats)
+    }
+
+    async fn gc(&self, older_than: Duration) -> Result<u64, LocalStateStoreError> {
+        let cutoff = gc_cutoff(older_than);
+        let mut state = self.state.lock().unwrap();
+
+        let stale: Vec<Uuid> = state.files
+            .keys()
+            .filter(|file_id| state.leases.get(file_id).map_or(true, |lease| lease.is_expired()))
+            .filter(|file_id| state.tx_infos(file_id).iter().all(|tx_info| tx_info.created_at <= cutoff))
+            .copied()
+            .collect();
+
+        for file_id in stale.iter() {
+            state.flush(file_id);
+        }
+
+        Ok(stale.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::LocalStateStoreError;
+    use crate::state_memory::MemoryLocalStateStore;
+    use crate::tests::test_local_state_store_common;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_local_state_store() -> Result<(), LocalStateStoreError> {
+        test_local_state_store_common(Box::new(MemoryLocalStateStore::new())).await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    async fn test_local_state_store() -> Result<(), LocalStateStoreError> {
+        test_local_state_store_common(Box::new(MemoryLocalStateStore::new())).await
+    }
+}
@@ -0,0 +1,481 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+use async_trait::async_trait;
+use prost::Message;
+
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{File, FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetDirectoryOptions, FileSystemGetFileOptions, FileSystemWritableFileStream};
+
+use libtfslite::protos::transaction::Transaction;
+use crate::state::{gc_cutoff, pending_migrations, Lease, LocalStateStore, LocalStateStoreError, LocalStateStoreStats, TransactionId, TransactionInfo, TransactionStatus, TransactionSubmitId, TransactionUpdate};
+use crate::state_indexeddb::{check_quota, storage_usage};
+use crate::types::{TransactionReceipt, UploadInfo, UploadProgress};
+
+use serde::{Serialize, Deserialize};
+
+const ROOT_DIRECTORY: &str = "tfslite";
+const TX_BYTES_DIRECTORY: &str = "tx_bytes";
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TxInfo {
+    order: u64,
+    file_id: String,
+    submit_id: Option<String>,
+    status: String,
+    created_at: i64,
+}
+
+// Everything but the transaction bytes, which are kept one file per transaction so the index
+// stays small enough to rewrite on every change.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OpfsIndex {
+    schema_version: u32,
+    files: HashMap<String, u64>,
+    txs: HashMap<TransactionId, TxInfo>,
+    receipts: HashMap<TransactionId, TransactionReceipt>,
+    leases: HashMap<String, Lease>,
+    #[serde(default)]
+    uploads: HashMap<String, UploadInfo>,
+}
+
+impl OpfsIndex {
+    fn tx_infos(&self, file_id: &str) -> Vec<TransactionInfo> {
+        let mut results: Vec<TransactionInfo> = self.txs
+            .iter()
+            .filter(|(_tx_id, tx_info)| tx_info.file_id == file_id)
+            .map(|(tx_id, tx_info)| to_transaction_info(tx_id, tx_info))
+            .collect();
+        results.sort_by(|a, b| a.order.cmp(&b.order));
+
+        results
+    }
+}
+
+fn to_transaction_info(tx_id: &TransactionId, tx_info: &TxInfo) -> TransactionInfo {
+    TransactionInfo {
+        order: tx_info.order,
+        tx_id: tx_id.clone(),
+        submit_id: tx_info.submit_id.clone(),
+        status: tx_info.status.clone().into(),
+        created_at: tx_info.created_at,
+    }
+}
+
+impl From<JsValue> for LocalStateStoreError {
+    fn from(value: JsValue) -> Self {
+        LocalStateStoreError::ImplementationError(format!("JsValue: {:?}", value))
+    }
+}
+
+impl From<serde_json::Error> for LocalStateStoreError {
+    fn from(value: serde_json::Error) -> Self {
+        LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", value))
+    }
+}
+
+type Migration = fn(&mut OpfsIndex) -> Result<(), LocalStateStoreError>;
+
+const MIGRATIONS: [Migration; 1] = [
+    migrate_baseline,
+];
+
+fn migrate_baseline(_index: &mut OpfsIndex) -> Result<(), LocalStateStoreError> {
+    Ok(())
+}
+
+async fn resolve<T: JsCast>(promise: js_sys::Promise) -> Result<T, LocalStateStoreError> {
+    let value = JsFuture::from(promise).await?;
+    value.dyn_into::<T>()
+        .map_err(|value| LocalStateStoreError::ImplementationError(format!("Unexpected JsValue: {:?}", value)))
+}
+
+async fn get_directory(parent: &FileSystemDirectoryHandle, name: &str) -> Result<FileSystemDirectoryHandle, LocalStateStoreError> {
+    let options = FileSystemGetDirectoryOptions::new();
+    options.set_create(true);
+    resolve(parent.get_directory_handle_with_options(name, &options)).await
+}
+
+async fn read_file(directory: &FileSystemDirectoryHandle, name: &str) -> Result<Vec<u8>, LocalStateStoreError> {
+    let options = FileSystemGetFileOptions::new();
+    options.set_create(true);
+    let handle: FileSystemFileHandle = resolve(directory.get_file_handle_with_options(name, &options)).await?;
+    let file: File = resolve(handle.get_file()).await?;
+    let buffer: js_sys::ArrayBuffer = resolve(file.array_buffer()).await?;
+
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+// Writes go to a swap file that replaces the old contents on close(), so readers never see a
+// partial write.
+async fn write_file(directory: &FileSystemDirectoryHandle, name: &str, bytes: &[u8]) -> Result<(), LocalStateStoreError> {
+    let options = FileSystemGetFileOptions::new();
+    options.set_create(true);
+    let handle: FileSystemFileHandle = resolve(directory.get_file_handle_with_options(name, &options)).await?;
+    let writable: FileSystemWritableFileStream = resolve(handle.create_writable()).await?;
+    JsFuture::from(writable.write_with_u8_array(bytes)?).await?;
+    JsFuture::from(writable.close()).await?;
+
+    Ok(())
+}
+
+async fn remove_file(directory: &FileSystemDirectoryHandle, name: &str) {
+    // Already missing is as good as removed.
+    let _ = JsFuture::from(directory.remove_entry(name)).await;
+}
+
+// Keeps state in the Origin Private File System, which browsers evict less eagerly than
+// IndexedDB and which handles large transaction blobs better. The index is read and rewritten
+// whole by each call, so two tabs writing at the same moment can lose one of the writes.
+pub struct OpfsLocalStateStore {
+    root: FileSystemDirectoryHandle,
+    tx_bytes: FileSystemDirectoryHandle,
+}
+
+impl OpfsLocalStateStore {
+    // Fails where the browser has no OPFS, e.g. outside a secure context.
+    pub async fn new() -> Result<Self, LocalStateStoreError> {
+        let storage = web_sys::window()
+            .ok_or_else(|| LocalStateStoreError::ImplementationError(String::from("No window")))?
+            .navigator()
+            .storage();
+        if !js_sys::Reflect::has(&storage, &JsValue::from_str("getDirectory"))? {
+            return Err(LocalStateStoreError::ImplementationError(String::from("OPFS is not supported")));
+        }
+
+        let origin_root: FileSystemDirectoryHandle = resolve(storage.get_directory()).await?;
+        let root = get_directory(&origin_root, ROOT_DIRECTORY).await?;
+        let tx_bytes = get_directory(&root, TX_BYTES_DIRECTORY).await?;
+
+        let result = OpfsLocalStateStore {
+            root,
+            tx_bytes,
+        };
+        result.migrate().await?;
+
+        Ok(result)
+    }
+
+    async fn migrate(&self) -> Result<(), LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+
+        let migrations = pending_migrations(&MIGRATIONS, index.schema_version)?;
+        if migrations.is_empty() {
+            return Ok(());
+        }
+
+        for migration in migrations {
+            migration(&mut index)?;
+        }
+        index.schema_version = MIGRATIONS.len() as u32;
+
+        self.write_index(&index).await
+    }
+
+    async fn read_index(&self) -> Result<OpfsIndex, LocalStateStoreError> {
+        let bytes = read_file(&self.root, INDEX_FILE).await?;
+        if bytes.is_empty() {
+            return Ok(OpfsIndex::default());
+        }
+
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    async fn write_index(&self, index: &OpfsIndex) -> Result<(), LocalStateStoreError> {
+        let bytes = serde_json::to_vec(index)?;
+        write_file(&self.root, INDEX_FILE, &bytes).await
+    }
+
+    async fn write_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration, require_owner: bool) -> Result<(), LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+        let key = file_id.to_string();
+
+        match index.leases.get(&key) {
+            Some(lease) => {
+                lease.check_owner(owner_id)?;
+                if require_owner && lease.owner_id != owner_id {
+                    return Err(LocalStateStoreError::LeaseLost);
+                }
+            },
+            None => {
+                if require_owner {
+                    return Err(LocalStateStoreError::LeaseLost);
+                }
+            },
+        }
+
+        index.leases.insert(key, Lease::new(owner_id, ttl));
+        self.write_index(&index).await
+    }
+}
+
+#[async_trait(?Send)]
+impl LocalStateStore for OpfsLocalStateStore {
+    async fn get_files(&self) -> Result<Vec<Uuid>, LocalStateStoreError> {
+        let index = self.read_index().await?;
+
+        let files = index.files
+            .keys()
+            .map(|file_id| Uuid::parse_str(file_id))
+            .collect::<Result<Vec<Uuid>, _>>()
+            .map_err(|err| LocalStateStoreError::ImplementationError(format!("uuid::Error: {}", err)))?;
+
+        Ok(files)
+    }
+
+    async fn get_txs(&self, file_id: &Uuid) -> Result<Vec<TransactionInfo>, LocalStateStoreError> {
+        let index = self.read_index().await?;
+
+        let file_id = file_id.to_string();
+        if !index.files.contains_key(&file_id) {
+            return Err(LocalStateStoreError::NoSuchFile);
+        }
+
+        Ok(index.tx_infos(&file_id))
+    }
+
+    async fn get_txs_by_status(&self, file_id: &Uuid, statuses: &[TransactionStatus]) -> Result<Vec<TransactionInfo>, LocalStateStoreError> {
+        let results = self.get_txs(file_id)
+            .await?
+            .into_iter()
+            .filter(|tx_info| statuses.contains(&tx_info.status))
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn get_all_txs_by_status(&self, statuses: &[TransactionStatus]) -> Result<Vec<(Uuid, TransactionInfo)>, LocalStateStoreError> {
+        let index = self.read_index().await?;
+
+        let mut results: Vec<(Uuid, TransactionInfo)> = Vec::new();
+        for (tx_id, tx_info) in index.txs.iter() {
+            let status = TransactionStatus::from(tx_info.status.clone());
+            if !statuses.contains(&status) {
+                continue;
+            }
+
+            let file_id = Uuid::parse_str(&tx_info.file_id)
+                .map_err(|err| LocalStateStoreError::ImplementationError(format!("uuid::Error: {}", err)))?;
+            results.push((file_id, to_transaction_info(tx_id, tx_info)));
+        }
+        results.sort_by(|a, b| (a.0, a.1.order).cmp(&(b.0, b.1.order)));
+
+        Ok(results)
+    }
+
+    async fn get_tx_bytes(&self, tx_id: &TransactionId) -> Result<Vec<u8>, LocalStateStoreError> {
+        let index = self.read_index().await?;
+        if !index.txs.contains_key(tx_id) {
+            return Err(LocalStateStoreError::NoSuchTransaction);
+        }
+
+        read_file(&self.tx_bytes, tx_id).await
+    }
+
+    async fn update_tx(&self, tx_id: &TransactionId, submit_id: Option<TransactionSubmitId>, status: Option<TransactionStatus>) -> Result<(), LocalStateStoreError> {
+        self.update_txs(&[(tx_id.clone(), submit_id, status)]).await
+    }
+
+    async fn update_txs(&self, updates: &[TransactionUpdate]) -> Result<(), LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+
+        // The index is only written back once every update has applied.
+        for (tx_id, submit_id, status) in updates {
+            let tx_info = index.txs
+                .get_mut(tx_id)
+                .ok_or(LocalStateStoreError::NoSuchTransaction)?;
+
+            if let Some(submit_id) = submit_id {
+                tx_info.submit_id = Some(submit_id.clone());
+            }
+
+            if let Some(status) = status {
+                tx_info.status = status.clone().into();
+            }
+        }
+
+        self.write_index(&index).await
+    }
+
+    async fn flush_txs(&self, file_id: &Uuid) -> Result<(), LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+
+        let file_id = file_id.to_string();
+        let tx_ids: Vec<TransactionId> = index.tx_infos(&file_id)
+            .into_iter()
+            .map(|tx_info| tx_info.tx_id)
+            .collect();
+        for tx_id in tx_ids.iter() {
+            index.txs.remove(tx_id);
+        }
+        index.files.remove(&file_id);
+        index.uploads.remove(&file_id);
+        self.write_index(&index).await?;
+
+        // Blobs go after the index, so a failure here leaves unreferenced files rather than
+        // transactions with missing bytes.
+        for tx_id in tx_ids.iter() {
+            remove_file(&self.tx_bytes, tx_id).await;
+        }
+
+        Ok(())
+    }
+
+    async fn add_tx(&self, file_id: &Uuid, transaction: &Transaction) -> Result<(), LocalStateStoreError> {
+        self.add_txs(file_id, std::slice::from_ref(transaction)).await
+    }
+
+    async fn add_txs(&self, file_id: &Uuid, transactions: &[Transaction]) -> Result<(), LocalStateStoreError> {
+        let needed: u64 = transactions.iter().map(|transaction| transaction.encoded_len() as u64).sum();
+        check_quota(needed).await?;
+
+        // Blobs are written first and only become visible once the index refers to them.
+        for transaction in transactions {
+            let bytes = transaction.encode_to_vec();
+            write_file(&self.tx_bytes, transaction.get_header_signature(), &bytes).await?;
+        }
+
+        let mut index = self.read_index().await?;
+        let file_id = file_id.to_string();
+        let created_at = chrono::Utc::now().timestamp_millis();
+
+        let mut next_order = index.files.get(&file_id).copied().unwrap_or(0);
+        for transaction in transactions {
+            index.txs.insert(transaction.get_header_signature().to_string(), TxInfo {
+                order: next_order,
+                file_id: file_id.clone(),
+                submit_id: None,
+                status: TransactionStatus::Local.into(),
+                created_at,
+            });
+            next_order += 1;
+        }
+        index.files.insert(file_id, next_order);
+
+        self.write_index(&index).await
+    }
+
+    async fn add_receipt(&self, receipt: &TransactionReceipt) -> Result<(), LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+        index.receipts.insert(receipt.tx_id.clone(), receipt.clone());
+
+        self.write_index(&index).await
+    }
+
+    async fn get_receipt(&self, tx_id: &TransactionId) -> Result<TransactionReceipt, LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+
+        index.receipts
+            .remove(tx_id)
+            .ok_or(LocalStateStoreError::NoSuchReceipt)
+    }
+
+    async fn acquire_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        self.write_lease(file_id, owner_id, ttl, false).await
+    }
+
+    async fn renew_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        self.write_lease(file_id, owner_id, ttl, true).await
+    }
+
+    async fn release_lease(&self, file_id: &Uuid, owner_id: &str) -> Result<(), LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+
+        let key = file_id.to_string();
+        let held_by_owner = match index.leases.get(&key) {
+            None => false,
+            Some(lease) => lease.owner_id == owner_id,
+        };
+
+        if held_by_owner {
+            index.leases.remove(&key);
+            self.write_index(&index).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_upload_info(&self, info: &UploadInfo) -> Result<(), LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+
+        let file_id = info.file_id.to_string();
+        index.files.entry(file_id.clone()).or_insert(0);
+        index.uploads.insert(file_id, info.clone());
+
+        self.write_index(&index).await
+    }
+
+    async fn set_upload_progress(&self, file_id: &Uuid, progress: &UploadProgress) -> Result<(), LocalStateStoreError> {
+        let mut index = self.read_index().await?;
+
+        match index.uploads.get_mut(&file_id.to_string()) {
+            Some(upload) => upload.progress = *progress,
+            None => return Ok(()),
+        }
+
+        self.write_index(&index).await
+    }
+
+    async fn list_pending_uploads(&self) -> Result<Vec<UploadInfo>, LocalStateStoreError> {
+        let index = self.read_index().await?;
+
+        Ok(index.uploads.into_values().collect())
+    }
+
+    async fn stats(&self) -> Result<LocalStateStoreStats, LocalStateStoreError> {
+        let index = self.read_index().await?;
+
+        let mut stats = LocalStateStoreStats {
+            files: index.files.len() as u64,
+            receipts: index.receipts.len() as u64,
+            ..Default::default()
+        };
+
+        for (tx_id, tx_info) in index.txs.iter() {
+            *stats.txs_by_status.entry(tx_info.status.clone().into()).or_default() += 1;
+            stats.tx_bytes += read_file(&self.tx_bytes, tx_id).await?.len() as u64;
+        }
+
+        stats.size_on_disk = storage_usage().await;
+
+        Ok(stats)
+    }
+
+    async fn gc(&self, older_than: Duration) -> Result<u64, LocalStateStoreError> {
+        let cutoff = gc_cutoff(older_than);
+        let index = self.read_index().await?;
+
+        let mut collected = 0;
+        for file_id in self.get_files().await? {
+            if let Some(lease) = index.leases.get(&file_id.to_string()) {
+                if !lease.is_expired() {
+                    continue;
+                }
+            }
+
+            let txs = index.tx_infos(&file_id.to_string());
+            if txs.iter().all(|tx_info| tx_info.created_at <= cutoff) {
+                self.flush_txs(&file_id).await?;
+                collected += 1;
+            }
+        }
+
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::LocalStateStoreError;
+    use crate::state_opfs::OpfsLocalStateStore;
+    use crate::tests::test_local_state_store_common;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    async fn test_local_state_store() -> Result<(), LocalStateStoreError> {
+        let store = Box::new(OpfsLocalStateStore::new().await?);
+        test_local_state_store_common(store).await
+    }
+}
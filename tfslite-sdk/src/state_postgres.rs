@@ -0,0 +1,514 @@
+use std::time::Duration;
+use prost::Message;
+use uuid::Uuid;
+use async_trait::async_trait;
+use tokio_postgres::{Client, NoTls, Row};
+
+use libtfslite::protos::transaction::Transaction;
+use crate::state::{gc_cutoff, pending_migrations, Lease, LocalStateStore, LocalStateStoreError, LocalStateStoreStats, TransactionId, TransactionInfo, TransactionStatus, TransactionSubmitId, TransactionUpdate};
+use crate::types::{TransactionReceipt, UploadInfo, UploadProgress};
+
+// Migrations run in order; MIGRATIONS[i] upgrades the schema from version i to i + 1.
+const MIGRATIONS: [&str; 5] = [
+    "
+    CREATE TABLE IF NOT EXISTS tfs_files (
+        file_id UUID PRIMARY KEY,
+        next_order BIGINT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tfs_txs (
+        tx_id TEXT PRIMARY KEY,
+        file_id UUID NOT NULL REFERENCES tfs_files (file_id) ON DELETE CASCADE,
+        tx_order BIGINT NOT NULL,
+        submit_id TEXT,
+        status TEXT NOT NULL,
+        tx_bytes BYTEA NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tfs_txs_file_order ON tfs_txs (file_id, tx_order);
+    CREATE TABLE IF NOT EXISTS tfs_receipts (
+        tx_id TEXT PRIMARY KEY,
+        receipt BYTEA NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tfs_leases (
+        file_id UUID PRIMARY KEY,
+        owner_id TEXT NOT NULL,
+        expires_at BIGINT NOT NULL
+    );
+    ",
+    "
+    CREATE INDEX IF NOT EXISTS tfs_txs_status ON tfs_txs (status, file_id);
+    ",
+    // Existing rows are dated to the upgrade, so they get a full retention period before gc().
+    "
+    ALTER TABLE tfs_txs ADD COLUMN IF NOT EXISTS created_at BIGINT NOT NULL
+        DEFAULT (extract(epoch FROM now()) * 1000)::BIGINT;
+    CREATE INDEX IF NOT EXISTS tfs_txs_file_created ON tfs_txs (file_id, created_at);
+    ",
+    // Set only for files added through set_upload_info().
+    "
+    ALTER TABLE tfs_files
+        ADD COLUMN IF NOT EXISTS filename TEXT,
+        ADD COLUMN IF NOT EXISTS size BIGINT,
+        ADD COLUMN IF NOT EXISTS chunk_size BIGINT,
+        ADD COLUMN IF NOT EXISTS created_at BIGINT;
+    ",
+    "
+    ALTER TABLE tfs_files
+        ADD COLUMN IF NOT EXISTS bytes_read BIGINT NOT NULL DEFAULT 0,
+        ADD COLUMN IF NOT EXISTS txs_submitted BIGINT NOT NULL DEFAULT 0,
+        ADD COLUMN IF NOT EXISTS txs_committed BIGINT NOT NULL DEFAULT 0,
+        ADD COLUMN IF NOT EXISTS progress_started_at BIGINT NOT NULL DEFAULT 0,
+        ADD COLUMN IF NOT EXISTS progress_updated_at BIGINT NOT NULL DEFAULT 0;
+    ",
+];
+
+impl From<tokio_postgres::Error> for LocalStateStoreError {
+    fn from(value: tokio_postgres::Error) -> Self {
+        LocalStateStoreError::ImplementationError(format!("PostgresError: {}", value))
+    }
+}
+
+fn tx_info_from_row(row: &Row) -> TransactionInfo {
+    TransactionInfo {
+        order: row.get::<_, i64>(0) as u64,
+        tx_id: row.get(1),
+        submit_id: row.get(2),
+        status: TransactionStatus::from(row.get::<_, String>(3)),
+        created_at: row.get(4),
+    }
+}
+
+// Keeps pending uploads in a shared database so any replica of a service can resume them. Each
+// write is a single statement, so no client-side transactions are needed; leases are what keep
+// two replicas from working on the same file at once.
+pub struct PostgresLocalStateStore {
+    client: Client,
+}
+
+impl PostgresLocalStateStore {
+    // Connects without TLS and drives the connection on the current tokio runtime. Use new()
+    // with an already connected client for TLS or pooled setups.
+    pub async fn connect(config: &str) -> Result<Self, LocalStateStoreError> {
+        let (client, connection) = tokio_postgres::connect(config, NoTls).await?;
+        tokio::spawn(async move {
+            let _ = connection.await;
+        });
+
+        Self::new(client).await
+    }
+
+    pub async fn new(client: Client) -> Result<Self, LocalStateStoreError> {
+        let store = PostgresLocalStateStore {
+            client,
+        };
+        store.migrate().await?;
+
+        Ok(store)
+    }
+
+    // Each migration commits together with its version bump. The advisory lock keeps replicas
+    // that start at the same time from migrating concurrently.
+    async fn migrate(&self) -> Result<(), LocalStateStoreError> {
+        self.client
+            .batch_execute("
+                SELECT pg_advisory_lock(hashtext('tfs_schema'));
+                CREATE TABLE IF NOT EXISTS tfs_schema (version INTEGER NOT NULL);
+                INSERT INTO tfs_schema (version) SELECT 0 WHERE NOT EXISTS (SELECT 1 FROM tfs_schema);
+            ")
+            .await?;
+
+        let result = self.run_migrations().await;
+
+        self.client
+            .batch_execute("SELECT pg_advisory_unlock(hashtext('tfs_schema'));")
+            .await?;
+
+        result
+    }
+
+    async fn run_migrations(&self) -> Result<(), LocalStateStoreError> {
+        let version: i32 = self.client
+            .query_one("SELECT version FROM tfs_schema", &[])
+            .await?
+            .get(0);
+
+        let migrations = pending_migrations(&MIGRATIONS, version as u32)?;
+        for (index, migration) in migrations.iter().enumerate() {
+            let next_version = version + index as i32 + 1;
+            let result = self.client
+                .batch_execute(&format!("BEGIN; {} UPDATE tfs_schema SET version = {}; COMMIT;", migration, next_version))
+                .await;
+            if let Err(err) = result {
+                let _ = self.client.batch_execute("ROLLBACK;").await;
+                return Err(err.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn current_lease(&self, file_id: &Uuid) -> Result<Option<Lease>, LocalStateStoreError> {
+        let row = self.client
+            .query_opt("SELECT owner_id, expires_at FROM tfs_leases WHERE file_id = $1", &[file_id])
+            .await?;
+
+        Ok(row.map(|row| Lease { owner_id: row.get(0), expires_at: row.get(1) }))
+    }
+}
+
+#[async_trait]
+impl LocalStateStore for PostgresLocalStateStore {
+    async fn get_files(&self) -> Result<Vec<Uuid>, LocalStateStoreError> {
+        let rows = self.client
+            .query("SELECT file_id FROM tfs_files", &[])
+            .await?;
+
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn get_txs(&self, file_id: &Uuid) -> Result<Vec<TransactionInfo>, LocalStateStoreError> {
+        let file = self.client
+            .query_opt("SELECT 1 FROM tfs_files WHERE file_id = $1", &[file_id])
+            .await?;
+        if file.is_none() {
+            return Err(LocalStateStoreError::NoSuchFile);
+        }
+
+        let rows = self.client
+            .query("SELECT tx_order, tx_id, submit_id, status, created_at FROM tfs_txs WHERE file_id = $1 ORDER BY tx_order", &[file_id])
+            .await?;
+
+        Ok(rows.iter().map(tx_info_from_row).collect())
+    }
+
+    async fn get_txs_by_status(&self, file_id: &Uuid, statuses: &[TransactionStatus]) -> Result<Vec<TransactionInfo>, LocalStateStoreError> {
+        let file = self.client
+            .query_opt("SELECT 1 FROM tfs_files WHERE file_id = $1", &[file_id])
+            .await?;
+        if file.is_none() {
+            return Err(LocalStateStoreError::NoSuchFile);
+        }
+
+        let statuses: Vec<String> = statuses.iter().cloned().map(String::from).collect();
+        let rows = self.client
+            .query(
+                "SELECT tx_order, tx_id, submit_id, status, created_at FROM tfs_txs WHERE file_id = $1 AND status = ANY($2) ORDER BY tx_order",
+                &[file_id, &statuses],
+            )
+            .await?;
+
+        Ok(rows.iter().map(tx_info_from_row).collect())
+    }
+
+    async fn get_all_txs_by_status(&self, statuses: &[TransactionStatus]) -> Result<Vec<(Uuid, TransactionInfo)>, LocalStateStoreError> {
+        let statuses: Vec<String> = statuses.iter().cloned().map(String::from).collect();
+        let rows = self.client
+            .query(
+                "SELECT tx_order, tx_id, submit_id, status, created_at, file_id FROM tfs_txs WHERE status = ANY($1) ORDER BY file_id, tx_order",
+                &[&statuses],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|row| (row.get(5), tx_info_from_row(row))).collect())
+    }
+
+    async fn get_tx_bytes(&self, tx_id: &TransactionId) -> Result<Vec<u8>, LocalStateStoreError> {
+        let row = self.client
+            .query_opt("SELECT tx_bytes FROM tfs_txs WHERE tx_id = $1", &[tx_id])
+            .await?;
+
+        match row {
+            None => Err(LocalStateStoreError::NoSuchTransaction),
+            Some(row) => Ok(row.get(0)),
+        }
+    }
+
+    async fn update_tx(&self, tx_id: &TransactionId, submit_id: Option<TransactionSubmitId>, status: Option<TransactionStatus>) -> Result<(), LocalStateStoreError> {
+        self.update_txs(&[(tx_id.clone(), submit_id, status)]).await
+    }
+
+    async fn update_txs(&self, updates: &[TransactionUpdate]) -> Result<(), LocalStateStoreError> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+
+        let tx_ids: Vec<String> = updates.iter().map(|(tx_id, _, _)| tx_id.clone()).collect();
+        let submit_ids: Vec<Option<String>> = updates.iter().map(|(_, submit_id, _)| submit_id.clone()).collect();
+        let statuses: Vec<Option<String>> = updates.iter().map(|(_, _, status)| status.clone().map(String::from)).collect();
+        let count = updates.len() as i64;
+
+        // Nothing is updated unless every transaction exists.
+        let updated = self.client
+            .execute(
+                "WITH updates AS (
+                    SELECT * FROM unnest($1::text[], $2::text[], $3::text[]) AS u (tx_id, submit_id, status)
+                )
+                UPDATE tfs_txs
+                SET submit_id = COALESCE(updates.submit_id, tfs_txs.submit_id),
+                    status = COALESCE(updates.status, tfs_txs.status)
+                FROM updates
+                WHERE tfs_txs.tx_id = updates.tx_id
+                AND (SELECT count(*) FROM tfs_txs JOIN updates USING (tx_id)) = $4",
+                &[&tx_ids, &submit_ids, &statuses, &count],
+            )
+            .await?;
+
+        if updated == 0 {
+            return Err(LocalStateStoreError::NoSuchTransaction);
+        }
+
+        Ok(())
+    }
+
+    async fn flush_txs(&self, file_id: &Uuid) -> Result<(), LocalStateStoreError> {
+        // Transactions are removed along with the file by the foreign key cascade.
+        self.client
+            .execute("DELETE FROM tfs_files WHERE file_id = $1", &[file_id])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn add_tx(&self, file_id: &Uuid, transaction: &Transaction) -> Result<(), LocalStateStoreError> {
+        self.add_txs(file_id, std::slice::from_ref(transaction)).await
+    }
+
+    async fn add_txs(&self, file_id: &Uuid, transactions: &[Transaction]) -> Result<(), LocalStateStoreError> {
+        if transactions.is_empty() {
+            return Ok(());
+        }
+
+        let tx_ids: Vec<String> = transactions
+            .iter()
+            .map(|transaction| transaction.get_header_signature().to_string())
+            .collect();
+        let tx_bytes = transactions
+            .iter()
+            .map(|transaction| transaction.encode_to_vec())
+            .collect::<Vec<Vec<u8>>>();
+        let count = transactions.len() as i64;
+        let status = String::from(TransactionStatus::Local);
+        let created_at = chrono::Utc::now().timestamp_millis();
+
+        // Reserves a run of orders for the file and inserts the transactions in one statement.
+        self.client
+            .execute(
+                "WITH file AS (
+                    INSERT INTO tfs_files (file_id, next_order) VALUES ($1, $2)
+                    ON CONFLICT (file_id) DO UPDATE SET next_order = tfs_files.next_order + $2
+                    RETURNING next_order - $2 AS first_order
+                )
+                INSERT INTO tfs_txs (tx_id, file_id, tx_order, submit_id, status, tx_bytes, created_at)
+                SELECT tx.tx_id, $1, file.first_order + tx.ordinal - 1, NULL, $3, tx.tx_bytes, $6
+                FROM file, unnest($4::text[], $5::bytea[]) WITH ORDINALITY AS tx (tx_id, tx_bytes, ordinal)",
+                &[file_id, &count, &status, &tx_ids, &tx_bytes, &created_at],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn add_receipt(&self, receipt: &TransactionReceipt) -> Result<(), LocalStateStoreError> {
+        let receipt_bytes = serde_json::to_vec(receipt)
+            .map_err(|err| LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", err)))?;
+
+        self.client
+            .execute(
+                "INSERT INTO tfs_receipts (tx_id, receipt) VALUES ($1, $2)
+                ON CONFLICT (tx_id) DO UPDATE SET receipt = EXCLUDED.receipt",
+                &[&receipt.tx_id, &receipt_bytes],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_receipt(&self, tx_id: &TransactionId) -> Result<TransactionReceipt, LocalStateStoreError> {
+        let row = self.client
+            .query_opt("SELECT receipt FROM tfs_receipts WHERE tx_id = $1", &[tx_id])
+            .await?;
+
+        match row {
+            None => Err(LocalStateStoreError::NoSuchReceipt),
+            Some(row) => serde_json::from_slice(row.get::<_, &[u8]>(0))
+                .map_err(|err| LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", err)))
+        }
+    }
+
+    async fn acquire_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        let lease = Lease::new(owner_id, ttl);
+        let now = chrono::Utc::now().timestamp_millis();
+
+        // The conditional upsert takes the lease only if it is free, ours or expired.
+        let updated = self.client
+            .execute(
+                "INSERT INTO tfs_leases (file_id, owner_id, expires_at) VALUES ($1, $2, $3)
+                ON CONFLICT (file_id) DO UPDATE SET owner_id = EXCLUDED.owner_id, expires_at = EXCLUDED.expires_at
+                WHERE tfs_leases.owner_id = EXCLUDED.owner_id OR tfs_leases.expires_at <= $4",
+                &[file_id, &lease.owner_id, &lease.expires_at, &now],
+            )
+            .await?;
+
+        if updated == 0 {
+            if let Some(current) = self.current_lease(file_id).await? {
+                current.check_owner(owner_id)?;
+            }
+            return Err(LocalStateStoreError::LeaseLost);
+        }
+
+        Ok(())
+    }
+
+    async fn renew_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        let lease = Lease::new(owner_id, ttl);
+
+        let updated = self.client
+            .execute(
+                "UPDATE tfs_leases SET expires_at = $3 WHERE file_id = $1 AND owner_id = $2",
+                &[file_id, &lease.owner_id, &lease.expires_at],
+            )
+            .await?;
+
+        if updated == 0 {
+            if let Some(current) = self.current_lease(file_id).await? {
+                current.check_owner(owner_id)?;
+            }
+            return Err(LocalStateStoreError::LeaseLost);
+        }
+
+        Ok(())
+    }
+
+    async fn release_lease(&self, file_id: &Uuid, owner_id: &str) -> Result<(), LocalStateStoreError> {
+        self.client
+            .execute("DELETE FROM tfs_leases WHERE file_id = $1 AND owner_id = $2", &[file_id, &owner_id])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_upload_info(&self, info: &UploadInfo) -> Result<(), LocalStateStoreError> {
+        let size = info.size as i64;
+        let chunk_size = info.chunk_size as i64;
+
+        self.client
+            .execute(
+                "INSERT INTO tfs_files (file_id, next_order, filename, size, chunk_size, created_at) VALUES ($1, 0, $2, $3, $4, $5)
+                ON CONFLICT (file_id) DO UPDATE SET filename = EXCLUDED.filename, size = EXCLUDED.size,
+                    chunk_size = EXCLUDED.chunk_size, created_at = EXCLUDED.created_at",
+                &[&info.file_id, &info.filename, &size, &chunk_size, &info.created_at],
+            )
+            .await?;
+
+        self.set_upload_progress(&info.file_id, &info.progress).await
+    }
+
+    async fn set_upload_progress(&self, file_id: &Uuid, progress: &UploadProgress) -> Result<(), LocalStateStoreError> {
+        let bytes_read = progress.bytes_read as i64;
+        let txs_submitted = progress.txs_submitted as i64;
+        let txs_committed = progress.txs_committed as i64;
+
+        self.client
+            .execute(
+                "UPDATE tfs_files SET bytes_read = $2, txs_submitted = $3, txs_committed = $4, progress_started_at = $5,
+                    progress_updated_at = $6 WHERE file_id = $1 AND filename IS NOT NULL",
+                &[file_id, &bytes_read, &txs_submitted, &txs_committed, &progress.started_at, &progress.updated_at],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn list_pending_uploads(&self) -> Result<Vec<UploadInfo>, LocalStateStoreError> {
+        let rows = self.client
+            .query("SELECT file_id, filename, size, chunk_size, created_at, bytes_read, txs_submitted, txs_committed, progress_started_at,
+                progress_updated_at FROM tfs_files WHERE filename IS NOT NULL", &[])
+            .await?;
+
+        let results = rows
+            .iter()
+            .map(|row| UploadInfo {
+                file_id: row.get(0),
+                filename: row.get(1),
+                size: row.get::<_, i64>(2) as u64,
+                chunk_size: row.get::<_, i64>(3) as u64,
+                created_at: row.get(4),
+                progress: UploadProgress {
+                    bytes_read: row.get::<_, i64>(5) as u64,
+                    txs_submitted: row.get::<_, i64>(6) as u64,
+                    txs_committed: row.get::<_, i64>(7) as u64,
+                    started_at: row.get(8),
+                    updated_at: row.get(9),
+                },
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn stats(&self) -> Result<LocalStateStoreStats, LocalStateStoreError> {
+        let row = self.client
+            .query_one(
+                "SELECT
+                    (SELECT count(*) FROM tfs_files),
+                    (SELECT count(*) FROM tfs_receipts),
+                    (SELECT COALESCE(sum(octet_length(tx_bytes)), 0)::BIGINT FROM tfs_txs),
+                    (SELECT sum(pg_total_relation_size(relid))::BIGINT FROM pg_catalog.pg_statio_user_tables WHERE relname LIKE 'tfs\\_%')",
+                &[],
+            )
+            .await?;
+
+        let mut stats = LocalStateStoreStats {
+            files: row.get::<_, i64>(0) as u64,
+            receipts: row.get::<_, i64>(1) as u64,
+            tx_bytes: row.get::<_, i64>(2) as u64,
+            size_on_disk: row.get::<_, Option<i64>>(3).map(|size| size as u64),
+            ..Default::default()
+        };
+
+        let rows = self.client
+            .query("SELECT status, count(*) FROM tfs_txs GROUP BY status", &[])
+            .await?;
+        for row in rows {
+            let status = TransactionStatus::from(row.get::<_, String>(0));
+            *stats.txs_by_status.entry(status).or_default() += row.get::<_, i64>(1) as u64;
+        }
+
+        Ok(stats)
+    }
+
+    async fn gc(&self, older_than: Duration) -> Result<u64, LocalStateStoreError> {
+        let cutoff = gc_cutoff(older_than);
+        let now = chrono::Utc::now().timestamp_millis();
+
+        // Transactions are removed along with their files by the foreign key cascade.
+        let collected = self.client
+            .execute(
+                "DELETE FROM tfs_files
+                WHERE NOT EXISTS (SELECT 1 FROM tfs_txs WHERE tfs_txs.file_id = tfs_files.file_id AND tfs_txs.created_at > $1)
+                AND NOT EXISTS (SELECT 1 FROM tfs_leases WHERE tfs_leases.file_id = tfs_files.file_id AND tfs_leases.expires_at > $2)",
+                &[&cutoff, &now],
+            )
+            .await?;
+
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::LocalStateStoreError;
+    use crate::state_postgres::PostgresLocalStateStore;
+    use crate::tests::test_local_state_store_common;
+
+    // Needs a database to run against, e.g. TFS_TEST_POSTGRES_URL="host=localhost user=postgres".
+    #[tokio::test]
+    async fn test_local_state_store() -> Result<(), LocalStateStoreError> {
+        let config = match std::env::var("TFS_TEST_POSTGRES_URL") {
+            Ok(config) => config,
+            Err(_) => return Ok(()),
+        };
+
+        let store = Box::new(PostgresLocalStateStore::connect(&config).await?);
+        test_local_state_store_common(store).await
+    }
+}
@@ -1,16 +1,67 @@
-use std::path::Path;
-use protobuf::Message;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use prost::Message;
 use uuid::Uuid;
 use async_trait::async_trait;
 
-use redb::{Database,ReadableTable, ReadableMultimapTable, TableDefinition, MultimapTableDefinition, TransactionError, TableError, StorageError, CommitError};
+use redb::{Database,ReadableTable, ReadableMultimapTable, TableDefinition, MultimapTableDefinition, WriteTransaction, TransactionError, TableError, StorageError, CommitError};
 use libtfslite::protos::transaction::Transaction;
-use crate::state::{LocalStateStore, LocalStateStoreError, TransactionId, TransactionInfo, TransactionStatus, TransactionSubmitId};
+use crate::state::{gc_cutoff, pending_migrations, Lease, LocalStateStore, LocalStateStoreError, LocalStateStoreStats, TransactionId, TransactionInfo, TransactionStatus, TransactionSubmitId, TransactionUpdate};
+use crate::types::{TransactionReceipt, UploadInfo, UploadProgress};
 
 const FILES_TABLE: TableDefinition<u128, u64> = TableDefinition::new("files");
 const FILE_TXS_TABLE: MultimapTableDefinition<u128, &str> = MultimapTableDefinition::new("file_txs");
 const TX_INFO_TABLE: TableDefinition<&str, (u64, &str, &str)> = TableDefinition::new("tx_info");
 const TX_BYTES_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("tx_bytes");
+const TX_RECEIPTS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("tx_receipts");
+const TX_CREATED_TABLE: TableDefinition<&str, i64> = TableDefinition::new("tx_created");
+const TX_QUARANTINE_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("tx_quarantine");
+const LEASES_TABLE: TableDefinition<u128, (&str, i64)> = TableDefinition::new("leases");
+// Kept apart from the files table, whose values are the next order to hand out.
+const UPLOADS_TABLE: TableDefinition<u128, &[u8]> = TableDefinition::new("uploads");
+const META_TABLE: TableDefinition<&str, u32> = TableDefinition::new("meta");
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+type Migration = fn(&WriteTransaction) -> Result<(), LocalStateStoreError>;
+
+// Stores created before versioning have no meta table but already hold the version 1 tables,
+// which migration 1 leaves untouched.
+const MIGRATIONS: [Migration; 3] = [
+    migrate_create_tables,
+    migrate_add_tx_created,
+    migrate_add_uploads,
+];
+
+fn migrate_create_tables(write_txn: &WriteTransaction) -> Result<(), LocalStateStoreError> {
+    let _table_files = write_txn.open_table(FILES_TABLE)?;
+    let _table_file_txs = write_txn.open_multimap_table(FILE_TXS_TABLE)?;
+    let _table_info = write_txn.open_table(TX_INFO_TABLE)?;
+    let _table_tx_bytes = write_txn.open_table(TX_BYTES_TABLE)?;
+    let _table_tx_receipts = write_txn.open_table(TX_RECEIPTS_TABLE)?;
+    let _table_leases = write_txn.open_table(LEASES_TABLE)?;
+    Ok(())
+}
+
+// Transactions stored before creation times were recorded are dated to the upgrade, so they get
+// a full retention period before gc() considers them.
+fn migrate_add_tx_created(write_txn: &WriteTransaction) -> Result<(), LocalStateStoreError> {
+    let table_info = write_txn.open_table(TX_INFO_TABLE)?;
+    let mut table_created = write_txn.open_table(TX_CREATED_TABLE)?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    for entry in table_info.iter()? {
+        let (tx_id, _tx_info) = entry?;
+        let _ = table_created.insert(tx_id.value(), now)?;
+    }
+    Ok(())
+}
+
+fn migrate_add_uploads(write_txn: &WriteTransaction) -> Result<(), LocalStateStoreError> {
+    let _table_uploads = write_txn.open_table(UPLOADS_TABLE)?;
+    Ok(())
+}
 
 impl From<TransactionError> for LocalStateStoreError {
     fn from(value: TransactionError) -> Self {
@@ -36,30 +87,196 @@ impl From<CommitError> for LocalStateStoreError {
     }
 }
 
+// What fsck() does with the entries it finds broken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsckRepair {
+    // Only report them.
+    None,
+    // Remove them.
+    Drop,
+    // Remove them, but keep the bytes of removed transactions in the tx_quarantine table.
+    Quarantine,
+}
+
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    // Listed for a file but missing their tx_info or tx_bytes.
+    pub incomplete_txs: Vec<(Uuid, TransactionId)>,
+    // Stored but not listed for any file.
+    pub orphaned_txs: Vec<TransactionId>,
+    // Have transactions listed but no entry in the files table.
+    pub orphaned_files: Vec<Uuid>,
+    // Transaction orders are not 0..n, or the file's next order would reuse one.
+    pub misordered_files: Vec<Uuid>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.incomplete_txs.is_empty()
+            && self.orphaned_txs.is_empty()
+            && self.orphaned_files.is_empty()
+            && self.misordered_files.is_empty()
+    }
+}
+
 pub struct RedbLocalStateStore {
-    db: Database
+    db: Database,
+    path: PathBuf,
 }
 
 impl RedbLocalStateStore {
     pub async fn new(path: impl AsRef<Path>) -> Result<Self, LocalStateStoreError> {
         let db = Database::create(&path).unwrap();
 
+        // All pending migrations run in one write transaction, so a failure leaves the store as
+        // it was.
         let write_txn = db.begin_write()?;
         {
-            let _table_files = write_txn.open_table(FILES_TABLE)?;
-            let _table_file_txs = write_txn.open_multimap_table(FILE_TXS_TABLE)?;
-            let _table_info = write_txn.open_table(TX_INFO_TABLE)?;
-            let _table_tx_bytes = write_txn.open_table(TX_BYTES_TABLE)?;
+            let mut table_meta = write_txn.open_table(META_TABLE)?;
+            let version = match table_meta.get(SCHEMA_VERSION_KEY)? {
+                None => 0,
+                Some(version) => version.value(),
+            };
+
+            let migrations = pending_migrations(&MIGRATIONS, version)?;
+            for migration in migrations {
+                migration(&write_txn)?;
+            }
+            let _ = table_meta.insert(SCHEMA_VERSION_KEY, MIGRATIONS.len() as u32)?;
         }
         write_txn.commit()?;
 
         let result = RedbLocalStateStore{
             db,
+            path: path.as_ref().to_path_buf(),
         };
 
         Ok(result)
     }
 
+    // Opens the store and runs fsck() on it, e.g. after an unclean shutdown.
+    pub async fn new_with_fsck(path: impl AsRef<Path>, repair: FsckRepair) -> Result<(Self, FsckReport), LocalStateStoreError> {
+        let store = Self::new(path).await?;
+        let report = store.fsck(repair).await?;
+
+        Ok((store, report))
+    }
+
+    // Checks that the tables agree with each other. Repairs, when asked for, happen in the same
+    // write transaction as the check, and files left with gaps in their orders are renumbered.
+    pub async fn fsck(&self, repair: FsckRepair) -> Result<FsckReport, LocalStateStoreError> {
+        let mut report = FsckReport::default();
+
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table_files = write_txn.open_table(FILES_TABLE)?;
+            let mut table_file_txs = write_txn.open_multimap_table(FILE_TXS_TABLE)?;
+            let mut table_info = write_txn.open_table(TX_INFO_TABLE)?;
+            let mut table_bytes = write_txn.open_table(TX_BYTES_TABLE)?;
+            let mut table_created = write_txn.open_table(TX_CREATED_TABLE)?;
+            let mut table_quarantine = write_txn.open_table(TX_QUARANTINE_TABLE)?;
+
+            let mut files: HashMap<u128, u64> = HashMap::new();
+            for entry in table_files.iter()? {
+                let (file_id, next_order) = entry?;
+                files.insert(file_id.value(), next_order.value());
+            }
+
+            let mut file_txs: HashMap<u128, Vec<String>> = HashMap::new();
+            for entry in table_file_txs.iter()? {
+                let (file_id, tx_ids) = entry?;
+                let tx_ids = tx_ids
+                    .map(|tx_id| tx_id.map(|tx_id| tx_id.value().to_string()))
+                    .collect::<Result<Vec<String>, _>>()?;
+                file_txs.insert(file_id.value(), tx_ids);
+            }
+
+            let mut listed: HashMap<u128, Vec<(u64, String)>> = HashMap::new();
+            let mut listed_tx_ids: HashSet<String> = HashSet::new();
+            for (file_id, tx_ids) in file_txs.iter() {
+                if !files.contains_key(file_id) {
+                    report.orphaned_files.push(Uuid::from_u128(*file_id));
+                    continue;
+                }
+
+                let mut orders: Vec<(u64, String)> = Vec::new();
+                for tx_id in tx_ids {
+                    let order = table_info.get(tx_id.as_str())?.map(|tx_info| tx_info.value().0);
+                    let has_bytes = table_bytes.get(tx_id.as_str())?.is_some();
+                    match order {
+                        Some(order) if has_bytes => {
+                            orders.push((order, tx_id.clone()));
+                            listed_tx_ids.insert(tx_id.clone());
+                        },
+                        _ => report.incomplete_txs.push((Uuid::from_u128(*file_id), tx_id.clone())),
+                    }
+                }
+                orders.sort();
+
+                let contiguous = orders.iter().enumerate().all(|(index, (order, _tx_id))| *order == index as u64);
+                if !contiguous || files[file_id] < orders.len() as u64 {
+                    report.misordered_files.push(Uuid::from_u128(*file_id));
+                }
+                listed.insert(*file_id, orders);
+            }
+
+            let incomplete_tx_ids: HashSet<String> = report.incomplete_txs.iter().map(|(_file_id, tx_id)| tx_id.clone()).collect();
+            let mut stored_tx_ids: BTreeSet<String> = BTreeSet::new();
+            for entry in table_info.iter()? {
+                stored_tx_ids.insert(entry?.0.value().to_string());
+            }
+            for entry in table_bytes.iter()? {
+                stored_tx_ids.insert(entry?.0.value().to_string());
+            }
+            for entry in table_created.iter()? {
+                stored_tx_ids.insert(entry?.0.value().to_string());
+            }
+            report.orphaned_txs = stored_tx_ids
+                .into_iter()
+                .filter(|tx_id| !listed_tx_ids.contains(tx_id) && !incomplete_tx_ids.contains(tx_id))
+                .collect();
+
+            if repair == FsckRepair::None {
+                return Ok(report);
+            }
+
+            for file_id in report.orphaned_files.iter() {
+                let _ = table_file_txs.remove_all(file_id.as_u128())?;
+            }
+            for (file_id, tx_id) in report.incomplete_txs.iter() {
+                let _ = table_file_txs.remove(file_id.as_u128(), tx_id.as_str())?;
+            }
+
+            for tx_id in incomplete_tx_ids.iter().chain(report.orphaned_txs.iter()) {
+                let _ = table_info.remove(tx_id.as_str())?;
+                let _ = table_created.remove(tx_id.as_str())?;
+                if let Some(tx_bytes) = table_bytes.remove(tx_id.as_str())? {
+                    if repair == FsckRepair::Quarantine {
+                        let _ = table_quarantine.insert(tx_id.as_str(), tx_bytes.value())?;
+                    }
+                }
+            }
+
+            for file_id in report.misordered_files.iter() {
+                let orders = &listed[&file_id.as_u128()];
+                for (index, (_order, tx_id)) in orders.iter().enumerate() {
+                    let (submit_id, status) = match table_info.get(tx_id.as_str())? {
+                        None => continue,
+                        Some(tx_info) => {
+                            let value = tx_info.value();
+                            (value.1.to_string(), value.2.to_string())
+                        },
+                    };
+                    let _ = table_info.insert(tx_id.as_str(), (index as u64, submit_id.as_str(), status.as_str()))?;
+                }
+                let _ = table_files.insert(file_id.as_u128(), orders.len() as u64)?;
+            }
+        }
+        write_txn.commit()?;
+
+        Ok(report)
+    }
+
     pub async fn set_has_file(&self, file_id: &uuid::Uuid) -> Result<(), LocalStateStoreError> {
         let write_txn = self.db.begin_write()?;
         {
@@ -71,6 +288,50 @@ impl RedbLocalStateStore {
         Ok(())
     }
 
+    async fn write_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration, require_owner: bool) -> Result<(), LocalStateStoreError> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table_leases = write_txn.open_table(LEASES_TABLE)?;
+
+            let current = table_leases.get(file_id.as_u128())?.map(|value| {
+                let (owner_id, expires_at) = value.value();
+                Lease { owner_id: owner_id.to_string(), expires_at }
+            });
+
+            match current {
+                Some(lease) => {
+                    lease.check_owner(owner_id)?;
+                    if require_owner && lease.owner_id != owner_id {
+                        return Err(LocalStateStoreError::LeaseLost);
+                    }
+                },
+                None => {
+                    if require_owner {
+                        return Err(LocalStateStoreError::LeaseLost);
+                    }
+                },
+            }
+
+            let lease = Lease::new(owner_id, ttl);
+            let _ = table_leases.insert(file_id.as_u128(), (lease.owner_id.as_str(), lease.expires_at))?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    async fn current_lease(&self, file_id: &Uuid) -> Result<Option<Lease>, LocalStateStoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table_leases = read_txn.open_table(LEASES_TABLE)?;
+
+        let lease = table_leases.get(file_id.as_u128())?.map(|value| {
+            let (owner_id, expires_at) = value.value();
+            Lease { owner_id: owner_id.to_string(), expires_at }
+        });
+
+        Ok(lease)
+    }
+
     pub async fn check_has_file(&self, file_id: &uuid::Uuid) -> Result<(), LocalStateStoreError> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(FILES_TABLE)?;
@@ -84,7 +345,7 @@ impl RedbLocalStateStore {
     }
 }
 
-#[async_trait(?Send)]
+#[async_trait]
 impl LocalStateStore for RedbLocalStateStore {
     async fn get_files(&self) -> Result<Vec<Uuid>, LocalStateStoreError> {
         let read_txn = self.db.begin_read()?;
@@ -101,6 +362,7 @@ impl LocalStateStore for RedbLocalStateStore {
 
         let table_file_txs = read_txn.open_multimap_table(FILE_TXS_TABLE)?;
         let table_tx_info = read_txn.open_table(TX_INFO_TABLE)?;
+        let table_tx_created = read_txn.open_table(TX_CREATED_TABLE)?;
 
         let mut results = Vec::<TransactionInfo>::new();
         for file_tx in table_file_txs.get(file_id.as_u128())? {
@@ -109,6 +371,7 @@ impl LocalStateStore for RedbLocalStateStore {
 
             let tx_info = table_tx_info.get(file_tx_id)?.unwrap();
             let (order, submit_id, status) =  tx_info.value();
+            let created_at = table_tx_created.get(file_tx_id)?.map(|created_at| created_at.value()).unwrap_or(0);
 
             results.push(TransactionInfo{
                 order,
@@ -117,7 +380,8 @@ impl LocalStateStore for RedbLocalStateStore {
                     "" => None,
                     other => Some(other.to_string()),
                 },
-                status: TransactionStatus::from(status.to_string())
+                status: TransactionStatus::from(status.to_string()),
+                created_at,
             });
         }
 
@@ -126,6 +390,28 @@ impl LocalStateStore for RedbLocalStateStore {
         Ok(results)
     }
 
+    // tx_info rows are small and only a file's own rows are read, so filtering here is cheap.
+    async fn get_txs_by_status(&self, file_id: &Uuid, statuses: &[TransactionStatus]) -> Result<Vec<TransactionInfo>, LocalStateStoreError> {
+        let results = self.get_txs(file_id)
+            .await?
+            .into_iter()
+            .filter(|tx_info| statuses.contains(&tx_info.status))
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn get_all_txs_by_status(&self, statuses: &[TransactionStatus]) -> Result<Vec<(Uuid, TransactionInfo)>, LocalStateStoreError> {
+        let mut results: Vec<(Uuid, TransactionInfo)> = Vec::new();
+        for file_id in self.get_files().await? {
+            for tx_info in self.get_txs_by_status(&file_id, statuses).await? {
+                results.push((file_id, tx_info));
+            }
+        }
+
+        Ok(results)
+    }
+
     async fn get_tx_bytes(&self, tx_id: &TransactionId) -> Result<Vec<u8>, LocalStateStoreError> {
         let read_txn = self.db.begin_read()?;
 
@@ -139,41 +425,39 @@ impl LocalStateStore for RedbLocalStateStore {
     }
 
     async fn update_tx(&self, tx_id: &TransactionId, submit_id: Option<TransactionSubmitId>, status: Option<TransactionStatus>) -> Result<(), LocalStateStoreError> {
-        let order_db: u64;
-        let mut submit_id_db: String;
-        let mut status_db: String;
+        self.update_txs(&[(tx_id.clone(), submit_id, status)]).await
+    }
 
+    async fn update_txs(&self, updates: &[TransactionUpdate]) -> Result<(), LocalStateStoreError> {
         let mut need_commit = false;
 
         let write_txn = self.db.begin_write()?;
         {
-            let table_tx_info = write_txn.open_table(TX_INFO_TABLE)?;
+            let mut table_tx_info = write_txn.open_table(TX_INFO_TABLE)?;
 
-            let value = table_tx_info.get(tx_id.as_str())?;
-            match value {
-                None => {
-                    return Err(LocalStateStoreError::NoSuchTransaction);
-                },
-                Some(tx_info) => {
-                    let value = tx_info.value();
-                    (order_db, submit_id_db, status_db) = (value.0, value.1.to_string(), value.2.to_string());
+            for (tx_id, submit_id, status) in updates {
+                let (order_db, mut submit_id_db, mut status_db) = match table_tx_info.get(tx_id.as_str())? {
+                    None => {
+                        return Err(LocalStateStoreError::NoSuchTransaction);
+                    },
+                    Some(tx_info) => {
+                        let value = tx_info.value();
+                        (value.0, value.1.to_string(), value.2.to_string())
+                    }
+                };
+
+                if let Some(submit_id) = submit_id {
+                    submit_id_db = submit_id.clone();
+                    need_commit = true;
                 }
-            }
-        }
-        {
-            let mut table_tx_info = write_txn.open_table(TX_INFO_TABLE)?;
 
-            if let Some(submit_id) = submit_id {
-                submit_id_db = submit_id;
-                need_commit = true;
-            }
+                if let Some(status) = status {
+                    status_db = status.clone().into();
+                    need_commit = true;
+                }
 
-            if let Some(status) = status {
-                status_db = status.into();
-                need_commit = true;
+                table_tx_info.insert(tx_id.as_str(), (order_db, submit_id_db.as_str(), status_db.as_str()))?;
             }
-
-            table_tx_info.insert(tx_id.as_str(), (order_db, submit_id_db.as_str(), status_db.as_str()))?;
         }
 
         if need_commit {
@@ -190,6 +474,7 @@ impl LocalStateStore for RedbLocalStateStore {
             let mut table_file_txs = write_txn.open_multimap_table(FILE_TXS_TABLE)?;
             let mut table_info = write_txn.open_table(TX_INFO_TABLE)?;
             let mut table_bytes = write_txn.open_table(TX_BYTES_TABLE)?;
+            let mut table_created = write_txn.open_table(TX_CREATED_TABLE)?;
 
             for file_tx in table_file_txs.get(file_id.as_u128())? {
                 let file_tx = file_tx?;
@@ -197,9 +482,13 @@ impl LocalStateStore for RedbLocalStateStore {
 
                 let _ = table_info.remove(tx_id)?;
                 let _ = table_bytes.remove(tx_id)?;
+                let _ = table_created.remove(tx_id)?;
             }
             let _ = table_files.remove(file_id.as_u128())?;
             let _ = table_file_txs.remove_all(file_id.as_u128())?;
+
+            let mut table_uploads = write_txn.open_table(UPLOADS_TABLE)?;
+            let _ = table_uploads.remove(file_id.as_u128())?;
         }
         write_txn.commit()?;
 
@@ -208,7 +497,12 @@ impl LocalStateStore for RedbLocalStateStore {
 
 
     async fn add_tx(&self, file_id: &Uuid, transaction: &Transaction) -> Result<(), LocalStateStoreError> {
-        let next_order: u64;
+        self.add_txs(file_id, std::slice::from_ref(transaction)).await
+    }
+
+    async fn add_txs(&self, file_id: &Uuid, transactions: &[Transaction]) -> Result<(), LocalStateStoreError> {
+        let mut next_order: u64;
+        let created_at = chrono::Utc::now().timestamp_millis();
 
         let write_txn = self.db.begin_write()?;
         {
@@ -219,28 +513,195 @@ impl LocalStateStore for RedbLocalStateStore {
             };
         }
         {
+            let mut table_file_txs = write_txn.open_multimap_table(FILE_TXS_TABLE)?;
+            let mut table_info = write_txn.open_table(TX_INFO_TABLE)?;
+            let mut table_bytes = write_txn.open_table(TX_BYTES_TABLE)?;
+            let mut table_created = write_txn.open_table(TX_CREATED_TABLE)?;
+
+            for transaction in transactions {
+                let _ = table_file_txs.insert(file_id.as_u128(), transaction.get_header_signature())?;
+                let _ = table_info.insert(transaction.get_header_signature(), (next_order, "", String::from(TransactionStatus::Local).as_str()))?;
+                let _ = table_bytes.insert(transaction.get_header_signature(), transaction.encode_to_vec().as_slice());
+                let _ = table_created.insert(transaction.get_header_signature(), created_at)?;
+                next_order += 1;
+            }
+
             let mut table_files = write_txn.open_table(FILES_TABLE)?;
-            let _ = table_files.insert(file_id.as_u128(), next_order + 1)?;
+            let _ = table_files.insert(file_id.as_u128(), next_order)?;
+        }
+        write_txn.commit()?;
 
-            let mut table_file_txs = write_txn.open_multimap_table(FILE_TXS_TABLE)?;
-            let _ = table_file_txs.insert(file_id.as_u128(), transaction.get_header_signature())?;
+        Ok(())
+    }
 
-            let mut table_info = write_txn.open_table(TX_INFO_TABLE)?;
-            let _ = table_info.insert(transaction.get_header_signature(), (next_order, "", String::from(TransactionStatus::Local).as_str()))?;
+    async fn add_receipt(&self, receipt: &TransactionReceipt) -> Result<(), LocalStateStoreError> {
+        let receipt_bytes = serde_json::to_vec(receipt)
+            .map_err(|err| LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", err)))?;
 
-            let mut table_bytes = write_txn.open_table(TX_BYTES_TABLE)?;
-            let _ = table_bytes.insert(transaction.get_header_signature(), transaction.write_to_bytes().unwrap().as_slice());
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table_receipts = write_txn.open_table(TX_RECEIPTS_TABLE)?;
+            let _ = table_receipts.insert(receipt.tx_id.as_str(), receipt_bytes.as_slice())?;
         }
         write_txn.commit()?;
 
         Ok(())
     }
+
+    async fn get_receipt(&self, tx_id: &TransactionId) -> Result<TransactionReceipt, LocalStateStoreError> {
+        let read_txn = self.db.begin_read()?;
+
+        let table_receipts = read_txn.open_table(TX_RECEIPTS_TABLE)?;
+        let value = table_receipts.get(tx_id.as_str())?;
+
+        match value {
+            None => Err(LocalStateStoreError::NoSuchReceipt),
+            Some(bytes) => serde_json::from_slice(bytes.value())
+                .map_err(|err| LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", err)))
+        }
+    }
+
+    async fn acquire_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        self.write_lease(file_id, owner_id, ttl, false).await
+    }
+
+    async fn renew_lease(&self, file_id: &Uuid, owner_id: &str, ttl: Duration) -> Result<(), LocalStateStoreError> {
+        self.write_lease(file_id, owner_id, ttl, true).await
+    }
+
+    async fn release_lease(&self, file_id: &Uuid, owner_id: &str) -> Result<(), LocalStateStoreError> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table_leases = write_txn.open_table(LEASES_TABLE)?;
+
+            let held_by_owner = match table_leases.get(file_id.as_u128())? {
+                None => false,
+                Some(value) => value.value().0 == owner_id,
+            };
+
+            if held_by_owner {
+                let _ = table_leases.remove(file_id.as_u128())?;
+            }
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    async fn set_upload_info(&self, info: &UploadInfo) -> Result<(), LocalStateStoreError> {
+        let info_bytes = serde_json::to_vec(info)
+            .map_err(|err| LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", err)))?;
+
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table_files = write_txn.open_table(FILES_TABLE)?;
+            if table_files.get(info.file_id.as_u128())?.is_none() {
+                let _ = table_files.insert(info.file_id.as_u128(), 0)?;
+            }
+
+            let mut table_uploads = write_txn.open_table(UPLOADS_TABLE)?;
+            let _ = table_uploads.insert(info.file_id.as_u128(), info_bytes.as_slice())?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    async fn set_upload_progress(&self, file_id: &Uuid, progress: &UploadProgress) -> Result<(), LocalStateStoreError> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table_uploads = write_txn.open_table(UPLOADS_TABLE)?;
+            let info = table_uploads.get(file_id.as_u128())?
+                .map(|info_bytes| serde_json::from_slice::<UploadInfo>(info_bytes.value()))
+                .transpose()
+                .map_err(|err| LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", err)))?;
+
+            if let Some(mut info) = info {
+                info.progress = *progress;
+                let info_bytes = serde_json::to_vec(&info)
+                    .map_err(|err| LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", err)))?;
+                let _ = table_uploads.insert(file_id.as_u128(), info_bytes.as_slice())?;
+            }
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    async fn list_pending_uploads(&self) -> Result<Vec<UploadInfo>, LocalStateStoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table_uploads = read_txn.open_table(UPLOADS_TABLE)?;
+
+        let mut results: Vec<UploadInfo> = Vec::new();
+        for entry in table_uploads.iter()? {
+            let (_file_id, info_bytes) = entry?;
+            let info = serde_json::from_slice(info_bytes.value())
+                .map_err(|err| LocalStateStoreError::ImplementationError(format!("serde_json::Error: {}", err)))?;
+            results.push(info);
+        }
+
+        Ok(results)
+    }
+
+    async fn stats(&self) -> Result<LocalStateStoreStats, LocalStateStoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table_files = read_txn.open_table(FILES_TABLE)?;
+        let table_info = read_txn.open_table(TX_INFO_TABLE)?;
+        let table_bytes = read_txn.open_table(TX_BYTES_TABLE)?;
+        let table_receipts = read_txn.open_table(TX_RECEIPTS_TABLE)?;
+
+        let mut stats = LocalStateStoreStats {
+            files: table_files.len()?,
+            receipts: table_receipts.len()?,
+            size_on_disk: std::fs::metadata(&self.path).ok().map(|metadata| metadata.len()),
+            ..Default::default()
+        };
+
+        for entry in table_info.iter()? {
+            let (_tx_id, tx_info) = entry?;
+            let status = TransactionStatus::from(tx_info.value().2.to_string());
+            *stats.txs_by_status.entry(status).or_default() += 1;
+        }
+
+        for entry in table_bytes.iter()? {
+            let (_tx_id, tx_bytes) = entry?;
+            stats.tx_bytes += tx_bytes.value().len() as u64;
+        }
+
+        Ok(stats)
+    }
+
+    async fn gc(&self, older_than: Duration) -> Result<u64, LocalStateStoreError> {
+        let cutoff = gc_cutoff(older_than);
+
+        let mut collected = 0;
+        for file_id in self.get_files().await? {
+            if let Some(lease) = self.current_lease(&file_id).await? {
+                if !lease.is_expired() {
+                    continue;
+                }
+            }
+
+            let txs = self.get_txs(&file_id).await?;
+            if txs.iter().all(|tx_info| tx_info.created_at <= cutoff) {
+                self.flush_txs(&file_id).await?;
+                collected += 1;
+            }
+        }
+
+        Ok(collected)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::state::LocalStateStoreError;
-    use crate::state_redb::RedbLocalStateStore;
+    use redb::ReadableTable;
+    use uuid::Uuid;
+    use libtfslite::client::keys::PrivateKey;
+    use libtfslite::client::payload::{PayloadBuilder, PayloadOperation};
+    use libtfslite::client::transaction::TransactionBuilder;
+    use crate::state::{LocalStateStore, LocalStateStoreError};
+    use crate::state_redb::{FsckRepair, RedbLocalStateStore, TX_INFO_TABLE, TX_QUARANTINE_TABLE};
     use crate::tests::test_local_state_store_common;
 
     #[tokio::test]
@@ -248,4 +709,56 @@ mod tests {
         let store = Box::new(RedbLocalStateStore::new("/tmp/redb-test.db").await?);
         test_local_state_store_common(store).await
     }
+
+    #[tokio::test]
+    async fn test_fsck() -> Result<(), LocalStateStoreError> {
+        let path = "/tmp/redb-fsck-test.db";
+        let _ = std::fs::remove_file(path);
+        let store = RedbLocalStateStore::new(path).await?;
+
+        let key = PrivateKey::generate_random_key();
+        let uuid = Uuid::new_v4();
+        let transactions: Vec<_> = (0..3)
+            .map(|_| {
+                let payload = PayloadBuilder::new(PayloadOperation::FileAppend)
+                    .with_uuid(uuid)
+                    .with_block(Vec::new())
+                    .build()
+                    .unwrap();
+                TransactionBuilder::new()
+                    .with_payload(payload)
+                    .build(&key)
+                    .unwrap()
+            })
+            .collect();
+        store.add_txs(&uuid, &transactions).await?;
+        assert!(store.fsck(FsckRepair::None).await?.is_clean());
+
+        // Lose the tx_info of the middle transaction, leaving its bytes behind.
+        let missing = transactions[1].get_header_signature();
+        let write_txn = store.db.begin_write()?;
+        {
+            let mut table_info = write_txn.open_table(TX_INFO_TABLE)?;
+            let _ = table_info.remove(missing)?;
+        }
+        write_txn.commit()?;
+
+        let report = store.fsck(FsckRepair::None).await?;
+        assert_eq!(report.incomplete_txs, vec![(uuid, missing.to_string())]);
+        assert_eq!(report.misordered_files, vec![uuid]);
+
+        store.fsck(FsckRepair::Quarantine).await?;
+        assert!(store.fsck(FsckRepair::None).await?.is_clean());
+
+        let tx_infos = store.get_txs(&uuid).await?;
+        let orders: Vec<u64> = tx_infos.iter().map(|tx_info| tx_info.order).collect();
+        assert_eq!(orders, vec![0, 1]);
+        assert_eq!(tx_infos[1].tx_id, transactions[2].get_header_signature());
+
+        let read_txn = store.db.begin_read()?;
+        let table_quarantine = read_txn.open_table(TX_QUARANTINE_TABLE)?;
+        assert!(table_quarantine.get(missing)?.is_some());
+
+        Ok(())
+    }
 }
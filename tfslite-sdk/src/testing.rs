@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use libtfslite::client::keys::{PrivateKey, PublicKey, Signature, Signer, SigningError};
+use libtfslite::client::payload::{PayloadBuilder, PayloadOperation};
+use libtfslite::client::transaction::{TransactionBuildError, TransactionBuilder};
+use libtfslite::protos::compat::Message;
+use libtfslite::protos::transaction::Transaction;
+use libtfslite::types::FileMode;
+use crate::http::{HttpClient, HttpError, HttpRequest, HttpResponse, RequestEncoding, CONTENT_ENCODING_HEADER, STATUS_CONFLICT, STATUS_NOT_FOUND};
+use crate::state::{LocalStateStore, LocalStateStoreError, TransactionStatus, TransactionSubmitId};
+use crate::state_memory::MemoryLocalStateStore;
+use crate::types::{UploadInfo, ENDPOINT_MULTIPLE_STATUS, ENDPOINT_STATUS_WAIT};
+
+// Base URL to give the client; the mock answers whatever host it is sent to.
+pub const MOCK_GATEWAY_URL: &str = "http://mock-gateway";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockRoute {
+    BuildInfo,
+    BatcherPublicKey,
+    TransactionSubmit,
+    // The single, multiple and wait status endpoints.
+    TransactionStatus,
+    // Chain state reads. The mock holds no state, so these find nothing.
+    State,
+}
+
+impl MockRoute {
+    // Paths as laid out by DefaultGatewayDialect.
+    fn from_path(path: &str) -> Option<MockRoute> {
+        let path = path.split('?').next().unwrap_or_default();
+        match path {
+            "/build-info" => Some(MockRoute::BuildInfo),
+            "/batcher-public-key" => Some(MockRoute::BatcherPublicKey),
+            "/transaction/submit" => Some(MockRoute::TransactionSubmit),
+            path if path.starts_with("/transaction/status/") => Some(MockRoute::TransactionStatus),
+            path if path.starts_with("/state/") => Some(MockRoute::State),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockBehavior {
+    // Answers normally, after waiting.
+    Delay(Duration),
+    // Answers with this status and message instead.
+    Reject(u16, String),
+    // Fails as if the gateway couldn't be reached.
+    Unreachable,
+    // Answers normally for up requests, then with 503 for down requests, and so on.
+    Flapping { up: u32, down: u32 },
+}
+
+struct MockTransaction {
+    transaction: Transaction,
+    status: TransactionStatus,
+    polls: u32,
+}
+
+struct MockGatewayState {
+    build_info: Value,
+    batcher_public_key: String,
+    commit_after: u32,
+    // With the number of requests each has applied to, for Flapping.
+    behaviors: HashMap<MockRoute, (MockBehavior, u32)>,
+    once: Vec<(MockRoute, MockBehavior)>,
+    request_counts: HashMap<MockRoute, u32>,
+    transactions: HashMap<TransactionSubmitId, MockTransaction>,
+    // Submit ids by transaction id, to answer resubmissions as the gateway does.
+    submit_ids: HashMap<String, TransactionSubmitId>,
+    encoded_submits: u32,
+}
+
+// An in-process gateway for integration tests of upload logic. Hand it to the client with
+// set_http_client (or to a transport with with_http_client) and point the client at
+// MOCK_GATEWAY_URL. Submitted transactions are reported PENDING until they have been polled
+// commit_after times, then COMMITTED, unless given another status with set_status.
+pub struct MockGateway {
+    state: Mutex<MockGatewayState>,
+}
+
+impl Default for MockGateway {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockGateway {
+    pub fn new() -> Self {
+        let batcher_key = PrivateKey::generate_random_key();
+        let batcher_public_key = batcher_key.public_key()
+            .map(|public_key| public_key.as_hex())
+            .unwrap_or_default();
+
+        MockGateway {
+            state: Mutex::new(MockGatewayState {
+                build_info: json!({
+                    "commit_hash": "mock",
+                    "api_version": 1,
+                    "endpoints": [ENDPOINT_MULTIPLE_STATUS, ENDPOINT_STATUS_WAIT],
+                    "request_encodings": ["gzip", "deflate"],
+                }),
+                batcher_public_key,
+                commit_after: 1,
+                behaviors: HashMap::new(),
+                once: Vec::new(),
+                request_counts: HashMap::new(),
+                transactions: HashMap::new(),
+                submit_ids: HashMap::new(),
+                encoded_submits: 0,
+            }),
+        }
+    }
+
+    pub fn with_build_info(self, build_info: Value) -> Self {
+        self.state.lock().unwrap().build_info = build_info;
+        self
+    }
+
+    pub fn with_batcher_public_key(self, batcher_public_key: &str) -> Self {
+        self.state.lock().unwrap().batcher_public_key = batcher_public_key.to_string();
+        self
+    }
+
+    // How many status checks a transaction stays PENDING for; 0 commits it on submit.
+    pub fn with_commit_after(self, polls: u32) -> Self {
+        self.state.lock().unwrap().commit_after = polls;
+        self
+    }
+
+    // Applies behavior to every request to route from now on.
+    pub fn set_behavior(&self, route: MockRoute, behavior: MockBehavior) {
+        self.state.lock().unwrap().behaviors.insert(route, (behavior, 0));
+    }
+
+    pub fn clear_behavior(&self, route: MockRoute) {
+        self.state.lock().unwrap().behaviors.remove(&route);
+    }
+
+    // Applies behavior to the next request to route only, ahead of any set with set_behavior.
+    // Queued behaviors for a route are used in the order given.
+    pub fn push_behavior(&self, route: MockRoute, behavior: MockBehavior) {
+        self.state.lock().unwrap().once.push((route, behavior));
+    }
+
+    // Fixes the status the transaction is reported with, e.g. INVALID_STATUS for one the chain
+    // rejected.
+    pub fn set_status(&self, tx_id: &str, status: TransactionStatus) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(submit_id) = state.submit_ids.get(tx_id).cloned() {
+            if let Some(entry) = state.transactions.get_mut(&submit_id) {
+                entry.status = status;
+            }
+        }
+    }
+
+    pub fn status(&self, tx_id: &str) -> Option<TransactionStatus> {
+        let state = self.state.lock().unwrap();
+        state.submit_ids.get(tx_id)
+            .and_then(|submit_id| state.transactions.get(submit_id))
+            .map(|entry| entry.status.clone())
+    }
+
+    // Transactions accepted so far, in no particular order.
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.state.lock().unwrap().transactions
+            .values()
+            .map(|entry| entry.transaction.clone())
+            .collect()
+    }
+
+    // Submissions that came with a compressed body.
+    pub fn encoded_submits(&self) -> u32 {
+        self.state.lock().unwrap().encoded_submits
+    }
+
+    // Requests to route so far, including ones answered by a behavior.
+    pub fn request_count(&self, route: MockRoute) -> u32 {
+        self.state.lock().unwrap().request_counts.get(&route).copied().unwrap_or(0)
+    }
+
+    // Picks the behavior for this request and counts it.
+    fn next_behavior(&self, route: MockRoute) -> Option<MockBehavior> {
+        let mut state = self.state.lock().unwrap();
+        *state.request_counts.entry(route).or_insert(0) += 1;
+
+        if let Some(index) = state.once.iter().position(|(once_route, _)| *once_route == route) {
+            return Some(state.once.remove(index).1);
+        }
+
+        let (behavior, count) = state.behaviors.get_mut(&route)?;
+        *count += 1;
+        match behavior {
+            MockBehavior::Flapping { up, down } => {
+                let period = (*up + *down).max(1);
+                if (*count - 1) % period < *up {
+                    None
+                } else {
+                    Some(MockBehavior::Reject(503, "Gateway unavailable".to_string()))
+                }
+            }
+            behavior => Some(behavior.clone()),
+        }
+    }
+
+    fn submit(&self, request: &HttpRequest) -> HttpResponse {
+        let encoding = request.header(CONTENT_ENCODING_HEADER).map(RequestEncoding::from_name);
+        let body = match encoding {
+            Some(Some(encoding)) => match encoding.decode(&request.body) {
+                Ok(body) => body,
+                Err(err) => return text_response(request, 400, &format!("{}", err)),
+            },
+            Some(None) => return text_response(request, 415, "Unsupported Content-Encoding"),
+            None => request.body.clone(),
+        };
+        let transaction = match Transaction::parse_from_bytes(&body) {
+            Ok(transaction) => transaction,
+            Err(err) => return text_response(request, 400, &format!("Invalid transaction: {}", err)),
+        };
+        let tx_id = transaction.get_header_signature().to_string();
+
+        let mut state = self.state.lock().unwrap();
+        if encoding.is_some() {
+            state.encoded_submits += 1;
+        }
+        if let Some(submit_id) = state.submit_ids.get(&tx_id) {
+            return json_response(request, STATUS_CONFLICT, json!({"submit_id": submit_id}));
+        }
+
+        let submit_id = format!("mock-{}", state.transactions.len() + 1);
+        let status = if state.commit_after == 0 { TransactionStatus::Committed } else { TransactionStatus::Pending };
+        state.submit_ids.insert(tx_id, submit_id.clone());
+        state.transactions.insert(submit_id.clone(), MockTransaction {
+            transaction,
+            status,
+            polls: 0,
+        });
+
+        json_response(request, 200, json!({"submit_id": submit_id}))
+    }
+
+    fn poll(&self, submit_id: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        let commit_after = state.commit_after;
+        match state.transactions.get_mut(submit_id) {
+            Some(entry) => {
+                entry.polls += 1;
+                if entry.status == TransactionStatus::Pending && entry.polls >= commit_after {
+                    entry.status = TransactionStatus::Committed;
+                }
+                String::from(entry.status.clone())
+            }
+            None => String::from(TransactionStatus::Unknown),
+        }
+    }
+
+    fn status_response(&self, request: &HttpRequest) -> HttpResponse {
+        #[derive(Deserialize)]
+        struct StatusRequest {
+            submit_ids: Vec<TransactionSubmitId>,
+        }
+
+        let path = request.path();
+        if path == "/transaction/status/multiple" || path == "/transaction/status/wait" {
+            let submit_ids = match serde_json::from_slice::<StatusRequest>(&request.body) {
+                Ok(status_request) => status_request.submit_ids,
+                Err(err) => return text_response(request, 400, &format!("Invalid status request: {}", err)),
+            };
+            let statuses: HashMap<TransactionSubmitId, String> = submit_ids
+                .into_iter()
+                .map(|submit_id| {
+                    let status = self.poll(&submit_id);
+                    (submit_id, status)
+                })
+                .collect();
+            json_response(request, 200, json!(statuses))
+        } else {
+            let submit_id = path.trim_start_matches("/transaction/status/");
+            json_response(request, 200, json!({"status": self.poll(submit_id)}))
+        }
+    }
+}
+
+fn json_response(request: &HttpRequest, status: u16, body: Value) -> HttpResponse {
+    HttpResponse {
+        status,
+        url: request.url.clone(),
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body: body.to_string().into_bytes(),
+    }
+}
+
+fn text_response(request: &HttpRequest, status: u16, message: &str) -> HttpResponse {
+    HttpResponse {
+        status,
+        url: request.url.clone(),
+        headers: Vec::new(),
+        body: message.as_bytes().to_vec(),
+    }
+}
+
+#[async_trait(?Send)]
+impl HttpClient for MockGateway {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+        let route = match MockRoute::from_path(&request.path()) {
+            Some(route) => route,
+            None => return Ok(text_response(&request, STATUS_NOT_FOUND, "Not Found")),
+        };
+
+        match self.next_behavior(route) {
+            Some(MockBehavior::Delay(delay)) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                std::thread::sleep(delay);
+                #[cfg(target_arch = "wasm32")]
+                gloo_timers::future::sleep(delay).await;
+            }
+            Some(MockBehavior::Reject(status, message)) => return Ok(text_response(&request, status, &message)),
+            Some(MockBehavior::Unreachable) => return Err(HttpError("Mock gateway unreachable".to_string())),
+            Some(MockBehavior::Flapping { .. }) | None => {}
+        }
+
+        let response = match route {
+            MockRoute::BuildInfo => {
+                let build_info = self.state.lock().unwrap().build_info.clone();
+                json_response(&request, 200, build_info)
+            }
+            MockRoute::BatcherPublicKey => {
+                let batcher_public_key = self.state.lock().unwrap().batcher_public_key.clone();
+                json_response(&request, 200, json!({"batcher_public_key": batcher_public_key}))
+            }
+            MockRoute::TransactionSubmit => self.submit(&request),
+            MockRoute::TransactionStatus => self.status_response(&request),
+            MockRoute::State => text_response(&request, STATUS_NOT_FOUND, "Not Found"),
+        };
+
+        Ok(response)
+    }
+}
+
+// Signs with a key derived from seed. secp256k1 signing is deterministic, so the same seed
+// signs the same data the same way on every run.
+#[derive(Clone)]
+pub struct SeededSigner {
+    key: PrivateKey,
+}
+
+impl SeededSigner {
+    pub fn new(seed: u64) -> Self {
+        let key_bytes = Sha256::digest(format!("tfslite-testing-{}", seed));
+        SeededSigner {
+            key: PrivateKey::load_from_bytes(key_bytes.as_slice()),
+        }
+    }
+
+    pub fn private_key(&self) -> &PrivateKey {
+        &self.key
+    }
+}
+
+impl Signer for SeededSigner {
+    fn sign(&self, data: &[u8]) -> Result<Signature, SigningError> {
+        Signer::sign(&self.key, data)
+    }
+
+    fn public_key(&self) -> Result<PublicKey, SigningError> {
+        Signer::public_key(&self.key)
+    }
+
+    fn clone_box(&self) -> Box<dyn Signer> {
+        Box::new(self.clone())
+    }
+}
+
+pub const FIXTURE_BLOCK_SIZE: usize = 1024;
+
+// File ids used by fixture_store, numbered from 0.
+pub fn fixture_file_id(index: u64) -> Uuid {
+    Uuid::from_u128(index as u128 + 1)
+}
+
+// Create, append and seal transactions for a file of blocks blocks. Nonces are fixed, so with
+// a SeededSigner the transactions, and their ids, are the same on every run.
+pub fn fixture_transactions(signer: &dyn Signer, file_id: Uuid, blocks: u64) -> Result<Vec<Transaction>, TransactionBuildError> {
+    let mut payloads = vec![
+        PayloadBuilder::new(PayloadOperation::FileCreate)
+            .with_uuid(file_id)
+            .with_mode(FileMode::Immutable),
+    ];
+    for number in 0..blocks {
+        payloads.push(PayloadBuilder::new(PayloadOperation::FileAppend)
+            .with_uuid(file_id)
+            .with_block(vec![number as u8; FIXTURE_BLOCK_SIZE])
+            .with_chunk_index(number)
+            .with_chunk_total(blocks));
+    }
+    payloads.push(PayloadBuilder::new(PayloadOperation::FileSeal)
+        .with_uuid(file_id));
+
+    payloads
+        .into_iter()
+        .enumerate()
+        .map(|(index, builder)| {
+            let payload = builder.build()
+                .map_err(|err| TransactionBuildError::SerializationError(format!("{}", err)))?;
+            let mut nonce = file_id.as_bytes().to_vec();
+            nonce.extend_from_slice(&(index as u64).to_be_bytes());
+
+            TransactionBuilder::new()
+                .with_payload(payload)
+                .with_nonce(nonce)
+                .build(signer)
+        })
+        .collect()
+}
+
+// A store holding files prepared for upload but not yet sent, as after prepare_transactions():
+// each has upload info and its transactions are LOCAL.
+pub async fn fixture_store(signer: &dyn Signer, files: u64, blocks_per_file: u64) -> Result<MemoryLocalStateStore, LocalStateStoreError> {
+    let store = MemoryLocalStateStore::new();
+
+    for index in 0..files {
+        let file_id = fixture_file_id(index);
+        let transactions = fixture_transactions(signer, file_id, blocks_per_file)
+            .map_err(|err| LocalStateStoreError::ImplementationError(format!("{}", err)))?;
+        store.add_txs(&file_id, &transactions).await?;
+
+        let size = blocks_per_file * FIXTURE_BLOCK_SIZE as u64;
+        store.set_upload_info(&UploadInfo::new(file_id, &format!("fixture-{}", index), size, FIXTURE_BLOCK_SIZE as u64))
+            .await?;
+    }
+
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_fixtures_common, test_mock_gateway_common, test_request_compression_common};
+    use crate::client::TFSLiteClientError;
+    use crate::state::LocalStateStoreError;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_mock_gateway() -> Result<(), TFSLiteClientError> {
+        test_mock_gateway_common().await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    async fn test_mock_gateway() -> Result<(), TFSLiteClientError> {
+        test_mock_gateway_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_request_compression() -> Result<(), TFSLiteClientError> {
+        test_request_compression_common().await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    async fn test_request_compression() -> Result<(), TFSLiteClientError> {
+        test_request_compression_common().await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_fixtures() -> Result<(), LocalStateStoreError> {
+        test_fixtures_common().await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    async fn test_fixtures() -> Result<(), LocalStateStoreError> {
+        test_fixtures_common().await
+    }
+}
@@ -0,0 +1,692 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use async_trait::async_trait;
+use libtfslite::protos::compat::Message;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use libtfslite::client::batch::{build_batch_list, BatchBuilder};
+use libtfslite::client::keys::{PublicKey, Signer};
+use libtfslite::protos::batch::Batch;
+use libtfslite::protos::transaction::Transaction;
+use crate::client::{TFSLiteClientError, TFSLiteClientErrorType};
+use crate::state::{TransactionStatus, TransactionSubmitId};
+use crate::dialect::{DefaultGatewayDialect, GatewayDialect, GatewayEndpoint};
+use crate::gateway_pool::GatewayPool;
+use crate::http::{default_http_client, HttpClient, HttpRequest, HttpResponse, RequestEncoding, CONTENT_ENCODING_HEADER, STATUS_CONFLICT, STATUS_NOT_FOUND};
+use crate::request_signing::{RequestSigner, ResponseVerifier, RESPONSE_SIGNATURE_HEADER};
+use crate::types::GatewayCapabilities;
+use crate::debug::debug_println;
+
+const DEFAULT_MAX_BATCH_SIZE: usize = 100;
+// How long the gateway may hold a status wait open before answering.
+const STATUS_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_STATUS_PAGE_SIZE: usize = 1000;
+// Compressed secp256k1 public key.
+const PUBLIC_KEY_LENGTH: usize = 33;
+// Sent with each submitted transaction, so a gateway can recognise a retry of one it already has.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+#[async_trait(?Send)]
+pub trait Transport {
+    async fn get_batcher_public_key(&self) -> Result<PublicKey, TFSLiteClientError>;
+    async fn submit_transactions(&self, txs: Vec<Vec<u8>>) -> Result<Vec<TransactionSubmitId>, TFSLiteClientError>;
+    async fn get_transaction_statuses(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<HashMap<TransactionSubmitId, TransactionStatus>, TFSLiteClientError>;
+
+    // Like get_transaction_statuses, but returns only once one of the transactions is no longer
+    // pending, or after a timeout. None means the transport can't wait and the caller should poll.
+    async fn wait_transaction_statuses(&self, _submit_ids: Vec<TransactionSubmitId>) -> Result<Option<HashMap<TransactionSubmitId, TransactionStatus>>, TFSLiteClientError> {
+        Ok(None)
+    }
+
+    fn max_transactions_per_submit(&self) -> usize {
+        1
+    }
+}
+
+// A rejection the gateway explains with a structured body, {"code": ..., "message": ...}.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GatewayError {
+    InsufficientBalance,
+    InvalidSignature,
+    Duplicate,
+    QueueFull,
+}
+
+impl GatewayError {
+    fn from_code(code: &str) -> Option<GatewayError> {
+        match code {
+            "insufficient_balance" => Some(GatewayError::InsufficientBalance),
+            "invalid_signature" => Some(GatewayError::InvalidSignature),
+            "duplicate" => Some(GatewayError::Duplicate),
+            "queue_full" => Some(GatewayError::QueueFull),
+            _ => None,
+        }
+    }
+
+    // A full queue drains by itself; the others would be rejected the same way again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, GatewayError::QueueFull)
+    }
+}
+
+impl Display for GatewayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GatewayError::InsufficientBalance => write!(f, "insufficient balance"),
+            GatewayError::InvalidSignature => write!(f, "invalid signature"),
+            GatewayError::Duplicate => write!(f, "duplicate transaction"),
+            GatewayError::QueueFull => write!(f, "queue full"),
+        }
+    }
+}
+
+fn parse_gateway_error(body: &[u8]) -> Option<(GatewayError, String)> {
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        code: String,
+        #[serde(default)]
+        message: String,
+    }
+
+    let response: ErrorResponse = serde_json::from_slice(body).ok()?;
+    Some((GatewayError::from_code(&response.code)?, response.message))
+}
+
+pub(crate) fn check_response(response: HttpResponse) -> Result<HttpResponse, TFSLiteClientError> {
+    if response.is_success() {
+        Ok(response)
+    } else {
+        let status = response.status;
+        if let Some((gateway_error, msg)) = parse_gateway_error(&response.body) {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::GatewayError(gateway_error), Some(format!("Response Code: {}, Message: {}", status, msg))));
+        }
+
+        let msg = match response.text() {
+            msg if msg.is_empty() => String::from("(No Message Found)"),
+            msg => msg,
+        };
+
+        Err(TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("Response Code: {}, Message: {}", status, msg))))
+    }
+}
+
+// Reads a gateway response body as JSON, first checking its signature when the client was
+// given a gateway key and the endpoint is one gateways sign.
+pub(crate) fn read_response_body(verifier: Option<&ResponseVerifier>, endpoint: &GatewayEndpoint, response: HttpResponse) -> Result<serde_json::Value, TFSLiteClientError> {
+    if let Some(verifier) = verifier.filter(|_| ResponseVerifier::covers(endpoint)) {
+        verifier.verify(&response.path(), &response.body, response.header(RESPONSE_SIGNATURE_HEADER))?;
+    }
+
+    serde_json::from_slice(&response.body)
+        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))
+}
+
+// Decodes a gateway response body, naming the field that didn't match when it fails.
+pub(crate) fn decode_response<T: DeserializeOwned>(endpoint: &GatewayEndpoint, body: serde_json::Value) -> Result<T, TFSLiteClientError> {
+    serde_path_to_error::deserialize(body)
+        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{:?} response, field {}: {}", endpoint, err.path(), err.inner()))))
+}
+
+pub(crate) fn http_error(err: impl std::fmt::Display) -> TFSLiteClientError {
+    TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("{}", err)))
+}
+
+pub(crate) fn build_signed_batch(signer: &dyn Signer, txs: Vec<Vec<u8>>) -> Result<Batch, TFSLiteClientError> {
+    let transactions = txs
+        .iter()
+        .map(|tx_bytes| Transaction::parse_from_bytes(tx_bytes))
+        .collect::<Result<Vec<Transaction>, _>>()
+        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+    BatchBuilder::new()
+        .with_transactions(transactions)
+        .build(signer)
+        .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))
+}
+
+// Requests that fail to connect or get a 5xx response are tried up to max_attempts times in
+// all, waiting backoff before the first retry and doubling it before each one after.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+pub struct GatewayTransport {
+    url: String,
+    http: Arc<dyn HttpClient>,
+    dialect: Arc<dyn GatewayDialect>,
+    retry_policy: RetryPolicy,
+    // Cleared when the gateway doesn't offer the multiple-status endpoint, either by its
+    // capabilities or by answering it with a 404.
+    multiple_status: AtomicBool,
+    // Set from the gateway's capabilities, and cleared if the wait endpoint answers with a 404.
+    status_wait: AtomicBool,
+    // Most submit ids sent in one status request.
+    status_page_size: usize,
+    // How submitted transactions are compressed, when the gateway takes compressed bodies.
+    request_encoding: Option<RequestEncoding>,
+    request_signer: Option<Arc<RequestSigner>>,
+    response_verifier: Option<Arc<ResponseVerifier>>,
+}
+
+impl GatewayTransport {
+    pub fn new(url: String) -> Self {
+        GatewayTransport {
+            url,
+            http: default_http_client(),
+            dialect: Arc::new(DefaultGatewayDialect),
+            retry_policy: RetryPolicy::default(),
+            multiple_status: AtomicBool::new(true),
+            status_wait: AtomicBool::new(false),
+            status_page_size: DEFAULT_STATUS_PAGE_SIZE,
+            request_encoding: None,
+            request_signer: None,
+            response_verifier: None,
+        }
+    }
+
+    pub fn with_dialect(mut self, dialect: Arc<dyn GatewayDialect>) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_status_page_size(mut self, status_page_size: usize) -> Self {
+        self.status_page_size = status_page_size.max(1);
+        self
+    }
+
+    // Every request is then signed by request_signer, afresh on each retry.
+    pub fn with_request_signer(mut self, request_signer: Option<Arc<RequestSigner>>) -> Self {
+        self.request_signer = request_signer;
+        self
+    }
+
+    // Status responses must then carry a signature by the gateway key, see ResponseVerifier.
+    pub fn with_response_verifier(mut self, response_verifier: Option<Arc<ResponseVerifier>>) -> Self {
+        self.response_verifier = response_verifier;
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: &GatewayCapabilities) -> Self {
+        self.multiple_status.store(capabilities.multiple_status, Ordering::SeqCst);
+        self.status_wait.store(capabilities.status_wait, Ordering::SeqCst);
+        self.request_encoding = capabilities.request_encoding;
+        self
+    }
+
+    // Bodies that don't get smaller, such as already compressed or encrypted chunks, are sent
+    // as they are.
+    fn with_encoded_body(&self, request: HttpRequest, body: Vec<u8>) -> HttpRequest {
+        if let Some(encoding) = self.request_encoding {
+            let encoded = encoding.encode(&body);
+            if encoded.len() < body.len() {
+                return request.with_header(CONTENT_ENCODING_HEADER, encoding.name()).with_body(encoded);
+            }
+        }
+
+        request.with_body(body)
+    }
+
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, TFSLiteClientError> {
+        let mut backoff = self.retry_policy.backoff;
+        let mut attempt = 1;
+        loop {
+            let attempt_request = match &self.request_signer {
+                Some(request_signer) => request_signer.sign(request.clone())?,
+                None => request.clone(),
+            };
+            let result = self.http.send(attempt_request).await;
+            let retry = match &result {
+                Ok(response) => response.is_server_error(),
+                Err(_) => true,
+            };
+            if !retry || attempt >= self.retry_policy.max_attempts {
+                return result.map_err(http_error);
+            }
+
+            debug_println!("Request failed, retrying in {:?} (attempt {} of {})", backoff, attempt + 1, self.retry_policy.max_attempts);
+            #[cfg(not(target_arch = "wasm32"))]
+            tokio::time::sleep(backoff).await;
+            #[cfg(target_arch = "wasm32")]
+            gloo_timers::future::sleep(backoff).await;
+
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
+    async fn get_transaction_statuses_page(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<HashMap<TransactionSubmitId, TransactionStatus>, TFSLiteClientError> {
+        if !self.multiple_status.load(Ordering::SeqCst) {
+            return self.get_transaction_statuses_single(submit_ids).await;
+        }
+
+        let mut request: HashMap<&str, Vec<String>> = HashMap::new();
+        request.insert("submit_ids", submit_ids);
+        debug_println!("{:?}", request);
+
+        let endpoint = GatewayEndpoint::TransactionStatusMultiple;
+        let url = self.dialect.url(&self.url, &endpoint);
+        let response = self.send(HttpRequest::post(url).with_json(&request).map_err(http_error)?)
+            .await?;
+
+        if response.status == STATUS_NOT_FOUND {
+            debug_println!("Gateway has no multiple-status endpoint, falling back to per-transaction status");
+            self.multiple_status.store(false, Ordering::SeqCst);
+            let submit_ids = request.remove("submit_ids").unwrap_or_default();
+            return self.get_transaction_statuses_single(submit_ids).await;
+        }
+
+        let response_data: HashMap<String, String> = self.parse_json(&endpoint, response)?;
+
+        let mut response: HashMap<TransactionSubmitId, TransactionStatus> = HashMap::new();
+        response_data.iter().for_each(|(k,v)| {
+            response.insert(k.clone(), v.clone().into());
+        });
+
+        Ok(response)
+    }
+
+    async fn get_transaction_statuses_single(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<HashMap<TransactionSubmitId, TransactionStatus>, TFSLiteClientError> {
+        #[derive(Deserialize)]
+        struct StatusResponse {
+            status: String,
+        }
+
+        let mut response: HashMap<TransactionSubmitId, TransactionStatus> = HashMap::new();
+
+        for submit_id in submit_ids {
+            let endpoint = GatewayEndpoint::TransactionStatus(submit_id.clone());
+            let url = self.dialect.url(&self.url, &endpoint);
+            let status_response = self.send(HttpRequest::get(url))
+                .await?;
+
+            let response_data: StatusResponse = self.parse_json(&endpoint, status_response)?;
+            response.insert(submit_id, response_data.status.into());
+        }
+
+        Ok(response)
+    }
+
+    fn parse_json<T: DeserializeOwned>(&self, endpoint: &GatewayEndpoint, response: HttpResponse) -> Result<T, TFSLiteClientError> {
+        self.parse_body(endpoint, check_response(response)?)
+    }
+
+    // Like parse_json, without checking the response status first.
+    fn parse_body<T: DeserializeOwned>(&self, endpoint: &GatewayEndpoint, response: HttpResponse) -> Result<T, TFSLiteClientError> {
+        let body = read_response_body(self.response_verifier.as_deref(), endpoint, response)?;
+
+        let body = self.dialect.parse_response(endpoint, body)
+            .map_err(|msg| TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(msg)))?;
+
+        decode_response(endpoint, body)
+    }
+}
+
+#[async_trait(?Send)]
+impl Transport for GatewayTransport {
+    async fn get_batcher_public_key(&self) -> Result<PublicKey, TFSLiteClientError> {
+        #[derive(Deserialize)]
+        struct BatcherPublicKeyResponse {
+            batcher_public_key: String,
+        }
+
+        let endpoint = GatewayEndpoint::BatcherPublicKey;
+        let url = self.dialect.url(&self.url, &endpoint);
+        debug_println!("GET {}", crate::debug::redact("url", &url));
+
+        let response = self.send(HttpRequest::get(url.as_str()))
+            .await?;
+
+        let response_data: BatcherPublicKeyResponse = self.parse_json(&endpoint, response)?;
+
+        let result = hex::decode(response_data.batcher_public_key)
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{:?} response, field batcher_public_key: {}", endpoint, err))))?;
+        if result.len() != PUBLIC_KEY_LENGTH {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{:?} response, field batcher_public_key: expected {} bytes, got {}", endpoint, PUBLIC_KEY_LENGTH, result.len()))));
+        }
+
+        Ok(PublicKey::load_from_bytes(result.as_slice()))
+    }
+
+    async fn submit_transactions(&self, txs: Vec<Vec<u8>>) -> Result<Vec<TransactionSubmitId>, TFSLiteClientError> {
+        #[derive(Deserialize)]
+        struct SubmitResponse {
+            submit_id: String,
+        }
+
+        let endpoint = GatewayEndpoint::TransactionSubmit;
+        let url = self.dialect.url(&self.url, &endpoint);
+        let mut submit_ids: Vec<TransactionSubmitId> = Vec::new();
+
+        for tx_bytes in txs {
+            debug_println!("POST {} ({} bytes)", crate::debug::redact("url", &url), tx_bytes.len());
+
+            // The header signature already identifies the transaction uniquely.
+            let idempotency_key = Transaction::parse_from_bytes(&tx_bytes)
+                .map(|tx| tx.get_header_signature().to_string())
+                .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+            let request = HttpRequest::post(url.as_str())
+                .with_header("Content-Type", "application/octet-stream")
+                .with_header(IDEMPOTENCY_KEY_HEADER, idempotency_key.as_str());
+            let response = self.send(self.with_encoded_body(request, tx_bytes))
+                .await?;
+
+            // A gateway that already accepted the transaction, say before a retried request
+            // timed out, answers 409 Conflict with the submit id it gave it the first time.
+            let response_data: SubmitResponse = if response.status == STATUS_CONFLICT {
+                debug_println!("Transaction {} already submitted", idempotency_key);
+                self.parse_body(&endpoint, response)?
+            } else {
+                self.parse_json(&endpoint, response)?
+            };
+
+            submit_ids.push(response_data.submit_id);
+        }
+
+        Ok(submit_ids)
+    }
+
+    async fn get_transaction_statuses(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<HashMap<TransactionSubmitId, TransactionStatus>, TFSLiteClientError> {
+        let mut response: HashMap<TransactionSubmitId, TransactionStatus> = HashMap::new();
+        for page in submit_ids.chunks(self.status_page_size) {
+            response.extend(self.get_transaction_statuses_page(page.to_vec()).await?);
+        }
+
+        Ok(response)
+    }
+
+    async fn wait_transaction_statuses(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<Option<HashMap<TransactionSubmitId, TransactionStatus>>, TFSLiteClientError> {
+        #[derive(Serialize)]
+        struct WaitRequest {
+            submit_ids: Vec<TransactionSubmitId>,
+            timeout_ms: u64,
+        }
+
+        // A wait spanning several pages could sit on one page while another needs attention.
+        if !self.status_wait.load(Ordering::SeqCst) || submit_ids.len() > self.status_page_size {
+            return Ok(None);
+        }
+
+        let request = WaitRequest {
+            submit_ids,
+            timeout_ms: STATUS_WAIT_TIMEOUT.as_millis() as u64,
+        };
+
+        let endpoint = GatewayEndpoint::TransactionStatusWait;
+        let url = self.dialect.url(&self.url, &endpoint);
+        let response = self.send(HttpRequest::post(url).with_json(&request).map_err(http_error)?)
+            .await?;
+
+        if response.status == STATUS_NOT_FOUND {
+            debug_println!("Gateway has no status wait endpoint, falling back to polling");
+            self.status_wait.store(false, Ordering::SeqCst);
+            return Ok(None);
+        }
+
+        let response_data: HashMap<String, String> = self.parse_json(&endpoint, response)?;
+
+        Ok(Some(response_data.into_iter().map(|(k, v)| (k, v.into())).collect()))
+    }
+}
+
+// Sends through the pool's current write gateway. A transport error from a gateway that then
+// fails a health probe moves the pool on to the next one and the call is retried there; errors
+// from a gateway that still answers (e.g. a rejected transaction) are returned as they are.
+pub struct FailoverTransport {
+    pool: Arc<GatewayPool>,
+    dialect: Arc<dyn GatewayDialect>,
+    http: Arc<dyn HttpClient>,
+    transports: HashMap<String, GatewayTransport>,
+}
+
+impl FailoverTransport {
+    pub fn new(pool: Arc<GatewayPool>, dialect: Arc<dyn GatewayDialect>, retry_policy: RetryPolicy) -> Self {
+        let transports = pool.urls()
+            .iter()
+            .map(|url| {
+                let transport = GatewayTransport::new(url.clone())
+                    .with_dialect(dialect.clone())
+                    .with_retry_policy(retry_policy.clone());
+                (url.clone(), transport)
+            })
+            .collect();
+
+        FailoverTransport {
+            pool,
+            dialect,
+            http: default_http_client(),
+            transports,
+        }
+    }
+
+    // The gateways front the same network, so they are taken to share one set of capabilities.
+    pub fn with_capabilities(mut self, capabilities: &GatewayCapabilities) -> Self {
+        self.transports = self.transports
+            .into_iter()
+            .map(|(url, transport)| (url, transport.with_capabilities(capabilities)))
+            .collect();
+        self
+    }
+
+    pub fn with_status_page_size(mut self, status_page_size: usize) -> Self {
+        self.transports = self.transports
+            .into_iter()
+            .map(|(url, transport)| (url, transport.with_status_page_size(status_page_size)))
+            .collect();
+        self
+    }
+
+    pub fn with_request_signer(mut self, request_signer: Option<Arc<RequestSigner>>) -> Self {
+        self.transports = self.transports
+            .into_iter()
+            .map(|(url, transport)| (url, transport.with_request_signer(request_signer.clone())))
+            .collect();
+        self
+    }
+
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.transports = self.transports
+            .into_iter()
+            .map(|(url, transport)| (url, transport.with_http_client(http.clone())))
+            .collect();
+        self.http = http;
+        self
+    }
+
+    pub fn with_response_verifier(mut self, response_verifier: Option<Arc<ResponseVerifier>>) -> Self {
+        self.transports = self.transports
+            .into_iter()
+            .map(|(url, transport)| (url, transport.with_response_verifier(response_verifier.clone())))
+            .collect();
+        self
+    }
+
+    async fn with_failover<'a, T, F>(&'a self, call: impl Fn(&'a GatewayTransport) -> F) -> Result<T, TFSLiteClientError>
+    where
+        F: std::future::Future<Output = Result<T, TFSLiteClientError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let url = self.pool.write_url().to_string();
+            let result = call(&self.transports[&url]).await;
+
+            match result {
+                Err(err) if matches!(err.error_type(), TFSLiteClientErrorType::TransportError) && attempt < self.pool.len() => {
+                    if self.pool.probe(self.http.as_ref(), &url, self.dialect.as_ref()).await {
+                        return Err(err);
+                    }
+                    debug_println!("Failing over from {}: {}", crate::debug::redact("url", &url), err);
+                    self.pool.mark_failed(&url);
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Transport for FailoverTransport {
+    async fn get_batcher_public_key(&self) -> Result<PublicKey, TFSLiteClientError> {
+        self.with_failover(|transport| transport.get_batcher_public_key()).await
+    }
+
+    // A batch half submitted before a failover is resubmitted whole; gateways front the same
+    // network, so the transactions already accepted are duplicates and not applied twice.
+    async fn submit_transactions(&self, txs: Vec<Vec<u8>>) -> Result<Vec<TransactionSubmitId>, TFSLiteClientError> {
+        self.with_failover(|transport| transport.submit_transactions(txs.clone())).await
+    }
+
+    async fn get_transaction_statuses(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<HashMap<TransactionSubmitId, TransactionStatus>, TFSLiteClientError> {
+        self.with_failover(|transport| transport.get_transaction_statuses(submit_ids.clone())).await
+    }
+
+    async fn wait_transaction_statuses(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<Option<HashMap<TransactionSubmitId, TransactionStatus>>, TFSLiteClientError> {
+        self.with_failover(|transport| transport.wait_transaction_statuses(submit_ids.clone())).await
+    }
+}
+
+// Talks to a stock Sawtooth REST API. Transactions are wrapped in batches signed by the
+// transport's signer, so prepared transactions must name that signer as their batcher.
+// The batch id is used as the submit id of every transaction in the batch.
+pub struct SawtoothRestTransport {
+    url: String,
+    http: Arc<dyn HttpClient>,
+    signer: Box<dyn Signer>,
+    max_batch_size: usize,
+}
+
+impl SawtoothRestTransport {
+    pub fn new(url: String, signer: &dyn Signer) -> Self {
+        SawtoothRestTransport {
+            url,
+            http: default_http_client(),
+            signer: signer.clone_box(),
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+        }
+    }
+
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    pub fn with_http_client(mut self, http: Arc<dyn HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+}
+
+#[async_trait(?Send)]
+impl Transport for SawtoothRestTransport {
+    async fn get_batcher_public_key(&self) -> Result<PublicKey, TFSLiteClientError> {
+        self.signer
+            .public_key()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))
+    }
+
+    async fn submit_transactions(&self, txs: Vec<Vec<u8>>) -> Result<Vec<TransactionSubmitId>, TFSLiteClientError> {
+        #[derive(Deserialize)]
+        struct BatchesResponse {
+            #[allow(dead_code)]
+            link: String,
+        }
+
+        let tx_count = txs.len();
+        let batch = build_signed_batch(self.signer.as_ref(), txs)?;
+        let batch_id = batch.get_header_signature().to_string();
+
+        let batch_list_bytes = build_batch_list(vec![batch])
+            .write_to_bytes()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+        debug_println!("POST {} ({} bytes)", crate::debug::redact("url", format!("{}/batches", self.url)), batch_list_bytes.len());
+
+        let response = self.http
+            .send(HttpRequest::post(format!("{}/batches", self.url.as_str()))
+                .with_header("Content-Type", "application/octet-stream")
+                .with_body(batch_list_bytes))
+            .await
+            .map_err(http_error)?;
+
+        check_response(response)?
+            .json::<BatchesResponse>()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+        Ok(vec![batch_id; tx_count])
+    }
+
+    async fn get_transaction_statuses(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<HashMap<TransactionSubmitId, TransactionStatus>, TFSLiteClientError> {
+        #[derive(Deserialize)]
+        struct BatchStatus {
+            id: String,
+            status: String,
+        }
+
+        #[derive(Deserialize)]
+        struct BatchStatusesResponse {
+            data: Vec<BatchStatus>,
+        }
+
+        let batch_ids: Vec<TransactionSubmitId> = submit_ids
+            .into_iter()
+            .collect::<HashSet<TransactionSubmitId>>()
+            .into_iter()
+            .collect();
+
+        let request = HttpRequest::post(format!("{}/batch_statuses", self.url.as_str()))
+            .with_json(&batch_ids)
+            .map_err(http_error)?;
+        let response = self.http
+            .send(request)
+            .await
+            .map_err(http_error)?;
+
+        let response_data = check_response(response)?
+            .json::<BatchStatusesResponse>()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+        let response = response_data.data
+            .into_iter()
+            .map(|batch_status| {
+                let status = match batch_status.status.as_str() {
+                    "COMMITTED" => TransactionStatus::Committed,
+                    "PENDING" => TransactionStatus::Pending,
+                    "UNKNOWN" => TransactionStatus::Unknown,
+                    _ => TransactionStatus::InvalidStatus,
+                };
+                (batch_status.id, status)
+            })
+            .collect();
+
+        Ok(response)
+    }
+
+    fn max_transactions_per_submit(&self) -> usize {
+        self.max_batch_size
+    }
+}
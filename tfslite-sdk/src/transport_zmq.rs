@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use async_trait::async_trait;
+use protobuf::{Message, RepeatedField};
+use uuid::Uuid;
+use sawtooth_sdk::messages::client_batch_submit::{
+    ClientBatchStatus_Status, ClientBatchStatusRequest, ClientBatchStatusResponse, ClientBatchStatusResponse_Status,
+    ClientBatchSubmitRequest, ClientBatchSubmitResponse, ClientBatchSubmitResponse_Status,
+};
+use sawtooth_sdk::messages::batch::Batch;
+use sawtooth_sdk::messages::validator::Message_MessageType;
+use sawtooth_sdk::messaging::stream::{MessageConnection, MessageSender};
+use sawtooth_sdk::messaging::zmq_stream::{ZmqMessageConnection, ZmqMessageSender};
+use libtfslite::client::keys::{PublicKey, Signer};
+use crate::client::{TFSLiteClientError, TFSLiteClientErrorType};
+use crate::state::{TransactionStatus, TransactionSubmitId};
+use crate::transport::{build_signed_batch, GatewayError, Transport};
+use crate::debug::debug_println;
+
+const DEFAULT_MAX_BATCH_SIZE: usize = 100;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Speaks the validator's ZMQ client protocol directly, for services running next to a
+// validator. Batching works the same way as SawtoothRestTransport.
+pub struct ZmqTransport {
+    sender: ZmqMessageSender,
+    signer: Box<dyn Signer>,
+    max_batch_size: usize,
+    timeout: Duration,
+}
+
+impl ZmqTransport {
+    pub fn new(validator_url: &str, signer: &dyn Signer) -> Self {
+        let connection = ZmqMessageConnection::new(validator_url);
+        let (sender, _receiver) = connection.create();
+
+        ZmqTransport {
+            sender,
+            signer: signer.clone_box(),
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn request<R: Message, T: Message>(&self, message_type: Message_MessageType, request: &R) -> Result<T, TFSLiteClientError> {
+        let request_bytes = request
+            .write_to_bytes()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))?;
+
+        let correlation_id = Uuid::new_v4().to_string();
+        debug_println!("ZMQ {:?} ({} bytes)", message_type, request_bytes.len());
+
+        let mut future = self.sender
+            .send(message_type, &correlation_id, &request_bytes)
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("{:?}", err))))?;
+
+        let response = future
+            .get_timeout(self.timeout)
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("{:?}", err))))?;
+
+        T::parse_from_bytes(response.get_content())
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::DecodeError, Some(format!("{}", err))))
+    }
+}
+
+#[async_trait(?Send)]
+impl Transport for ZmqTransport {
+    async fn get_batcher_public_key(&self) -> Result<PublicKey, TFSLiteClientError> {
+        self.signer
+            .public_key()
+            .map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::SigningError, Some(format!("{}", err))))
+    }
+
+    async fn submit_transactions(&self, txs: Vec<Vec<u8>>) -> Result<Vec<TransactionSubmitId>, TFSLiteClientError> {
+        let tx_count = txs.len();
+        let batch = build_signed_batch(self.signer.as_ref(), txs)?;
+        let batch_id = batch.get_header_signature().to_string();
+
+        let mut request = ClientBatchSubmitRequest::new();
+        request.set_batches(RepeatedField::from_vec(vec![Batch::from(batch)]));
+
+        let response: ClientBatchSubmitResponse = self.request(Message_MessageType::CLIENT_BATCH_SUBMIT_REQUEST, &request)?;
+        if response.get_status() == ClientBatchSubmitResponse_Status::QUEUE_FULL {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::GatewayError(GatewayError::QueueFull), Some("Batch submit failed: validator queue full".to_string())));
+        }
+        if response.get_status() != ClientBatchSubmitResponse_Status::OK {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("Batch submit failed: {:?}", response.get_status()))));
+        }
+
+        Ok(vec![batch_id; tx_count])
+    }
+
+    async fn get_transaction_statuses(&self, submit_ids: Vec<TransactionSubmitId>) -> Result<HashMap<TransactionSubmitId, TransactionStatus>, TFSLiteClientError> {
+        let mut batch_ids = submit_ids;
+        batch_ids.sort();
+        batch_ids.dedup();
+
+        let mut request = ClientBatchStatusRequest::new();
+        request.set_batch_ids(RepeatedField::from_vec(batch_ids));
+
+        let response: ClientBatchStatusResponse = self.request(Message_MessageType::CLIENT_BATCH_STATUS_REQUEST, &request)?;
+        if response.get_status() != ClientBatchStatusResponse_Status::OK {
+            return Err(TFSLiteClientError::new(TFSLiteClientErrorType::TransportError, Some(format!("Batch status request failed: {:?}", response.get_status()))));
+        }
+
+        let statuses = response.get_batch_statuses()
+            .iter()
+            .map(|batch_status| {
+                let status = match batch_status.get_status() {
+                    ClientBatchStatus_Status::COMMITTED => TransactionStatus::Committed,
+                    ClientBatchStatus_Status::PENDING => TransactionStatus::Pending,
+                    ClientBatchStatus_Status::UNKNOWN => TransactionStatus::Unknown,
+                    _ => TransactionStatus::InvalidStatus,
+                };
+                (batch_status.get_batch_id().to_string(), status)
+            })
+            .collect();
+
+        Ok(statuses)
+    }
+
+    fn max_transactions_per_submit(&self) -> usize {
+        self.max_batch_size
+    }
+}
@@ -1,15 +1,149 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use chrono::prelude::*;
 use serde::{Serialize, Deserialize};
 use wasm_bindgen::prelude::wasm_bindgen;
-use libtfslite::types::{FileMode, FileState};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+use libtfslite::common::{TOKEN_DECIMALS, TOKEN_SYMBOL};
+use libtfslite::types::{FileMode, FileState, TokenAmount};
+use crate::state::{TransactionId, TransactionStatus};
+use crate::http::RequestEncoding;
 
 #[wasm_bindgen]
 #[derive(Deserialize, Debug)]
 #[allow(dead_code)]
 pub struct BuildInfo {
     commit_hash: String,
+    // Reported by gateways that take part in capability negotiation; older ones send only the
+    // commit hash.
+    #[serde(default)]
+    api_version: Option<u32>,
+    #[serde(default)]
+    max_payload_size: Option<u64>,
+    #[serde(default)]
+    endpoints: Option<Vec<String>>,
+    // Content-Encodings the gateway takes on request bodies.
+    #[serde(default)]
+    request_encodings: Option<Vec<String>>,
+}
+
+#[wasm_bindgen]
+impl BuildInfo {
+    #[wasm_bindgen(getter)]
+    pub fn commit_hash(&self) -> String {
+        self.commit_hash.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn api_version(&self) -> Option<u32> {
+        self.api_version
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn max_payload_size(&self) -> Option<u64> {
+        self.max_payload_size
+    }
+}
+
+// Endpoint names a gateway can list in its build info.
+pub const ENDPOINT_MULTIPLE_STATUS: &str = "multiple-status";
+pub const ENDPOINT_BATCHES: &str = "batches";
+pub const ENDPOINT_EVENTS: &str = "events";
+pub const ENDPOINT_STATUS_WAIT: &str = "status-wait";
+
+// What the SDK can rely on a gateway for. Gateways that don't list their endpoints are assumed to
+// have those every gateway has shipped with, which includes multiple-status; the transport still
+// falls back to per-transaction status calls if that turns out to be missing.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct GatewayCapabilities {
+    pub(crate) api_version: u32,
+    pub(crate) max_payload_size: Option<u64>,
+    pub(crate) multiple_status: bool,
+    pub(crate) batches: bool,
+    pub(crate) events: bool,
+    pub(crate) status_wait: bool,
+    pub(crate) request_encoding: Option<RequestEncoding>,
+}
+
+impl GatewayCapabilities {
+    pub fn from_build_info(build_info: &BuildInfo) -> Self {
+        let has_endpoint = |name: &str, default: bool| match &build_info.endpoints {
+            Some(endpoints) => endpoints.iter().any(|endpoint| endpoint == name),
+            None => default,
+        };
+
+        GatewayCapabilities {
+            api_version: build_info.api_version.unwrap_or(0),
+            max_payload_size: build_info.max_payload_size,
+            multiple_status: has_endpoint(ENDPOINT_MULTIPLE_STATUS, true),
+            batches: has_endpoint(ENDPOINT_BATCHES, false),
+            events: has_endpoint(ENDPOINT_EVENTS, false),
+            status_wait: has_endpoint(ENDPOINT_STATUS_WAIT, false),
+            // gzip is preferred when both are offered.
+            request_encoding: [RequestEncoding::Gzip, RequestEncoding::Deflate]
+                .into_iter()
+                .find(|encoding| build_info.request_encodings
+                    .iter()
+                    .flatten()
+                    .any(|name| RequestEncoding::from_name(name) == Some(*encoding))),
+        }
+    }
+}
+
+impl Default for GatewayCapabilities {
+    fn default() -> Self {
+        GatewayCapabilities {
+            api_version: 0,
+            max_payload_size: None,
+            multiple_status: true,
+            batches: false,
+            events: false,
+            status_wait: false,
+            request_encoding: None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl GatewayCapabilities {
+    // 0 for gateways from before versioning.
+    #[wasm_bindgen(getter)]
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn max_payload_size(&self) -> Option<u64> {
+        self.max_payload_size
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn multiple_status(&self) -> bool {
+        self.multiple_status
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn batches(&self) -> bool {
+        self.batches
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn events(&self) -> bool {
+        self.events
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn status_wait(&self) -> bool {
+        self.status_wait
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn request_encoding(&self) -> Option<String> {
+        self.request_encoding.map(|encoding| encoding.name().to_string())
+    }
 }
 
 //#[wasm_bindgen]
@@ -64,17 +198,192 @@ impl FileListEntry {
     }
 }
 
+// An upload the local store holds transactions for, as recorded when it was prepared, so it can
+// be shown to the user after a restart.
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UploadInfo {
+    pub(crate) file_id: uuid::Uuid,
+    pub(crate) filename: String,
+    pub(crate) size: u64,
+    pub(crate) chunk_size: u64,
+    pub(crate) created_at: i64,
+    #[serde(default)]
+    pub(crate) progress: UploadProgress,
+}
+
+impl UploadInfo {
+    pub fn new(file_id: uuid::Uuid, filename: &str, size: u64, chunk_size: u64) -> Self {
+        UploadInfo {
+            file_id,
+            filename: filename.to_string(),
+            size,
+            chunk_size,
+            created_at: Utc::now().timestamp_millis(),
+            progress: UploadProgress::default(),
+        }
+    }
+
+    pub fn get_progress(&self) -> UploadProgress {
+        self.progress
+    }
+
+    pub fn get_file_id(&self) -> uuid::Uuid {
+        self.file_id
+    }
+
+    pub fn get_filename(&self) -> String {
+        self.filename.clone()
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn get_chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+
+    pub fn get_created_at(&self) -> i64 {
+        self.created_at
+    }
+}
+
+// How far an upload had got when last saved, so a resumed upload can show its progress straight
+// away. Timestamps are in milliseconds; 0 until the upload starts.
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UploadProgress {
+    pub bytes_read: u64,
+    pub txs_submitted: u64,
+    pub txs_committed: u64,
+    pub started_at: i64,
+    pub updated_at: i64,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl UploadInfo {
+    #[wasm_bindgen(getter = id)]
+    pub fn js_id(&self) -> String {
+        self.file_id.to_string()
+    }
+
+    #[wasm_bindgen(getter = filename)]
+    pub fn js_filename(&self) -> String {
+        self.filename.clone()
+    }
+
+    #[wasm_bindgen(getter = size)]
+    pub fn js_size(&self) -> u64 {
+        self.size
+    }
+
+    #[wasm_bindgen(getter = chunk_size)]
+    pub fn js_chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+
+    #[wasm_bindgen(getter = created_at)]
+    pub fn js_created_at(&self) -> js_sys::Date {
+        js_sys::Date::new(&(self.created_at as f64).into())
+    }
+
+    #[wasm_bindgen(getter = progress)]
+    pub fn js_progress(&self) -> UploadProgress {
+        self.progress
+    }
+}
+
+// Property accessors for JS, using the same string forms the gateway reports.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl FileListEntry {
+    #[wasm_bindgen(getter = id)]
+    pub fn js_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    #[wasm_bindgen(getter = state)]
+    pub fn js_state(&self) -> JsFileState {
+        wasm_bindgen::JsValue::from_str(&self.state.to_string()).unchecked_into()
+    }
+
+    #[wasm_bindgen(getter = mode)]
+    pub fn js_mode(&self) -> JsFileMode {
+        wasm_bindgen::JsValue::from_str(&self.mode.to_string()).unchecked_into()
+    }
+
+    #[wasm_bindgen(getter = last_updated)]
+    pub fn js_last_updated(&self) -> Option<js_sys::Date> {
+        self.last_updated
+            .map(|last_updated| js_sys::Date::new(&(last_updated.timestamp_millis() as f64).into()))
+    }
+
+    #[wasm_bindgen(getter = name)]
+    pub fn js_name(&self) -> Option<String> {
+        self.name.clone()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+export type FileState = "OPEN" | "SEALED";
+export type FileMode = "IMMUTABLE" | "DESTROYABLE";
+export type FileList = Array<FileListEntry>;
+export type PendingUploadList = Array<UploadInfo>;
+export type StatusCallback = (status: bigint, total: bigint) => void;
+
+export interface TFSLiteSigner {
+    sign(buf: Uint8Array): Signature;
+    public_key(): PublicKey;
+}
+
+export interface TFSLiteAsyncSigner {
+    sign(buf: Uint8Array): Promise<Signature>;
+    public_key(): PublicKey | Promise<PublicKey>;
+}
+"#;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "FileList")]
+    pub type JsFileList;
+
+    #[wasm_bindgen(typescript_type = "PendingUploadList")]
+    pub type JsPendingUploadList;
+
+    #[wasm_bindgen(typescript_type = "StatusCallback")]
+    pub type StatusCallback;
+
+    #[wasm_bindgen(typescript_type = "FileState")]
+    pub type JsFileState;
+
+    #[wasm_bindgen(typescript_type = "FileMode")]
+    pub type JsFileMode;
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub type FileList = Vec<FileListEntry>;
 #[cfg(target_arch = "wasm32")]
-pub type FileList = js_sys::Array;
+pub type FileList = JsFileList;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type PendingUploadList = Vec<UploadInfo>;
+#[cfg(target_arch = "wasm32")]
+pub type PendingUploadList = JsPendingUploadList;
 
 #[derive(Debug)]
-pub struct FileListParseError;
+pub struct FileListParseError {
+    field: &'static str,
+    value: String,
+}
 
 impl Display for FileListParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "FileListParseError")
+        write!(f, "FileListParseError: unexpected {} {:?}", self.field, self.value)
     }
 }
 
@@ -90,14 +399,14 @@ impl TryFrom<&FileListEntryIntermediate> for FileListEntry {
                 "OPEN" => FileState::Open,
                 "SEALED" => FileState::Sealed,
                 _ => {
-                    return Err(FileListParseError)
+                    return Err(FileListParseError { field: "state", value: value.state.clone() })
                 },
             },
             mode: match value.mode.as_str() {
                 "IMMUTABLE" => FileMode::Immutable,
                 "DESTROYABLE" => FileMode::Destroyable,
                 _ => {
-                    return Err(FileListParseError)
+                    return Err(FileListParseError { field: "mode", value: value.mode.clone() })
                 },
             },
             last_updated: value.last_updated,
@@ -111,11 +420,208 @@ impl TryFrom<&FileListEntryIntermediate> for FileListEntry {
 }
 
 #[wasm_bindgen]
-pub struct AccountBalance(pub u64);
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AccountBalance(#[wasm_bindgen(skip)] pub TokenAmount);
 
+// Balances can exceed what a u64 or a JS number holds, so they cross into JS as strings.
 #[wasm_bindgen]
 impl AccountBalance {
+    // Saturates at u64::MAX.
     pub fn as_u64(&self) -> u64 {
+        self.0.to_u64().unwrap_or(u64::MAX)
+    }
+
+    // Base units, with no decimal point.
+    pub fn base_units(&self) -> String {
+        self.0.to_string()
+    }
+
+    // Whole tokens of 10^decimals base units, e.g. "1.5".
+    pub fn format(&self, decimals: u32) -> String {
+        self.0.format(decimals)
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_display_string(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    // Whether the balance covers estimate, a cost in the same units.
+    pub fn can_afford(&self, estimate: &AccountBalance) -> bool {
+        self.0 >= estimate.0
+    }
+
+    // How much short of estimate the balance is; zero when it can afford it.
+    pub fn shortfall(&self, estimate: &AccountBalance) -> AccountBalance {
+        AccountBalance(estimate.0.checked_sub(self.0).unwrap_or_default())
+    }
+
+    pub fn checked_add(&self, other: &AccountBalance) -> Option<AccountBalance> {
+        self.0.checked_add(other.0).map(AccountBalance)
+    }
+
+    pub fn checked_sub(&self, other: &AccountBalance) -> Option<AccountBalance> {
+        self.0.checked_sub(other.0).map(AccountBalance)
+    }
+}
+
+impl AccountBalance {
+    pub fn amount(&self) -> TokenAmount {
         self.0
     }
 }
+
+impl From<TokenAmount> for AccountBalance {
+    fn from(value: TokenAmount) -> Self {
+        AccountBalance(value)
+    }
+}
+
+impl From<u64> for AccountBalance {
+    fn from(value: u64) -> Self {
+        AccountBalance(TokenAmount::from(value))
+    }
+}
+
+// In whole tokens, e.g. "1.5 TFS".
+impl Display for AccountBalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.0.format(TOKEN_DECIMALS), TOKEN_SYMBOL)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum StateChangeType {
+    Set,
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StateChange {
+    pub address: String,
+    pub value: String,
+    #[serde(rename = "type")]
+    pub change_type: StateChangeType,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransactionReceipt {
+    pub tx_id: String,
+    pub block_num: u64,
+    pub block_id: String,
+    pub state_changes: Vec<StateChange>,
+}
+
+// Where a FileUpload has got to. An upload interrupted by an error stays in the phase it was in.
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadPhase {
+    New,
+    Preparing,
+    Prepared,
+    Sending,
+    Sent,
+    Waiting,
+    Committed,
+    FailedToCommit,
+}
+
+// One transaction's outcome in a CommitReport. commit_ms runs from when the transaction was sent
+// by this upload, or from the start of the wait if it was sent earlier, to when it was seen
+// committed; it is None for transactions already committed when the wait began.
+#[derive(Serialize, Debug, Clone)]
+pub struct TxCommitReport {
+    pub tx_id: TransactionId,
+    pub status: TransactionStatus,
+    pub commit_ms: Option<u64>,
+    pub resubmissions: u32,
+}
+
+// Everything the local store holds, from TFSLiteClient::overall_status(), for dashboards.
+// Transactions count as pending until they commit or are given up on.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct OverallStatus {
+    pub files: u64,
+    pub pending_uploads: u64,
+    pub files_with_pending_txs: u64,
+    pub txs_by_status: HashMap<TransactionStatus, u64>,
+    pub pending_txs: u64,
+    pub oldest_pending_age_ms: Option<u64>,
+}
+
+// Returned by FileUpload::wait_transactions().
+#[wasm_bindgen]
+#[derive(Serialize, Debug, Clone)]
+pub struct CommitReport {
+    pub(crate) txs: Vec<TxCommitReport>,
+    pub(crate) elapsed_ms: u64,
+}
+
+impl CommitReport {
+    pub fn txs(&self) -> &[TxCommitReport] {
+        &self.txs
+    }
+}
+
+#[wasm_bindgen]
+impl CommitReport {
+    #[wasm_bindgen(getter)]
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed_ms
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tx_count(&self) -> usize {
+        self.txs.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn committed_count(&self) -> usize {
+        self.txs.iter().filter(|tx| tx.status == TransactionStatus::Committed).count()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn resubmissions(&self) -> u32 {
+        self.txs.iter().map(|tx| tx.resubmissions).sum()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{test_account_balance_common, test_commit_report_common};
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_account_balance() {
+        test_account_balance_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_account_balance() {
+        test_account_balance_common()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_commit_report() {
+        test_commit_report_common()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    fn test_commit_report() {
+        test_commit_report_common()
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use notify::event::{AccessKind, AccessMode};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use libtfslite::client::keys::Signer;
+use crate::client::{TFSLiteClient, TFSLiteClientError, TFSLiteClientErrorType};
+use crate::debug::debug_println;
+
+// What to do with a watched file once its upload has sealed.
+#[derive(Clone, Debug)]
+pub enum SealedAction {
+    Keep,
+    MoveTo(PathBuf),
+    Delete,
+}
+
+#[derive(Clone, Debug)]
+pub struct WatchOptions {
+    // How long a file must go without events before it is uploaded.
+    pub debounce: Duration,
+    pub on_sealed: SealedAction,
+    pub chunk_size: Option<usize>,
+    pub recursive: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            debounce: Duration::from_secs(2),
+            on_sealed: SealedAction::Keep,
+            chunk_size: None,
+            recursive: false,
+        }
+    }
+}
+
+// Only inotify reports files closed after writing; elsewhere a file that has gone quiet for the
+// debounce period is taken as complete.
+const REPORTS_CLOSE: bool = cfg!(target_os = "linux");
+
+struct PendingFile {
+    last_event: Instant,
+    closed: bool,
+}
+
+pub struct UploadManager {
+    client: TFSLiteClient,
+    signer: Box<dyn Signer>,
+    upload_callback: Option<Box<dyn FnMut(&Path, Result<Uuid, TFSLiteClientError>)>>,
+}
+
+impl UploadManager {
+    pub fn new(client: TFSLiteClient, signer: &dyn Signer) -> Self {
+        UploadManager {
+            client,
+            signer: signer.clone_box(),
+            upload_callback: None,
+        }
+    }
+
+    // Called after each upload with the new file's id, or the error that stopped it.
+    pub fn set_upload_callback(&mut self, func: impl FnMut(&Path, Result<Uuid, TFSLiteClientError>) + 'static) {
+        self.upload_callback = Some(Box::new(func));
+    }
+
+    // Uploads files as they are written into path, the "drop folder" pattern. Runs until the
+    // watcher fails; a failed upload is reported to the callback and the file left in place.
+    pub async fn watch_dir(&mut self, path: &Path, options: WatchOptions) -> Result<(), TFSLiteClientError> {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<notify::Result<Event>>();
+        let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        }).map_err(watch_error)?;
+
+        let mode = if options.recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        watcher.watch(path, mode)
+            .map_err(watch_error)?;
+
+        let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+        loop {
+            match tokio::time::timeout(options.debounce, receiver.recv()).await {
+                Ok(Some(Ok(event))) => {
+                    let closed = matches!(event.kind, EventKind::Access(AccessKind::Close(AccessMode::Write)));
+                    let written = closed || matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+                    if !written {
+                        continue;
+                    }
+
+                    for path in event.paths {
+                        let file = pending.entry(path).or_insert(PendingFile { last_event: Instant::now(), closed: false });
+                        file.last_event = Instant::now();
+                        file.closed = closed;
+                    }
+                },
+                Ok(Some(Err(err))) => return Err(watch_error(err)),
+                Ok(None) => return Ok(()),
+                Err(_elapsed) => {},
+            }
+
+            // Closed files still wait out the debounce, so a burst of writes uploads once.
+            let ready: Vec<PathBuf> = pending.iter()
+                .filter(|(_, file)| file.last_event.elapsed() >= options.debounce && (file.closed || !REPORTS_CLOSE))
+                .map(|(path, _)| path.clone())
+                .collect();
+
+            for path in ready {
+                pending.remove(&path);
+                if !path.is_file() {
+                    continue;
+                }
+
+                let result = self.upload(&path, &options).await;
+                if let Some(callback) = self.upload_callback.as_mut() {
+                    callback(&path, result);
+                }
+            }
+        }
+    }
+
+    async fn upload(&self, path: &Path, options: &WatchOptions) -> Result<Uuid, TFSLiteClientError> {
+        debug_println!("Uploading {}", crate::debug::redact("filename", path.display().to_string()));
+
+        let mut upload = self.client.upload_file(path).await?;
+        upload.set_signer(self.signer.as_ref());
+        if let Some(chunk_size) = options.chunk_size {
+            upload.set_chunk_size(chunk_size);
+        }
+
+        upload.prepare_transactions().await?;
+        upload.send_transactions().await?;
+        upload.wait_transactions().await?;
+
+        let result = match &options.on_sealed {
+            SealedAction::Keep => Ok(()),
+            SealedAction::MoveTo(dir) => {
+                let target = dir.join(path.file_name().unwrap_or_default());
+                std::fs::create_dir_all(dir).and_then(|_| std::fs::rename(path, target))
+            },
+            SealedAction::Delete => std::fs::remove_file(path),
+        };
+        result.map_err(|err| TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("Uploaded {} but could not clear it: {}", upload.uuid(), err))))?;
+
+        Ok(upload.uuid())
+    }
+}
+
+fn watch_error(err: notify::Error) -> TFSLiteClientError {
+    TFSLiteClientError::new(TFSLiteClientErrorType::MissingFile, Some(format!("Unable to watch: {}", err)))
+}